@@ -0,0 +1,33 @@
+// Generates the agent's gRPC service from the definition below when the `grpc` feature is on. It has to
+// match proto/morpho.proto, which is what clients generate from; the messages are written by hand with
+// prost's derive in the agent, so building needs no protoc.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    generate_grpc_service();
+}
+
+#[cfg(feature = "grpc")]
+fn generate_grpc_service() {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    // Every call streams the output back in chunks, as the agent's `"stream": true` responses do
+    let method = |name: &str, route: &str, input: &str| {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("crate::grpc::{}", input))
+            .output_type("crate::grpc::OutputChunk")
+            .codec_path("tonic::codec::ProstCodec")
+            .server_streaming()
+            .build()
+    };
+    let agent = Service::builder()
+        .name("Agent")
+        .package("morpho")
+        .method(method("list_all", "ListAll", "ListAllRequest"))
+        .method(method("generate_call_graph", "GenerateCallGraph", "CallGraphRequest"))
+        .method(method("get_source", "GetSource", "SourceRequest"))
+        .build();
+    Builder::new().build_client(false).build_transport(false).compile(&[agent]);
+}
@@ -1,10 +1,31 @@
 // agent/main.rs
 
-use axum::{http::StatusCode, response::Json, routing::{get, post}, Router};
-use morpho_rs::{generate_output_multi_dir, OutputMode, VisibilityFilter};
+use axum::{
+    body::Body,
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get, post},
+    Router,
+};
+use morpho_rs::{
+    fingerprint_dirs_with_options, generate_output_for_project_to, generate_output_for_project_with_format,
+    load_project_with_options, merge_projects, Budget, LoadOptions, Metric, MorphoError, OutputFormat,
+    OutputMode, Page, Project, Timings, VisibilityFilter,
+};
 use serde::{Deserialize, Serialize};
-use std::sync::OnceLock;
-use std::collections::HashMap;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::collections::{BTreeSet, VecDeque};
+use std::io::Write;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityRequirement, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
 
 #[derive(Clone, Debug)]
 struct ProjectInfo {
@@ -13,65 +34,530 @@ struct ProjectInfo {
     is_primary: bool,
 }
 
-static PROJECT_DIRS: OnceLock<Vec<String>> = OnceLock::new();
-static PROJECT_INFO: OnceLock<Vec<ProjectInfo>> = OnceLock::new();
-static NAME_TO_PATH: OnceLock<HashMap<String, String>> = OnceLock::new();
+// Source files the watcher saw change since the last refresh, as (project directory, path as the
+// project's load walks it)
+static STALE_FILES: Mutex<BTreeSet<(String, PathBuf)>> = Mutex::new(BTreeSet::new());
 
-#[derive(Deserialize)]
+// The platform watcher and the directories it watches, as (project directory, canonical root); projects
+// added or removed at runtime are watched or unwatched here
+static WATCHER: Mutex<Option<RecommendedWatcher>> = Mutex::new(None);
+static WATCHED_ROOTS: Mutex<Vec<(String, PathBuf)>> = Mutex::new(vec![]);
+
+// What each swap of the ProjectSet changed, as JSON `IndexEvent`s for the /ws subscribers
+static EVENTS: LazyLock<broadcast::Sender<String>> = LazyLock::new(|| broadcast::channel(EVENT_BUFFER).0);
+const EVENT_BUFFER: usize = 64; // events a slow subscriber can fall behind by before it is told it lagged
+
+// Indexes of every project directory, swapped in whole so the directories and their indexes always change
+// together. syn trees share token buffers through `Rc`, so a set lives on the index thread alone: it is
+// built, queried, cloned and dropped there, in the jobs `SharedProjects` sends it.
+struct ProjectSet {
+    infos: Vec<ProjectInfo>,
+    projects: Vec<Project>, // parallel to infos
+    merged: Project,
+    generation: u64, // increases with every set built, so cached responses can tell which index they came from
+}
+
+static GENERATIONS: AtomicU64 = AtomicU64::new(0);
+
+// Handle on the index thread, which owns the current ProjectSet and runs jobs against it one at a time.
+// Refreshes, reloads and project changes build the next set and swap it in within one job, so none of them
+// swaps in a set built from a stale one.
+#[derive(Clone)]
+struct SharedProjects {
+    jobs: std::sync::mpsc::Sender<Job>,
+}
+
+type Job = Box<dyn FnOnce(&mut ProjectSet) + Send>;
+
+impl SharedProjects {
+    // Starts the index thread on the set `load` builds; jobs sent meanwhile wait for it
+    fn start(load: impl FnOnce() -> ProjectSet + Send + 'static) -> SharedProjects {
+        let (jobs, queue) = std::sync::mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name("index".to_string())
+            .spawn(move || {
+                let mut projects = load();
+                for job in queue {
+                    // A job that panics fails its own request only: its result is never sent
+                    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| job(&mut projects)));
+                }
+            })
+            .expect("Failed to start the index thread");
+        SharedProjects { jobs }
+    }
+
+    // Queues `job` on the index thread without waiting for it
+    fn spawn(&self, job: impl FnOnce(&mut ProjectSet) + Send + 'static) {
+        let _ = self.jobs.send(Box::new(job));
+    }
+
+    // Runs `job` on the index thread and waits for its result; Err when it panicked
+    async fn run<T: Send + 'static>(&self, job: impl FnOnce(&mut ProjectSet) -> T + Send + 'static) -> Result<T, String> {
+        let (done, result) = oneshot::channel();
+        self.spawn(move |projects| {
+            let _ = done.send(job(projects));
+        });
+        result.await.map_err(|_| "the index thread failed".to_string())
+    }
+
+    // `cached_respond` against the current set
+    async fn respond(
+        &self,
+        directory: Option<String>,
+        blacklist: Vec<String>,
+        mode: OutputMode,
+        format_name: Option<String>,
+        action: &'static str,
+    ) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+        let response = self.run(move |projects| {
+            cached_respond(projects, directory.as_deref(), &blacklist, mode, format_name.as_deref(), action)
+        });
+        response.await.unwrap_or_else(|e| {
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse {
+                error: format!("Failed while {}: {}", action, e),
+                fingerprint: None,
+                candidates: vec![],
+            })))
+        })
+    }
+}
+
+const DEFAULT_REFRESH_SECS: u64 = 2;
+
+const DEFAULT_CACHE_SIZE: usize = 64;
+
+// Responses to recent tool calls, least recently used first, keyed by the call's resolved directory,
+// blacklist, format and mode. They hold for one ProjectSet generation: the first call against a newer set
+// (after a refresh, reload or project change) empties the cache.
+struct ResponseCache {
+    generation: u64,
+    entries: VecDeque<(String, ToolCallResponse)>,
+}
+
+static RESPONSES: Mutex<ResponseCache> = Mutex::new(ResponseCache { generation: 0, entries: VecDeque::new() });
+
+// Entries kept in RESPONSES, from MORPHO_CACHE_SIZE; 0 turns caching off
+static CACHE_SIZE: OnceLock<usize> = OnceLock::new();
+
+impl ResponseCache {
+    // Moves the cache on to `generation` if it is newer; false when it is older, as for a request still
+    // working on the set a refresh has since replaced
+    fn current(&mut self, generation: u64) -> bool {
+        if generation > self.generation {
+            self.generation = generation;
+            self.entries.clear();
+        }
+        generation == self.generation
+    }
+
+    fn get(&mut self, generation: u64, key: &str) -> Option<ToolCallResponse> {
+        if !self.current(generation) {
+            return None;
+        }
+        let index = self.entries.iter().position(|(cached, _)| cached == key)?;
+        let entry = self.entries.remove(index)?;
+        let response = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(response)
+    }
+
+    fn insert(&mut self, generation: u64, key: String, response: &ToolCallResponse, capacity: usize) {
+        if !self.current(generation) {
+            return;
+        }
+        self.entries.retain(|(cached, _)| *cached != key);
+        self.entries.push_back((key, response.clone()));
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+}
+
+const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+
+// Token every request must present as `Authorization: Bearer <token>`, from MORPHO_AUTH_TOKEN; no auth when unset
+static AUTH_TOKEN: OnceLock<Option<String>> = OnceLock::new();
+
+#[derive(Deserialize, ToSchema)]
 pub struct CallGraphRequest {
+    /// Function to start from: a qualified name such as "src/lib.rs::run", or a short name
     root_function: String,
+    /// More functions to start from, named the same way; the output has one types section and a call
+    /// tree per root
+    root_functions: Option<Vec<String>>,
+    /// Only `pub` items
     public_only: Option<bool>,
+    /// `pub(crate)`, `pub(super)` and `pub(in path)` items as well as `pub` ones
+    crate_visible: Option<bool>,
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
+    /// Expand at most this many functions, nearest the root first
+    max_nodes: Option<usize>,
+    /// Expand as many as fit in this many bytes of output
+    max_bytes: Option<usize>,
+    /// Follow calls into the other indexed projects, which are marked `[dep: name]` (default true); when
+    /// false the tree stops at them
+    descend_into_deps: Option<bool>,
+    /// List the calls the project doesn't resolve as well, marked `[external]`, e.g., "sqlx::query"
+    show_external: Option<bool>,
+    /// Only reach the functions under this path or module, e.g., "src/server/" or "server::handlers";
+    /// calls out of it are left unresolved, like calls into other crates
+    scope: Option<String>,
+    /// Send the output itself as a chunked body instead of a JSON response
+    stream: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CallGraphSummaryRequest {
+    /// Function to start from, named as for `generate_call_graph`
+    root_function: String,
+    /// More functions to start from
+    root_functions: Option<Vec<String>>,
+    /// Only `pub` items, besides the roots
+    public_only: Option<bool>,
+    /// `pub(crate)`, `pub(super)` and `pub(in path)` items as well as `pub` ones
+    crate_visible: Option<bool>,
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CallGraphSourceRequest {
+    /// Function to start from, named as for `generate_call_graph`
+    root_function: String,
+    /// More functions to start from
+    root_functions: Option<Vec<String>>,
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CallersRequest {
+    /// Function whose callers to find, named as for `root_function`
+    target_function: String,
+    /// Levels of callers to show; unlimited when absent
+    depth: Option<usize>,
+    /// Paths containing any of these strings are left out
     blacklist: Option<Vec<String>>,
-    directory: Option<String>, // Filter to specific directory
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct SourceRequest {
+    /// Function or type to show, e.g., "Button", "main" or "src/lib.rs::Button"
     function: String,
+    /// Put the doc comment above the source
+    include_docs: Option<bool>,
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct TypeRequest {
+    /// Struct, enum or trait name
+    type_name: String,
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SearchRequest {
+    /// Name or fragment to look for, e.g., "parse" or "Config::load"
+    query: String,
+    /// At most this many matches; 20 when absent
+    limit: Option<usize>,
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct TraitRequest {
+    /// Trait name; traits from other crates are matched by name
+    trait_name: String,
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UnreachableRequest {
+    /// Functions to trace from; every main, pub function and trait impl method when absent
+    roots: Option<Vec<String>>,
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MetricsRequest {
+    /// "complexity" (default), "loc", "callees" or "fan-in"
+    sort: Option<String>,
+    /// The top this many functions; all of them when absent
+    limit: Option<usize>,
+    /// Only `pub` items
+    public_only: Option<bool>,
+    /// `pub(crate)`, `pub(super)` and `pub(in path)` items as well as `pub` ones
+    crate_visible: Option<bool>,
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UnsafeAuditRequest {
+    /// Also list the call paths from this function into unsafe code
+    root: Option<String>,
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct EntryPointsRequest {
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct PublicApiRequest {
+    /// Paths containing any of these strings are left out
     blacklist: Option<Vec<String>>,
-    directory: Option<String>, // Filter to specific directory
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default) or "json"
+    format: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
+pub struct CyclesRequest {
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default), "json" or "mermaid"
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CallPathRequest {
+    /// Function the chains start from, e.g., "handle_request"
+    from: String,
+    /// Function the chains end at, e.g., "Db::query"
+    to: String,
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default), "json" or "mermaid"
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CallEdgesRequest {
+    /// Only the calls of the call graph from this function; every resolved call in the project when absent
+    root_function: Option<String>,
+    /// Paths containing any of these strings are left out
+    blacklist: Option<Vec<String>>,
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default: caller, callee and context separated by tabs, one call per line) or "json"
+    format: Option<String>,
+    /// Send the output itself as a chunked body instead of a JSON response
+    stream: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema)]
 pub struct ListAllRequest {
+    /// Only `pub` items
     public_only: Option<bool>,
+    /// `pub(crate)`, `pub(super)` and `pub(in path)` items as well as `pub` ones
+    crate_visible: Option<bool>,
+    /// Paths containing any of these strings are left out
     blacklist: Option<Vec<String>>,
-    directory: Option<String>, // Filter to specific directory
+    /// Project short name or directory to restrict the query to, e.g., "my-crate/src/net"
+    directory: Option<String>,
+    /// "text" (default), "json" or "rustdoc-json"
+    format: Option<String>,
+    /// List at most this many items, by file then name
+    max_nodes: Option<usize>,
+    /// List as many as fit in this many bytes of output
+    max_bytes: Option<usize>,
+    /// Leave out consts, statics and macro_rules! macros
+    hide_items: Option<bool>,
+    /// Show each item's doc comment above it
+    include_docs: Option<bool>,
+    /// Items to skip, by file then name
+    offset: Option<usize>,
+    /// Items in this page, at least one; the rest of the listing when absent
+    limit: Option<usize>,
+    /// Only the functions with this attribute, e.g., "instrument" or "tokio::main"; the paging and size
+    /// options don't apply
+    attribute: Option<String>,
+    /// Send the output itself as a chunked body instead of a JSON response
+    stream: Option<bool>,
 }
 
-#[derive(Serialize)]
+// Every response carries the index fingerprint so clients can tell when cached results are stale
+#[derive(Clone, Serialize, ToSchema)]
 pub struct ToolCallResponse {
-    pub result: String,
+    /// The text output as a string, or the JSON document itself
+    pub result: serde_json::Value,
+    pub fingerprint: String,
+    pub timings: TimingsResponse,
+    /// Answered from the agent's response cache: the same call was made against the same index, and the
+    /// timings are those of that first call
+    pub cached: bool,
+}
+
+// Milliseconds per phase; load phases come from the last index build, trace and render from this request
+#[derive(Clone, Serialize, ToSchema)]
+pub struct TimingsResponse {
+    pub walk_ms: f64,
+    pub read_ms: f64,
+    pub parse_ms: f64,
+    pub index_ms: f64,
+    pub trace_ms: f64,
+    pub render_ms: f64,
+    pub slowest_files: Vec<FileTimingResponse>,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize, ToSchema)]
+pub struct FileTimingResponse {
+    pub path: String,
+    pub ms: f64,
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct ErrorResponse {
     pub error: String,
+    /// Absent when the request never reached a project
+    pub fingerprint: Option<String>,
+    /// The functions an ambiguous name matches, to pick one from; left out for other errors
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct ProjectInfoResponse {
     pub name: String,
     pub path: String,
+    /// Cargo package names in the directory, from `cargo metadata`
+    pub packages: Vec<String>,
+    pub stats: IndexStatsResponse,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct IndexStatsResponse {
+    pub files_parsed: usize,
+    pub files_skipped: Vec<SkippedFileResponse>,
+    pub functions: usize,
+    pub types: usize,
+    /// Seconds since the Unix epoch
+    pub indexed_at: u64,
+    pub fingerprint: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SkippedFileResponse {
+    pub path: String,
+    pub reason: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct InfoResponse {
     pub primary_project: ProjectInfoResponse,
     pub dependencies: Vec<ProjectInfoResponse>,
+    /// Covers all projects, as returned by tool calls without `directory`
+    pub fingerprint: String,
+    /// Seconds since the Unix epoch of the most recent (re-)index of any project
+    pub last_indexed_at: u64,
+    /// Files changed on disk that the next refresh will re-parse
+    pub stale_files: usize,
 }
 
-// Helper function to resolve directory name to full path
-fn resolve_directory(name: &str) -> Result<String, String> {
-    let name_map = NAME_TO_PATH.get().unwrap();
+// Sent over /ws when the index changes, and once on connecting
+#[derive(Serialize, ToSchema)]
+pub struct IndexEvent {
+    /// "connected", "refresh" (files changed on disk), "reload", "project_added", "project_removed", or
+    /// "lagged" when the subscriber fell behind and missed events; refetch what you show then
+    pub reason: String,
+    /// Covers all projects after the change, as returned by tool calls without `directory`
+    pub fingerprint: String,
+    /// The projects whose index changed; empty for "connected" and "lagged"
+    pub projects: Vec<ProjectChanges>,
+}
 
+#[derive(Serialize, ToSchema)]
+pub struct ProjectChanges {
+    pub name: String,
+    pub fingerprint: String,
+    /// Files added, removed or edited, as the tools name them
+    pub files_changed: Vec<String>,
+    /// Functions by qualified name
+    pub functions_added: Vec<String>,
+    pub functions_removed: Vec<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddProjectRequest {
+    /// Directory to index, e.g., "../deps/gpui-component"
+    directory: String,
+    /// Short name to select it by in `directory` parameters; the directory's last path component when absent
+    name: Option<String>,
+}
+
+// Helper function to resolve directory name to full path
+fn resolve_directory(projects: &ProjectSet, name: &str) -> Result<String, String> {
     // Check if it's a short name for a top-level project
-    if let Some(path) = name_map.get(name) {
-        return Ok(path.clone());
+    if let Some(info) = projects.infos.iter().find(|info| info.short_name == name) {
+        return Ok(info.full_path.clone());
     }
 
     // Check if it starts with a short name followed by a path (e.g., "gpui-component/crates/ui")
-    for (short_name, base_path) in name_map.iter() {
+    for ProjectInfo { short_name, full_path: base_path, .. } in &projects.infos {
         if name.starts_with(&format!("{}/", short_name)) {
             // Extract the subpath after the short name
             let subpath = &name[short_name.len() + 1..];
@@ -87,8 +573,7 @@ fn resolve_directory(name: &str) -> Result<String, String> {
     }
 
     // Otherwise assume it's a full path
-    let all_dirs = PROJECT_DIRS.get().unwrap();
-    if all_dirs.contains(&name.to_string()) {
+    if projects.infos.iter().any(|info| info.full_path == name) {
         return Ok(name.to_string());
     }
 
@@ -98,9 +583,8 @@ fn resolve_directory(name: &str) -> Result<String, String> {
     }
 
     // Build helpful error message with available options
-    let project_info = PROJECT_INFO.get().unwrap();
     let mut available = Vec::new();
-    for info in project_info {
+    for info in &projects.infos {
         available.push(format!("  '{}' -> {}", info.short_name, info.full_path));
     }
     available.push("\nYou can also use subdirectories: 'project-name/subdir/path'".to_string());
@@ -112,153 +596,1318 @@ fn resolve_directory(name: &str) -> Result<String, String> {
     ))
 }
 
-async fn get_info() -> Json<InfoResponse> {
-    let project_info = PROJECT_INFO.get().unwrap();
+impl ProjectSet {
+    fn load(infos: Vec<ProjectInfo>) -> ProjectSet {
+        let projects: Vec<Project> = infos.iter().map(|info| load_or_empty(&info.full_path)).collect();
+        ProjectSet::new(infos, projects)
+    }
+
+    // Each project's files are shown under its short name in the merged index, e.g., "gpui/src/lib.rs::run",
+    // the same name that selects it as a `directory`
+    fn new(infos: Vec<ProjectInfo>, projects: Vec<Project>) -> ProjectSet {
+        let named = projects.iter().zip(&infos).map(|(project, info)| project.clone().with_namespace(&info.short_name));
+        ProjectSet {
+            merged: merge_projects(named.collect()),
+            infos,
+            projects,
+            generation: GENERATIONS.fetch_add(1, Ordering::Relaxed) + 1,
+        }
+    }
+
+    fn with_project(&self, info: ProjectInfo, project: Project) -> ProjectSet {
+        let mut infos = self.infos.clone();
+        let mut projects = self.projects.clone();
+        infos.push(info);
+        projects.push(project);
+        ProjectSet::new(infos, projects)
+    }
+
+    fn without_project(&self, index: usize) -> ProjectSet {
+        let mut infos = self.infos.clone();
+        let mut projects = self.projects.clone();
+        infos.remove(index);
+        projects.remove(index);
+        ProjectSet::new(infos, projects)
+    }
+
+    // Re-parses the files the watcher reported, then fully re-indexes any directory whose sources still
+    // differ from its index (changes the watcher missed, or no watcher at all); None if nothing changed
+    fn refreshed(&self, stale: &BTreeSet<(String, PathBuf)>) -> Option<ProjectSet> {
+        let mut changed = false;
+        let projects: Vec<Project> = self
+            .infos
+            .iter()
+            .zip(&self.projects)
+            .map(|(info, project)| {
+                let dir = &info.full_path;
+                let stale_files: Vec<PathBuf> =
+                    stale.iter().filter(|(project, _)| project == dir).map(|(_, path)| path.clone()).collect();
+                let mut project = Cow::Borrowed(project);
+                if !stale_files.is_empty() {
+                    changed = true;
+                    proc_macro2::extra::invalidate_current_thread_spans();
+                    project.to_mut().reindex_files(dir, &stale_files, &LoadOptions::from_env());
+                }
+
+                let options = LoadOptions::from_env();
+                if fingerprint_dirs_with_options(std::slice::from_ref(dir), &options) == project.stats.fingerprint {
+                    project.into_owned()
+                } else {
+                    changed = true;
+                    // Span positions are only read during a load, so earlier loads on this thread can
+                    // release their source text instead of accumulating across refreshes
+                    proc_macro2::extra::invalidate_current_thread_spans();
+                    load_or_empty(dir)
+                }
+            })
+            .collect();
+
+        changed.then(|| ProjectSet::new(self.infos.clone(), projects))
+    }
+}
+
+fn load_or_empty(dir: &str) -> Project {
+    load_project_with_options(dir, &LoadOptions::from_env()).unwrap_or_else(|e| {
+        eprintln!("Error indexing {}: {}", dir, e);
+        Project::default()
+    })
+}
+
+async fn refresh_projects(state: SharedProjects, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.tick().await; // the first tick completes immediately, right after the initial load
 
-    let primary = project_info.iter().find(|p| p.is_primary).unwrap();
+    loop {
+        ticker.tick().await;
+        let refreshed = state.run(|projects| {
+            let stale = std::mem::take(&mut *STALE_FILES.lock().unwrap());
+            if let Some(next) = projects.refreshed(&stale) {
+                swap_projects(projects, next, "refresh");
+            }
+        });
+        if let Err(e) = refreshed.await {
+            eprintln!("Error refreshing index: {}", e);
+        }
+    }
+}
+
+// Makes `next` the index requests work on, and tells /ws subscribers what changed. Called by the job that
+// built `next`, so the event is worked out on the index thread too, never on an async task.
+fn swap_projects(current: &mut ProjectSet, next: ProjectSet, reason: &str) {
+    let previous = std::mem::replace(current, next);
+    if EVENTS.receiver_count() > 0 {
+        let event = index_event(reason, &previous, current);
+        let _ = EVENTS.send(serde_json::to_string(&event).expect("events always serialize"));
+    }
+}
+
+// Projects are matched by directory; one only on one side was added or removed, so all of it changed. A
+// project whose fingerprint is the same on both sides has no changed files, so its sources aren't compared.
+fn index_event(reason: &str, previous: &ProjectSet, next: &ProjectSet) -> IndexEvent {
+    let empty = Project::default();
+    let find = |set: &ProjectSet, dir: &str| set.infos.iter().position(|info| info.full_path == dir);
+    // Files and functions are named as tool calls without `directory` name them, whichever side they are on
+    let mut loaded_from: Vec<String> =
+        next.merged.loaded_from.iter().chain(&previous.merged.loaded_from).cloned().collect();
+    loaded_from.sort();
+    loaded_from.dedup();
+    let roots = previous.merged.roots.iter().chain(&next.merged.roots).map(|(dir, name)| (dir.clone(), name.clone()));
+    let mut naming = Project::default();
+    naming.loaded_from = loaded_from;
+    naming.roots = roots.collect();
+
+    let mut dirs: Vec<&ProjectInfo> = next.infos.iter().collect();
+    dirs.extend(previous.infos.iter().filter(|info| find(next, &info.full_path).is_none()));
+
+    let projects = dirs
+        .into_iter()
+        .filter_map(|info| {
+            let before = find(previous, &info.full_path).map_or(&empty, |i| &previous.projects[i]);
+            let after = find(next, &info.full_path).map_or(&empty, |i| &next.projects[i]);
+            if before.stats.fingerprint == after.stats.fingerprint {
+                return None;
+            }
+            let files_changed: Vec<String> = before
+                .sources
+                .keys()
+                .chain(after.sources.keys().filter(|file| !before.sources.contains_key(*file)))
+                .filter(|file| before.sources.get(*file) != after.sources.get(*file))
+                .map(|file| naming.relative_path(file))
+                .collect();
+            let only_in = |a: &Project, b: &Project| -> Vec<String> {
+                let names = a.functions.keys().filter(|name| !b.functions.contains_key(*name));
+                names.map(|name| naming.relative_path(name)).collect()
+            };
+            let (functions_added, functions_removed) = (only_in(after, before), only_in(before, after));
+            let unchanged = files_changed.is_empty() && functions_added.is_empty() && functions_removed.is_empty();
+            (!unchanged).then(|| ProjectChanges {
+                name: info.short_name.clone(),
+                fingerprint: after.stats.fingerprint.clone(),
+                files_changed,
+                functions_added,
+                functions_removed,
+            })
+        })
+        .collect();
+
+    IndexEvent {
+        reason: reason.to_string(),
+        fingerprint: next.merged.stats.fingerprint.clone(),
+        projects,
+    }
+}
+
+// Starts the watcher that records changed `.rs` files in every watched directory for the next refresh, so
+// only those are re-parsed. Without one (the platform watcher can't be started), refreshes compare
+// fingerprints only.
+fn start_watching(dirs: &[String]) {
+    let handler = move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else { return };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        // Events carry absolute paths; map them back to each directory as it was given
+        let roots = WATCHED_ROOTS.lock().unwrap();
+        let mut stale = STALE_FILES.lock().unwrap();
+        for path in event.paths.iter().filter(|path| path.extension().is_some_and(|ext| ext == "rs")) {
+            for (dir, root) in roots.iter() {
+                if let Ok(relative) = path.strip_prefix(root) {
+                    stale.insert((dir.clone(), Path::new(dir).join(relative)));
+                }
+            }
+        }
+    };
+
+    match notify::recommended_watcher(handler) {
+        Ok(watcher) => *WATCHER.lock().unwrap() = Some(watcher),
+        Err(e) => eprintln!("File watching unavailable, falling back to fingerprint checks: {}", e),
+    }
+    for dir in dirs {
+        watch_directory(dir);
+    }
+}
+
+fn watch_directory(dir: &str) {
+    let Ok(root) = Path::new(dir).canonicalize() else { return };
+    if let Some(watcher) = WATCHER.lock().unwrap().as_mut() {
+        if let Err(e) = watcher.watch(&root, RecursiveMode::Recursive) {
+            eprintln!("Cannot watch {}: {}", root.display(), e);
+        }
+    }
+    WATCHED_ROOTS.lock().unwrap().push((dir.to_string(), root));
+}
+
+// Stops watching `dir` (unless another project shares its root) and forgets its pending changes
+fn unwatch_directory(dir: &str) {
+    let mut roots = WATCHED_ROOTS.lock().unwrap();
+    let Some(index) = roots.iter().position(|(watched, _)| watched == dir) else { return };
+    let (_, root) = roots.remove(index);
+    if !roots.iter().any(|(_, other)| *other == root) {
+        if let Some(watcher) = WATCHER.lock().unwrap().as_mut() {
+            let _ = watcher.unwatch(&root);
+        }
+    }
+    STALE_FILES.lock().unwrap().retain(|(project, _)| project != dir);
+}
+
+// The index a request works on: every project, or the one containing `directory`, minus blacklisted paths
+fn select_project<'a>(
+    projects: &'a ProjectSet,
+    directory: Option<&str>,
+    blacklist: &[String],
+) -> Result<Cow<'a, Project>, (StatusCode, Json<ErrorResponse>)> {
+    let mut project = match directory {
+        None => Cow::Borrowed(&projects.merged),
+        Some(dir_name) => {
+            let resolved = resolve_directory(projects, dir_name).map_err(|error_msg| {
+                (StatusCode::BAD_REQUEST, Json(ErrorResponse {
+                    error: error_msg,
+                    fingerprint: None,
+                    candidates: vec![],
+                }))
+            })?;
+            match projects.infos.iter().position(|info| in_directory(&resolved, &info.full_path)) {
+                Some(idx) if projects.infos[idx].full_path == resolved => Cow::Borrowed(&projects.projects[idx]),
+                Some(idx) => Cow::Owned(projects.projects[idx].filter_files(|file| in_directory(file, &resolved))),
+                // Outside every indexed project, so there is nothing cached to serve it from
+                None => Cow::Owned(load_or_empty(&resolved)),
+            }
+        }
+    };
+
+    if !blacklist.is_empty() {
+        project = Cow::Owned(project.filter_files(|file| !blacklist.iter().any(|bl| file.contains(bl))));
+    }
+    Ok(project)
+}
+
+fn in_directory(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+// `respond` on the index `select_project` picks, or the response to the same call against the same index.
+// Directories outside every project are loaded afresh per request and aren't watched, so those calls, like
+// errors, are never cached.
+fn cached_respond(
+    projects: &ProjectSet,
+    directory: Option<&str>,
+    blacklist: &[String],
+    mode: OutputMode,
+    format_name: Option<&str>,
+    action: &str,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let capacity = CACHE_SIZE.get().copied().unwrap_or(DEFAULT_CACHE_SIZE);
+    // Keyed by the directory resolved, so a project's short name and its path share entries
+    let resolved = directory.map(|dir| resolve_directory(projects, dir).ok());
+    let indexed = match &resolved {
+        None => true,
+        Some(resolved) => {
+            resolved.as_ref().is_some_and(|dir| projects.infos.iter().any(|info| in_directory(dir, &info.full_path)))
+        }
+    };
+    let key = format!("{:?} {:?} {:?} {:?}", resolved.flatten(), blacklist, format_name, mode);
+    if capacity > 0 && indexed {
+        if let Some(mut response) = RESPONSES.lock().unwrap().get(projects.generation, &key) {
+            response.cached = true;
+            return Ok(Json(response));
+        }
+    }
+
+    let project = select_project(projects, directory, blacklist)?;
+    let response = respond(&project, mode, format_name, action)?;
+    if capacity > 0 && indexed {
+        RESPONSES.lock().unwrap().insert(projects.generation, key, &response, capacity);
+    }
+    Ok(response)
+}
+
+fn respond(
+    project: &Project,
+    mode: OutputMode,
+    format_name: Option<&str>,
+    action: &str,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let format = output_format(format_name, project)?;
+    let result = generate_output_for_project_with_format(project, mode, format).map(|output| {
+        let result = match format {
+            OutputFormat::Text | OutputFormat::Mermaid => serde_json::Value::String(output.content),
+            OutputFormat::Json | OutputFormat::RustdocJson => serde_json::from_str(&output.content).expect("JSON output always parses"),
+        };
+        (result, output.fingerprint, output.timings)
+    });
+    match result {
+        Ok((result, fingerprint, timings)) => Ok(Json(ToolCallResponse {
+            timings: timings_response(&timings),
+            result,
+            fingerprint,
+            cached: false,
+        })),
+        Err(e) => Err(error_response(project, e, action)),
+    }
+}
+
+fn output_format(
+    format_name: Option<&str>,
+    project: &Project,
+) -> Result<OutputFormat, (StatusCode, Json<ErrorResponse>)> {
+    match format_name.map(OutputFormat::from_name) {
+        None => Ok(OutputFormat::Text),
+        Some(Some(format)) => Ok(format),
+        Some(None) => Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+            error: format!("Unknown format '{}' (expected text, json, mermaid or rustdoc-json)", format_name.unwrap_or_default()),
+            fingerprint: Some(project.stats.fingerprint.clone()),
+            candidates: vec![],
+        }))),
+    }
+}
+
+fn error_response(project: &Project, e: MorphoError, action: &str) -> (StatusCode, Json<ErrorResponse>) {
+    eprintln!("Error {}: {}", action, e);
+    let status = match e {
+        MorphoError::NotFound { .. } => StatusCode::NOT_FOUND,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    let candidates = match &e {
+        MorphoError::Ambiguous { candidates, .. } => candidates.clone(),
+        _ => vec![],
+    };
+    (status, Json(ErrorResponse {
+        error: e.to_string(),
+        fingerprint: Some(project.stats.fingerprint.clone()),
+        candidates,
+    }))
+}
+
+// The output itself as the response body instead of a `ToolCallResponse`, sent in chunks as it is rendered so
+// a whole-workspace listing is never held in memory twice. The fingerprint comes in the X-Morpho-Fingerprint
+// header. Errors raised before the first chunk are ordinary error responses; later ones end the body early.
+async fn stream_output(
+    state: &SharedProjects,
+    directory: Option<String>,
+    blacklist: Vec<String>,
+    mode: OutputMode,
+    format_name: Option<String>,
+    action: &'static str,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let (fingerprint, format, body) = start_stream(state, directory, blacklist, mode, format_name, action).await?;
+    let content_type = match format {
+        OutputFormat::Json | OutputFormat::RustdocJson => "application/json",
+        OutputFormat::Text | OutputFormat::Mermaid => "text/plain; charset=utf-8",
+    };
+    let stream = futures_util::stream::unfold(body, |mut body| async move {
+        body.recv().await.map(|chunk| (Ok::<Vec<u8>, std::io::Error>(chunk), body))
+    });
+    let headers = [(header::CONTENT_TYPE.as_str(), content_type.to_string()), ("x-morpho-fingerprint", fingerprint)];
+    Ok((headers, Body::from_stream(stream)).into_response())
+}
+
+// Renders the output on the index thread into a channel of chunks, once the first one is ready; with the
+// fingerprint and format it was rendered for. Chunks queue without a bound, so a slow client never holds
+// up the index thread.
+async fn start_stream(
+    state: &SharedProjects,
+    directory: Option<String>,
+    blacklist: Vec<String>,
+    mode: OutputMode,
+    format_name: Option<String>,
+    action: &'static str,
+) -> Result<(String, OutputFormat, mpsc::UnboundedReceiver<Vec<u8>>), (StatusCode, Json<ErrorResponse>)> {
+    let (chunks, body) = mpsc::unbounded_channel();
+    let (start, started) = oneshot::channel();
+    state.spawn(move |projects| {
+        let selected = select_project(projects, directory.as_deref(), &blacklist)
+            .and_then(|project| output_format(format_name.as_deref(), &project).map(|format| (project, format)));
+        let (project, format) = match selected {
+            Ok(selected) => selected,
+            Err(error) => {
+                let _ = start.send(Err(error));
+                return;
+            }
+        };
+
+        let mut out = ChunkWriter {
+            start: Some((start, project.stats.fingerprint.clone(), format)),
+            chunks,
+            buffer: vec![],
+        };
+        let written = generate_output_for_project_to(&mut out, &project, mode, format)
+            .and_then(|_| out.flush().map_err(|e| MorphoError::Write(e.to_string())));
+        if let Err(e) = written {
+            match out.start.take() {
+                Some((start, ..)) => {
+                    let _ = start.send(Err(error_response(&project, e, action)));
+                }
+                None => eprintln!("Error {} after the response started: {}", action, e),
+            }
+        }
+    });
+
+    let (fingerprint, format) = started.await.map_err(|_| {
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse {
+            error: format!("Rendering failed while {}", action),
+            fingerprint: None,
+            candidates: vec![],
+        }))
+    })??;
+    Ok((fingerprint, format, body))
+}
+
+const STREAM_CHUNK_BYTES: usize = 64 * 1024;
+
+type StreamStart = oneshot::Sender<Result<(String, OutputFormat), (StatusCode, Json<ErrorResponse>)>>;
+
+// Hands what is written to the response body in chunks; the first chunk starts the response
+struct ChunkWriter {
+    start: Option<(StreamStart, String, OutputFormat)>, // with the fingerprint and format to start it with
+    chunks: mpsc::UnboundedSender<Vec<u8>>,
+    buffer: Vec<u8>,
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        if self.buffer.len() >= STREAM_CHUNK_BYTES {
+            self.flush()?;
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if let Some((start, fingerprint, format)) = self.start.take() {
+            let _ = start.send(Ok((fingerprint, format)));
+        }
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::take(&mut self.buffer);
+        self.chunks
+            .send(chunk)
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "the client went away"))
+    }
+}
+
+fn timings_response(timings: &Timings) -> TimingsResponse {
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    TimingsResponse {
+        walk_ms: ms(timings.walk),
+        read_ms: ms(timings.read),
+        parse_ms: ms(timings.parse),
+        index_ms: ms(timings.index),
+        trace_ms: ms(timings.trace),
+        render_ms: ms(timings.render),
+        slowest_files: timings
+            .slowest_files
+            .iter()
+            .map(|(path, duration)| FileTimingResponse { path: path.clone(), ms: ms(*duration) })
+            .collect(),
+    }
+}
+
+fn package_names(project: &Project) -> Vec<String> {
+    project.packages.iter().map(|package| package.name.clone()).collect()
+}
+
+// What the index covers for one project
+fn index_stats(project: &Project) -> IndexStatsResponse {
+    IndexStatsResponse {
+        files_parsed: project.stats.files_parsed,
+        files_skipped: project
+            .stats
+            .skipped
+            .iter()
+            .map(|s| SkippedFileResponse { path: s.path.clone(), reason: s.reason.clone() })
+            .collect(),
+        functions: project.functions.len(),
+        types: project.types.len(),
+        indexed_at: project.stats.indexed_at,
+        fingerprint: project.stats.fingerprint.clone(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/info",
+    summary = "Indexed projects and dependencies with their index stats",
+    responses((status = 200, description = "Projects and index stats", body = InfoResponse))
+)]
+async fn get_info(State(state): State<SharedProjects>) -> Json<InfoResponse> {
+    Json(state.run(|projects| info_response(projects)).await.expect("index thread answers"))
+}
+
+// Re-indexes every directory now rather than waiting for the background refresh to notice a change,
+// e.g., after edits that keep file sizes and modification times
+#[utoipa::path(
+    post,
+    path = "/tool/reload",
+    summary = "Re-index every project now",
+    responses(
+        (status = 200, description = "Projects and index stats after the re-index", body = InfoResponse),
+        (status = 500, description = "The re-index failed", body = ErrorResponse),
+    )
+)]
+async fn reload(State(state): State<SharedProjects>) -> Result<Json<InfoResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let reloaded = state.run(|projects| {
+        STALE_FILES.lock().unwrap().clear(); // the full re-index picks up every change made so far
+        // As in the background refresh, earlier loads on this thread can release their source text
+        proc_macro2::extra::invalidate_current_thread_spans();
+        let next = ProjectSet::load(projects.infos.clone());
+        swap_projects(projects, next, "reload");
+        info_response(projects)
+    });
+
+    match reloaded.await {
+        Ok(info) => Ok(Json(info)),
+        Err(e) => {
+            eprintln!("Error reloading index: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorResponse {
+                error: format!("Reload failed: {}", e),
+                fingerprint: None,
+                candidates: vec![],
+            })))
+        }
+    }
+}
+
+fn info_response(projects: &ProjectSet) -> InfoResponse {
+    let project_info = &projects.infos;
+
+    let primary = project_info.iter().position(|p| p.is_primary).unwrap();
     let dependencies: Vec<ProjectInfoResponse> = project_info
         .iter()
-        .filter(|p| !p.is_primary)
-        .map(|p| ProjectInfoResponse {
+        .zip(&projects.projects)
+        .filter(|(p, _)| !p.is_primary)
+        .map(|(p, project)| ProjectInfoResponse {
             name: p.short_name.clone(),
             path: p.full_path.clone(),
+            packages: package_names(project),
+            stats: index_stats(project),
         })
         .collect();
 
-    Json(InfoResponse {
+    InfoResponse {
         primary_project: ProjectInfoResponse {
-            name: primary.short_name.clone(),
-            path: primary.full_path.clone(),
+            name: project_info[primary].short_name.clone(),
+            path: project_info[primary].full_path.clone(),
+            packages: package_names(&projects.projects[primary]),
+            stats: index_stats(&projects.projects[primary]),
         },
         dependencies,
-    })
+        fingerprint: projects.merged.stats.fingerprint.clone(),
+        last_indexed_at: projects.projects.iter().map(|project| project.stats.indexed_at).max().unwrap_or_default(),
+        stale_files: STALE_FILES.lock().unwrap().len(),
+    }
 }
 
+// Last path component of a project directory, e.g., "gpui-component" for "../deps/gpui-component"
+fn short_name(dir: &str) -> String {
+    Path::new(dir).file_name().and_then(|n| n.to_str()).unwrap_or("unknown").to_string()
+}
+
+// Indexes another directory and adds it as a dependency, so a new checkout can be queried without a
+// restart; the response is the same as `GET /info`
+#[utoipa::path(
+    post,
+    path = "/projects",
+    summary = "Index another directory as a dependency",
+    request_body = AddProjectRequest,
+    responses(
+        (status = 201, description = "Projects and index stats with the new one", body = InfoResponse),
+        (status = 400, description = "No such directory, or it can't be indexed", body = ErrorResponse),
+        (status = 409, description = "A project with that name or directory is already indexed", body = ErrorResponse),
+    )
+)]
+async fn add_project(
+    State(state): State<SharedProjects>,
+    Json(req): Json<AddProjectRequest>,
+) -> Result<(StatusCode, Json<InfoResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| {
+        (status, Json(ErrorResponse { error, fingerprint: None, candidates: vec![] }))
+    };
+    if !Path::new(&req.directory).is_dir() {
+        return Err(error(StatusCode::BAD_REQUEST, format!("Directory '{}' does not exist", req.directory)));
+    }
+    // Absolute paths are read off canonical directories, as for the directories given at startup
+    let dir = if LoadOptions::from_env().absolute_paths {
+        std::fs::canonicalize(&req.directory).map_or(req.directory, |path| path.to_string_lossy().into_owned())
+    } else {
+        req.directory
+    };
+    let info = ProjectInfo {
+        short_name: req.name.unwrap_or_else(|| short_name(&dir)),
+        full_path: dir,
+        is_primary: false,
+    };
+
+    let dir = info.full_path.clone();
+    let added = state.run(move |projects| {
+        if let Some(existing) =
+            projects.infos.iter().find(|p| p.short_name == info.short_name || p.full_path == info.full_path)
+        {
+            return Err(error(StatusCode::CONFLICT, format!(
+                "'{}' is already indexed as '{}' ({})",
+                info.full_path, existing.short_name, existing.full_path
+            )));
+        }
+        proc_macro2::extra::invalidate_current_thread_spans();
+        let dir = info.full_path.clone();
+        let project = load_project_with_options(&dir, &LoadOptions::from_env())
+            .map_err(|e| error(StatusCode::BAD_REQUEST, format!("Cannot index {}: {}", dir, e)))?;
+        let next = projects.with_project(info, project);
+        swap_projects(projects, next, "project_added");
+        watch_directory(&dir);
+        Ok(info_response(projects))
+    });
+    match added.await {
+        Ok(Ok(info)) => {
+            println!("   Added dependency: {}", dir);
+            Ok((StatusCode::CREATED, Json(info)))
+        }
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(error(StatusCode::INTERNAL_SERVER_ERROR, format!("Indexing {} failed: {}", dir, e))),
+    }
+}
+
+// Drops a dependency added at startup or with `POST /projects`; the primary project stays
+#[utoipa::path(
+    delete,
+    path = "/projects/{name}",
+    summary = "Stop indexing a dependency",
+    params(("name" = String, Path, description = "The dependency's short name, as listed by GET /info")),
+    responses(
+        (status = 200, description = "Projects and index stats without it", body = InfoResponse),
+        (status = 400, description = "The primary project can't be removed", body = ErrorResponse),
+        (status = 404, description = "No project with that name", body = ErrorResponse),
+    )
+)]
+async fn remove_project(
+    State(state): State<SharedProjects>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Result<Json<InfoResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| {
+        (status, Json(ErrorResponse { error, fingerprint: None, candidates: vec![] }))
+    };
+    let removing = name.clone();
+    let removed = state.run(move |projects| {
+        let name = removing;
+        let Some(index) = projects.infos.iter().position(|p| p.short_name == name) else {
+            let names: Vec<&str> = projects.infos.iter().map(|p| p.short_name.as_str()).collect();
+            return Err(error(StatusCode::NOT_FOUND, format!(
+                "No project named '{}' (indexed: {})",
+                name,
+                names.join(", ")
+            )));
+        };
+        if projects.infos[index].is_primary {
+            return Err(error(StatusCode::BAD_REQUEST, format!("'{}' is the primary project and can't be removed", name)));
+        }
+
+        let dir = projects.infos[index].full_path.clone();
+        let next = projects.without_project(index);
+        swap_projects(projects, next, "project_removed");
+        unwatch_directory(&dir);
+        Ok((dir, info_response(projects)))
+    });
+    let (dir, info) = removed
+        .await
+        .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, format!("Removing {} failed: {}", name, e)))??;
+    println!("   Removed dependency: {}", dir);
+    Ok(Json(info))
+}
+
+// Pushes an `IndexEvent` as a text message whenever the index changes, starting with a "connected" one
+// carrying the current fingerprint, so clients can refresh what they show without polling
+#[utoipa::path(
+    get,
+    path = "/ws",
+    summary = "WebSocket of index change events",
+    responses((status = 101, description = "Switched to a WebSocket; each text message is an event", body = IndexEvent))
+)]
+async fn watch_events(State(state): State<SharedProjects>, ws: WebSocketUpgrade) -> Response {
+    // Subscribed before the current fingerprint is read, so no change falls between the two
+    let events = EVENTS.subscribe();
+    ws.on_upgrade(move |socket| send_events(socket, events, state))
+}
+
+async fn send_events(mut socket: WebSocket, mut events: broadcast::Receiver<String>, state: SharedProjects) {
+    let status = |reason: &'static str| {
+        let fingerprint = state.run(|projects| projects.merged.stats.fingerprint.clone());
+        async move {
+            let fingerprint = fingerprint.await.unwrap_or_default();
+            let event = IndexEvent { reason: reason.to_string(), fingerprint, projects: vec![] };
+            serde_json::to_string(&event).expect("events always serialize")
+        }
+    };
+    let mut next = status("connected").await;
+    while socket.send(Message::Text(next)).await.is_ok() {
+        next = loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => break event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => break status("lagged").await,
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                // Incoming messages are ignored; the client closing, or going away, ends the subscription
+                message = socket.recv() => match message {
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                    Some(Ok(_)) => {}
+                },
+            }
+        };
+    }
+}
+
+// OpenAPI description of every endpoint, generated from the handlers and the request and response structs
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "morpho-rs-agent", description = "Call graphs, source and type queries over indexed Rust projects"),
+    paths(
+        get_info,
+        generate_call_graph,
+        call_graph_summary,
+        call_graph_source,
+        find_callers,
+        get_source,
+        find_type_usages,
+        get_type_api,
+        find_implementors,
+        search,
+        find_unreachable,
+        function_metrics,
+        audit_unsafe,
+        find_entry_points,
+        public_api,
+        find_cycles,
+        call_edges,
+        find_call_path,
+        list_all,
+        reload,
+        add_project,
+        remove_project,
+        watch_events,
+    ),
+    modifiers(&BearerAuth)
+)]
+struct ApiDoc;
+
+// Declares the bearer token on every endpoint when MORPHO_AUTH_TOKEN is set
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if AUTH_TOKEN.get().and_then(Option::as_ref).is_none() {
+            return;
+        }
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("bearer", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+        openapi.security = Some(vec![SecurityRequirement::new("bearer", Vec::<String>::new())]);
+    }
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/generate_call_graph",
+    summary = "The call tree below a function, with the types it reaches",
+    request_body = CallGraphRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
 async fn generate_call_graph(
+    State(state): State<SharedProjects>,
     Json(req): Json<CallGraphRequest>,
-) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
     let visibility = if req.public_only.unwrap_or(false) {
         VisibilityFilter::PublicOnly
+    } else if req.crate_visible.unwrap_or(false) {
+        VisibilityFilter::CrateVisible
     } else {
         VisibilityFilter::All
     };
 
     let blacklist = req.blacklist.unwrap_or_default();
+    let roots = std::iter::once(req.root_function).chain(req.root_functions.unwrap_or_default()).collect();
+    let mode = OutputMode::CallGraph {
+        roots,
+        visibility,
+        budget: Budget { max_nodes: req.max_nodes, max_bytes: req.max_bytes },
+        descend_into_deps: req.descend_into_deps.unwrap_or(true),
+        show_external: req.show_external.unwrap_or(false),
+        scope: req.scope,
+    };
+    if req.stream.unwrap_or(false) {
+        return stream_output(&state, req.directory, blacklist, mode, req.format, "generating call graph").await;
+    }
+    state.respond(req.directory, blacklist, mode, req.format, "generating call graph").await.map(IntoResponse::into_response)
+}
 
-    // Use specified directory or all directories
-    let all_dirs = PROJECT_DIRS.get().unwrap();
-    let dirs = if let Some(ref dir_name) = req.directory {
-        match resolve_directory(dir_name) {
-            Ok(resolved) => vec![resolved],
-            Err(error_msg) => {
-                return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
-                    error: error_msg,
-                })));
-            }
-        }
+#[utoipa::path(
+    post,
+    path = "/tool/call_graph_summary",
+    summary = "The signatures and types a call graph reaches, by file, without the call tree",
+    request_body = CallGraphSummaryRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn call_graph_summary(
+    State(state): State<SharedProjects>,
+    Json(req): Json<CallGraphSummaryRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let visibility = if req.public_only.unwrap_or(false) {
+        VisibilityFilter::PublicOnly
+    } else if req.crate_visible.unwrap_or(false) {
+        VisibilityFilter::CrateVisible
     } else {
-        all_dirs.clone()
+        VisibilityFilter::All
     };
 
-    match generate_output_multi_dir(
-        &dirs,
-        OutputMode::CallGraph {
-            root: req.root_function,
-            visibility,
-        },
-        &blacklist,
-    ) {
-        Ok(output) => Ok(Json(ToolCallResponse {
-            result: output.content,
-        })),
-        Err(e) => {
-            eprintln!("Error generating call graph: {}", e);
-            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
-                error: e,
-            })))
-        }
-    }
+    let blacklist = req.blacklist.unwrap_or_default();
+    let roots = std::iter::once(req.root_function).chain(req.root_functions.unwrap_or_default()).collect();
+    let mode = OutputMode::CallGraphSummary { roots, visibility };
+    state.respond(req.directory, blacklist, mode, req.format, "summarizing call graph").await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/call_graph_source",
+    summary = "The full source of every function a call graph reaches, and the types it reaches, by file",
+    request_body = CallGraphSourceRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn call_graph_source(
+    State(state): State<SharedProjects>,
+    Json(req): Json<CallGraphSourceRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    let roots = std::iter::once(req.root_function).chain(req.root_functions.unwrap_or_default()).collect();
+    state
+        .respond(
+            req.directory,
+            blacklist,
+            OutputMode::CallGraphSource { roots },
+            req.format,
+            "bundling call graph source",
+        )
+        .await
 }
 
+#[utoipa::path(
+    post,
+    path = "/tool/find_callers",
+    summary = "Every function that calls a function, directly or transitively",
+    request_body = CallersRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn find_callers(
+    State(state): State<SharedProjects>,
+    Json(req): Json<CallersRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    state
+        .respond(
+            req.directory,
+            blacklist,
+            OutputMode::Callers { target: req.target_function, depth: req.depth },
+            req.format,
+            "finding callers",
+        )
+        .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/get_source",
+    summary = "Source of a function or type",
+    request_body = SourceRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
 async fn get_source(
+    State(state): State<SharedProjects>,
     Json(req): Json<SourceRequest>,
 ) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
     let blacklist = req.blacklist.unwrap_or_default();
+    let mode = OutputMode::Source { function: req.function, include_docs: req.include_docs.unwrap_or(false) };
+    state.respond(req.directory, blacklist, mode, req.format, "getting source").await
+}
 
-    // Use specified directory or all directories
-    let all_dirs = PROJECT_DIRS.get().unwrap();
-    let dirs = if let Some(ref dir_name) = req.directory {
-        match resolve_directory(dir_name) {
-            Ok(resolved) => vec![resolved],
-            Err(error_msg) => {
-                return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
-                    error: error_msg,
-                })));
-            }
-        }
-    } else {
-        all_dirs.clone()
-    };
+#[utoipa::path(
+    post,
+    path = "/tool/find_type_usages",
+    summary = "Every function that uses a type, and how",
+    request_body = TypeRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn find_type_usages(
+    State(state): State<SharedProjects>,
+    Json(req): Json<TypeRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    state
+        .respond(
+            req.directory,
+            blacklist,
+            OutputMode::TypeUsages { type_name: req.type_name },
+            req.format,
+            "finding type usages",
+        )
+        .await
+}
 
-    match generate_output_multi_dir(&dirs, OutputMode::Source { function: req.function }, &blacklist) {
-        Ok(output) => Ok(Json(ToolCallResponse {
-            result: output.content,
-        })),
-        Err(e) => {
-            eprintln!("Error getting source: {}", e);
-            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
-                error: e,
-            })))
-        }
+#[utoipa::path(
+    post,
+    path = "/tool/get_type_api",
+    summary = "A type's definition, impl blocks and methods",
+    request_body = TypeRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn get_type_api(
+    State(state): State<SharedProjects>,
+    Json(req): Json<TypeRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    state
+        .respond(
+            req.directory,
+            blacklist,
+            OutputMode::TypeApi { type_name: req.type_name },
+            req.format,
+            "getting type API",
+        )
+        .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/find_implementors",
+    summary = "Every impl of a trait, with the methods each defines or inherits",
+    request_body = TraitRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn find_implementors(
+    State(state): State<SharedProjects>,
+    Json(req): Json<TraitRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    state
+        .respond(
+            req.directory,
+            blacklist,
+            OutputMode::Implementors { trait_name: req.trait_name },
+            req.format,
+            "finding implementors",
+        )
+        .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/search",
+    summary = "Functions and types by name, best matches first",
+    request_body = SearchRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn search(
+    State(state): State<SharedProjects>,
+    Json(req): Json<SearchRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    state
+        .respond(
+            req.directory,
+            blacklist,
+            OutputMode::Search { query: req.query, limit: req.limit },
+            req.format,
+            "searching",
+        )
+        .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/find_unreachable",
+    summary = "Functions never reached from the roots (dead code)",
+    request_body = UnreachableRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn find_unreachable(
+    State(state): State<SharedProjects>,
+    Json(req): Json<UnreachableRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    state
+        .respond(
+            req.directory,
+            blacklist,
+            OutputMode::Unreachable { roots: req.roots.unwrap_or_default() },
+            req.format,
+            "finding unreachable functions",
+        )
+        .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/audit_unsafe",
+    summary = "Unsafe fns and blocks, and the call paths into them from a root",
+    request_body = UnsafeAuditRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn audit_unsafe(
+    State(state): State<SharedProjects>,
+    Json(req): Json<UnsafeAuditRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    state
+        .respond(
+            req.directory,
+            blacklist,
+            OutputMode::UnsafeAudit { root: req.root },
+            req.format,
+            "auditing unsafe code",
+        )
+        .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/find_entry_points",
+    summary = "Main functions, runtime mains, exported functions, route handlers, tests and bin targets",
+    request_body = EntryPointsRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+    )
+)]
+async fn find_entry_points(
+    State(state): State<SharedProjects>,
+    Json(req): Json<EntryPointsRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    state.respond(req.directory, blacklist, OutputMode::EntryPoints, req.format, "finding entry points").await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/public_api",
+    summary = "What a library exports, by the paths downstream crates write",
+    request_body = PublicApiRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+    )
+)]
+async fn public_api(
+    State(state): State<SharedProjects>,
+    Json(req): Json<PublicApiRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    state.respond(req.directory, blacklist, OutputMode::PublicApi, req.format, "listing the public API").await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/find_cycles",
+    summary = "Mutually recursive function groups and self-recursive functions, with the calls closing each loop",
+    request_body = CyclesRequest,
+    responses(
+        (status = 200, description = "The output, as text, a JSON document or a Mermaid diagram", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+    )
+)]
+async fn find_cycles(
+    State(state): State<SharedProjects>,
+    Json(req): Json<CyclesRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    state.respond(req.directory, blacklist, OutputMode::Cycles, req.format, "finding cycles").await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/find_call_path",
+    summary = "The shortest chains of calls from one function to another",
+    request_body = CallPathRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn find_call_path(
+    State(state): State<SharedProjects>,
+    Json(req): Json<CallPathRequest>,
+) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    state
+        .respond(
+            req.directory,
+            blacklist,
+            OutputMode::PathBetween { from: req.from, to: req.to },
+            req.format,
+            "finding call paths",
+        )
+        .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/call_edges",
+    summary = "Resolved calls as a flat caller, callee, context edge list for graph tools",
+    request_body = CallEdgesRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn call_edges(
+    State(state): State<SharedProjects>,
+    Json(req): Json<CallEdgesRequest>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let blacklist = req.blacklist.unwrap_or_default();
+    let mode = OutputMode::CallEdges { root: req.root_function };
+    if req.stream.unwrap_or(false) {
+        return stream_output(&state, req.directory, blacklist, mode, req.format, "listing call edges").await;
     }
+    state.respond(req.directory, blacklist, mode, req.format, "listing call edges").await.map(IntoResponse::into_response)
 }
 
-async fn list_all(
-    Json(req): Json<ListAllRequest>,
+#[utoipa::path(
+    post,
+    path = "/tool/function_metrics",
+    summary = "Functions ranked by size, complexity, callees or callers",
+    request_body = MetricsRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn function_metrics(
+    State(state): State<SharedProjects>,
+    Json(req): Json<MetricsRequest>,
 ) -> Result<Json<ToolCallResponse>, (StatusCode, Json<ErrorResponse>)> {
     let visibility = if req.public_only.unwrap_or(false) {
         VisibilityFilter::PublicOnly
+    } else if req.crate_visible.unwrap_or(false) {
+        VisibilityFilter::CrateVisible
     } else {
         VisibilityFilter::All
     };
 
     let blacklist = req.blacklist.unwrap_or_default();
-
-    // Use specified directory or all directories
-    let all_dirs = PROJECT_DIRS.get().unwrap();
-    let dirs = if let Some(ref dir_name) = req.directory {
-        match resolve_directory(dir_name) {
-            Ok(resolved) => vec![resolved],
-            Err(error_msg) => {
-                return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
-                    error: error_msg,
-                })));
-            }
+    let sort = match req.sort.as_deref().map(Metric::from_name) {
+        None => Metric::default(),
+        Some(Some(sort)) => sort,
+        Some(None) => {
+            let (directory, blacklist) = (req.directory, blacklist);
+            let fingerprint = state.run(move |projects| {
+                select_project(projects, directory.as_deref(), &blacklist).map(|project| project.stats.fingerprint.clone())
+            });
+            let fingerprint = fingerprint.await.ok().transpose()?;
+            return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
+                error: format!("Unknown sort '{}' (expected loc, complexity, callees or fan-in)", req.sort.unwrap_or_default()),
+                fingerprint,
+                candidates: vec![],
+            })));
         }
+    };
+    state
+        .respond(
+            req.directory,
+            blacklist,
+            OutputMode::Metrics { sort, visibility, limit: req.limit },
+            req.format,
+            "measuring functions",
+        )
+        .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/tool/list_all",
+    summary = "Every type and function signature by file",
+    request_body = ListAllRequest,
+    responses(
+        (status = 200, description = "The output, as text or a JSON document", body = ToolCallResponse),
+        (status = 400, description = "Bad request or unsupported options", body = ErrorResponse),
+        (status = 404, description = "No such function or type", body = ErrorResponse),
+    )
+)]
+async fn list_all(
+    State(state): State<SharedProjects>,
+    Json(req): Json<ListAllRequest>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let visibility = if req.public_only.unwrap_or(false) {
+        VisibilityFilter::PublicOnly
+    } else if req.crate_visible.unwrap_or(false) {
+        VisibilityFilter::CrateVisible
     } else {
-        all_dirs.clone()
+        VisibilityFilter::All
     };
 
-    match generate_output_multi_dir(&dirs, OutputMode::ListAll { visibility }, &blacklist) {
-        Ok(output) => Ok(Json(ToolCallResponse {
-            result: output.content,
-        })),
-        Err(e) => {
-            eprintln!("Error listing all: {}", e);
-            Err((StatusCode::BAD_REQUEST, Json(ErrorResponse {
-                error: e,
-            })))
-        }
+    let blacklist = req.blacklist.unwrap_or_default();
+    let budget = Budget { max_nodes: req.max_nodes, max_bytes: req.max_bytes };
+    let page = Page { offset: req.offset.unwrap_or(0), limit: req.limit };
+    let mode = match req.attribute {
+        Some(attribute) => OutputMode::WithAttribute { attribute, visibility },
+        None => OutputMode::ListAll {
+            visibility,
+            hide_items: req.hide_items.unwrap_or(false),
+            include_docs: req.include_docs.unwrap_or(false),
+            budget,
+            page,
+        },
+    };
+    if req.stream.unwrap_or(false) {
+        return stream_output(&state, req.directory, blacklist, mode, req.format, "listing all").await;
     }
+    state.respond(req.directory, blacklist, mode, req.format, "listing all").await.map(IntoResponse::into_response)
 }
 
 #[tokio::main]
 async fn main() {
     // Determine project directories:
-    // 1. CLI args (everything after program name)
+    // 1. CLI args (everything after program name but the options below)
     // 2. MORPHO_PROJECT_DIRS environment variable (colon-separated)
     // 3. Current directory as fallback
-    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    // Where and how to serve: --listen, --tls-cert and --tls-key, each falling back to its environment variable
+    let mut option = |flag: &str, env: &str| -> Option<String> {
+        match args.iter().position(|arg| arg == flag) {
+            Some(pos) if pos + 1 < args.len() => Some(args.drain(pos..pos + 2).nth(1).expect("value follows flag")),
+            Some(_) => {
+                eprintln!("Error: {} requires a value", flag);
+                std::process::exit(1);
+            }
+            None => std::env::var(env).ok().filter(|value| !value.is_empty()),
+        }
+    };
+    let listen = option("--listen", "MORPHO_LISTEN").unwrap_or_else(|| DEFAULT_LISTEN.to_string());
+    let tls = match (option("--tls-cert", "MORPHO_TLS_CERT"), option("--tls-key", "MORPHO_TLS_KEY")) {
+        (Some(cert), Some(key)) => Some(tls_acceptor(&cert, &key).unwrap_or_else(|e| {
+            eprintln!("Error: TLS: {}", e);
+            std::process::exit(1);
+        })),
+        (None, None) => None,
+        _ => {
+            eprintln!("Error: TLS needs both a certificate (--tls-cert) and a private key (--tls-key)");
+            std::process::exit(1);
+        }
+    };
+    AUTH_TOKEN
+        .set(std::env::var("MORPHO_AUTH_TOKEN").ok().filter(|token| !token.is_empty()))
+        .expect("Failed to set AUTH_TOKEN");
+    let cache_size = std::env::var("MORPHO_CACHE_SIZE")
+        .ok()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(DEFAULT_CACHE_SIZE);
+    CACHE_SIZE.set(cache_size).expect("Failed to set CACHE_SIZE");
 
     let dirs = if !args.is_empty() {
         args
@@ -267,49 +1916,100 @@ async fn main() {
     } else {
         vec![".".to_string()]
     };
+    // Absolute paths are read off canonical directories, so refreshes must walk those too
+    let dirs: Vec<String> = if LoadOptions::from_env().absolute_paths {
+        dirs.into_iter()
+            .map(|dir| std::fs::canonicalize(&dir).map_or(dir, |path| path.to_string_lossy().into_owned()))
+            .collect()
+    } else {
+        dirs
+    };
 
     // Build project info structures
-    let mut project_info_vec = Vec::new();
-    let mut name_to_path_map = HashMap::new();
-
-    for (idx, dir) in dirs.iter().enumerate() {
-        // Extract short name from path (last component)
-        let short_name = std::path::Path::new(dir)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-
-        let info = ProjectInfo {
+    let project_info_vec: Vec<ProjectInfo> = dirs
+        .iter()
+        .enumerate()
+        .map(|(idx, dir)| ProjectInfo {
             full_path: dir.clone(),
-            short_name: short_name.clone(),
+            short_name: short_name(dir),
             is_primary: idx == 0, // First one is primary
-        };
+        })
+        .collect();
 
-        name_to_path_map.insert(short_name, dir.clone());
-        project_info_vec.push(info);
-    }
+    // Index once up front, then keep the index fresh in the background: the watcher collects changed
+    // files and each refresh re-parses just those
+    start_watching(&dirs);
+    let infos = project_info_vec.clone();
+    let state = SharedProjects::start(move || ProjectSet::load(infos));
+    state.run(|_| ()).await.expect("Failed to index projects");
 
-    PROJECT_DIRS.set(dirs.clone()).expect("Failed to set PROJECT_DIRS");
-    PROJECT_INFO.set(project_info_vec.clone()).expect("Failed to set PROJECT_INFO");
-    NAME_TO_PATH.set(name_to_path_map).expect("Failed to set NAME_TO_PATH");
+    let refresh_secs = std::env::var("MORPHO_REFRESH_SECS")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_REFRESH_SECS);
+    tokio::spawn(refresh_projects(state.clone(), Duration::from_secs(refresh_secs.max(1))));
 
     let app = Router::new()
         .route("/info", get(get_info))
+        .route("/openapi.json", get(openapi))
         .route("/tool/generate_call_graph", post(generate_call_graph))
+        .route("/tool/call_graph_summary", post(call_graph_summary))
+        .route("/tool/call_graph_source", post(call_graph_source))
+        .route("/tool/find_callers", post(find_callers))
         .route("/tool/get_source", post(get_source))
-        .route("/tool/list_all", post(list_all));
+        .route("/tool/find_type_usages", post(find_type_usages))
+        .route("/tool/get_type_api", post(get_type_api))
+        .route("/tool/find_implementors", post(find_implementors))
+        .route("/tool/search", post(search))
+        .route("/tool/find_unreachable", post(find_unreachable))
+        .route("/tool/function_metrics", post(function_metrics))
+        .route("/tool/audit_unsafe", post(audit_unsafe))
+        .route("/tool/find_entry_points", post(find_entry_points))
+        .route("/tool/public_api", post(public_api))
+        .route("/tool/find_cycles", post(find_cycles))
+        .route("/tool/call_edges", post(call_edges))
+        .route("/tool/find_call_path", post(find_call_path))
+        .route("/tool/list_all", post(list_all))
+        .route("/tool/reload", post(reload))
+        .route("/projects", post(add_project))
+        .route("/projects/:name", delete(remove_project))
+        .route("/ws", get(watch_events))
+        .with_state(state.clone());
+    #[cfg(feature = "grpc")]
+    let app = app.merge(grpc::routes(state));
+    let app = app.layer(axum::middleware::from_fn(require_token));
 
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:8080")
-        .await
-        .unwrap();
+    let listener = match tokio::net::TcpListener::bind(&listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Error: cannot listen on {}: {}", listen, e);
+            std::process::exit(1);
+        }
+    };
 
-    println!("🚀 morpho-rs-agent (HTTP) listening on http://127.0.0.1:8080");
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    println!("🚀 morpho-rs-agent (HTTP) listening on {}://{}", scheme, listen);
     println!("   Primary project: {} ({})",
         project_info_vec[0].short_name,
         project_info_vec[0].full_path
     );
 
+    println!("   Index refresh: changed files re-parsed every {}s (MORPHO_REFRESH_SECS)", refresh_secs.max(1));
+    println!("   Response cache: {} entries (MORPHO_CACHE_SIZE)", cache_size);
+    if std::env::var_os("MORPHO_INCLUDE_TESTS").is_none() {
+        println!("   Test code: excluded (set MORPHO_INCLUDE_TESTS=1 to index it)");
+    }
+    if !LoadOptions::from_env().absolute_paths {
+        println!("   Paths: relative to each project (set MORPHO_ABSOLUTE_PATHS=1 for absolute paths)");
+    }
+    match AUTH_TOKEN.get().unwrap() {
+        Some(_) => println!("   Auth: bearer token required (MORPHO_AUTH_TOKEN)"),
+        None if listener.local_addr().is_ok_and(|addr| !addr.ip().is_loopback()) => {
+            println!("   ⚠️  Auth: none, on a non-loopback address; set MORPHO_AUTH_TOKEN to require a bearer token")
+        }
+        None => {}
+    }
+
     if project_info_vec.len() > 1 {
         println!("   Dependencies:");
         for info in &project_info_vec[1..] {
@@ -318,10 +2018,418 @@ async fn main() {
     }
 
     println!("\n   Available endpoints:");
-    println!("   GET  /info                    - Get project and dependency information");
+    println!("   GET  /info                    - Get project and dependency information with indexing stats");
+    println!("   GET  /openapi.json            - OpenAPI spec of these endpoints and their parameters");
     println!("   POST /tool/generate_call_graph - Generate call graph from a function");
+    println!("   POST /tool/call_graph_summary  - List the signatures and types a call graph reaches, without the tree");
+    println!("   POST /tool/call_graph_source   - Show the full source of everything a call graph reaches");
+    println!("   POST /tool/find_callers        - Find every function that calls a function");
     println!("   POST /tool/get_source          - Get source code of a function");
+    println!("   POST /tool/find_type_usages    - Find every function that uses a type");
+    println!("   POST /tool/get_type_api        - Get a type's definition, impl blocks and methods");
+    println!("   POST /tool/find_implementors   - List every impl of a trait and the methods each defines");
+    println!("   POST /tool/search              - Find functions and types by name");
+    println!("   POST /tool/find_unreachable    - List functions never reached from the roots (dead code)");
+    println!("   POST /tool/function_metrics    - Rank functions by size, complexity, callees or callers");
+    println!("   POST /tool/audit_unsafe        - List unsafe fns and blocks, and call paths into them from a root");
+    println!("   POST /tool/find_entry_points   - List main functions, exported functions, route handlers, tests and binaries");
+    println!("   POST /tool/public_api          - List what the library exports, following pub use re-exports");
+    println!("   POST /tool/find_cycles         - List mutually recursive functions and the calls closing each loop");
+    println!("   POST /tool/call_edges          - List resolved calls as caller/callee/context edges for graph tools");
+    println!("   POST /tool/find_call_path      - Show the shortest chains of calls from one function to another");
     println!("   POST /tool/list_all            - List all types and functions in project");
+    println!("   POST /tool/reload              - Re-index every project now");
+    println!("   POST /projects                 - Index another directory as a dependency");
+    println!("   DELETE /projects/{{name}}        - Stop indexing a dependency");
+    println!("   GET  /ws                       - WebSocket of re-index events");
+    #[cfg(feature = "grpc")]
+    println!("   gRPC morpho.Agent              - ListAll, GenerateCallGraph and GetSource, streamed");
+
+    match tls {
+        Some(acceptor) => serve_tls(listener, app, acceptor).await,
+        None => axum::serve(listener, app).await.unwrap(),
+    }
+}
+
+// Rejects requests without the configured bearer token with a 401; passes everything when none is set
+async fn require_token(request: axum::extract::Request, next: axum::middleware::Next) -> Response {
+    if authorized(request.headers(), AUTH_TOKEN.get().and_then(Option::as_deref)) {
+        return next.run(request).await;
+    }
+    let error = ErrorResponse {
+        error: "missing or invalid bearer token".to_string(),
+        fingerprint: None,
+        candidates: vec![],
+    };
+    (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], Json(error)).into_response()
+}
+
+// Whether `headers` carry `token` as a bearer token; true for any request when there is no token
+fn authorized(headers: &axum::http::HeaderMap, token: Option<&str>) -> bool {
+    let Some(token) = token else { return true };
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    presented.is_some_and(|presented| tokens_match(presented, token))
+}
+
+// Compares every byte whatever the first mismatch, so response times don't reveal how much of a guess was right
+fn tokens_match(presented: &str, token: &str) -> bool {
+    presented.len() == token.len() && presented.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(feature = "tls")]
+type TlsAcceptor = tokio_rustls::TlsAcceptor;
+#[cfg(not(feature = "tls"))]
+type TlsAcceptor = std::convert::Infallible;
 
-    axum::serve(listener, app).await.unwrap();
+// HTTPS with the PEM certificate chain and private key, over HTTP/1.1 or HTTP/2 as the client prefers
+#[cfg(feature = "tls")]
+fn tls_acceptor(cert: &str, key: &str) -> Result<TlsAcceptor, String> {
+    use tokio_rustls::rustls;
+
+    let read = |path: &str| std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e));
+    let certs = rustls_pemfile::certs(&mut read(cert)?.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid certificate {}: {}", cert, e))?;
+    let private_key = rustls_pemfile::private_key(&mut read(key)?.as_slice())
+        .map_err(|e| format!("invalid private key {}: {}", key, e))?
+        .ok_or_else(|| format!("no private key in {}", key))?;
+    let provider = std::sync::Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, private_key))
+        .map_err(|e| e.to_string())?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(std::sync::Arc::new(config)))
+}
+
+#[cfg(not(feature = "tls"))]
+fn tls_acceptor(_cert: &str, _key: &str) -> Result<TlsAcceptor, String> {
+    Err("this build has no TLS support; rebuild with `--features tls`".to_string())
+}
+
+#[cfg(feature = "tls")]
+async fn serve_tls(listener: tokio::net::TcpListener, app: Router, acceptor: TlsAcceptor) {
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use hyper_util::server::conn::auto::Builder;
+    use hyper_util::service::TowerToHyperService;
+
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("Error accepting connection: {}", e);
+                continue;
+            }
+        };
+        let (acceptor, app) = (acceptor.clone(), app.clone());
+        tokio::spawn(async move {
+            // Failed handshakes (plain HTTP, untrusted certificate) only concern that client
+            let Ok(stream) = acceptor.accept(stream).await else { return };
+            let service = TowerToHyperService::new(app);
+            let _ = Builder::new(TokioExecutor::new()).serve_connection_with_upgrades(TokioIo::new(stream), service).await;
+        });
+    }
+}
+
+#[cfg(not(feature = "tls"))]
+async fn serve_tls(_listener: tokio::net::TcpListener, _app: Router, acceptor: TlsAcceptor) {
+    match acceptor {}
+}
+
+// The list_all, generate_call_graph and get_source tools over gRPC (proto/morpho.proto), for tool platforms
+// that only speak gRPC. Served on the HTTP port, behind the same token and TLS. Every call streams its output
+// as it is rendered, as the HTTP endpoints do with `"stream": true`.
+#[cfg(feature = "grpc")]
+mod grpc {
+    use super::{start_stream, ErrorResponse, SharedProjects};
+    use axum::{http::StatusCode, Json};
+    use futures_util::Stream;
+    use morpho_rs::{Budget, OutputMode, Page, VisibilityFilter};
+    use std::pin::Pin;
+    use tonic::{Request, Response, Status};
+
+    include!(concat!(env!("OUT_DIR"), "/morpho.Agent.rs"));
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ListAllRequest {
+        #[prost(bool, tag = "1")]
+        pub public_only: bool,
+        #[prost(bool, tag = "2")]
+        pub crate_visible: bool,
+        #[prost(string, repeated, tag = "3")]
+        pub blacklist: Vec<String>,
+        #[prost(string, optional, tag = "4")]
+        pub directory: Option<String>,
+        #[prost(string, optional, tag = "5")]
+        pub format: Option<String>,
+        #[prost(bool, tag = "6")]
+        pub hide_items: bool,
+        #[prost(bool, tag = "7")]
+        pub include_docs: bool,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CallGraphRequest {
+        #[prost(string, tag = "1")]
+        pub root_function: String,
+        #[prost(string, repeated, tag = "2")]
+        pub root_functions: Vec<String>,
+        #[prost(bool, tag = "3")]
+        pub public_only: bool,
+        #[prost(bool, tag = "4")]
+        pub crate_visible: bool,
+        #[prost(string, repeated, tag = "5")]
+        pub blacklist: Vec<String>,
+        #[prost(string, optional, tag = "6")]
+        pub directory: Option<String>,
+        #[prost(string, optional, tag = "7")]
+        pub format: Option<String>,
+        #[prost(uint64, optional, tag = "8")]
+        pub max_nodes: Option<u64>,
+        #[prost(uint64, optional, tag = "9")]
+        pub max_bytes: Option<u64>,
+        #[prost(bool, optional, tag = "10")]
+        pub descend_into_deps: Option<bool>,
+        #[prost(bool, tag = "11")]
+        pub show_external: bool,
+        #[prost(string, optional, tag = "12")]
+        pub scope: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SourceRequest {
+        #[prost(string, tag = "1")]
+        pub function: String,
+        #[prost(bool, tag = "2")]
+        pub include_docs: bool,
+        #[prost(string, repeated, tag = "3")]
+        pub blacklist: Vec<String>,
+        #[prost(string, optional, tag = "4")]
+        pub directory: Option<String>,
+        #[prost(string, optional, tag = "5")]
+        pub format: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct OutputChunk {
+        #[prost(bytes = "vec", tag = "1")]
+        pub content: Vec<u8>,
+        #[prost(string, tag = "2")]
+        pub fingerprint: String,
+    }
+
+    type OutputStream = Pin<Box<dyn Stream<Item = Result<OutputChunk, Status>> + Send>>;
+
+    pub fn routes(state: SharedProjects) -> axum::Router {
+        tonic::service::Routes::new(agent_server::AgentServer::new(AgentService { state })).into_axum_router()
+    }
+
+    struct AgentService {
+        state: SharedProjects,
+    }
+
+    impl AgentService {
+        async fn stream(
+            &self,
+            directory: Option<String>,
+            blacklist: Vec<String>,
+            mode: OutputMode,
+            format_name: Option<String>,
+            action: &'static str,
+        ) -> Result<Response<OutputStream>, Status> {
+            let (fingerprint, _, body) =
+                start_stream(&self.state, directory, blacklist, mode, format_name, action).await.map_err(status)?;
+            let chunks = futures_util::stream::unfold((body, Some(fingerprint)), |(mut body, fingerprint)| async move {
+                match (body.recv().await, fingerprint) {
+                    (Some(content), fingerprint) => {
+                        Some((Ok(OutputChunk { content, fingerprint: fingerprint.unwrap_or_default() }), (body, None)))
+                    }
+                    // An empty output is still sent as one chunk, so the fingerprint always arrives
+                    (None, Some(fingerprint)) => Some((Ok(OutputChunk { content: vec![], fingerprint }), (body, None))),
+                    (None, None) => None,
+                }
+            });
+            Ok(Response::new(Box::pin(chunks)))
+        }
+    }
+
+    #[tonic::async_trait]
+    impl agent_server::Agent for AgentService {
+        type ListAllStream = OutputStream;
+        type GenerateCallGraphStream = OutputStream;
+        type GetSourceStream = OutputStream;
+
+        async fn list_all(&self, request: Request<ListAllRequest>) -> Result<Response<OutputStream>, Status> {
+            let req = request.into_inner();
+            let mode = OutputMode::ListAll {
+                visibility: visibility(req.public_only, req.crate_visible),
+                hide_items: req.hide_items,
+                include_docs: req.include_docs,
+                budget: Budget::default(),
+                page: Page::default(),
+            };
+            self.stream(req.directory, req.blacklist, mode, req.format, "listing all").await
+        }
+
+        async fn generate_call_graph(
+            &self,
+            request: Request<CallGraphRequest>,
+        ) -> Result<Response<OutputStream>, Status> {
+            let req = request.into_inner();
+            let mode = OutputMode::CallGraph {
+                roots: std::iter::once(req.root_function).chain(req.root_functions).collect(),
+                visibility: visibility(req.public_only, req.crate_visible),
+                budget: Budget {
+                    max_nodes: req.max_nodes.map(|n| n as usize),
+                    max_bytes: req.max_bytes.map(|n| n as usize),
+                },
+                descend_into_deps: req.descend_into_deps.unwrap_or(true),
+                show_external: req.show_external,
+                scope: req.scope,
+            };
+            self.stream(req.directory, req.blacklist, mode, req.format, "generating call graph").await
+        }
+
+        async fn get_source(&self, request: Request<SourceRequest>) -> Result<Response<OutputStream>, Status> {
+            let req = request.into_inner();
+            let mode = OutputMode::Source { function: req.function, include_docs: req.include_docs };
+            self.stream(req.directory, req.blacklist, mode, req.format, "getting source").await
+        }
+    }
+
+    fn visibility(public_only: bool, crate_visible: bool) -> VisibilityFilter {
+        if public_only {
+            VisibilityFilter::PublicOnly
+        } else if crate_visible {
+            VisibilityFilter::CrateVisible
+        } else {
+            VisibilityFilter::All
+        }
+    }
+
+    // The HTTP error as a gRPC status; the candidates for an ambiguous name go in the message
+    fn status((code, Json(error)): (StatusCode, Json<ErrorResponse>)) -> Status {
+        let message = match error.candidates.is_empty() {
+            true => error.error,
+            false => format!("{} (candidates: {})", error.error, error.candidates.join(", ")),
+        };
+        match code {
+            StatusCode::NOT_FOUND => Status::not_found(message),
+            StatusCode::BAD_REQUEST => Status::invalid_argument(message),
+            _ => Status::internal(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASIC: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/basic");
+
+    fn response(result: &str) -> ToolCallResponse {
+        ToolCallResponse {
+            result: serde_json::Value::String(result.to_string()),
+            fingerprint: String::new(),
+            timings: timings_response(&Timings::default()),
+            cached: false,
+        }
+    }
+
+    // Built by hand rather than with `ProjectSet::new`, so no generation is used up under the response cache
+    fn basic_set(project: Option<Project>) -> ProjectSet {
+        let info = ProjectInfo { full_path: BASIC.to_string(), short_name: "basic".to_string(), is_primary: true };
+        let (infos, projects): (Vec<ProjectInfo>, Vec<Project>) =
+            project.map(|project| (info, project)).into_iter().unzip();
+        ProjectSet { merged: merge_projects(projects.clone()), infos, projects, generation: 0 }
+    }
+
+    #[test]
+    fn response_cache_drops_the_least_recently_used() {
+        let mut cache = ResponseCache { generation: 1, entries: VecDeque::new() };
+        cache.insert(1, "a".to_string(), &response("a"), 2);
+        cache.insert(1, "b".to_string(), &response("b"), 2);
+        assert!(cache.get(1, "a").is_some());
+        cache.insert(1, "c".to_string(), &response("c"), 2);
+        assert!(cache.get(1, "b").is_none());
+        assert_eq!(cache.get(1, "a").map(|cached| cached.result), Some(serde_json::json!("a")));
+        assert!(cache.get(1, "c").is_some());
+    }
+
+    #[test]
+    fn response_cache_holds_for_one_generation() {
+        let mut cache = ResponseCache { generation: 1, entries: VecDeque::new() };
+        cache.insert(1, "a".to_string(), &response("a"), 2);
+        assert!(cache.get(2, "a").is_none());
+        // A request still working on the replaced set neither reads nor fills the cache
+        cache.insert(1, "a".to_string(), &response("a"), 2);
+        assert!(cache.get(1, "a").is_none());
+        assert!(cache.get(2, "a").is_none());
+    }
+
+    #[test]
+    fn cached_respond_shares_entries_between_a_short_name_and_its_path() {
+        let info = ProjectInfo { full_path: BASIC.to_string(), short_name: "basic".to_string(), is_primary: true };
+        let projects = ProjectSet::new(vec![info], vec![load_or_empty(BASIC)]);
+        let cached = |directory: Option<&str>| {
+            let mode = OutputMode::Search { query: "paint".to_string(), limit: None };
+            let response = cached_respond(&projects, directory, &[], mode, None, "searching");
+            response.ok().map(|Json(response)| response.cached)
+        };
+        assert_eq!(cached(Some("basic")), Some(false));
+        assert_eq!(cached(Some(BASIC)), Some(true));
+        assert_eq!(cached(None), Some(false));
+        assert_eq!(cached(None), Some(true));
+    }
+
+    #[test]
+    fn bearer_token_must_match_exactly() {
+        assert!(tokens_match("s3cret", "s3cret"));
+        assert!(!tokens_match("s3creT", "s3cret"));
+        assert!(!tokens_match("s3cret!", "s3cret"));
+
+        let mut headers = axum::http::HeaderMap::new();
+        assert!(authorized(&headers, None));
+        assert!(!authorized(&headers, Some("s3cret")));
+        headers.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert!(authorized(&headers, Some("s3cret")));
+        assert!(!authorized(&headers, Some("other")));
+        headers.insert(header::AUTHORIZATION, "Basic s3cret".parse().unwrap());
+        assert!(!authorized(&headers, Some("s3cret")));
+    }
+
+    #[test]
+    fn index_event_lists_changed_files_and_functions() {
+        let before = load_or_empty(BASIC);
+        let mut after = before.clone();
+        let (name, func) = after.functions.pop_first().expect("the fixture has functions");
+        after.functions.insert(format!("{}_renamed", name), func);
+        let (file, text) = after.sources.iter_mut().next().expect("the fixture has sources");
+        let file = file.clone();
+        text.push_str("// edited\n");
+        after.stats.fingerprint = "edited".to_string();
+
+        let event = index_event("refresh", &basic_set(Some(before.clone())), &basic_set(Some(after)));
+        assert_eq!(event.reason, "refresh");
+        let [changes] = event.projects.as_slice() else { panic!("one project changed") };
+        assert_eq!(changes.name, "basic");
+        assert_eq!(changes.fingerprint, "edited");
+        assert!(matches!(changes.files_changed.as_slice(), [changed] if file.ends_with(changed.as_str())));
+        assert!(matches!(changes.functions_added.as_slice(), [added] if added.ends_with("_renamed")));
+        assert!(matches!(changes.functions_removed.as_slice(), [removed] if name.ends_with(removed.as_str())));
+
+        // The same fingerprint on both sides: nothing to report
+        let event = index_event("reload", &basic_set(Some(before.clone())), &basic_set(Some(before.clone())));
+        assert!(event.projects.is_empty());
+
+        // An added project changed whole
+        let event = index_event("project_added", &basic_set(None), &basic_set(Some(before.clone())));
+        let [changes] = event.projects.as_slice() else { panic!("one project added") };
+        assert_eq!(changes.files_changed.len(), before.sources.len());
+        assert_eq!(changes.functions_added.len(), before.functions.len());
+        assert!(changes.functions_removed.is_empty());
+    }
 }
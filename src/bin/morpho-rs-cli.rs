@@ -1,21 +1,110 @@
 // cli/main.rs
 
-use morpho_rs::{generate_output_with_blacklist, OutputMode, VisibilityFilter};
+use morpho_rs::emit::write_lsif;
+use morpho_rs::export::{export, ExportFormat};
+use morpho_rs::{
+    generate_output_for_project_to, generate_output_to, generate_output_with_format, load_project_with_options, Budget, CfgOptions, LoadOptions,
+    Metric, MorphoError, OutputFormat, OutputMode, Page, VisibilityFilter,
+};
+use notify::{EventKind, RecursiveMode, Watcher};
 use std::env;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("export") {
+        run_export(&args);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("emit") {
+        run_emit(&args);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("semver") {
+        run_semver(&args);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("tui") {
+        run_tui(&args);
+        return;
+    }
+    // `watch <directory> ...` takes the same query arguments as a one-off run
+    let watch = args.get(1).map(String::as_str) == Some("watch");
+    if watch {
+        args.remove(1);
+        if let Some(flag) = args.iter().find(|arg| ["--diff", "--diff-rev", "--max-memory"].contains(&arg.as_str())) {
+            eprintln!("Error: {} can't be used with watch", flag);
+            std::process::exit(1);
+        }
+    }
 
     if args.len() < 2 {
         eprintln!(
-            "Usage: {} <directory> [function] [--source] [--public-only] [--blacklist <paths>]",
+            "Usage: {} <directory> [function] [--source] [--callers] [--usages] [--api] [--implementors] [--search] [--limit <n>] [--offset <n>] [--hide-items] [--docs] [--unreachable] [--metrics [--sort <loc|complexity|callees|fan-in>]] [--unsafe] [--entry-points] [--public-api] [--cycles] [--edges] [--summary] [--bundle] [--attr <name>] [--path-to <function>] [--diff <before-dir> | --diff-rev <git-rev>] [--depth <n>] [--show-external] [--scope <path|module>] [--max-nodes <n>] [--max-bytes <n>] [--public-only] [--crate-visible] [--json] [--format <text|json|mermaid|rustdoc-json>] [--blacklist <paths>] [--exclude <globs>] [--include <globs>] [--no-follow-links] [--gitignore] [--max-file-size <bytes>] [--closure-nodes <n>] [--expand-trait-calls] [--include-tests | --exclude-tests] [--features <names>] [--target-os <os>] [--cfg <names>] [--all-cfgs] [--workspace] [--absolute-paths] [--max-memory <mb>] [--threads <n>] [--profile]",
             args[0]
         );
+        eprintln!("       {} export <directory> --format <jsonl|csv|graphml|graphml-types|html> [--blacklist <paths>]", args[0]);
+        eprintln!("       {} emit <directory> [--format lsif] [--blacklist <paths>]", args[0]);
+        eprintln!("       {} semver <before-dir> <after-dir> [--json] [--workspace] [--blacklist <paths>]  - Rate public API changes major, minor or patch", args[0]);
+        eprintln!("       {} tui <directory> [--blacklist <paths>]  - Browse files, call trees and source in the terminal (tui feature)", args[0]);
+        eprintln!("       {} watch <directory> [function] [flags]  - Re-run the query whenever a .rs file changes, printing how the output changed", args[0]);
         eprintln!("  <directory>           - Directory to analyze");
-        eprintln!("  [function]            - Optional: Function name for call graph or source view");
+        eprintln!("  [function]            - Optional: Function name for call graph or source view; comma-separated names draw one call graph from several roots");
         eprintln!("  --source              - Show source code of function (requires function name)");
+        eprintln!("  --callers             - Show every function that calls the function, directly or transitively");
+        eprintln!("  --usages              - Treat the name as a type and show every function that uses it");
+        eprintln!("  --api                 - Treat the name as a type and show its impl blocks and methods");
+        eprintln!("  --implementors        - Treat the name as a trait and show every impl of it, with the methods each defines or inherits");
+        eprintln!("  --search              - Treat the name as a query and list matching functions and types, best first");
+        eprintln!("  --limit <n>           - With --search, show at most n matches (default 20); when listing, show at most n items");
+        eprintln!("  --offset <n>          - When listing, skip the first n items (by file, then name)");
+        eprintln!("  --hide-items          - When listing, leave out consts, statics and macro_rules! macros");
+        eprintln!("  --docs                - When listing or showing source, put each item's doc comment above it");
+        eprintln!("  --unreachable         - List functions never reached from the comma-separated roots (default: main, pub functions, trait impls)");
+        eprintln!("  --metrics             - Rank functions by lines, cyclomatic complexity, distinct callees and callers (with --limit, the top n)");
+        eprintln!("  --sort <metric>       - With --metrics, rank by loc, complexity (default), callees or fan-in");
+        eprintln!("  --unsafe              - List unsafe fns and unsafe blocks, and the call paths from [function] into them");
+        eprintln!("  --attr <name>         - List the functions with an attribute, e.g., instrument or tokio::main; name \"#[name]\" as [function] for their call graph");
+        eprintln!("  --entry-points        - List likely entry points: main functions, runtime mains, exported functions, route handlers, tests and bin targets");
+        eprintln!("  --public-api          - List what the library exports, by the paths downstream crates use, following pub use re-exports");
+        eprintln!("  --cycles              - List mutually recursive function groups and self-recursive functions, with the calls closing each loop");
+        eprintln!("  --path-to <function>  - Show the shortest chains of calls from [function] to this one");
+        eprintln!("  --edges               - Print resolved calls as caller<TAB>callee<TAB>context lines (from [function] if given), or a JSON array with --json");
+        eprintln!("  --summary             - With [function], list the signatures and types its call graph reaches, by file, without the tree");
+        eprintln!("  --bundle              - With [function], show the full source of every function and type its call graph reaches");
+        eprintln!("  --diff <before-dir>   - Compare against another checkout: functions added, removed and with changed calls (from [function] if given)");
+        eprintln!("  --diff-rev <git-rev>  - Like --diff, comparing against the directory as of a git revision");
+        eprintln!("  --depth <n>           - With --callers, stop after n levels of callers");
+        eprintln!("  --show-external       - In a call graph, also list the calls into other crates and std, marked [external]");
+        eprintln!("  --scope <path|module> - Keep a call graph to the functions under a path or module, e.g., src/server/; calls out of it count as external");
+        eprintln!("  --max-nodes <n>       - Expand at most n call graph functions (nearest the root first) or list at most n items");
+        eprintln!("  --max-bytes <n>       - Cut a call graph or listing down to about n bytes, noting what was left out");
         eprintln!("  --public-only         - Show only public items");
+        eprintln!("  --crate-visible       - Show pub, pub(crate), pub(super) and pub(in path) items, leaving out private ones");
+        eprintln!("  --json                - Emit a structured JSON document instead of text");
+        eprintln!("  --format <fmt>        - Output format: text, json, mermaid (call graphs, callers and call paths only), or rustdoc-json (listings only)");
         eprintln!("  --blacklist <paths>   - Comma-separated list of directories/paths to exclude (e.g., 'target,tests')");
+        eprintln!("  --exclude <globs>     - Comma-separated globs of files to leave out, relative to the directory (e.g., '**/generated/**,*_test.rs')");
+        eprintln!("  --include <globs>     - Comma-separated globs; only matching files are indexed (e.g., 'src/**')");
+        eprintln!("  --no-follow-links     - Don't follow symlinks while walking the directory");
+        eprintln!("  --gitignore           - Also leave out what the directory's .gitignore ignores (its .morphoignore is always read)");
+        eprintln!("  --max-file-size <bytes> - Skip files larger than this, e.g., generated bindings");
+        eprintln!("  --closure-nodes <n>   - Show closures with more than n statements as their own call graph nodes");
+        eprintln!("  --expand-trait-calls  - Fan method calls on values of unknown type out to every implementor of the trait");
+        eprintln!("  --include-tests       - Index test code too (the default)");
+        eprintln!("  --exclude-tests       - Leave out #[test] functions, #[cfg(test)] items, mod tests and test files");
+        eprintln!("  --features <names>    - Evaluate #[cfg(..)] attributes with these comma-separated features enabled, leaving out disabled items");
+        eprintln!("  --target-os <os>      - Evaluate #[cfg(..)] attributes for this OS, e.g., linux or windows (default: this machine's)");
+        eprintln!("  --cfg <names>         - Evaluate #[cfg(..)] attributes with these comma-separated names set, e.g., test,debug_assertions");
+        eprintln!("  --all-cfgs            - Index every item whatever its cfg, noting each function's cfg in listings");
+        eprintln!("  --workspace           - Read Cargo packages (cargo metadata) so items also resolve by crate path, e.g. my_crate::net::connect");
+        eprintln!("  --absolute-paths      - Name files by absolute path instead of relative to the directory");
+        eprintln!("  --max-memory <mb>     - List items in low-memory batches, staying roughly under mb megabytes");
+        eprintln!("  --threads <n>         - Parse files on n threads (default: one per core)");
+        eprintln!("  --profile             - Report time spent per phase and the slowest files on stderr");
         std::process::exit(1);
     }
 
@@ -27,7 +116,54 @@ fn main() {
 
     // Check for flags
     let has_source = args.contains(&"--source".to_string());
+    let has_callers = args.contains(&"--callers".to_string());
+    let has_usages = args.contains(&"--usages".to_string());
+    let has_api = args.contains(&"--api".to_string());
+    let has_implementors = args.contains(&"--implementors".to_string());
+    let has_search = args.contains(&"--search".to_string());
+    let has_unreachable = args.contains(&"--unreachable".to_string());
+    let has_metrics = args.contains(&"--metrics".to_string());
+    let has_unsafe = args.contains(&"--unsafe".to_string());
+    let has_edges = args.contains(&"--edges".to_string());
+    let has_entry_points = args.contains(&"--entry-points".to_string());
+    let has_public_api = args.contains(&"--public-api".to_string());
+    let has_cycles = args.contains(&"--cycles".to_string());
+    let has_summary = args.contains(&"--summary".to_string());
+    let has_bundle = args.contains(&"--bundle".to_string());
+    let show_external = args.contains(&"--show-external".to_string());
+    let include_docs = args.contains(&"--docs".to_string());
     let has_public_only = args.contains(&"--public-only".to_string());
+    let has_crate_visible = args.contains(&"--crate-visible".to_string());
+    let has_profile = args.contains(&"--profile".to_string());
+    let expand_trait_calls = args.contains(&"--expand-trait-calls".to_string());
+    let workspace = args.contains(&"--workspace".to_string());
+    let absolute_paths = args.contains(&"--absolute-paths".to_string());
+    let follow_links = !args.contains(&"--no-follow-links".to_string());
+    let gitignore = args.contains(&"--gitignore".to_string());
+    // The last of --include-tests / --exclude-tests wins, so a wrapper's default can be overridden
+    let exclude_tests = args
+        .iter()
+        .rev()
+        .find_map(|arg| match arg.as_str() {
+            "--include-tests" => Some(false),
+            "--exclude-tests" => Some(true),
+            _ => None,
+        })
+        .unwrap_or(false);
+    let format = if let Some(pos) = args.iter().position(|arg| arg == "--format") {
+        let name = args.get(pos + 1).map(String::as_str).unwrap_or_default();
+        match OutputFormat::from_name(name) {
+            Some(format) => format,
+            None => {
+                eprintln!("Error: unknown output format '{}' (expected text, json, mermaid or rustdoc-json)", name);
+                std::process::exit(1);
+            }
+        }
+    } else if args.contains(&"--json".to_string()) {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Text
+    };
 
     // Parse blacklist
     let blacklist: Vec<String> = if let Some(pos) = args.iter().position(|arg| arg == "--blacklist") {
@@ -45,8 +181,212 @@ fn main() {
         vec![]
     };
 
+    // Parse include and exclude globs
+    let globs = |flag: &str| -> Vec<String> {
+        match args.iter().position(|arg| arg == flag) {
+            Some(pos) => match args.get(pos + 1) {
+                Some(list) => list.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect(),
+                None => {
+                    eprintln!("Error: {} requires a comma-separated list of globs", flag);
+                    std::process::exit(1);
+                }
+            },
+            None => vec![],
+        }
+    };
+    let exclude_globs = globs("--exclude");
+    let include_globs = globs("--include");
+
+    // Parse the build #[cfg(..)] attributes are evaluated for; without any of these every item is indexed
+    let cfg_names = |flag: &str| -> Option<Vec<String>> {
+        args.iter().position(|arg| arg == flag).map(|pos| match args.get(pos + 1) {
+            Some(list) => list.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect(),
+            None => {
+                eprintln!("Error: {} requires a comma-separated list of names", flag);
+                std::process::exit(1);
+            }
+        })
+    };
+    let features = cfg_names("--features");
+    let cfg_flags = cfg_names("--cfg");
+    let target_os = args.iter().position(|arg| arg == "--target-os").map(|pos| match args.get(pos + 1) {
+        Some(os) => os.clone(),
+        None => {
+            eprintln!("Error: --target-os requires an OS name, e.g., linux or windows");
+            std::process::exit(1);
+        }
+    });
+    let cfg = (features.is_some() || cfg_flags.is_some() || target_os.is_some()).then(|| CfgOptions {
+        features: features.unwrap_or_default(),
+        target_os,
+        flags: cfg_flags.unwrap_or_default(),
+    });
+    let all_cfgs = args.contains(&"--all-cfgs".to_string());
+
+    // Parse file size ceiling
+    let max_file_size: Option<u64> = if let Some(pos) = args.iter().position(|arg| arg == "--max-file-size") {
+        match args.get(pos + 1).and_then(|n| n.parse().ok()) {
+            Some(n) => Some(n),
+            None => {
+                eprintln!("Error: --max-file-size requires a size in bytes");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    // Parse closure node threshold
+    let closure_nodes: Option<usize> = if let Some(pos) = args.iter().position(|arg| arg == "--closure-nodes") {
+        match args.get(pos + 1).and_then(|n| n.parse().ok()) {
+            Some(n) => Some(n),
+            None => {
+                eprintln!("Error: --closure-nodes requires a statement count");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    // Parse memory ceiling for batched listing
+    let memory_limit_mb: Option<usize> = if let Some(pos) = args.iter().position(|arg| arg == "--max-memory") {
+        match args.get(pos + 1).and_then(|n| n.parse().ok()) {
+            Some(n) => Some(n),
+            None => {
+                eprintln!("Error: --max-memory requires a size in megabytes");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    // Parse caller depth limit
+    let depth: Option<usize> = if let Some(pos) = args.iter().position(|arg| arg == "--depth") {
+        match args.get(pos + 1).and_then(|n| n.parse().ok()) {
+            Some(n) => Some(n),
+            None => {
+                eprintln!("Error: --depth requires a number of levels");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    // Parse output budget
+    let mut budget = Budget::default();
+    for (flag, field) in [("--max-nodes", &mut budget.max_nodes), ("--max-bytes", &mut budget.max_bytes)] {
+        if let Some(pos) = args.iter().position(|arg| arg == flag) {
+            match args.get(pos + 1).and_then(|n| n.parse().ok()) {
+                Some(n) => *field = Some(n),
+                None => {
+                    eprintln!("Error: {} requires a number", flag);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+
+    // Parse search match limit
+    let limit: Option<usize> = if let Some(pos) = args.iter().position(|arg| arg == "--limit") {
+        match args.get(pos + 1).and_then(|n| n.parse().ok()) {
+            Some(n) => Some(n),
+            None => {
+                eprintln!("Error: --limit requires a number");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    // Parse listing page offset
+    let offset: usize = if let Some(pos) = args.iter().position(|arg| arg == "--offset") {
+        match args.get(pos + 1).and_then(|n| n.parse().ok()) {
+            Some(n) => n,
+            None => {
+                eprintln!("Error: --offset requires a number of items");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        0
+    };
+
+    // Parse the other side of a diff: a directory, or a git revision exported to a temporary directory
+    let diff_rev = args.iter().position(|arg| arg == "--diff-rev").map(|pos| match args.get(pos + 1) {
+        Some(rev) => rev.clone(),
+        None => {
+            eprintln!("Error: --diff-rev requires a git revision");
+            std::process::exit(1);
+        }
+    });
+    let diff_before: Option<String> = if let Some(rev) = &diff_rev {
+        match export_revision(dir, rev) {
+            Ok(before) => Some(before.to_string_lossy().into_owned()),
+            Err(e) => {
+                eprintln!("Error: cannot export {} of {}: {}", rev, dir, e);
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(pos) = args.iter().position(|arg| arg == "--diff") {
+        match args.get(pos + 1) {
+            Some(before) => Some(before.clone()),
+            None => {
+                eprintln!("Error: --diff requires the directory to compare against");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    // Parse the end of a call path
+    let path_to = args.iter().position(|arg| arg == "--path-to").map(|pos| match args.get(pos + 1) {
+        Some(to) => to.clone(),
+        None => {
+            eprintln!("Error: --path-to requires the function the path ends at");
+            std::process::exit(1);
+        }
+    });
+
+    // Parse the attribute to list functions by
+    let attribute = args.iter().position(|arg| arg == "--attr").map(|pos| match args.get(pos + 1) {
+        Some(attribute) => attribute.clone(),
+        None => {
+            eprintln!("Error: --attr requires an attribute name, e.g., instrument");
+            std::process::exit(1);
+        }
+    });
+
+    // Parse the path or module a call graph is kept within
+    let scope = args.iter().position(|arg| arg == "--scope").map(|pos| match args.get(pos + 1) {
+        Some(scope) => scope.clone(),
+        None => {
+            eprintln!("Error: --scope requires a path or module, e.g., src/server/ or server::handlers");
+            std::process::exit(1);
+        }
+    });
+
+    // Parse indexing thread count
+    let threads: Option<usize> = if let Some(pos) = args.iter().position(|arg| arg == "--threads") {
+        match args.get(pos + 1).and_then(|n| n.parse().ok()) {
+            Some(n) => Some(n),
+            None => {
+                eprintln!("Error: --threads requires a thread count");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
     let visibility = if has_public_only {
         VisibilityFilter::PublicOnly
+    } else if has_crate_visible {
+        VisibilityFilter::CrateVisible
     } else {
         VisibilityFilter::All
     };
@@ -59,25 +399,426 @@ fn main() {
         None
     };
 
-    let mode = if let Some(func) = function_name {
+    let mode = if let Some(before_dir) = &diff_before {
+        // How the call structure changed, optionally only below a root function
+        OutputMode::Diff {
+            before_dir: before_dir.clone(),
+            after_dir: dir.clone(),
+            root: function_name.cloned(),
+        }
+    } else if has_metrics {
+        // Refactoring hotspots, highest --sort metric first
+        let sort = match args.iter().position(|arg| arg == "--sort") {
+            Some(pos) => {
+                let name = args.get(pos + 1).map(String::as_str).unwrap_or_default();
+                Metric::from_name(name).unwrap_or_else(|| {
+                    eprintln!("Error: unknown sort '{}' (expected loc, complexity, callees or fan-in)", name);
+                    std::process::exit(1);
+                })
+            }
+            None => Metric::default(),
+        };
+        OutputMode::Metrics { sort, visibility, limit }
+    } else if let Some(to) = path_to {
+        // How the function ends up calling the other one
+        let Some(from) = function_name else {
+            eprintln!("Error: --path-to requires the function the path starts from");
+            std::process::exit(1);
+        };
+        OutputMode::PathBetween { from: from.clone(), to }
+    } else if has_edges {
+        // Flat edge list for graph tools, of the call graph from the function if one is given
+        OutputMode::CallEdges { root: function_name.cloned() }
+    } else if has_public_api {
+        // What downstream crates can name
+        OutputMode::PublicApi
+    } else if has_cycles {
+        // Where calls can recurse
+        OutputMode::Cycles
+    } else if has_entry_points {
+        // Where execution starts
+        OutputMode::EntryPoints
+    } else if has_unsafe {
+        // Unsafe-code audit, with the call paths from the function if one is given
+        OutputMode::UnsafeAudit { root: function_name.cloned() }
+    } else if has_unreachable {
+        // Dead-code audit from the given roots, or the default ones
+        OutputMode::Unreachable {
+            roots: function_name
+                .map(|roots| roots.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                .unwrap_or_default(),
+        }
+    } else if let Some(func) = function_name {
         if has_source {
             // Show source code
             OutputMode::Source {
                 function: func.to_string(),
+                include_docs,
+            }
+        } else if has_search {
+            // Find functions and types by name
+            OutputMode::Search {
+                query: func.to_string(),
+                limit,
+            }
+        } else if has_usages {
+            // Show every function that uses the type
+            OutputMode::TypeUsages {
+                type_name: func.to_string(),
+            }
+        } else if has_api {
+            // Show the type's impl blocks
+            OutputMode::TypeApi {
+                type_name: func.to_string(),
+            }
+        } else if has_implementors {
+            // Show every impl of the trait
+            OutputMode::Implementors {
+                trait_name: func.to_string(),
+            }
+        } else if has_bundle {
+            // Full source of everything the call graph reaches
+            OutputMode::CallGraphSource { roots: split_roots(func) }
+        } else if has_summary {
+            // Reachable signatures and types without the tree
+            OutputMode::CallGraphSummary { roots: split_roots(func), visibility }
+        } else if has_callers {
+            // Show who calls the function
+            OutputMode::Callers {
+                target: func.to_string(),
+                depth,
             }
         } else {
-            // Show call graph
+            // Show call graph, from each of several comma-separated roots
+            // One directory has no dependencies to stop at
             OutputMode::CallGraph {
-                root: func.to_string(),
+                roots: split_roots(func),
                 visibility,
+                budget,
+                descend_into_deps: true,
+                show_external,
+                scope,
             }
         }
+    } else if let Some(attribute) = attribute {
+        // Every function carrying the attribute
+        OutputMode::WithAttribute { attribute, visibility }
     } else {
         // Just directory (no function specified)
-        OutputMode::ListAll { visibility }
+        OutputMode::ListAll {
+            visibility,
+            hide_items: args.contains(&"--hide-items".to_string()),
+            include_docs,
+            budget,
+            page: Page { offset, limit },
+        }
     };
 
-    match generate_output_with_blacklist(dir, mode, &blacklist) {
+    let options = LoadOptions {
+        blacklist,
+        exclude_globs,
+        include_globs,
+        follow_links,
+        gitignore,
+        max_file_size,
+        closure_nodes,
+        memory_limit_mb,
+        threads,
+        expand_trait_calls,
+        exclude_tests,
+        workspace,
+        absolute_paths,
+        cfg,
+        all_cfgs,
+        ..Default::default()
+    };
+
+    if watch {
+        run_watch(dir, mode, &options, format);
+        return;
+    }
+
+    // Written as it is rendered, so a whole-workspace listing never sits in memory
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    let result = generate_output_to(&mut out, dir, mode, &options, format).and_then(|output| {
+        writeln!(out).and_then(|_| out.flush()).map_err(|e| MorphoError::Write(e.to_string()))?;
+        Ok(output)
+    });
+    if let (Some(before), Some(_)) = (&diff_before, &diff_rev) {
+        let _ = std::fs::remove_dir_all(before);
+    }
+    match result {
+        Ok(output) => {
+            if has_profile {
+                eprint!("{}", output.timings.report());
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// How long a burst of file events (an editor saving, a branch switch) must go quiet before the query re-runs
+const WATCH_SETTLE: Duration = Duration::from_millis(200);
+
+// Prints the query's output, then re-indexes the files that change under `dir` and prints how the output
+// changed, until interrupted. A query that fails is reported and the previous output kept for comparison.
+// "run,main" -> ["run", "main"]; commas inside generics and tuples, as in "<Pair as From<(i32, i32)>>::from",
+// don't split
+fn split_roots(names: &str) -> Vec<String> {
+    let mut roots = vec![];
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in names.char_indices() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                roots.push(&names[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    roots.push(&names[start..]);
+    roots.into_iter().map(str::trim).filter(|root| !root.is_empty()).map(str::to_string).collect()
+}
+
+fn run_watch(dir: &str, mode: OutputMode, options: &LoadOptions, format: OutputFormat) {
+    // With absolute paths the load walks the canonical directory, so re-indexing must name files the same way
+    let canonical = std::fs::canonicalize(dir).unwrap_or_else(|e| {
+        eprintln!("Error: cannot read {}: {}", dir, e);
+        std::process::exit(1);
+    });
+    let dir = if options.absolute_paths { canonical.to_string_lossy().into_owned() } else { dir.to_string() };
+    let mut project = load_project_with_options(&dir, options).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    let render = |project: &morpho_rs::Project| -> Result<String, MorphoError> {
+        let mut out = vec![];
+        generate_output_for_project_to(&mut out, project, mode.clone(), format)?;
+        Ok(String::from_utf8_lossy(&out).into_owned())
+    };
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let watcher = notify::recommended_watcher(tx).and_then(|mut watcher| {
+        watcher.watch(&canonical, RecursiveMode::Recursive)?;
+        Ok(watcher)
+    });
+    if let Err(e) = &watcher {
+        eprintln!("Error: cannot watch {}: {}", dir, e);
+        std::process::exit(1);
+    }
+
+    let mut previous = render(&project).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        String::new()
+    });
+    println!("{}", previous);
+    eprintln!("// watching {} for changes to .rs files (Ctrl-C to stop)", dir);
+
+    // Events carry canonical paths; map them back to the directory as the load walked it
+    let changed_files = |event: notify::Result<notify::Event>, changed: &mut std::collections::BTreeSet<_>| {
+        let Ok(event) = event else { return };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        for path in event.paths.iter().filter(|path| path.extension().is_some_and(|ext| ext == "rs")) {
+            if let Ok(relative) = path.strip_prefix(&canonical) {
+                changed.insert(Path::new(&dir).join(relative));
+            }
+        }
+    };
+    while let Ok(event) = rx.recv() {
+        let mut changed = std::collections::BTreeSet::new();
+        changed_files(event, &mut changed);
+        while let Ok(event) = rx.recv_timeout(WATCH_SETTLE) {
+            changed_files(event, &mut changed);
+        }
+        if changed.is_empty() {
+            continue;
+        }
+
+        let changed: Vec<_> = changed.into_iter().collect();
+        project.reindex_files(&dir, &changed, options);
+        let names: Vec<String> = changed.iter().map(|path| project.relative_path(&path.to_string_lossy())).collect();
+        println!("// changed: {}", names.join(", "));
+        match render(&project) {
+            Ok(output) if output == previous => println!("// no change to the output"),
+            Ok(output) => {
+                print!("{}", line_diff(&previous, &output));
+                previous = output;
+            }
+            Err(e) => println!("Error: {}", e),
+        }
+    }
+}
+
+// Lines the output lost (-) and gained (+), in hunks headed by the line they start at in the new output.
+// Lines common to both ends are set aside first, so the line-by-line match only covers the edited middle;
+// a middle too large to match is shown replaced whole.
+fn line_diff(before: &str, after: &str) -> String {
+    const MAX_MATCH_CELLS: usize = 4_000_000;
+
+    let (before, after): (Vec<&str>, Vec<&str>) = (before.lines().collect(), after.lines().collect());
+    let prefix = before.iter().zip(&after).take_while(|(a, b)| a == b).count();
+    let suffix = before[prefix..].iter().rev().zip(after[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&before[prefix..before.len() - suffix], &after[prefix..after.len() - suffix]);
+
+    // Longest common subsequence lengths of every pair of suffixes; not built, so nothing matches, when the
+    // table would be too large
+    let mut common = vec![];
+    if (old.len() + 1).saturating_mul(new.len() + 1) <= MAX_MATCH_CELLS {
+        common = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                common[i][j] =
+                    if old[i] == new[j] { common[i + 1][j + 1] + 1 } else { common[i + 1][j].max(common[i][j + 1]) };
+            }
+        }
+    }
+    let common = |i: usize, j: usize| common.get(i).map_or(0, |row| row[j]);
+
+    let mut diff = String::new();
+    let (mut i, mut j, mut in_hunk) = (0, 0, false);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] && common(i, j) == common(i + 1, j + 1) + 1 {
+            (i, j, in_hunk) = (i + 1, j + 1, false);
+            continue;
+        }
+        if !in_hunk {
+            diff.push_str(&format!("@@ line {} @@\n", prefix + j + 1));
+            in_hunk = true;
+        }
+        if i < old.len() && (j == new.len() || common(i + 1, j) >= common(i, j + 1)) {
+            diff.push_str(&format!("- {}\n", old[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("+ {}\n", new[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+// Writes `dir` as of git revision `rev` into a new temporary directory laid out like `dir` (`git archive`
+// from inside `dir` covers just that subtree), so the working tree and index are left alone
+fn export_revision(dir: &str, rev: &str) -> Result<std::path::PathBuf, String> {
+    use std::process::{Command, Stdio};
+
+    let target = env::temp_dir().join(format!("morpho-rs-diff-{}", std::process::id()));
+    std::fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+    let mut archive = Command::new("git")
+        .args(["archive", "--format=tar", rev, "."])
+        .current_dir(dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("cannot run git: {}", e))?;
+    let unpacked = Command::new("tar")
+        .arg("-x")
+        .current_dir(&target)
+        .stdin(archive.stdout.take().expect("stdout is piped"))
+        .status()
+        .map_err(|e| format!("cannot run tar: {}", e))?;
+    let archived = archive.wait_with_output().map_err(|e| e.to_string())?;
+
+    if !archived.status.success() || !unpacked.success() {
+        let _ = std::fs::remove_dir_all(&target);
+        let stderr = String::from_utf8_lossy(&archived.stderr);
+        return Err(stderr.lines().next().unwrap_or("git archive failed").to_string());
+    }
+    Ok(target)
+}
+
+// `export <directory> --format <fmt>`: the whole project in a machine-readable format on stdout
+fn run_export(args: &[String]) {
+    let Some(dir) = args.get(2).filter(|dir| !dir.starts_with("--")) else {
+        eprintln!("Usage: {} export <directory> --format <jsonl|csv|graphml|graphml-types|html> [--blacklist <paths>]", args[0]);
+        std::process::exit(1);
+    };
+    if !std::path::Path::new(dir).is_dir() {
+        eprintln!("Error: {} is not a directory", dir);
+        std::process::exit(1);
+    }
+
+    let format_name = match args.iter().position(|arg| arg == "--format") {
+        Some(pos) => args.get(pos + 1).map(String::as_str).unwrap_or_default(),
+        None => "jsonl",
+    };
+    let Some(format) = ExportFormat::from_name(format_name) else {
+        eprintln!("Error: unknown export format '{}' (expected jsonl, csv, graphml, graphml-types or html)", format_name);
+        std::process::exit(1);
+    };
+
+    let project = load_export_project(args, dir);
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    if let Err(e) = export(&project, format, &mut out).and_then(|_| out.flush()) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `emit <directory> [--format lsif]`: a code-navigation index of the project on stdout
+fn run_emit(args: &[String]) {
+    let Some(dir) = args.get(2).filter(|dir| !dir.starts_with("--")) else {
+        eprintln!("Usage: {} emit <directory> [--format lsif] [--blacklist <paths>]", args[0]);
+        std::process::exit(1);
+    };
+    if !std::path::Path::new(dir).is_dir() {
+        eprintln!("Error: {} is not a directory", dir);
+        std::process::exit(1);
+    }
+    if let Some(pos) = args.iter().position(|arg| arg == "--format") {
+        let format_name = args.get(pos + 1).map(String::as_str).unwrap_or_default();
+        if format_name != "lsif" {
+            eprintln!("Error: unknown index format '{}' (expected lsif)", format_name);
+            std::process::exit(1);
+        }
+    }
+
+    let project = load_export_project(args, dir);
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    if let Err(e) = write_lsif(&project, dir, &mut out).and_then(|_| out.flush()) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `semver <before-dir> <after-dir>`: how the library's public API changed and the version bump that calls for
+fn run_semver(args: &[String]) {
+    let dirs: Vec<&String> = args.iter().skip(2).take(2).filter(|dir| !dir.starts_with("--")).collect();
+    let [before, after] = dirs[..] else {
+        eprintln!("Usage: {} semver <before-dir> <after-dir> [--json] [--workspace] [--blacklist <paths>]", args[0]);
+        std::process::exit(1);
+    };
+    for dir in [before, after] {
+        if !Path::new(dir).is_dir() {
+            eprintln!("Error: {} is not a directory", dir);
+            std::process::exit(1);
+        }
+    }
+
+    let blacklist: Vec<String> = match args.iter().position(|arg| arg == "--blacklist") {
+        Some(pos) => args
+            .get(pos + 1)
+            .map(|list| list.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default(),
+        None => vec![],
+    };
+    let options = LoadOptions {
+        blacklist,
+        workspace: args.contains(&"--workspace".to_string()),
+        ..Default::default()
+    };
+    let format = if args.contains(&"--json".to_string()) { OutputFormat::Json } else { OutputFormat::Text };
+    let mode = OutputMode::Semver {
+        before_dir: before.clone(),
+        after_dir: after.clone(),
+    };
+    match generate_output_with_format(before, mode, &options, format) {
         Ok(output) => println!("{}", output.content),
         Err(e) => {
             eprintln!("Error: {}", e);
@@ -85,3 +826,73 @@ fn main() {
         }
     }
 }
+
+// `tui <directory>`: the terminal browser, when built with the `tui` feature
+fn run_tui(args: &[String]) {
+    let Some(dir) = args.get(2).filter(|dir| !dir.starts_with("--")) else {
+        eprintln!("Usage: {} tui <directory> [--blacklist <paths>]", args[0]);
+        std::process::exit(1);
+    };
+    if !std::path::Path::new(dir).is_dir() {
+        eprintln!("Error: {} is not a directory", dir);
+        std::process::exit(1);
+    }
+
+    #[cfg(feature = "tui")]
+    if let Err(e) = morpho_rs::tui::run(&load_export_project(args, dir)) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+    #[cfg(not(feature = "tui"))]
+    {
+        eprintln!("Error: this build has no terminal browser; rebuild with `--features tui`");
+        std::process::exit(1);
+    }
+}
+
+// The project `export`, `emit` and `tui` work on, less any --blacklist paths
+fn load_export_project(args: &[String], dir: &str) -> morpho_rs::Project {
+    let blacklist: Vec<String> = match args.iter().position(|arg| arg == "--blacklist") {
+        Some(pos) => args
+            .get(pos + 1)
+            .map(|list| list.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default(),
+        None => vec![],
+    };
+
+    let options = LoadOptions {
+        blacklist,
+        ..Default::default()
+    };
+    match load_project_with_options(dir, &options) {
+        Ok(project) => project,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::line_diff;
+
+    #[test]
+    fn line_diff_matches_lines_between_common_ends() {
+        let diff = line_diff("a\nb\nc\nd\n", "a\nx\nc\nd\ne\n");
+        assert_eq!(diff, "@@ line 2 @@\n- b\n+ x\n@@ line 5 @@\n+ e\n");
+    }
+
+    #[test]
+    fn line_diff_replaces_a_middle_too_large_to_match() {
+        let text = |side: &str| {
+            let lines = (0..2100).map(|i| if i == 1000 { "shared".to_string() } else { format!("{} {}", side, i) });
+            lines.collect::<Vec<_>>().join("\n")
+        };
+        let diff = line_diff(&text("old"), &text("new"));
+        assert_eq!(diff.matches("@@").count(), 2); // one hunk header
+        assert!(diff.starts_with("@@ line 1 @@\n- old 0\n"));
+        assert!(diff.contains("- shared\n") && diff.contains("+ shared\n"));
+        assert!(diff.ends_with("+ new 2099\n"));
+    }
+}
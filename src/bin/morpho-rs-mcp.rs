@@ -0,0 +1,584 @@
+// mcp/main.rs
+//
+// Model Context Protocol server on stdio: one JSON-RPC message per line on stdin, one response per
+// line on stdout. Diagnostics go to stderr, since stdout belongs to the protocol.
+
+use morpho_rs::{
+    fingerprint_dirs_with_options, generate_output_for_project_with_format, load_project_with_options, merge_projects,
+    Budget, LoadOptions, Metric, OutputFormat, OutputMode, Page, Project, VisibilityFilter,
+};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+// Used when the client doesn't say which protocol revision it speaks
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+// Indexes of every project directory; a directory is re-indexed before a tool call when its sources changed
+struct Projects {
+    dirs: Vec<String>,
+    projects: Vec<Project>, // parallel to `dirs`
+    merged: Project,
+}
+
+impl Projects {
+    fn load(dirs: Vec<String>) -> Projects {
+        let projects: Vec<Project> = dirs.iter().map(|dir| load_or_empty(dir)).collect();
+        Projects {
+            merged: merge_projects(projects.clone()),
+            dirs,
+            projects,
+        }
+    }
+
+    fn refresh(&mut self) {
+        let mut changed = false;
+        for (dir, project) in self.dirs.iter().zip(&mut self.projects) {
+            let fingerprint = fingerprint_dirs_with_options(std::slice::from_ref(dir), &LoadOptions::from_env());
+            if fingerprint != project.stats.fingerprint {
+                // Span positions are only read during a load, so earlier loads can release their source text
+                proc_macro2::extra::invalidate_current_thread_spans();
+                *project = load_or_empty(dir);
+                changed = true;
+            }
+        }
+        if changed {
+            self.merged = merge_projects(self.projects.clone());
+        }
+    }
+}
+
+fn load_or_empty(dir: &str) -> Project {
+    load_project_with_options(dir, &LoadOptions::from_env()).unwrap_or_else(|e| {
+        eprintln!("Error indexing {}: {}", dir, e);
+        Project::default()
+    })
+}
+
+fn main() {
+    // Project directories, as for the HTTP agent: CLI args, then MORPHO_PROJECT_DIRS (colon-separated),
+    // then the current directory
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let dirs = if !args.is_empty() {
+        args
+    } else if let Ok(env_dirs) = std::env::var("MORPHO_PROJECT_DIRS") {
+        env_dirs.split(':').map(|s| s.to_string()).collect()
+    } else {
+        vec![".".to_string()]
+    };
+    // Absolute paths are read off canonical directories, so fingerprint checks must walk those too
+    let dirs: Vec<String> = if std::env::var_os("MORPHO_ABSOLUTE_PATHS").is_some() {
+        dirs.into_iter()
+            .map(|dir| std::fs::canonicalize(&dir).map_or(dir, |path| path.to_string_lossy().into_owned()))
+            .collect()
+    } else {
+        dirs
+    };
+
+    let mut projects = Projects::load(dirs);
+    eprintln!(
+        "morpho-rs-mcp: indexed {} ({} functions, {} types)",
+        projects.dirs.join(", "),
+        projects.merged.functions.len(),
+        projects.merged.types.len()
+    );
+
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle_message(&mut projects, &message),
+            Err(e) => Some(error_response(Value::Null, PARSE_ERROR, &format!("Parse error: {}", e))),
+        };
+        if let Some(response) = response {
+            let written = writeln!(stdout, "{}", response).and_then(|_| stdout.flush());
+            if written.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+// The response to a request; None for notifications, which carry no id and get no answer
+fn handle_message(projects: &mut Projects, message: &Value) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let method = message["method"].as_str().unwrap_or_default();
+    let params = &message["params"];
+
+    let result = match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": params["protocolVersion"].as_str().unwrap_or(PROTOCOL_VERSION),
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "morpho-rs", "version": env!("CARGO_PKG_VERSION") },
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+        "tools/call" => call_tool(projects, params),
+        _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+    };
+
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(id, code, &message),
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn tool_definitions() -> Value {
+    let blacklist = json!({
+        "type": "array",
+        "items": { "type": "string" },
+        "description": "Paths to exclude, e.g. [\"target\", \"tests\"]",
+    });
+    let format = json!({
+        "type": "string",
+        "enum": ["text", "json", "mermaid", "rustdoc-json"],
+        "description": "text (default), a structured JSON document, a Mermaid flowchart (call graphs, callers and call paths), or a rustdoc-style JSON document (listings)",
+    });
+    let public_only = json!({ "type": "boolean", "description": "Only public items" });
+    let include_docs = json!({ "type": "boolean", "description": "Show each item's doc comment above it" });
+    let crate_visible = json!({
+        "type": "boolean",
+        "description": "Only items visible outside their module: pub, pub(crate), pub(super) and pub(in path)",
+    });
+
+    // Large crates can outgrow a context window; these cut the output down and say what was left out
+    let max_bytes = json!({
+        "type": "integer",
+        "minimum": 1,
+        "description": "Cut the output down to about this many bytes",
+    });
+
+    json!([
+        {
+            "name": "list_all",
+            "description": "List every type and function signature in the indexed Rust projects, grouped by file",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "public_only": public_only,
+                    "crate_visible": crate_visible,
+                    "blacklist": blacklist,
+                    "format": format,
+                    "max_nodes": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "List at most this many items, by file then name",
+                    },
+                    "max_bytes": max_bytes,
+                    "offset": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "description": "Items to skip, by file then name; a listing that stops early gives the next page's offset",
+                    },
+                    "limit": { "type": "integer", "minimum": 1, "description": "Items in this page" },
+                    "hide_items": {
+                        "type": "boolean",
+                        "description": "Leave out consts, statics and macro_rules! macros",
+                    },
+                    "include_docs": include_docs,
+                    "attribute": {
+                        "type": "string",
+                        "description": "Only the functions with this attribute, e.g., instrument or tokio::main",
+                    },
+                },
+            },
+        },
+        {
+            "name": "generate_call_graph",
+            "description": "Show what a Rust function calls, recursively, as a tree with the types it touches",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "root_function": {
+                        "type": "string",
+                        "description": "Fully qualified function name as shown by list_all, e.g. ./src/lib.rs::run",
+                    },
+                    "root_functions": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "More functions to start from; one types section, then a call tree per root",
+                    },
+                    "public_only": public_only,
+                    "crate_visible": crate_visible,
+                    "blacklist": blacklist,
+                    "format": format,
+                    "max_nodes": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Expand at most this many functions, nearest the root first",
+                    },
+                    "max_bytes": max_bytes,
+                    "descend_into_deps": {
+                        "type": "boolean",
+                        "description": "Follow calls into the other analyzed directories (marked [dep: name]); default true",
+                    },
+                    "show_external": {
+                        "type": "boolean",
+                        "description": "Also list calls into other crates and std, marked [external], e.g., sqlx::query",
+                    },
+                    "scope": {
+                        "type": "string",
+                        "description": "Only follow calls to functions under this path or module, e.g., src/server/ or server::handlers",
+                    },
+                },
+                "required": ["root_function"],
+            },
+        },
+        {
+            "name": "call_graph_summary",
+            "description": "List the signatures and type definitions a Rust function reaches, by file, without the call tree: denser than generate_call_graph",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "root_function": { "type": "string", "description": "Qualified or short function name" },
+                    "root_functions": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "More functions to start from",
+                    },
+                    "public_only": public_only,
+                    "crate_visible": crate_visible,
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+                "required": ["root_function"],
+            },
+        },
+        {
+            "name": "call_graph_source",
+            "description": "Show the full source of every function a Rust function reaches and the types it reaches, by file: the code a change there may need, in one call",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "root_function": { "type": "string", "description": "Qualified or short function name" },
+                    "root_functions": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "More functions to start from",
+                    },
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+                "required": ["root_function"],
+            },
+        },
+        {
+            "name": "find_callers",
+            "description": "Show every function that calls a Rust function, directly or transitively",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "target_function": { "type": "string", "description": "Qualified or short function name" },
+                    "depth": { "type": "integer", "minimum": 1, "description": "Levels of callers to show" },
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+                "required": ["target_function"],
+            },
+        },
+        {
+            "name": "find_type_usages",
+            "description": "Show every Rust function that receives, returns, constructs, matches or mentions a struct, enum or trait",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "type_name": { "type": "string", "description": "Type name, e.g. Config or net::Config" },
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+                "required": ["type_name"],
+            },
+        },
+        {
+            "name": "get_type_api",
+            "description": "Show a Rust struct, enum or trait with every impl block for it (for a trait, every impl of it) and their method signatures",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "type_name": { "type": "string", "description": "Type name, e.g. Config or net::Config" },
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+                "required": ["type_name"],
+            },
+        },
+        {
+            "name": "find_implementors",
+            "description": "List every impl of a Rust trait, by file, with the methods each impl provides, overrides and inherits from the trait's defaults",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "trait_name": { "type": "string", "description": "Trait name, e.g. Shape, net::Transport or From" },
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+                "required": ["trait_name"],
+            },
+        },
+        {
+            "name": "search",
+            "description": "Find Rust functions and types by name, best matches first: exact names, then prefixes, substrings and fuzzy matches",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Name or part of a name, e.g. connect or Client::conn" },
+                    "limit": { "type": "integer", "minimum": 1, "description": "Most matches to return (default 20)" },
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "find_unreachable",
+            "description": "List Rust functions never called, directly or transitively, from the roots: a dead-code audit",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "roots": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Functions to trace from; default every main, pub function and trait impl method",
+                    },
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+            },
+        },
+        {
+            "name": "function_metrics",
+            "description": "Rank Rust functions by lines of code, cyclomatic complexity, distinct callees or distinct callers (fan-in), to find refactoring hotspots",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "sort": {
+                        "type": "string",
+                        "enum": ["complexity", "loc", "callees", "fan-in"],
+                        "description": "Metric to rank by, highest first (default complexity)",
+                    },
+                    "limit": { "type": "integer", "minimum": 1, "description": "Only the top this many functions" },
+                    "public_only": public_only,
+                    "crate_visible": crate_visible,
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+            },
+        },
+        {
+            "name": "audit_unsafe",
+            "description": "List Rust unsafe fns and unsafe blocks by file and, from a root function, the shortest call path into each function holding unsafe code",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "root": { "type": "string", "description": "Function to trace call paths from" },
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+            },
+        },
+        {
+            "name": "find_entry_points",
+            "description": "List where a Rust project's execution starts: main functions, #[tokio::main] and similar, exported #[no_mangle] and extern \"C\" functions, route handlers, tests and bin targets. A good first call on an unfamiliar project",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+            },
+        },
+        {
+            "name": "public_api",
+            "description": "List exactly what a Rust library crate exports, by the paths downstream crates write: pub items reachable through pub modules and pub use re-exports, each with where it is defined",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+            },
+        },
+        {
+            "name": "find_cycles",
+            "description": "Find recursion in a Rust project: groups of functions that call each other in a loop and functions calling themselves, with the calls that close each loop",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+            },
+        },
+        {
+            "name": "find_call_path",
+            "description": "Show how one Rust function ends up calling another: the chains of calls between them with the fewest calls, or that there is none",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "from": { "type": "string", "description": "Function the chains start from, e.g. \"handle_request\"" },
+                    "to": { "type": "string", "description": "Function the chains end at, e.g. \"Db::query\"" },
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+                "required": ["from", "to"],
+            },
+        },
+        {
+            "name": "call_edges",
+            "description": "List resolved Rust calls as a flat edge list, one caller<TAB>callee<TAB>context line per call (or JSON records with format json), for loading into graph tools",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "root_function": { "type": "string", "description": "Only the calls of the call graph from this function; every call when absent" },
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+            },
+        },
+        {
+            "name": "get_source",
+            "description": "Show the source of a Rust function or type",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "function": { "type": "string", "description": "Qualified or short function or type name" },
+                    "include_docs": include_docs,
+                    "blacklist": blacklist,
+                    "format": format,
+                },
+                "required": ["function"],
+            },
+        },
+    ])
+}
+
+// Tool failures (unknown function, bad format) are results with `isError` so the model can see them;
+// only malformed calls are protocol errors
+fn call_tool(projects: &mut Projects, params: &Value) -> Result<Value, (i64, String)> {
+    let name = params["name"].as_str().unwrap_or_default();
+    let args = &params["arguments"];
+    let string_arg = |key: &str| {
+        args[key]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| (INVALID_PARAMS, format!("{} requires a string '{}' argument", name, key)))
+    };
+    // `root_function` and any `root_functions` after it
+    let roots_arg = || {
+        let more = args["root_functions"].as_array().into_iter().flatten().filter_map(Value::as_str);
+        string_arg("root_function").map(|root| std::iter::once(root).chain(more.map(str::to_string)).collect())
+    };
+    let visibility = if args["public_only"].as_bool().unwrap_or(false) {
+        VisibilityFilter::PublicOnly
+    } else if args["crate_visible"].as_bool().unwrap_or(false) {
+        VisibilityFilter::CrateVisible
+    } else {
+        VisibilityFilter::All
+    };
+    let budget = Budget {
+        max_nodes: args["max_nodes"].as_u64().map(|n| n as usize),
+        max_bytes: args["max_bytes"].as_u64().map(|n| n as usize),
+    };
+
+    let mode = match name {
+        "list_all" => match args["attribute"].as_str() {
+            Some(attribute) => OutputMode::WithAttribute { attribute: attribute.to_string(), visibility },
+            None => OutputMode::ListAll {
+                visibility,
+                hide_items: args["hide_items"].as_bool().unwrap_or(false),
+                include_docs: args["include_docs"].as_bool().unwrap_or(false),
+                budget,
+                page: Page {
+                    offset: args["offset"].as_u64().unwrap_or(0) as usize,
+                    limit: args["limit"].as_u64().map(|limit| limit as usize),
+                },
+            },
+        },
+        "generate_call_graph" => OutputMode::CallGraph {
+            roots: roots_arg()?,
+            visibility,
+            budget,
+            descend_into_deps: args["descend_into_deps"].as_bool().unwrap_or(true),
+            show_external: args["show_external"].as_bool().unwrap_or(false),
+            scope: args["scope"].as_str().map(str::to_string),
+        },
+        "call_graph_summary" => OutputMode::CallGraphSummary {
+            roots: roots_arg()?,
+            visibility,
+        },
+        "call_graph_source" => OutputMode::CallGraphSource { roots: roots_arg()? },
+        "find_callers" => OutputMode::Callers {
+            target: string_arg("target_function")?,
+            depth: args["depth"].as_u64().map(|depth| depth as usize),
+        },
+        "get_source" => OutputMode::Source {
+            function: string_arg("function")?,
+            include_docs: args["include_docs"].as_bool().unwrap_or(false),
+        },
+        "find_type_usages" => OutputMode::TypeUsages {
+            type_name: string_arg("type_name")?,
+        },
+        "get_type_api" => OutputMode::TypeApi {
+            type_name: string_arg("type_name")?,
+        },
+        "find_implementors" => OutputMode::Implementors {
+            trait_name: string_arg("trait_name")?,
+        },
+        "search" => OutputMode::Search {
+            query: string_arg("query")?,
+            limit: args["limit"].as_u64().map(|limit| limit as usize),
+        },
+        "find_unreachable" => OutputMode::Unreachable {
+            roots: args["roots"].as_array().into_iter().flatten().filter_map(Value::as_str).map(str::to_string).collect(),
+        },
+        "function_metrics" => OutputMode::Metrics {
+            sort: match args["sort"].as_str() {
+                None => Metric::default(),
+                Some(sort) => Metric::from_name(sort)
+                    .ok_or_else(|| (INVALID_PARAMS, format!("Unknown sort '{}' (expected loc, complexity, callees or fan-in)", sort)))?,
+            },
+            visibility,
+            limit: args["limit"].as_u64().map(|limit| limit as usize),
+        },
+        "audit_unsafe" => OutputMode::UnsafeAudit { root: args["root"].as_str().map(str::to_string) },
+        "find_entry_points" => OutputMode::EntryPoints,
+        "public_api" => OutputMode::PublicApi,
+        "find_cycles" => OutputMode::Cycles,
+        "find_call_path" => OutputMode::PathBetween { from: string_arg("from")?, to: string_arg("to")? },
+        "call_edges" => OutputMode::CallEdges { root: args["root_function"].as_str().map(str::to_string) },
+        _ => return Err((INVALID_PARAMS, format!("Unknown tool: {}", name))),
+    };
+
+    projects.refresh();
+    let blacklist: Vec<&str> = args["blacklist"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+    let filtered;
+    let project = if blacklist.is_empty() {
+        &projects.merged
+    } else {
+        filtered = projects.merged.filter_files(|file| !blacklist.iter().any(|bl| file.contains(bl)));
+        &filtered
+    };
+
+    let format_name = args["format"].as_str().unwrap_or("text");
+    let output = match OutputFormat::from_name(format_name) {
+        Some(format) => generate_output_for_project_with_format(project, mode, format).map_err(|e| e.to_string()),
+        None => Err(format!("Unknown format '{}' (expected text, json, mermaid or rustdoc-json)", format_name)),
+    };
+    Ok(match output {
+        Ok(output) => json!({ "content": [{ "type": "text", "text": output.content }], "isError": false }),
+        Err(e) => json!({ "content": [{ "type": "text", "text": e }], "isError": true }),
+    })
+}
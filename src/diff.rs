@@ -0,0 +1,144 @@
+// How the call structure changed between two loads of a project, e.g., before and after a PR: functions
+// added and removed, and functions whose set of callees changed. Functions are matched by qualified
+// name relative to each load's directory, so two checkouts in different places compare cleanly.
+
+use crate::{function_by_name, trace_calls, Function, MorphoError, Project};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+pub struct CallGraphDiff<'a> {
+    pub added: Vec<&'a Function>, // from the after project
+    pub removed: Vec<&'a Function>, // from the before project
+    pub changed: Vec<ChangedFunction<'a>>,
+}
+
+pub struct ChangedFunction<'a> {
+    pub function: &'a Function, // as it is after the change
+    pub calls_added: Vec<String>, // callees by relative name, e.g., "src/net.rs::connect"
+    pub calls_removed: Vec<String>,
+}
+
+// One side of the comparison: its functions and their callees, keyed by relative name
+struct Side<'a> {
+    functions: BTreeMap<String, &'a Function>,
+    callees: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl<'a> Side<'a> {
+    // With a root, only the functions it reaches count; a root missing on this side reaches nothing
+    fn new(dir: &str, project: &'a Project, root: Option<&str>) -> Result<Side<'a>, MorphoError> {
+        let reached: Option<HashSet<String>> = match root.map(|root| function_by_name(project, root)) {
+            None => None,
+            Some(Ok(root)) => Some(trace_calls(&root.qualified_name, project)?.0),
+            Some(Err(MorphoError::NotFound { .. })) => Some(HashSet::new()),
+            Some(Err(e)) => return Err(e),
+        };
+        let included = |qualified_name: &str| reached.as_ref().is_none_or(|reached| reached.contains(qualified_name));
+
+        let functions: BTreeMap<String, &Function> = project
+            .functions
+            .values()
+            .filter(|func| included(&func.qualified_name))
+            .map(|func| (relative_name(dir, &func.qualified_name), func))
+            .collect();
+        let mut callees: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for edge in project.call_edges_from(functions.values().copied()) {
+            callees.entry(relative_name(dir, &edge.caller)).or_default().insert(relative_name(dir, &edge.callee));
+        }
+        Ok(Side { functions, callees })
+    }
+
+    fn callees_of(&self, name: &str) -> BTreeSet<String> {
+        self.callees.get(name).cloned().unwrap_or_default()
+    }
+}
+
+// Compares the projects loaded from `before_dir` and `after_dir`; with `root`, only the functions it
+// reaches on either side. Every list is in name order.
+pub fn diff_projects<'a>(
+    before_dir: &str,
+    before: &'a Project,
+    after_dir: &str,
+    after: &'a Project,
+    root: Option<&str>,
+) -> Result<CallGraphDiff<'a>, MorphoError> {
+    let before_side = Side::new(before_dir, before, root)?;
+    let after_side = Side::new(after_dir, after, root)?;
+    if let Some(root) = root.filter(|_| before_side.functions.is_empty() && after_side.functions.is_empty()) {
+        return Err(MorphoError::not_found("function", root));
+    }
+
+    let added = after_side
+        .functions
+        .iter()
+        .filter(|(name, _)| !before_side.functions.contains_key(*name))
+        .map(|(_, func)| *func)
+        .collect();
+    let removed = before_side
+        .functions
+        .iter()
+        .filter(|(name, _)| !after_side.functions.contains_key(*name))
+        .map(|(_, func)| *func)
+        .collect();
+    let changed = after_side
+        .functions
+        .iter()
+        .filter(|(name, _)| before_side.functions.contains_key(*name))
+        .filter_map(|(name, func)| {
+            let (old, new) = (before_side.callees_of(name), after_side.callees_of(name));
+            (old != new).then(|| ChangedFunction {
+                function: func,
+                calls_added: new.difference(&old).cloned().collect(),
+                calls_removed: old.difference(&new).cloned().collect(),
+            })
+        })
+        .collect();
+
+    Ok(CallGraphDiff { added, removed, changed })
+}
+
+// "/tmp/before/src/lib.rs::run" -> "src/lib.rs::run" for the directory "/tmp/before"
+pub fn relative_name(dir: &str, qualified_name: &str) -> String {
+    let dir = dir.trim_end_matches('/');
+    qualified_name
+        .strip_prefix(dir)
+        .map_or(qualified_name, |rest| rest.trim_start_matches('/'))
+        .to_string()
+}
+
+// Sections for added, removed and changed functions, each signature under its relative name, and for
+// changed functions the calls gained (+) and lost (-)
+pub(crate) fn render_text(diff: &CallGraphDiff, before_dir: &str, after_dir: &str) -> String {
+    let signature = |dir: &str, func: &Function| func.signature_named(&relative_name(dir, &func.qualified_name));
+    let mut output = format!(
+        "// {} added, {} removed, {} with changed calls\n",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    );
+
+    if !diff.added.is_empty() {
+        output.push_str("=== added ===\n");
+        for func in &diff.added {
+            output.push_str(&format!("{}\n", signature(after_dir, func)));
+        }
+    }
+    if !diff.removed.is_empty() {
+        output.push_str("=== removed ===\n");
+        for func in &diff.removed {
+            output.push_str(&format!("{}\n", signature(before_dir, func)));
+        }
+    }
+    if !diff.changed.is_empty() {
+        output.push_str("=== changed calls ===\n");
+        for changed in &diff.changed {
+            output.push_str(&format!("{}\n", signature(after_dir, changed.function)));
+            for callee in &changed.calls_added {
+                output.push_str(&format!("    + {}\n", callee));
+            }
+            for callee in &changed.calls_removed {
+                output.push_str(&format!("    - {}\n", callee));
+            }
+        }
+    }
+    output
+}
@@ -0,0 +1,267 @@
+// An LSIF index of a project, for code-navigation services such as Sourcegraph: one JSON vertex or edge
+// per line, giving every function, type, const, static and macro a definition range, a hover with its
+// signature and a moniker with its crate path, and every resolved call a reference range at the callee
+// name. Paths in the index become `file://` URIs under the canonical project directory.
+//
+// Functions carry the column of their name. Other items record lines only, so their ranges are found by
+// reading the line that declares them; a range that can't be found starts at column 0.
+
+use crate::export::type_kind;
+use crate::{file_module_path, format_type_item, is_macro_export, is_public, join_path, Project};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use syn::Item;
+
+const LSIF_VERSION: &str = "0.4.3";
+
+pub fn write_lsif(project: &Project, root: &str, out: &mut impl Write) -> std::io::Result<()> {
+    let base = Path::new(root).canonicalize()?;
+    let mut emitter = Emitter { next_id: 0, out };
+    emitter.vertex(json!({
+        "label": "metaData",
+        "version": LSIF_VERSION,
+        "projectRoot": file_uri(&base),
+        "positionEncoding": "utf-16",
+        "toolInfo": { "name": "morpho-rs", "version": env!("CARGO_PKG_VERSION") },
+    }))?;
+    let project_id = emitter.vertex(json!({ "label": "project", "kind": "rust" }))?;
+
+    // Definitions, by file
+    let mut definitions: BTreeMap<&str, Vec<Definition>> = BTreeMap::new();
+    for (name, func) in &project.functions {
+        // Closures have no name to navigate to
+        if name.ends_with('}') {
+            continue;
+        }
+        let file = name.split_once("::").map_or("", |(file, _)| file);
+        let ident = func.sig.ident.to_string();
+        definitions.entry(file).or_default().push(Definition {
+            key: name.clone(),
+            line: func.start_line,
+            column: func.column,
+            length: ident.chars().count(),
+            hover: func.signature_named(&ident),
+            moniker: project.function_package_path(name).or_else(|| project.function_crate_path(name)),
+            exported: is_public(&func.vis),
+        });
+    }
+    let mut lines = SourceLines::default();
+    for (key, (file, item)) in &project.types {
+        let Some((kind, vis)) = type_kind(item) else { continue };
+        let keyword = declaration_keyword(kind);
+        let (start, end) = project.type_lines.get(key).copied().unwrap_or_default();
+        let name = key.rsplit("::").next().unwrap_or(key);
+        let (line, column) = lines.find(file, start, end, keyword, name);
+        definitions.entry(file).or_default().push(Definition {
+            key: format!("{}::{}", file, key),
+            line,
+            column,
+            length: name.chars().count(),
+            hover: format_type_item(item),
+            moniker: project.type_package_path(key).or_else(|| project.type_crate_path(key)),
+            exported: is_public(vis),
+        });
+    }
+    for (key, (file, item)) in &project.items {
+        let (keyword, exported) = match item {
+            Item::Const(c) => ("const", is_public(&c.vis)),
+            Item::Static(s) => ("static", is_public(&s.vis)),
+            Item::Macro(m) => ("macro_rules!", is_macro_export(m)),
+            _ => continue,
+        };
+        let (start, end) = project.item_lines.get(key).copied().unwrap_or_default();
+        let name = key.rsplit("::").next().unwrap_or(key);
+        let (line, column) = lines.find(file, start, end, keyword, name);
+        definitions.entry(file).or_default().push(Definition {
+            key: format!("{}::{}", file, key),
+            line,
+            column,
+            length: name.chars().count(),
+            hover: format_type_item(item),
+            moniker: Some(join_path(&file_module_path(file), key)),
+            exported,
+        });
+    }
+
+    // References, by the caller's file; only calls into indexed functions can be navigated
+    let mut references: BTreeMap<&str, Vec<(usize, usize, &str)>> = BTreeMap::new();
+    let edges = project.call_edges();
+    for edge in &edges {
+        if edge.line == 0 || edge.callee.ends_with('}') {
+            continue;
+        }
+        let file = edge.caller.split_once("::").map_or("", |(file, _)| file);
+        references.entry(file).or_default().push((edge.line, edge.column, edge.callee.as_str()));
+    }
+    for calls in references.values_mut() {
+        calls.sort_unstable();
+        calls.dedup();
+    }
+
+    let mut files: Vec<&str> = definitions.keys().chain(references.keys()).copied().collect();
+    files.sort_unstable();
+    files.dedup();
+
+    // Result sets first, so references in any document can point at them
+    let mut result_sets: BTreeMap<String, ResultSet> = BTreeMap::new();
+    for definition in definitions.values().flatten() {
+        let set = emitter.vertex(json!({ "label": "resultSet" }))?;
+        let hover = emitter.vertex(json!({
+            "label": "hoverResult",
+            "result": { "contents": [{ "language": "rust", "value": definition.hover }] },
+        }))?;
+        emitter.edge("textDocument/hover", set, hover)?;
+        if let Some(identifier) = &definition.moniker {
+            let kind = if definition.exported { "export" } else { "local" };
+            let moniker =
+                emitter.vertex(json!({ "label": "moniker", "scheme": "rust", "identifier": identifier, "kind": kind }))?;
+            emitter.edge("moniker", set, moniker)?;
+        }
+        let definition_result = emitter.vertex(json!({ "label": "definitionResult" }))?;
+        emitter.edge("textDocument/definition", set, definition_result)?;
+        let reference_result = emitter.vertex(json!({ "label": "referenceResult" }))?;
+        emitter.edge("textDocument/references", set, reference_result)?;
+        result_sets.insert(definition.key.clone(), ResultSet { id: set, definition_result, reference_result });
+    }
+
+    let mut documents = vec![];
+    for file in files {
+        let path = Path::new(file).strip_prefix(root).unwrap_or(Path::new(file));
+        let uri = file_uri(&base.join(path));
+        let document = emitter.vertex(json!({ "label": "document", "uri": uri, "languageId": "rust" }))?;
+        documents.push(document);
+
+        let mut ranges = vec![];
+        for definition in definitions.get(file).into_iter().flatten() {
+            let range = emitter.range(definition.line, definition.column, definition.length)?;
+            let set = &result_sets[&definition.key];
+            emitter.edge("next", range, set.id)?;
+            emitter.item(set.definition_result, &[range], document, None)?;
+            emitter.item(set.reference_result, &[range], document, Some("definitions"))?;
+            ranges.push(range);
+        }
+        for &(line, column, callee) in references.get(file).into_iter().flatten() {
+            let Some(set) = result_sets.get(callee) else { continue };
+            let name = callee.rsplit("::").next().unwrap_or(callee);
+            let range = emitter.range(line, column, name.chars().count())?;
+            emitter.edge("next", range, set.id)?;
+            emitter.item(set.reference_result, &[range], document, Some("references"))?;
+            ranges.push(range);
+        }
+        if !ranges.is_empty() {
+            emitter.edges("contains", document, &ranges)?;
+        }
+    }
+    if !documents.is_empty() {
+        emitter.edges("contains", project_id, &documents)?;
+    }
+    Ok(())
+}
+
+struct Definition {
+    key: String, // qualified name of a function, or "file::key" of a type or item
+    line: usize, // 1-based line and column of the name; column 0 if unknown
+    column: usize,
+    length: usize,
+    hover: String,
+    moniker: Option<String>,
+    exported: bool,
+}
+
+struct ResultSet {
+    id: usize,
+    definition_result: usize,
+    reference_result: usize,
+}
+
+struct Emitter<'a, W: Write> {
+    next_id: usize,
+    out: &'a mut W,
+}
+
+impl<W: Write> Emitter<'_, W> {
+    fn emit(&mut self, kind: &str, mut element: Value) -> std::io::Result<usize> {
+        self.next_id += 1;
+        element["id"] = json!(self.next_id);
+        element["type"] = json!(kind);
+        writeln!(self.out, "{}", element)?;
+        Ok(self.next_id)
+    }
+
+    fn vertex(&mut self, vertex: Value) -> std::io::Result<usize> {
+        self.emit("vertex", vertex)
+    }
+
+    fn edge(&mut self, label: &str, out_v: usize, in_v: usize) -> std::io::Result<usize> {
+        self.emit("edge", json!({ "label": label, "outV": out_v, "inV": in_v }))
+    }
+
+    fn edges(&mut self, label: &str, out_v: usize, in_vs: &[usize]) -> std::io::Result<usize> {
+        self.emit("edge", json!({ "label": label, "outV": out_v, "inVs": in_vs }))
+    }
+
+    fn item(&mut self, out_v: usize, in_vs: &[usize], document: usize, property: Option<&str>) -> std::io::Result<usize> {
+        let mut edge = json!({ "label": "item", "outV": out_v, "inVs": in_vs, "document": document });
+        if let Some(property) = property {
+            edge["property"] = json!(property);
+        }
+        self.emit("edge", edge)
+    }
+
+    // LSIF positions are 0-based
+    fn range(&mut self, line: usize, column: usize, length: usize) -> std::io::Result<usize> {
+        let (line, character) = (line.saturating_sub(1), column.saturating_sub(1));
+        self.vertex(json!({
+            "label": "range",
+            "start": { "line": line, "character": character },
+            "end": { "line": line, "character": character + length },
+        }))
+    }
+}
+
+fn declaration_keyword(kind: &str) -> &'static str {
+    match kind {
+        "struct" => "struct",
+        "enum" => "enum",
+        "trait" => "trait",
+        _ => "type",
+    }
+}
+
+// Source files read on demand, to place names the index only has lines for
+#[derive(Default)]
+struct SourceLines {
+    files: BTreeMap<String, Vec<String>>,
+}
+
+impl SourceLines {
+    // 1-based line and column of `name` after `keyword` within lines `start..=end`; the start line and column 0
+    // when the declaration can't be found
+    fn find(&mut self, file: &str, start: usize, end: usize, keyword: &str, name: &str) -> (usize, usize) {
+        let lines = self.files.entry(file.to_string()).or_insert_with(|| {
+            std::fs::read_to_string(file).map(|text| text.lines().map(str::to_string).collect()).unwrap_or_default()
+        });
+        let declared = format!("{} {}", keyword, name);
+        for (number, text) in lines.iter().enumerate().take(end).skip(start.saturating_sub(1)) {
+            if let Some(at) = text.find(&declared) {
+                let column = text[..at + keyword.len() + 1].chars().count() + 1;
+                return (number + 1, column);
+            }
+        }
+        (start, 0)
+    }
+}
+
+// Percent-encodes everything but unreserved characters and separators
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
@@ -0,0 +1,289 @@
+// Machine-readable exports of a whole project, for loading into other tools (jq, DuckDB, ...)
+
+use crate::report::write_html_report;
+use crate::{format_type_item, visibility_text, CallEdge, Function, Project};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io::Write;
+use syn::{Item, Visibility};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Jsonl, // one JSON object per function and per type
+    Csv,   // call edges: caller,callee,context,file,line
+    Graphml,      // call graph, for Gephi and yEd
+    GraphmlTypes, // type-dependency graph
+    Html,         // browsable report, see `report::write_html_report`
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        match name {
+            "jsonl" => Some(ExportFormat::Jsonl),
+            "csv" => Some(ExportFormat::Csv),
+            "graphml" => Some(ExportFormat::Graphml),
+            "graphml-types" => Some(ExportFormat::GraphmlTypes),
+            "html" => Some(ExportFormat::Html),
+            _ => None,
+        }
+    }
+}
+
+pub fn export(project: &Project, format: ExportFormat, out: &mut impl Write) -> std::io::Result<()> {
+    match format {
+        ExportFormat::Jsonl => write_jsonl(project, out),
+        ExportFormat::Csv => write_csv_edges(project, out),
+        ExportFormat::Graphml => write_graphml_calls(project, out),
+        ExportFormat::GraphmlTypes => write_graphml_types(project, out),
+        ExportFormat::Html => write_html_report(project, out),
+    }
+}
+
+// Functions first, then types, each in name order. Every record has the same fields:
+// name, kind, file, start_line, end_line, signature, visibility, callees.
+pub fn write_jsonl(project: &Project, out: &mut impl Write) -> std::io::Result<()> {
+    let mut callees: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let edges: Vec<CallEdge> = project.call_edges();
+    for edge in &edges {
+        let list = callees.entry(edge.caller.as_str()).or_default();
+        let callee = project.relative_path(&edge.callee);
+        if !list.contains(&callee) {
+            list.push(callee);
+        }
+    }
+
+    for (name, func) in &project.functions {
+        let shown = project.relative_path(name);
+        let record = json!({
+            "name": shown,
+            "kind": function_kind(func),
+            "file": shown.split_once("::").map_or("", |(file, _)| file),
+            "start_line": func.start_line,
+            "end_line": func.end_line,
+            "signature": project.signature(func),
+            "visibility": visibility_name(&func.vis),
+            "callees": callees.get(name.as_str()).cloned().unwrap_or_default(),
+        });
+        writeln!(out, "{}", record)?;
+    }
+
+    for (name, (file, item)) in &project.types {
+        let (start_line, end_line) = project.type_lines.get(name).copied().unwrap_or_default();
+        let Some((kind, vis)) = type_kind(item) else { continue };
+        let record = json!({
+            "name": name,
+            "kind": kind,
+            "file": project.relative_path(file),
+            "start_line": start_line,
+            "end_line": end_line,
+            "signature": format_type_item(item),
+            "visibility": visibility_name(vis),
+            "callees": Vec::<String>::new(),
+        });
+        writeln!(out, "{}", record)?;
+    }
+
+    Ok(())
+}
+
+// One row per resolved call with a header row; `file` and `line` locate the call in the caller
+pub fn write_csv_edges(project: &Project, out: &mut impl Write) -> std::io::Result<()> {
+    writeln!(out, "caller,callee,context,file,line")?;
+    for edge in project.call_edges() {
+        let (caller, callee) = (project.relative_path(&edge.caller), project.relative_path(&edge.callee));
+        let file = caller.split_once("::").map_or("", |(file, _)| file);
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            csv_field(&caller),
+            csv_field(&callee),
+            csv_field(edge.context.as_deref().unwrap_or_default()),
+            csv_field(file),
+            edge.line
+        )?;
+    }
+    Ok(())
+}
+
+// RFC 4180 quoting: fields with commas, quotes or line breaks are quoted, quotes doubled
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Directed call graph: one node per function (file, visibility, kind, complexity, lines) and one
+// edge per resolved call (context, line). Node ids are qualified names.
+pub fn write_graphml_calls(project: &Project, out: &mut impl Write) -> std::io::Result<()> {
+    write_graphml_header(
+        out,
+        &[
+            ("node", "label", "string"),
+            ("node", "file", "string"),
+            ("node", "visibility", "string"),
+            ("node", "kind", "string"),
+            ("node", "complexity", "int"),
+            ("node", "start_line", "int"),
+            ("node", "end_line", "int"),
+            ("edge", "context", "string"),
+            ("edge", "line", "int"),
+        ],
+    )?;
+
+    for (name, func) in &project.functions {
+        let name = &project.relative_path(name);
+        let file = name.split_once("::").map_or("", |(file, _)| file);
+        write_graphml_node(
+            out,
+            name,
+            &[
+                ("label", name.strip_prefix(&format!("{}::", file)).unwrap_or(name).to_string()),
+                ("file", file.to_string()),
+                ("visibility", visibility_name(&func.vis)),
+                ("kind", function_kind(func).to_string()),
+                ("complexity", func.complexity().to_string()),
+                ("start_line", func.start_line.to_string()),
+                ("end_line", func.end_line.to_string()),
+            ],
+        )?;
+    }
+
+    let edges = project.call_edges();
+    for (i, edge) in edges.iter().enumerate() {
+        let mut data = vec![("line", edge.line.to_string())];
+        if let Some(context) = &edge.context {
+            data.insert(0, ("context", context.clone()));
+        }
+        let (caller, callee) = (project.relative_path(&edge.caller), project.relative_path(&edge.callee));
+        write_graphml_edge(out, i, &caller, &callee, &data)?;
+    }
+
+    writeln!(out, "  </graph>\n</graphml>")
+}
+
+// Directed type-dependency graph: one node per struct, enum, trait and type alias (file, visibility,
+// kind, lines) and one edge per dependency, labelled with its relation ("field", "implements", ...)
+pub fn write_graphml_types(project: &Project, out: &mut impl Write) -> std::io::Result<()> {
+    write_graphml_header(
+        out,
+        &[
+            ("node", "label", "string"),
+            ("node", "file", "string"),
+            ("node", "visibility", "string"),
+            ("node", "kind", "string"),
+            ("node", "start_line", "int"),
+            ("node", "end_line", "int"),
+            ("edge", "relation", "string"),
+        ],
+    )?;
+
+    for (name, (file, item)) in &project.types {
+        let (start_line, end_line) = project.type_lines.get(name).copied().unwrap_or_default();
+        let Some((kind, vis)) = type_kind(item) else { continue };
+        write_graphml_node(
+            out,
+            name,
+            &[
+                ("label", name.rsplit("::").next().unwrap_or(name).to_string()),
+                ("file", project.relative_path(file)),
+                ("visibility", visibility_name(vis)),
+                ("kind", kind.to_string()),
+                ("start_line", start_line.to_string()),
+                ("end_line", end_line.to_string()),
+            ],
+        )?;
+    }
+
+    for (i, edge) in project.type_dependencies().iter().enumerate() {
+        write_graphml_edge(out, i, &edge.from, &edge.to, &[("relation", edge.relation.to_string())])?;
+    }
+
+    writeln!(out, "  </graph>\n</graphml>")
+}
+
+// XML prolog, one <key> per (element, attribute, type) and the opening <graph>; key ids are attribute names
+fn write_graphml_header(out: &mut impl Write, keys: &[(&str, &str, &str)]) -> std::io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+    for (element, name, ty) in keys {
+        writeln!(
+            out,
+            r#"  <key id="{}" for="{}" attr.name="{}" attr.type="{}"/>"#,
+            name, element, name, ty
+        )?;
+    }
+    writeln!(out, r#"  <graph id="G" edgedefault="directed">"#)
+}
+
+fn write_graphml_node(out: &mut impl Write, id: &str, data: &[(&str, String)]) -> std::io::Result<()> {
+    writeln!(out, r#"    <node id="{}">"#, xml_escape(id))?;
+    write_graphml_data(out, data)?;
+    writeln!(out, "    </node>")
+}
+
+fn write_graphml_edge(
+    out: &mut impl Write,
+    index: usize,
+    source: &str,
+    target: &str,
+    data: &[(&str, String)],
+) -> std::io::Result<()> {
+    writeln!(
+        out,
+        r#"    <edge id="e{}" source="{}" target="{}">"#,
+        index,
+        xml_escape(source),
+        xml_escape(target)
+    )?;
+    write_graphml_data(out, data)?;
+    writeln!(out, "    </edge>")
+}
+
+fn write_graphml_data(out: &mut impl Write, data: &[(&str, String)]) -> std::io::Result<()> {
+    for (key, value) in data {
+        writeln!(out, r#"      <data key="{}">{}</data>"#, key, xml_escape(value))?;
+    }
+    Ok(())
+}
+
+// Escapes text and attribute values; qualified names contain `<`/`>` for generics and closures use `{}`
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+pub(crate) fn function_kind(func: &Function) -> &'static str {
+    if func.qualified_name.ends_with('}') {
+        "closure"
+    } else if func.impl_generics.is_some() {
+        "method"
+    } else {
+        "function"
+    }
+}
+
+// Kind and visibility of an indexed type item
+pub(crate) fn type_kind(item: &Item) -> Option<(&'static str, &Visibility)> {
+    match item {
+        Item::Struct(s) => Some(("struct", &s.vis)),
+        Item::Union(u) => Some(("union", &u.vis)),
+        Item::Enum(e) => Some(("enum", &e.vis)),
+        Item::Trait(t) => Some(("trait", &t.vis)),
+        Item::Type(t) => Some(("type_alias", &t.vis)),
+        _ => None,
+    }
+}
+
+// "pub", "pub(crate)", "pub(in crate::net)" or "private"
+pub(crate) fn visibility_name(vis: &Visibility) -> String {
+    match vis {
+        Visibility::Inherited => "private".to_string(),
+        vis => visibility_text(vis),
+    }
+}
@@ -0,0 +1,641 @@
+// Structured JSON for every output mode, for clients that would otherwise have to parse the text format.
+// Each document names its mode and carries the index fingerprint; functions and types carry their file
+// line span and name column (and their crate path when Cargo packages were read), and call edges their
+// context, line and column.
+
+use crate::diff::{relative_name, CallGraphDiff};
+use crate::export::{function_kind, type_kind, visibility_name};
+use crate::query::{ItemRef, TypeDef};
+use crate::semver::SemverReport;
+use crate::{
+    combine_fingerprints, diff_needs_directories, fit_budget, format_impl_const, format_impl_type, format_type_item, impl_header,
+    item_source, is_macro_export, trait_impl_segment,
+    item_matches_visibility_filter, matches_visibility_filter, CallEdge, Function, Metric, MorphoError, OutputMode,
+    Project, Budget, Page, TraceLimits, VisibilityFilter, DEFAULT_SEARCH_LIMIT,
+};
+use syn::Item;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+pub(crate) fn render(project: &Project, mode: OutputMode) -> Result<Value, MorphoError> {
+    let mut document = match mode {
+        OutputMode::ListAll { visibility, hide_items, include_docs, budget, page } => {
+            list_all(project, visibility, hide_items, include_docs, budget, page)?
+        }
+        OutputMode::CallGraph { roots, visibility, budget, descend_into_deps, scope, .. } => {
+            let limits = TraceLimits { stop_at_deps: !descend_into_deps, scope };
+            call_graph(project, &roots, visibility, budget, &limits)?
+        }
+        OutputMode::Callers { target, depth } => callers(project, &target, depth)?,
+        OutputMode::Source { function, include_docs } => source(project, &function, include_docs)?,
+        OutputMode::TypeUsages { type_name } => type_usages(project, &type_name)?,
+        OutputMode::TypeApi { type_name } => type_api(project, &type_name)?,
+        OutputMode::Implementors { trait_name } => implementors(project, &trait_name)?,
+        OutputMode::Search { query, limit } => search(project, &query, limit)?,
+        OutputMode::Unreachable { roots } => unreachable(project, &roots)?,
+        OutputMode::Metrics { sort, visibility, limit } => metrics(project, sort, visibility, limit),
+        OutputMode::UnsafeAudit { root } => unsafe_audit(project, root.as_deref())?,
+        OutputMode::CallEdges { root } => call_edges(project, root.as_deref())?,
+        OutputMode::PathBetween { from, to } => path_between(project, &from, &to)?,
+        OutputMode::CallGraphSummary { roots, visibility } => call_graph_summary(project, &roots, visibility)?,
+        OutputMode::CallGraphSource { roots } => call_graph_source(project, &roots)?,
+        OutputMode::WithAttribute { attribute, visibility } => with_attribute(project, &attribute, visibility),
+        OutputMode::EntryPoints => entry_points(project),
+        OutputMode::PublicApi => public_api(project),
+        OutputMode::Cycles => cycles(project),
+        OutputMode::Diff { .. } | OutputMode::Semver { .. } => return Err(diff_needs_directories()),
+    };
+    document["fingerprint"] = json!(project.stats.fingerprint);
+    Ok(document)
+}
+
+// Functions, types and other items in listing order, by file then name, with `include_docs` each with its
+// `docs` (null when undocumented). A budget keeps the first items of the page.
+fn list_all(
+    project: &Project,
+    visibility: VisibilityFilter,
+    hide_items: bool,
+    include_docs: bool,
+    budget: Budget,
+    page: Page,
+) -> Result<Value, MorphoError> {
+    let reexports = project.reexport_paths();
+    let reexported_as = |crate_path: Option<String>| {
+        crate_path.and_then(|path| reexports.get(&path).cloned()).unwrap_or_default()
+    };
+    let paged = page.offset > 0 || page.limit.is_some();
+    let page = project.list_page(visibility, hide_items, page.offset, page.limit)?;
+    let total = page.items.len();
+
+    fit_budget(budget, total, document_size, |limit| {
+        let shown = limit.unwrap_or(total);
+        let (mut functions, mut types, mut items) = (vec![], vec![], vec![]);
+        for item in &page.items[..shown] {
+            let with_docs = |mut record: Value| {
+                if include_docs {
+                    record["docs"] = json!(item.docs());
+                }
+                record
+            };
+            match item {
+                ItemRef::Function(func) => {
+                    let mut record = function_record(project, func);
+                    record["reexported_as"] = json!(reexported_as(project.function_crate_path(&func.qualified_name)));
+                    functions.push(with_docs(record));
+                }
+                ItemRef::Type(ty) => {
+                    if let Some(mut record) = type_record(project, *ty) {
+                        record["reexported_as"] = json!(reexported_as(project.type_crate_path(ty.key)));
+                        types.push(with_docs(record));
+                    }
+                }
+                ItemRef::Item(it) => items.push(with_docs(item_record(project, *it))),
+            }
+        }
+        let mut document = json!({ "mode": "list_all", "functions": functions, "types": types, "items": items });
+        if limit.is_some() {
+            document["truncated"] = json!({ "shown": shown, "total": total });
+        }
+        if paged || limit.is_some() {
+            let end = page.offset + shown;
+            let next_offset = (end < page.total).then_some(end);
+            document["page"] = json!({ "offset": page.offset, "total": page.total, "next_offset": next_offset });
+        }
+        Ok(document)
+    })
+}
+
+fn document_size(document: &Value) -> usize {
+    serde_json::to_string_pretty(document).map_or(0, |text| text.len())
+}
+
+// Reachable functions and types plus every call edge between reachable functions; `root` is the first of
+// `roots`. A budget keeps the functions nearest the roots, breadth-first, and the calls made by them.
+fn call_graph(
+    project: &Project,
+    roots: &[String],
+    visibility: VisibilityFilter,
+    budget: Budget,
+    limits: &TraceLimits,
+) -> Result<Value, MorphoError> {
+    let graph = project.call_graph_limited(roots, limits)?;
+    let types: Vec<Value> = graph
+        .types
+        .iter()
+        .filter(|ty| item_matches_visibility_filter(ty.item, visibility))
+        .filter_map(|ty| type_record(project, *ty))
+        .collect();
+    let order = graph.breadth_first();
+
+    fit_budget(budget, order.len(), document_size, |limit| {
+        let kept: HashSet<&str> =
+            order.iter().take(limit.unwrap_or(order.len())).map(|func| func.qualified_name.as_str()).collect();
+        let functions: Vec<Value> = graph
+            .functions
+            .iter()
+            .filter(|func| kept.contains(func.qualified_name.as_str()))
+            .filter(|func| matches_visibility_filter(&func.vis, visibility))
+            .map(|func| function_record(project, func))
+            .collect();
+        // Calls into another merged directory name it as `dep`
+        let edges: Vec<Value> = graph
+            .edges
+            .iter()
+            .filter(|edge| kept.contains(edge.caller.as_str()))
+            .map(|edge| {
+                let mut record = edge_record(project, edge);
+                if let Some(dep) = project.dependency_name(&edge.caller, &edge.callee) {
+                    record["dep"] = json!(dep);
+                }
+                record
+            })
+            .collect();
+
+        let mut document = json!({
+            "mode": "call_graph",
+            "root": project.relative_path(&graph.roots[0].qualified_name),
+            "roots": graph.roots.iter().map(|root| project.relative_path(&root.qualified_name)).collect::<Vec<_>>(),
+            "functions": functions,
+            "types": types,
+            "edges": edges,
+        });
+        if limit.is_some() {
+            document["truncated"] = json!({ "shown": kept.len(), "total": order.len() });
+        }
+        Ok(document)
+    })
+}
+
+// The call graph's functions and types without its edges, each as a listing records it
+fn call_graph_summary(project: &Project, roots: &[String], visibility: VisibilityFilter) -> Result<Value, MorphoError> {
+    let graph = project.call_graph_from(roots)?;
+    let (mut functions, mut types) = (vec![], vec![]);
+    for item in graph.summary(visibility) {
+        match item {
+            ItemRef::Function(func) => functions.push(function_record(project, func)),
+            ItemRef::Type(ty) => types.extend(type_record(project, ty)),
+            ItemRef::Item(_) => {}
+        }
+    }
+    Ok(json!({
+        "mode": "call_graph_summary",
+        "roots": graph.roots.iter().map(|root| project.relative_path(&root.qualified_name)).collect::<Vec<_>>(),
+        "functions": functions,
+        "types": types,
+    }))
+}
+
+// Each reached function's record with its `source`, and each reached type's with its definition
+fn call_graph_source(project: &Project, roots: &[String]) -> Result<Value, MorphoError> {
+    let graph = project.call_graph_from(roots)?;
+    let (mut functions, mut types) = (vec![], vec![]);
+    for item in graph.summary(VisibilityFilter::All) {
+        match item {
+            ItemRef::Function(func) => {
+                let mut record = function_record(project, func);
+                record["source"] = json!(item_source(project, item));
+                functions.push(record);
+            }
+            ItemRef::Type(ty) => types.extend(type_record(project, ty).map(|mut record| {
+                record["source"] = json!(item_source(project, item));
+                record
+            })),
+            ItemRef::Item(_) => {}
+        }
+    }
+    Ok(json!({
+        "mode": "call_graph_source",
+        "roots": graph.roots.iter().map(|root| project.relative_path(&root.qualified_name)).collect::<Vec<_>>(),
+        "functions": functions,
+        "types": types,
+    }))
+}
+
+// The functions carrying the attribute, each as a listing records it
+fn with_attribute(project: &Project, attribute: &str, visibility: VisibilityFilter) -> Value {
+    let functions = project.functions_with_attribute(attribute, visibility);
+    json!({
+        "mode": "with_attribute",
+        "attribute": attribute,
+        "functions": functions.iter().map(|func| function_record(project, func)).collect::<Vec<_>>(),
+    })
+}
+
+// Each exported path with its item's record and the crate path of its definition
+fn public_api(project: &Project) -> Value {
+    let items: Vec<Value> = project
+        .public_api()
+        .iter()
+        .map(|public| {
+            let mut record = match public.item {
+                ItemRef::Function(func) => function_record(project, func),
+                ItemRef::Type(ty) => type_record(project, ty).unwrap_or_default(),
+                ItemRef::Item(it) => item_record(project, it),
+            };
+            record["path"] = json!(public.path);
+            record["defined_at"] = json!(public.defined_at);
+            record
+        })
+        .collect();
+    json!({ "mode": "public_api", "items": items })
+}
+
+// Each cycle's function records and the calls between them
+fn cycles(project: &Project) -> Value {
+    let cycles: Vec<Value> = project
+        .cycles()
+        .iter()
+        .map(|cycle| {
+            let functions: Vec<Value> = cycle.functions.iter().map(|func| function_record(project, func)).collect();
+            let edges: Vec<Value> = cycle.edges.iter().map(|edge| edge_record(project, edge)).collect();
+            json!({ "functions": functions, "edges": edges })
+        })
+        .collect();
+    json!({
+        "mode": "cycles",
+        "cycles": cycles,
+    })
+}
+
+// Each entry point's function record with its `entry_kind` and `detail`, and the bin targets
+fn entry_points(project: &Project) -> Value {
+    let entry_points = project.entry_points();
+    let functions: Vec<Value> = entry_points
+        .functions
+        .iter()
+        .map(|entry| {
+            let mut record = function_record(project, entry.function);
+            record["entry_kind"] = json!(entry.kind.name());
+            record["detail"] = json!(entry.detail);
+            record
+        })
+        .collect();
+    let binaries: Vec<Value> = entry_points
+        .binaries
+        .iter()
+        .map(|target| json!({ "name": target.crate_name, "file": project.relative_path(&target.src_path) }))
+        .collect();
+    json!({
+        "mode": "entry_points",
+        "functions": functions,
+        "binaries": binaries,
+    })
+}
+
+// The target, its transitive callers up to `depth` levels, and the edges between them
+fn callers(project: &Project, target: &str, depth: Option<usize>) -> Result<Value, MorphoError> {
+    let callers = project.callers_of(target, depth)?;
+    let functions: Vec<Value> = callers.functions.iter().map(|func| function_record(project, func)).collect();
+    let edges: Vec<Value> = callers.edges.iter().map(|edge| edge_record(project, edge)).collect();
+    Ok(json!({
+        "mode": "callers",
+        "target": project.relative_path(&callers.target.qualified_name),
+        "functions": functions,
+        "edges": edges,
+    }))
+}
+
+// With `include_docs`, the item's `docs` go in their own field and out of a type's `source`
+fn source(project: &Project, name: &str, include_docs: bool) -> Result<Value, MorphoError> {
+    let item = project.source(name)?;
+    let mut record = match item {
+        ItemRef::Function(func) => function_record(project, func),
+        ItemRef::Type(ty) => type_record(project, ty).unwrap_or_default(),
+        ItemRef::Item(it) => item_record(project, it),
+    };
+    record["source"] = json!(item_source(project, item));
+    if include_docs {
+        record["docs"] = json!(item.docs());
+    }
+    Ok(json!({ "mode": "source", "item": record }))
+}
+
+// The type and every function naming it, each with its references
+fn type_usages(project: &Project, name: &str) -> Result<Value, MorphoError> {
+    let type_usages = project.type_usages(name)?;
+    let usages: Vec<Value> = type_usages
+        .usages
+        .iter()
+        .map(|(func, refs)| {
+            let refs: Vec<Value> = refs
+                .iter()
+                .map(|r| json!({ "usage": r.usage.name(), "text": r.text, "line": r.line }))
+                .collect();
+            json!({ "function": function_record(project, func), "references": refs })
+        })
+        .collect();
+    Ok(json!({ "mode": "type_usages", "type": type_record(project, type_usages.ty), "usages": usages }))
+}
+
+// The type and its impl blocks, each with its header and methods
+fn type_api(project: &Project, name: &str) -> Result<Value, MorphoError> {
+    let api = project.type_api(name)?;
+    let impls: Vec<Value> = api
+        .impls
+        .iter()
+        .map(|block| {
+            let methods: Vec<Value> = block.methods.iter().map(|func| function_record(project, func)).collect();
+            let consts: Vec<Value> = block
+                .consts
+                .iter()
+                .map(|constant| {
+                    json!({
+                        "name": constant.item.ident.to_string(),
+                        "signature": format_impl_const(&constant.item),
+                        "line": constant.line,
+                    })
+                })
+                .collect();
+            let types: Vec<Value> = block
+                .types
+                .iter()
+                .map(|ty| {
+                    json!({
+                        "name": ty.item.ident.to_string(),
+                        "signature": format_impl_type(&ty.item),
+                        "line": ty.line,
+                    })
+                })
+                .collect();
+            let file = project.relative_path(&block.file);
+            json!({ "file": file, "header": block.header, "types": types, "consts": consts, "methods": methods })
+        })
+        .collect();
+    Ok(json!({ "mode": "type_api", "type": type_record(project, api.ty), "impls": impls }))
+}
+
+// The trait (null when it comes from another crate), and each impl with its lines and the methods it
+// provides, overrides and inherits
+fn implementors(project: &Project, name: &str) -> Result<Value, MorphoError> {
+    let implementors = project.implementors(name)?;
+    let records = |methods: &[&Function]| -> Vec<Value> {
+        methods.iter().map(|func| function_record(project, func)).collect()
+    };
+    let impls: Vec<Value> = implementors
+        .impls
+        .iter()
+        .map(|implementor| {
+            let imp = implementor.imp;
+            json!({
+                "type": imp.self_ty,
+                "file": project.relative_path(&imp.file_path),
+                "start_line": imp.start_line,
+                "end_line": imp.end_line,
+                "header": impl_header(&imp.generics, &trait_impl_segment(&imp.self_ty, &imp.trait_ref)),
+                "provides": records(&implementor.provides),
+                "overrides": records(&implementor.overrides),
+                "inherits": records(&implementor.inherits),
+            })
+        })
+        .collect();
+    Ok(json!({
+        "mode": "implementors",
+        "trait_name": implementors.trait_name,
+        "trait": implementors.trait_def.and_then(|ty| type_record(project, ty)),
+        "impls": impls,
+    }))
+}
+
+// Matching functions and types, best first, each with its score
+fn search(project: &Project, query: &str, limit: Option<usize>) -> Result<Value, MorphoError> {
+    let matches: Vec<Value> = project
+        .search(query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))?
+        .into_iter()
+        .filter_map(|hit| {
+            let record = match hit.item {
+                ItemRef::Function(func) => function_record(project, func),
+                ItemRef::Type(ty) => type_record(project, ty)?,
+                ItemRef::Item(it) => item_record(project, it),
+            };
+            Some(json!({ "score": hit.score, "item": record }))
+        })
+        .collect();
+    Ok(json!({ "mode": "search", "query": query, "matches": matches }))
+}
+
+// The roots traced and every function they never reach
+fn unreachable(project: &Project, roots: &[String]) -> Result<Value, MorphoError> {
+    let unreachable = project.unreachable(roots)?;
+    let functions: Vec<Value> = unreachable.functions.iter().map(|func| function_record(project, func)).collect();
+    let roots: Vec<String> = unreachable.roots.iter().map(|root| project.relative_path(root)).collect();
+    Ok(json!({ "mode": "unreachable", "roots": roots, "functions": functions }))
+}
+
+// Functions highest `sort` first, each record with its `metrics`; `total` counts those left out by `limit`
+fn metrics(project: &Project, sort: Metric, visibility: VisibilityFilter, limit: Option<usize>) -> Value {
+    let metrics = project.metrics(sort, visibility);
+    let functions: Vec<Value> = metrics
+        .iter()
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|row| {
+            let mut record = function_record(project, row.function);
+            record["metrics"] = json!({
+                "loc": row.lines,
+                "complexity": row.complexity,
+                "callees": row.callees,
+                "fan_in": row.fan_in,
+            });
+            record
+        })
+        .collect();
+    json!({ "mode": "metrics", "sort": sort.name(), "total": metrics.len(), "functions": functions })
+}
+
+// Functions holding unsafe code, each record with `unsafe_fn` and its `unsafe_blocks`; with a root, the call
+// `paths` from it as lists of names
+fn unsafe_audit(project: &Project, root: Option<&str>) -> Result<Value, MorphoError> {
+    let audit = project.unsafe_audit(root)?;
+    let functions: Vec<Value> = audit
+        .functions
+        .iter()
+        .map(|func| {
+            let mut record = function_record(project, func);
+            record["unsafe_fn"] = json!(func.sig.unsafety.is_some());
+            record["unsafe_blocks"] = func
+                .unsafe_blocks
+                .iter()
+                .map(|&(start, end)| json!({ "start_line": start, "end_line": end }))
+                .collect();
+            record
+        })
+        .collect();
+    let paths: Vec<Vec<String>> = audit
+        .paths
+        .iter()
+        .map(|path| path.iter().map(|func| project.relative_path(&func.qualified_name)).collect())
+        .collect();
+    Ok(json!({
+        "mode": "unsafe_audit",
+        "root": audit.root.map(|root| project.relative_path(&root.qualified_name)),
+        "functions": functions,
+        "paths": paths,
+    }))
+}
+
+// The bump the changes call for, and each change with its level and declarations before and after
+pub(crate) fn semver(report: &SemverReport, before_dir: &str, after_dir: &str) -> Value {
+    let changes: Vec<Value> = report
+        .changes
+        .iter()
+        .map(|change| {
+            json!({
+                "path": change.path,
+                "level": change.level.name(),
+                "change": change.change,
+                "before": change.before,
+                "after": change.after,
+            })
+        })
+        .collect();
+    json!({
+        "mode": "semver",
+        "before": before_dir,
+        "after": after_dir,
+        "level": report.level.name(),
+        "changes": changes,
+    })
+}
+
+// Added and removed functions, and changed ones with the callees they gained and lost; names are
+// relative to each load's directory
+pub(crate) fn diff(
+    diff: &CallGraphDiff,
+    before_dir: &str,
+    before: &Project,
+    after_dir: &str,
+    after: &Project,
+    root: Option<&str>,
+) -> Value {
+    let record = |dir: &str, project: &Project, func: &Function| {
+        let mut record = function_record(project, func);
+        record["name"] = json!(relative_name(dir, &func.qualified_name));
+        record["file"] = json!(relative_name(dir, record["file"].as_str().unwrap_or_default()));
+        record
+    };
+    let added: Vec<Value> = diff.added.iter().map(|func| record(after_dir, after, func)).collect();
+    let removed: Vec<Value> = diff.removed.iter().map(|func| record(before_dir, before, func)).collect();
+    let changed: Vec<Value> = diff
+        .changed
+        .iter()
+        .map(|changed| {
+            json!({
+                "function": record(after_dir, after, changed.function),
+                "calls_added": changed.calls_added,
+                "calls_removed": changed.calls_removed,
+            })
+        })
+        .collect();
+
+    json!({
+        "mode": "diff",
+        "before": before_dir,
+        "after": after_dir,
+        "root": root,
+        "added": added,
+        "removed": removed,
+        "changed": changed,
+        "fingerprint": combine_fingerprints(&[before.stats.fingerprint.clone(), after.stats.fingerprint.clone()]),
+    })
+}
+
+fn function_record(project: &Project, func: &Function) -> Value {
+    let name = project.relative_path(&func.qualified_name);
+    let mut record = json!({
+        "name": name,
+        "kind": function_kind(func),
+        "file": name.split_once("::").map_or("", |(file, _)| file),
+        "start_line": func.start_line,
+        "end_line": func.end_line,
+        "column": func.column,
+        "signature": project.signature(func),
+        "visibility": visibility_name(&func.vis),
+    });
+    // Only known when the project was loaded with its Cargo packages
+    if let Some(crate_path) = project.function_package_path(&func.qualified_name) {
+        record["crate_path"] = json!(crate_path);
+    }
+    // Only recorded when the project was loaded with `all_cfgs`
+    if let Some(cfg) = &func.cfg {
+        record["cfg"] = json!(cfg);
+    }
+    if !func.attributes.is_empty() {
+        record["attributes"] = json!(func.attributes);
+    }
+    record
+}
+
+fn type_record(project: &Project, ty: TypeDef) -> Option<Value> {
+    let (kind, vis) = type_kind(ty.item)?;
+    let (start_line, end_line) = project.type_lines.get(ty.key).copied().unwrap_or_default();
+    let mut record = json!({
+        "name": ty.key,
+        "kind": kind,
+        "file": project.relative_path(ty.file),
+        "start_line": start_line,
+        "end_line": end_line,
+        "signature": format_type_item(ty.item),
+        "visibility": visibility_name(vis),
+    });
+    if let Some(crate_path) = project.type_package_path(ty.key) {
+        record["crate_path"] = json!(crate_path);
+    }
+    Some(record)
+}
+
+// A const, static or macro_rules! macro; exported macros count as public
+fn item_record(project: &Project, it: TypeDef) -> Value {
+    let (kind, visibility) = match it.item {
+        Item::Const(c) => ("const", visibility_name(&c.vis)),
+        Item::Static(s) => ("static", visibility_name(&s.vis)),
+        Item::Macro(m) if is_macro_export(m) => ("macro", "pub".to_string()),
+        _ => ("macro", "private".to_string()),
+    };
+    let (start_line, end_line) = project.item_lines.get(it.key).copied().unwrap_or_default();
+    json!({
+        "name": it.key,
+        "kind": kind,
+        "file": project.relative_path(it.file),
+        "start_line": start_line,
+        "end_line": end_line,
+        "signature": format_type_item(it.item),
+        "visibility": visibility,
+    })
+}
+
+// Every call as an edge record, each call site its own record with its line and column
+fn call_edges(project: &Project, root: Option<&str>) -> Result<Value, MorphoError> {
+    let (root, edges) = match root {
+        Some(root) => {
+            let graph = project.call_graph(root)?;
+            (Some(project.relative_path(&graph.roots[0].qualified_name)), graph.edges)
+        }
+        None => (None, project.call_edges()),
+    };
+    let edges: Vec<Value> = edges.iter().map(|edge| edge_record(project, edge)).collect();
+    Ok(json!({ "mode": "call_edges", "root": root, "edges": edges }))
+}
+
+// The shortest chains from `from` to `to`, each a list of edge records in call order; `total` counts those
+// past MAX_CALL_PATHS too
+fn path_between(project: &Project, from: &str, to: &str) -> Result<Value, MorphoError> {
+    let paths = project.paths_between(from, to)?;
+    let chains: Vec<Vec<Value>> =
+        paths.paths.iter().map(|path| path.iter().map(|edge| edge_record(project, edge)).collect()).collect();
+    Ok(json!({
+        "mode": "path_between",
+        "from": project.relative_path(&paths.from.qualified_name),
+        "to": project.relative_path(&paths.to.qualified_name),
+        "total": paths.total,
+        "paths": chains,
+    }))
+}
+
+fn edge_record(project: &Project, edge: &CallEdge) -> Value {
+    json!({
+        "caller": project.relative_path(&edge.caller),
+        "callee": project.relative_path(&edge.callee),
+        "context": edge.context,
+        "line": edge.line,
+        "column": edge.column,
+    })
+}
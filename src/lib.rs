@@ -1,7 +1,40 @@
 use quote::ToTokens;
-use std::collections::{HashMap, HashSet};
-use syn::{Block, Expr, FnArg, Item, Type, Visibility};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use syn::{
+    Block, Expr, FnArg, GenericArgument, GenericParam, Item, PathArguments, Type, TypeParamBound, Visibility,
+    WherePredicate,
+};
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
 use walkdir::WalkDir;
+use query::{
+    CallGraph, CallPaths, Callers, EntryPoints, FunctionId, FunctionMetrics, Implementors, ItemRef, TypeApi, TypeUsages,
+    Unreachable, UnsafeAudit,
+};
+
+pub mod diff;
+pub mod emit;
+pub mod export;
+mod json;
+mod mermaid;
+#[cfg(feature = "notebook")]
+pub mod notebook;
+pub mod query;
+pub mod report;
+pub mod semver;
+mod rustdoc;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod workspace;
+
+// The version `Graph::to_petgraph` builds, so its algorithms can be called without a matching dependency
+#[cfg(feature = "petgraph")]
+pub use petgraph;
 
 // ============= PUBLIC API TYPES =============
 #[derive(Clone)]
@@ -10,734 +43,5387 @@ pub struct Function {
     pub sig: syn::Signature,
     pub block: Option<Block>,
     pub qualified_name: String, // e.g., "main" or "MyStruct::new"
+    pub impl_generics: Option<syn::Generics>, // generics of the enclosing impl block, if any
+    pub call_sites: Vec<CallSite>, // extracted when the function is built, while span info is available
+    pub module_path: String, // inline module the function is declared in, e.g., "net::http"; empty at file level
+    pub self_type: Option<String>, // type behind `self`/`Self` in impl methods, e.g., "Config" for `impl<T> Config<T>`
+    pub impl_trait: Option<String>, // trait path of a trait impl method, e.g., "fmt::Display" for `impl fmt::Display for Foo`
+    pub start_line: usize, // 1-based lines of the whole item, recorded while span info is available
+    pub end_line: usize,
+    pub column: usize, // 1-based column of the function's name (of the `|` for closures); 0 if unknown
+    // Bytes of the item in its file's text in `Project::sources`, leading doc comments left out; empty if
+    // unknown
+    pub byte_range: std::ops::Range<usize>,
+    pub type_refs: Vec<TypeRef>, // types named in the signature and body, extracted along with `call_sites`
+    pub unsafe_blocks: Vec<(usize, usize)>, // 1-based first and last lines of each `unsafe {}` block in the body
+    pub docs: Option<String>, // `///` doc comments and `#[doc]` attributes, one line each; None when undocumented
+    // Other attributes as written without `#[..]`, in order, e.g., ["inline", "instrument(skip(self))"]
+    pub attributes: Vec<String>,
+    // `#[cfg(..)]` predicate of the function and its enclosing items, e.g., `all(unix, feature = "tls")`;
+    // only recorded by loads with `all_cfgs`
+    pub cfg: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CallSite {
     pub name: String,
     pub context: Option<String>, // e.g., "if (x > 0)", "match Some(_)"
+    pub receiver: Option<String>, // e.g., "self" or "store" for `store.save()`
+    pub qualifier: Option<String>, // path before the name, e.g., "Config" for `Config::load()`
+    pub dispatch: Dispatch,
+    // Type of the receiver, from `self`, a parameter or a `let`, e.g., "Config" for `config.save()`
+    pub receiver_type: Option<String>,
+    // The call a method is chained onto, e.g., `get` for the `send` in `client.get(url)?.send()`, and
+    // whether `?` unwraps its result first; the receiver's type is what that call returns
+    pub receiver_call: Option<Box<CallSite>>,
+    pub receiver_tried: bool,
+    // A function path handed over as a value, e.g., `handler` in `register(handler)`, rather than called;
+    // kept when an enclosing `if`, `for` or `match` relabels its context
+    pub callback: bool,
+    pub start_line: usize, // 1-based line and column of the callee name; 0 if unknown
+    pub column: usize,
+    pub end_line: usize, // last line of the whole call, arguments included
+}
+
+impl CallSite {
+    // Where the call is: the callee name's line and column, and the last line of the whole call
+    fn at(mut self, name: proc_macro2::Span, call: &impl Spanned) -> Self {
+        self.start_line = name.start().line;
+        self.column = name.start().column + 1;
+        self.end_line = call.span().end().line;
+        self
+    }
+}
+
+// How a method call reaches its callee
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Dispatch {
+    #[default]
+    Static,
+    Generic(Vec<String>), // receiver is a generic parameter bounded by these traits
+    Dynamic(Vec<String>), // receiver is a trait object (`&dyn Trait`, `Box<dyn Trait>`)
+    Trait(Vec<String>), // receiver's type is unknown and these traits declare the method (`expand_trait_calls`)
+}
+
+// A resolved call from one project function to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallEdge {
+    pub caller: String, // qualified names
+    pub callee: String,
+    pub context: Option<String>, // as in the call graph, e.g., "map closure" or "dyn Shape"
+    pub line: usize, // line and column of the call in the caller's file; 0 if unknown
+    pub column: usize,
+}
+
+// One place a function names a type, found while span info is available
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeRef {
+    pub name: String, // last path segment with `Self` resolved, e.g., "Circle" for `shapes::Circle`
+    pub usage: TypeUsage,
+    pub text: String, // the code naming it, e.g., "&[Circle]", "Circle { .. }" or "Unit::Metric"
+    pub line: usize, // 1-based; 0 if unknown
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TypeUsage {
+    Receives, // a parameter, `self` included
+    Returns,
+    Bounds, // a generic or `where` bound, e.g., `S: Shape`
+    Constructs, // a struct literal, tuple struct call or enum variant
+    Matches, // a pattern
+    Mentions, // anything else in the body: annotations, casts, `Type::method` paths
+}
+
+impl TypeUsage {
+    pub fn name(self) -> &'static str {
+        match self {
+            TypeUsage::Receives => "receives",
+            TypeUsage::Returns => "returns",
+            TypeUsage::Bounds => "bounds",
+            TypeUsage::Constructs => "constructs",
+            TypeUsage::Matches => "matches",
+            TypeUsage::Mentions => "mentions",
+        }
+    }
 }
 
+// A dependency between two project types, keyed as in `Project::types`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TypeEdge {
+    pub from: String,
+    pub to: String,
+    pub relation: &'static str, // "field", "variant", "alias", "supertrait" or "implements"
+}
+
+#[derive(Debug, Clone)]
+pub struct TraitImpl {
+    pub self_ty: String, // e.g., "FileStore"
+    pub trait_ref: String, // the trait as it appears in method keys, e.g., "Display" or "From<io::Error>"
+    pub file_path: String,
+    pub module_path: String, // inline module containing the impl block; empty at file level
+    pub generics: String, // the impl's generics, e.g., "<T>"
+    pub start_line: usize, // 1-based first and last line of the impl block
+    pub end_line: usize,
+}
+
+// An associated const, e.g., `const MAX: usize = 64;` in `impl Buffer`
+#[derive(Clone)]
+pub struct ImplConst {
+    pub segment: String, // the impl segment of the block's method keys, e.g., "Buffer" or "<Buffer as Sized>"
+    pub generics: String, // the impl's generics, e.g., "<T>"
+    pub file_path: String,
+    pub module_path: String, // inline module containing the impl block; empty at file level
+    pub line: usize,
+    pub item: syn::ImplItemConst,
+}
+
+// An associated type, e.g., `type Item = Token;` in `impl Iterator for Lexer`, keyed like `ImplConst`
 #[derive(Clone)]
+pub struct ImplType {
+    pub segment: String,
+    pub generics: String,
+    pub file_path: String,
+    pub module_path: String,
+    pub line: usize,
+    pub item: syn::ImplItemType,
+}
+
+#[derive(Clone, Default)]
 pub struct Project {
-    pub functions: HashMap<String, Function>, // keyed by qualified_name
-    pub types: HashMap<String, (String, Item)>, // key = type name; value = (file_path, item)
+    // Ordered maps so every renderer's output is byte-identical across runs and platforms
+    pub functions: BTreeMap<String, Function>, // keyed by qualified_name
+    pub types: BTreeMap<String, (String, Item)>, // key = type name; value = (file_path, item)
+    pub trait_impls: BTreeMap<String, Vec<TraitImpl>>, // key = trait name
+    pub type_lines: BTreeMap<String, (usize, usize)>, // same keys as `types`; first and last line of the item
+    pub items: BTreeMap<String, (String, Item)>, // consts, statics and macro_rules! macros, keyed as `types` is
+    pub item_lines: BTreeMap<String, (usize, usize)>, // same keys as `items`
+    // Bytes of each type and item in its file's text in `sources`, leading doc comments left out
+    pub type_bytes: BTreeMap<String, std::ops::Range<usize>>,
+    pub item_bytes: BTreeMap<String, std::ops::Range<usize>>,
+    // Each indexed file's text as parsed, which renderers slice to show code as written; left empty by
+    // `signatures_only` loads
+    pub sources: BTreeMap<String, String>,
+    pub impl_consts: Vec<ImplConst>, // associated consts, listed by the type API with their impl's methods
+    pub impl_types: Vec<ImplType>, // associated types, listed the same way
+    pub imports: Vec<Import>, // every `use` declaration, flattened to one entry per imported name
+    pub modules: HashSet<String>, // crate module paths seen while loading, e.g., "" (root), "net", "net::http"
+    // Modules declared `pub mod`, by crate module path, with the file declaring them
+    pub public_modules: BTreeMap<String, String>,
+    pub packages: Vec<workspace::Package>, // from `cargo metadata` when loaded with `workspace`; else empty
+    // Directories (or files) loaded, which rendered paths are shown relative to; empty with `absolute_paths`
+    pub loaded_from: Vec<String>,
+    // Each directory (or file) loaded, as the paths of its files start, with the name those paths are shown
+    // under once several loads are merged: the directory's own name unless `with_namespace` renamed it.
+    // Calls resolve to functions under the caller's own root before any other.
+    pub roots: BTreeMap<String, String>,
+    pub stats: IndexStats,
+    // Lookups over `functions` and `imports`, built the first time a path is resolved; every load, merge
+    // and filtered copy starts without one
+    paths: std::sync::OnceLock<PathIndex>,
+}
+
+// Where each crate path, package path and `pub use` re-export leads, so resolving a call looks its path
+// up instead of scanning every function and import
+#[derive(Clone, Default)]
+struct PathIndex {
+    functions: HashMap<String, Vec<String>>, // crate path -> qualified names defined there, in `functions` order
+    packaged: HashMap<String, String>, // package path -> qualified name; empty without `packages`
+    reexports: HashMap<String, String>, // public path, e.g., "prelude::Thing" -> absolute path it re-exports
+}
+
+// What a load actually covered, so callers can check the index against their expectations
+#[derive(Debug, Clone, Default)]
+pub struct IndexStats {
+    pub files_parsed: usize,
+    pub skipped: Vec<SkippedFile>,
+    pub indexed_at: u64, // seconds since the Unix epoch
+    pub fingerprint: String, // changes whenever a source file is added, removed or modified
+    pub timings: Timings, // load phases only; trace and render are timed per output
+    // `mod` declarations disabled by the load's cfg: (declaring file, module file without its `.rs`),
+    // e.g., ("src/lib.rs", "src/unix") for `#[cfg(unix)] mod unix;`
+    pub disabled_modules: Vec<(String, std::path::PathBuf)>,
+}
+
+// Wall-clock time spent in each phase, reported by `--profile`
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    pub walk: Duration,
+    pub read: Duration,
+    pub parse: Duration,
+    pub index: Duration,
+    pub trace: Duration,
+    pub render: Duration,
+    pub slowest_files: Vec<(String, Duration)>, // read + parse + index per file, slowest first
+}
+
+// How many per-file outliers a profile keeps
+const SLOWEST_FILES: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String, // e.g., "blacklisted" or "parse error at 12:5: expected `;`"
+}
+
+// One name brought into scope by a `use` declaration
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub file_path: String,
+    pub module: String, // crate module containing the `use`, e.g., "prelude"; empty at the crate root
+    pub name: String, // name in scope (the alias for `as` renames), or "*" for globs
+    pub path: String, // path as written, e.g., "crate::net::Client" or "super::util"
+    pub is_pub: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum VisibilityFilter {
     All,
+    // Everything visible outside its own module: `pub` plus `pub(crate)`, `pub(super)` and `pub(in path)`
+    CrateVisible,
     PublicOnly,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum OutputMode {
-    ListAll { visibility: VisibilityFilter },
-    CallGraph { root: String, visibility: VisibilityFilter },
-    Source { function: String },
+    // Every type, const, static, macro_rules! macro and function signature by file; with `hide_items`,
+    // types and functions only, and with `include_docs`, each one's doc comment above it
+    ListAll { visibility: VisibilityFilter, hide_items: bool, include_docs: bool, budget: Budget, page: Page },
+    // Types reachable from the roots, then each root's call tree; several roots share one types section.
+    // Calls into another merged directory are marked `[dep: name]`; without `descend_into_deps` the tree
+    // stops there, and with `scope` it only reaches functions under that path or module (see
+    // `TraceLimits`). With `show_external`, the calls left unresolved (other crates, std, or out of scope)
+    // are listed too, marked `[external]`; text format only.
+    CallGraph {
+        roots: Vec<String>,
+        visibility: VisibilityFilter,
+        budget: Budget,
+        descend_into_deps: bool,
+        show_external: bool,
+        scope: Option<String>,
+    },
+    Source { function: String, include_docs: bool },
+    // Inverted call graph: every function that directly or transitively calls `target`, up to `depth`
+    // levels of callers (unlimited when None)
+    Callers { target: String, depth: Option<usize> },
+    // Every function whose signature or body names the struct, enum or trait, and how it uses it
+    TypeUsages { type_name: String },
+    // A type's definition and every impl block for it (for a trait, every impl of it), with method signatures
+    TypeApi { type_name: String },
+    // Functions and types whose names match `query`, best matches first; at most `limit` of them
+    // (DEFAULT_SEARCH_LIMIT when None)
+    Search { query: String, limit: Option<usize> },
+    // Dead-code audit: every function never reached from `roots`. With no roots, traces from every `main`,
+    // every `pub` function and every trait impl method (which callers reach through the trait).
+    Unreachable { roots: Vec<String> },
+    // Functions added, removed, and whose calls changed between two loads, e.g., checkouts of two git
+    // revisions; with `root`, only the functions it reaches. Loads both directories itself, so it is only
+    // available through `generate_output_with_format` (or `diff::diff_projects` on loaded projects).
+    Diff { before_dir: String, after_dir: String, root: Option<String> },
+    // Refactoring hotspots: each function matching `visibility` with its size, complexity, callees and
+    // callers, highest `sort` metric first; at most `limit` of them (all when None)
+    Metrics { sort: Metric, visibility: VisibilityFilter, limit: Option<usize> },
+    // Unsafe-code audit: every `unsafe fn` and `unsafe {}` block by file; with `root`, the shortest call path
+    // from it into each function holding unsafe code
+    UnsafeAudit { root: Option<String> },
+    // Every impl of a trait, by file, with the methods each one provides, overrides and inherits; the trait
+    // may come from another crate, e.g., `From`
+    Implementors { trait_name: String },
+    // Flat list of resolved calls for graph tools, without the tree: every call in the project, or with
+    // `root`, the calls of its call graph
+    CallEdges { root: Option<String> },
+    // How one function ends up calling another: the chains of calls from `from` to `to` with the fewest
+    // calls, at most MAX_CALL_PATHS of them, or a note that `to` isn't reached
+    PathBetween { from: String, to: String },
+    // The call graph from `roots` without its trees, for packing into a context window: the reachable
+    // type definitions and function signatures matching `visibility` (the roots always), by file
+    CallGraphSummary { roots: Vec<String>, visibility: VisibilityFilter },
+    // The code a change to `roots` may need to see, in one piece: the full source of every function
+    // their call graph reaches and the definitions of the types it reaches, by file
+    CallGraphSource { roots: Vec<String> },
+    // Every function carrying the attribute matching `visibility`, by file as a listing shows them; see
+    // `Function::has_attribute` for how `attribute` is matched
+    WithAttribute { attribute: String, visibility: VisibilityFilter },
+    // Where to start reading an unfamiliar project: `main` functions, runtime mains, exported functions,
+    // route handlers and tests, then the bin targets when the project was loaded with its packages
+    EntryPoints,
+    // What a library crate exports, by the paths downstream crates write: `pub` items reachable from the
+    // crate root through `pub` modules or `pub use` re-exports, with each one's definition
+    PublicApi,
+    // Recursion for reviewers: every group of mutually recursive functions and every function calling
+    // itself, with the calls that close each loop
+    Cycles,
+    // The semver bump between two versions of a library: how its public API changed, each change rated
+    // major, minor or patch. Loads both directories itself, as `Diff` does.
+    Semver { before_dir: String, after_dir: String },
 }
 
-#[derive(Debug)]
-pub struct Output {
-    pub content: String,
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+// Shortest call chains shown by `OutputMode::PathBetween`; the rest are counted
+pub const MAX_CALL_PATHS: usize = 10;
+
+// What `OutputMode::Metrics` ranks functions by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Metric {
+    Lines, // lines of the whole item, signature and attributes included
+    #[default]
+    Complexity, // cyclomatic, see `Function::complexity`
+    Callees, // distinct functions it calls
+    FanIn, // distinct functions calling it
 }
 
-// ============= CORE LOGIC (NO I/O) =============
-pub fn load_project(dir: &str) -> Result<Project, String> {
-    load_project_with_blacklist(dir, &[])
+impl Metric {
+    pub fn from_name(name: &str) -> Option<Metric> {
+        match name {
+            "loc" => Some(Metric::Lines),
+            "complexity" => Some(Metric::Complexity),
+            "callees" => Some(Metric::Callees),
+            "fan-in" => Some(Metric::FanIn),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Lines => "loc",
+            Metric::Complexity => "complexity",
+            Metric::Callees => "callees",
+            Metric::FanIn => "fan-in",
+        }
+    }
 }
 
-pub fn load_multiple_projects(dirs: &[String], blacklist: &[String]) -> Result<Project, String> {
-    let mut merged = Project {
-        functions: HashMap::new(),
-        types: HashMap::new(),
-    };
+// Caps on the size of a listing or call graph, for clients with little room such as LLM context windows.
+// A listing keeps its first items (by file, then name); a call graph expands functions breadth-first from
+// the root and marks calls into the rest `(omitted)`. Either way the output is cut the same way every time
+// and ends with a note on what was left out.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Budget {
+    pub max_nodes: Option<usize>, // items listed, or functions expanded in a call graph
+    pub max_bytes: Option<usize>, // size of the rendered output; fewer nodes are kept until it fits
+}
 
-    for dir in dirs {
-        let project = load_project_with_blacklist(dir, blacklist)?;
+// Which part of a listing to show, for clients that fetch a large workspace a page at a time. A listing
+// that stops early ends with the offset the next page starts at.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Page {
+    pub offset: usize, // items to skip, by file then name
+    pub limit: Option<usize>, // items to show; the rest of the listing when None
+}
 
-        // Merge functions (later entries override earlier ones if there are conflicts)
-        merged.functions.extend(project.functions);
+// How far a call graph's traversal goes; the default follows every call the project resolves
+#[derive(Debug, Clone, Default)]
+pub struct TraceLimits {
+    // Functions in another directory merged with the root's are reached, but their calls aren't followed
+    pub stop_at_deps: bool,
+    // Only functions under this path ("src/server/") or module ("server::handlers") are reached, besides
+    // the roots; calls out of it are left unresolved, like calls into other crates
+    pub scope: Option<String>,
+}
 
-        // Merge types
-        merged.types.extend(project.types);
-    }
+// How `generate_output_*` renders a mode: the human-readable text format, a structured JSON document, a
+// Mermaid diagram, or a rustdoc-style JSON document of the listing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Mermaid, // a flowchart of the call graph or callers tree, fenced for Markdown
+    RustdocJson, // the listing as `root`, `index` and `paths`, for tools that read rustdoc's JSON
+}
 
-    Ok(merged)
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "mermaid" => Some(OutputFormat::Mermaid),
+            "rustdoc-json" => Some(OutputFormat::RustdocJson),
+            _ => None,
+        }
+    }
 }
 
-pub fn load_project_with_blacklist(dir: &str, blacklist: &[String]) -> Result<Project, String> {
-    let mut project = Project {
-        functions: HashMap::new(),
-        types: HashMap::new(),
-    };
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    // Paths containing any of these strings are skipped; prefer `exclude_globs` for new code
+    pub blacklist: Vec<String>,
+    // Globs over paths relative to the loaded directory, e.g., "**/generated/**": `*` and `?` stay within
+    // one path segment and `**` spans any number of them. A glob without `/` matches file names, e.g.,
+    // "*_test.rs". Files matching an exclude glob are skipped; with include globs, so is every file
+    // matching none of them.
+    pub exclude_globs: Vec<String>,
+    pub include_globs: Vec<String>,
+    // Walk into symlinked directories and read symlinked files (the default)
+    pub follow_links: bool,
+    // Skip files larger than this many bytes, e.g., generated bindings
+    pub max_file_size: Option<u64>,
+    // Materialize closures with more than this many statements as `parent::{closure@line}` nodes
+    pub closure_nodes: Option<usize>,
+    // Drop function bodies once signatures are indexed; enough for listing, not for call graphs
+    pub signatures_only: bool,
+    // Parse in batches sized to stay roughly under this many megabytes (listing only)
+    pub memory_limit_mb: Option<usize>,
+    // Threads reading and parsing files; None uses every available core
+    pub threads: Option<usize>,
+    // Fan method calls on receivers of unknown type out to every implementor of a trait declaring the method
+    pub expand_trait_calls: bool,
+    // Leave out test code: `#[test]` functions, `#[cfg(test)]` items, `mod tests`, and files under
+    // `tests/` or named `tests.rs`
+    pub exclude_tests: bool,
+    // Read the Cargo packages around the directory so items can be named by crate paths, e.g.,
+    // `my_crate::net::connect`; without Cargo (or outside a package) the load goes ahead without them
+    pub workspace: bool,
+    // Name files by their canonical absolute paths, e.g., "/home/me/app/src/lib.rs::run", instead of
+    // showing them relative to the loaded directory ("src/lib.rs::run") in rendered output
+    pub absolute_paths: bool,
+    // Evaluate `#[cfg(..)]` attributes against these settings and leave out the items (and `mod` files)
+    // they disable; None indexes every item whatever its cfg
+    pub cfg: Option<CfgOptions>,
+    // Index every item whatever its cfg, even with `cfg` set, recording each function's predicate in
+    // `Function::cfg` so listings can show it
+    pub all_cfgs: bool,
+    // Also leave out what the loaded directory's `.gitignore` ignores; its `.morphoignore` is always read
+    pub gitignore: bool,
+}
 
-    for entry in WalkDir::new(dir).follow_links(true) {
-        let entry = match entry {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
+// The build a load's `#[cfg(..)]` attributes are evaluated for. `feature = ".."`, `target_os`,
+// `target_family`, `unix`, `windows` and bare names like `test` are decided; predicates on anything
+// else, e.g., `target_arch = ".."`, are taken to hold so their items stay in.
+#[derive(Debug, Clone, Default)]
+pub struct CfgOptions {
+    pub features: Vec<String>,
+    pub target_os: Option<String>, // e.g., "linux" or "windows"; None for the host's
+    pub flags: Vec<String>, // bare cfg names that are set, e.g., "test" or "debug_assertions"
+}
 
-        // Skip blacklisted directories
-        if entry.file_type().is_dir() {
-            let path_str = entry.path().to_string_lossy();
-            if blacklist.iter().any(|bl| path_str.contains(bl)) {
-                continue;
-            }
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            blacklist: vec![],
+            exclude_globs: vec![],
+            include_globs: vec![],
+            follow_links: true,
+            max_file_size: None,
+            closure_nodes: None,
+            signatures_only: false,
+            memory_limit_mb: None,
+            threads: None,
+            expand_trait_calls: false,
+            exclude_tests: false,
+            workspace: false,
+            absolute_paths: false,
+            cfg: None,
+            all_cfgs: false,
+            gitignore: false,
         }
+    }
+}
 
-        if !entry.file_type().is_file() || entry.path().extension().map_or(false, |e| e != "rs") {
-            continue;
+impl LoadOptions {
+    // What the HTTP and MCP agents load with. Test code is left out unless MORPHO_INCLUDE_TESTS is set;
+    // agents want the runtime code path. Cargo packages are read so items resolve by crate path. Paths are
+    // relative to each project unless MORPHO_ABSOLUTE_PATHS is set. With MORPHO_FEATURES (comma-separated,
+    // may be empty) #[cfg] attributes are evaluated for a build with those features, leaving out disabled
+    // items. Each project's .morphoignore is honored, and its .gitignore too when MORPHO_GITIGNORE is set.
+    pub fn from_env() -> LoadOptions {
+        LoadOptions {
+            exclude_tests: std::env::var_os("MORPHO_INCLUDE_TESTS").is_none(),
+            workspace: true,
+            absolute_paths: std::env::var_os("MORPHO_ABSOLUTE_PATHS").is_some(),
+            gitignore: std::env::var_os("MORPHO_GITIGNORE").is_some(),
+            cfg: std::env::var("MORPHO_FEATURES").ok().map(|features| CfgOptions {
+                features: features.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
         }
+    }
+}
 
-        // Skip files in blacklisted paths
-        let path_str = entry.path().to_string_lossy();
-        if blacklist.iter().any(|bl| path_str.contains(bl)) {
-            continue;
+#[derive(Debug, Default)]
+pub struct Output {
+    pub content: String,
+    pub fingerprint: String, // fingerprint of the project the output was generated from
+    pub timings: Timings, // the project's load timings plus tracing and rendering for this output
+}
+
+// Why a public API call failed, so callers can tell a missing function from an unreadable directory
+// without matching on message text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MorphoError {
+    NotFound { kind: &'static str, name: String }, // kind: "function", "type" or "function or type"
+    Io { path: String, message: String }, // the directory or file to load couldn't be read
+    Parse { path: String, line: usize, column: usize, message: String }, // 1-based position of the error
+    Ambiguous { name: String, candidates: Vec<String> }, // a short name matching several functions
+    Unsupported(String), // a mode, format and options that don't go together
+    Write(String), // streamed output couldn't be written, e.g., to a closed pipe
+}
+
+impl std::fmt::Display for MorphoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MorphoError::NotFound { kind, name } => write!(f, "no {} named '{}' in the project", kind, name),
+            MorphoError::Io { path, message } => write!(f, "cannot read {}: {}", path, message),
+            MorphoError::Parse { path, line, column, message } => {
+                write!(f, "parse error in {} at {}:{}: {}", path, line, column, message)
+            }
+            MorphoError::Ambiguous { name, candidates } => write!(
+                f,
+                "'{}' matches several functions: {}; name one as `Type::method`, `file#function` or in full",
+                name,
+                candidates.join(", ")
+            ),
+            MorphoError::Unsupported(message) => f.write_str(message),
+            MorphoError::Write(message) => write!(f, "cannot write output: {}", message),
         }
+    }
+}
 
-        let content = match std::fs::read_to_string(entry.path()) {
-            Ok(c) => c,
-            Err(_) => continue,
-        };
-        let file = match syn::parse_file(&content) {
-            Ok(f) => f,
-            Err(_) => continue,
-        };
+impl std::error::Error for MorphoError {}
 
-        let file_path_str = entry.path().to_string_lossy().into_owned();
+impl MorphoError {
+    fn not_found(kind: &'static str, name: &str) -> MorphoError {
+        MorphoError::NotFound { kind, name: name.to_string() }
+    }
+}
 
-        for item in file.items {
-            match &item {
-                syn::Item::Fn(f) => {
-                    let fn_item = Function::from_fn(&f, &file_path_str);
-                    project
-                        .functions
-                        .insert(fn_item.qualified_name.clone(), fn_item);
-                }
-                syn::Item::Impl(imp) => {
-                    let impl_target_str = format_type(&imp.self_ty);
-                    for item in &imp.items {
-                        if let syn::ImplItem::Fn(method) = item {
-                            let vis = method.vis.clone();
-                            if matches!(&vis, syn::Visibility::Public(_)) {
-                                let fn_item =
-                                    Function::from_impl_method(method, impl_target_str.clone(), &file_path_str);
-                                project
-                                    .functions
-                                    .insert(fn_item.qualified_name.clone(), fn_item);
-                            }
-                        }
-                    }
-                }
+// ============= CORE LOGIC (NO I/O) =============
+pub fn load_project(dir: &str) -> Result<Project, MorphoError> {
+    load_project_with_blacklist(dir, &[])
+}
 
-                syn::Item::Struct(s) => {
-                    project
-                        .types
-                        .insert(s.ident.to_string(), (file_path_str.clone(), item.clone()));
-                }
-                syn::Item::Enum(e) => {
-                    project
-                        .types
-                        .insert(e.ident.to_string(), (file_path_str.clone(), item.clone()));
-                }
-                syn::Item::Trait(t) => {
-                    project
-                        .types
-                        .insert(t.ident.to_string(), (file_path_str.clone(), item.clone()));
-                }
-                syn::Item::Type(t) => {
-                    project
-                        .types
-                        .insert(t.ident.to_string(), (file_path_str.clone(), item.clone()));
-                }
-                _ => {}
-            }
-        }
+pub fn load_multiple_projects(dirs: &[String], blacklist: &[String]) -> Result<Project, MorphoError> {
+    let projects = dirs
+        .iter()
+        .map(|dir| load_project_with_blacklist(dir, blacklist))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(merge_projects(projects))
+}
+
+// Combines separately loaded projects into one; later entries override earlier ones on conflicts
+pub fn merge_projects(projects: Vec<Project>) -> Project {
+    let mut merged = Project::default();
+    let mut fingerprints = vec![];
+
+    for mut project in projects {
+        fingerprints.push(std::mem::take(&mut project.stats.fingerprint));
+        merged.absorb(project);
     }
+    merged.stats.fingerprint = combine_fingerprints(&fingerprints);
 
-    Ok(project)
+    merged
 }
 
-impl Function {
-    pub fn signature(&self) -> String {
-        let vis = visibility_to_string(&self.vis);
-        let asyncness = if self.sig.asyncness.is_some() {
-            "async "
-        } else {
-            ""
-        };
-        let constness = if self.sig.constness.is_some() {
-            "const "
-        } else {
-            ""
-        };
-        let unsafety = if self.sig.unsafety.is_some() {
-            "unsafe "
-        } else {
-            ""
-        };
-        let args = format_args(&self.sig.inputs.iter().collect::<Vec<_>>());
-        let ret = match &self.sig.output {
-            syn::ReturnType::Default => "()".to_string(),
-            syn::ReturnType::Type(_, ty) => format_type(ty),
-        };
+pub fn load_project_with_blacklist(dir: &str, blacklist: &[String]) -> Result<Project, MorphoError> {
+    load_project_with_options(
+        dir,
+        &LoadOptions {
+            blacklist: blacklist.to_vec(),
+            ..Default::default()
+        },
+    )
+}
 
-        format!(
-            "{}{}{}{}fn {}({}) -> {}",
-            vis, asyncness, constness, unsafety, self.qualified_name, args, ret
-        )
+// The directory (or single file) itself must be readable, and a single file must parse; files found
+// inside a directory that can't be read or parsed are skipped and listed in `stats.skipped` instead.
+// With `absolute_paths`, files are walked from the canonical directory, so re-index with that one.
+pub fn load_project_with_options(dir: &str, options: &LoadOptions) -> Result<Project, MorphoError> {
+    let io_error = |e: std::io::Error| MorphoError::Io { path: dir.to_string(), message: e.to_string() };
+    if std::fs::metadata(dir).map_err(io_error)?.is_dir() {
+        std::fs::read_dir(dir).map_err(io_error)?;
+    } else {
+        parse_source(dir, &std::fs::read_to_string(dir).map_err(io_error)?)?;
     }
+    let dir = &walk_root(dir, options).map_err(io_error)?;
 
-    pub fn full_body(&self) -> String {
-        let sig = self.signature();
-        if let Some(block) = &self.block {
-            format!("{}\n{{\n{}}}\n", sig, indent_block(block))
-        } else {
-            format!("{}\n{{ ... }}\n", sig)
-        }
+    let mut project = Project::default();
+    if !options.absolute_paths {
+        project.loaded_from.push(dir.clone());
+    }
+    project.roots.insert(dir.clone(), directory_name(dir).to_string());
+    let started = Instant::now();
+    let (files, skipped) = walk_rust_files(dir, options);
+    project.stats.timings.walk = started.elapsed();
+    project.stats.skipped = skipped;
+    project.stats.fingerprint = fingerprint_files(&files);
+    let files = indexed_files(dir, files, options, &mut project.stats.skipped);
+    load_files(&mut project, &files, options);
+    drop_disabled_modules(&mut project, &files);
+    if options.expand_trait_calls {
+        mark_trait_calls(&mut project);
+    }
+    if options.workspace {
+        project.packages = workspace::read_packages(dir).unwrap_or_default();
     }
+    Ok(project)
+}
 
-    pub fn calls(&self) -> Vec<CallSite> {
-        let mut calls = vec![];
-        if let Some(block) = &self.block {
-            extract_calls_from_block(&block, &mut calls);
-        }
-        calls
+// The directory a load walks: as given, or its canonical path with `absolute_paths`
+fn walk_root(dir: &str, options: &LoadOptions) -> std::io::Result<String> {
+    if options.absolute_paths {
+        Ok(std::fs::canonicalize(dir)?.to_string_lossy().into_owned())
+    } else {
+        Ok(dir.to_string())
     }
+}
 
-    pub fn from_fn(f: &syn::ItemFn, file_path: &str) -> Self {
-        Function {
-            vis: f.vis.clone(),
-            sig: f.sig.clone(),
-            block: Some(*f.block.clone()),
-            qualified_name: format!("{}::{}", file_path, f.sig.ident),
+// Method calls whose receiver's type can't be read off the source are matched by name alone; with
+// `expand_trait_calls` those naming a project trait's method dispatch through that trait instead
+fn mark_trait_calls(project: &mut Project) {
+    let mut declaring_traits: HashMap<String, Vec<String>> = HashMap::new();
+    for (_, item) in project.types.values() {
+        let Item::Trait(t) = item else { continue };
+        for trait_item in &t.items {
+            if let syn::TraitItem::Fn(f) = trait_item {
+                declaring_traits.entry(f.sig.ident.to_string()).or_default().push(t.ident.to_string());
+            }
         }
     }
 
-    pub fn from_impl_method(method: &syn::ImplItemFn, impl_target_str: String, file_path: &str) -> Self {
-        Function {
-            vis: method.vis.clone(),
-            sig: method.sig.clone(),
-            block: Some(method.block.clone()),
-            qualified_name: format!("{}::{}::{}", file_path, impl_target_str, method.sig.ident),
+    let mut marked = vec![];
+    for (qualified_name, func) in &project.functions {
+        for (i, call) in func.call_sites.iter().enumerate() {
+            if call.receiver.is_none() || call.dispatch != Dispatch::Static {
+                continue;
+            }
+            if let Some(traits) = declaring_traits.get(&call.name) {
+                if receiver_type(func, call, project).is_none() {
+                    marked.push((qualified_name.clone(), i, traits.clone()));
+                }
+            }
+        }
+    }
+    for (qualified_name, i, traits) in marked {
+        if let Some(func) = project.functions.get_mut(&qualified_name) {
+            func.call_sites[i].dispatch = Dispatch::Trait(traits);
         }
     }
 }
 
-pub fn trace_calls(
-    root_func: &str,
-    project: &Project,
-) -> Result<(HashSet<String>, HashSet<String>), String> {
-    let mut visited = HashSet::new();
-    let mut reachable_types = HashSet::<String>::new();
-
-    if !project.functions.contains_key(root_func) {
-        return Err(format!("Function '{}' not found", root_func));
+// Files are parsed on `options.threads` worker threads, each indexing one file at a time into its own
+// Project; the parts are merged back in file order, so the result matches a sequential load. Read,
+// parse and index timings are summed across threads.
+fn load_files(project: &mut Project, files: &[std::path::PathBuf], options: &LoadOptions) {
+    let threads = options
+        .threads
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+        .min(files.len());
+    if threads <= 1 {
+        load_files_on_current_thread(project, files, options);
+        return;
     }
 
-    _trace_calls(root_func, project, &mut visited, &mut reachable_types);
+    let next_file = AtomicUsize::new(0);
+    let mut parts: Vec<(usize, FileIndex)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut parts = vec![];
+                    loop {
+                        let i = next_file.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = files.get(i) else { break };
+                        let mut part = Project::default();
+                        load_files_on_current_thread(&mut part, std::slice::from_ref(path), options);
+                        parts.push((i, FileIndex(part)));
+                    }
+                    parts
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("indexing thread panicked"))
+            .collect()
+    });
 
-    Ok((visited, reachable_types))
+    parts.sort_by_key(|(i, _)| *i);
+    for (_, FileIndex(part)) in parts {
+        project.absorb(part);
+    }
 }
 
-fn _trace_calls(
-    func_name: &str,
-    project: &Project,
-    visited: &mut HashSet<String>,
-    reachable_types: &mut HashSet<String>,
-) {
-    // Try exact match first, then try to find by short name
-    let func_entry = project.functions.get_key_value(func_name).or_else(|| {
-        // If not found, try to find a function whose qualified name ends with ::func_name
-        project.functions.iter()
-            .find(|(qualified_name, _)| {
-                qualified_name.ends_with(&format!("::{}", func_name))
-            })
-    });
+// One file's index, built on a worker thread and handed back to the loading thread
+struct FileIndex(Project);
+
+// SAFETY: syn trees are !Send because proc-macro2's fallback token streams share buffers through `Rc`
+// and its spans index a source map local to the parsing thread (compiler spans only exist inside
+// procedural macros). The worker drops the parsed file, and with it every other handle on those buffers,
+// before handing the index over, so each `Rc` moves with its only owner and is never used from two
+// threads at once. Spans moved along still index the worker's source map, which is freed when the worker
+// exits: lines and byte ranges are read into plain fields on the worker, and no span of an indexed item
+// may be queried afterwards, which would give wrong positions or panic.
+unsafe impl Send for FileIndex {}
+
+fn load_files_on_current_thread(project: &mut Project, files: &[std::path::PathBuf], options: &LoadOptions) {
+    let mut timings = Timings::default();
+    for path in files {
+        let file_path_str = path.to_string_lossy().into_owned();
+        let skip = |project: &mut Project, reason: String| {
+            project.stats.skipped.push(SkippedFile { path: file_path_str.clone(), reason });
+        };
 
-    let (qualified_name, func) = match func_entry {
-        Some((qn, f)) => (qn, f),
-        None => {
-            // Function not found - this can happen for external crate functions, macros, etc.
-            // Just skip it silently
-            return;
+        let started = Instant::now();
+        let content = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                skip(project, format!("unreadable: {}", e));
+                continue;
+            }
+        };
+        let read = started.elapsed();
+        let content = parsed_text(&content);
+        let file = match parse_source(&file_path_str, content) {
+            Ok(f) => f,
+            Err(MorphoError::Parse { line, column, message, .. }) => {
+                skip(project, format!("parse error at {}:{}: {}", line, column, message));
+                continue;
+            }
+            Err(e) => {
+                skip(project, e.to_string());
+                continue;
+            }
+        };
+        let parsed = started.elapsed();
+        if options.exclude_tests && is_test_code(&file.attrs) {
+            skip(project, "test code".to_string());
+            continue;
+        }
+        if !cfg_enabled(&file.attrs, options) {
+            skip(project, "disabled by cfg".to_string());
+            continue;
         }
-    };
 
-    // Use the actual qualified name for visited tracking
-    if !visited.insert(qualified_name.clone()) {
-        return;
+        let cfgs = item_cfgs(&[], &file.attrs, options);
+        index_items(project, &file.items, &file_path_str, "", &cfgs, options);
+        if !options.signatures_only {
+            project.sources.insert(file_path_str.clone(), content.to_string());
+        }
+        project.stats.files_parsed += 1;
+
+        let total = started.elapsed();
+        timings.read += read;
+        timings.parse += parsed - read;
+        timings.index += total - parsed;
+        timings.slowest_files.push((file_path_str, total));
     }
+    project.stats.timings.absorb(timings);
 
-    collect_types_in_signature(&func.sig, reachable_types);
+    project.stats.indexed_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+}
 
-    for callee in &func.calls() {
-        _trace_calls(&callee.name, project, visited, reachable_types);
+// The part of a file syn parses, which span byte ranges count from: without a byte order mark or `#!`
+// line, though the line break after that is kept so line numbers still match
+fn parsed_text(content: &str) -> &str {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    match content.strip_prefix("#!") {
+        Some(rest) if !rest.trim_start().starts_with('[') => {
+            content.find('\n').map_or("", |line_end| &content[line_end..])
+        }
+        _ => content,
     }
 }
 
-pub fn generate_output(dir: &str, mode: OutputMode) -> Result<Output, String> {
-    generate_output_with_blacklist(dir, mode, &[])
+fn parse_source(path: &str, content: &str) -> Result<syn::File, MorphoError> {
+    syn::parse_file(content).map_err(|e| {
+        let start = e.span().start();
+        MorphoError::Parse {
+            path: path.to_string(),
+            line: start.line,
+            column: start.column + 1,
+            message: e.to_string(),
+        }
+    })
 }
 
-pub fn generate_output_multi_dir(dirs: &[String], mode: OutputMode, blacklist: &[String]) -> Result<Output, String> {
-    let project = load_multiple_projects(dirs, blacklist)?;
+// Rough in-memory size of a parsed file (syntax tree plus span source map) per byte of source
+const AST_BYTES_PER_SOURCE_BYTE: u64 = 20;
+
+// Consecutive runs of files whose estimated parsed size fits in `limit_mb`; an oversized file gets a
+// batch to itself
+fn batch_files(files: &[std::path::PathBuf], limit_mb: usize) -> Vec<&[std::path::PathBuf]> {
+    let budget = (limit_mb as u64 * 1024 * 1024 / AST_BYTES_PER_SOURCE_BYTE).max(1);
+    let mut batches = vec![];
+    let (mut start, mut used) = (0, 0);
+    for (i, path) in files.iter().enumerate() {
+        let size = std::fs::metadata(path).map_or(0, |m| m.len());
+        if i > start && used + size > budget {
+            batches.push(&files[start..i]);
+            start = i;
+            used = 0;
+        }
+        used += size;
+    }
+    if start < files.len() {
+        batches.push(&files[start..]);
+    }
+    batches
+}
+
+// ListAll without holding the whole project: files are parsed in batches under the memory limit, each
+// batch is rendered and dropped before the next is parsed, and function bodies are never kept. Batches
+// are parsed on a thread of their own, which releases its span source map after each one; the calling
+// thread only writes out their text, so spans it holds from other loads stay valid.
+fn generate_list_all_low_memory(
+    dir: &str,
+    visibility: VisibilityFilter,
+    hide_items: bool,
+    include_docs: bool,
+    options: &LoadOptions,
+    limit_mb: usize,
+    out: &mut impl Write,
+) -> Result<Output, MorphoError> {
+    let dir = &walk_root(dir, options).map_err(|e| MorphoError::Io { path: dir.to_string(), message: e.to_string() })?;
+    let loaded_from = if options.absolute_paths { vec![] } else { vec![dir.clone()] };
+    let mut timings = Timings::default();
+    let started = Instant::now();
+    let (files, _) = walk_rust_files(dir, options);
+    let fingerprint = fingerprint_files(&files);
+    let files = indexed_files(dir, files, options, &mut vec![]);
+    timings.walk = started.elapsed();
+    let batch_options = LoadOptions {
+        closure_nodes: None,
+        signatures_only: true,
+        ..options.clone()
+    };
+
+    // One batch's text in flight at a time, so the listing thread waits while the caller writes
+    let (sender, receiver) = std::sync::mpsc::sync_channel::<Vec<u8>>(1);
+    let listed = std::thread::scope(|scope| {
+        let lister = scope.spawn(|| -> Result<Timings, MorphoError> {
+            let mut timings = Timings::default();
+            // Re-export notes need every `use` in the crate, so a first pass keeps only the imports
+            let mut reexports = HashMap::new();
+            if let VisibilityFilter::PublicOnly = visibility {
+                let mut imports = Project::default();
+                for batch in batch_files(&files, limit_mb) {
+                    let mut project = Project::default();
+                    load_files(&mut project, batch, &batch_options);
+                    timings.absorb(project.stats.timings);
+                    imports.imports.extend(project.imports);
+                    imports.modules.extend(project.modules);
+                    proc_macro2::extra::invalidate_current_thread_spans();
+                }
+                reexports = imports.reexport_paths();
+            }
 
-    match mode {
-        OutputMode::ListAll { visibility } => generate_list_all(&project, visibility),
-        OutputMode::CallGraph { root, visibility } => {
-            let (visited_funcs, reachable_types) = trace_calls(&root, &project)?;
-
-            // Filter functions and types by reachability
-            let mut file_to_funcs: HashMap<String, Vec<Function>> = HashMap::new();
-            for (name, func) in &project.functions {
-                if visited_funcs.contains(name) {
-                    let file = find_file_for_function(&func.qualified_name, &project)?;
-                    file_to_funcs.entry(file).or_default().push(func.clone());
+            // Files are sorted, so concatenating the batches matches a full listing. Module files a cfg
+            // disabled are only left out when declared in the same or an earlier batch.
+            let mut disabled_modules = vec![];
+            for batch in batch_files(&files, limit_mb) {
+                let mut project = Project { loaded_from: loaded_from.clone(), ..Default::default() };
+                load_files(&mut project, batch, &batch_options);
+                project.stats.disabled_modules.extend(std::mem::take(&mut disabled_modules));
+                drop_disabled_modules(&mut project, batch);
+                disabled_modules = std::mem::take(&mut project.stats.disabled_modules);
+                let started = Instant::now();
+                let items = project.list_items(visibility, hide_items);
+                let mut text = vec![];
+                write_list_all(&project, &items, &reexports, include_docs, &mut text).map_err(write_error)?;
+                timings.render += started.elapsed();
+                timings.absorb(std::mem::take(&mut project.stats.timings));
+                drop(project);
+                proc_macro2::extra::invalidate_current_thread_spans();
+                // Gone only once the caller stopped on a write error, which it reports
+                if sender.send(text).is_err() {
+                    break;
                 }
             }
+            drop(sender);
+            Ok(timings)
+        });
+        // Consumed here so a failed write drops the receiver and the listing thread stops sending
+        let written = receiver.into_iter().try_for_each(|text| out.write_all(&text)).map_err(write_error);
+        let listed = lister.join().expect("listing thread panicked")?;
+        written.map(|_| listed)
+    })?;
+    timings.absorb(listed);
+    out.flush().map_err(write_error)?;
+
+    Ok(Output {
+        content: String::new(),
+        fingerprint,
+        timings,
+    })
+}
+
+// The walked files a load indexes: all of them, or with `exclude_tests` those outside test directories
+// and `tests.rs` modules, the rest recorded in `skipped`. Fingerprints still cover every walked file.
+fn indexed_files(
+    dir: &str,
+    files: Vec<std::path::PathBuf>,
+    options: &LoadOptions,
+    skipped: &mut Vec<SkippedFile>,
+) -> Vec<std::path::PathBuf> {
+    if !options.exclude_tests {
+        return files;
+    }
+    let (tests, files): (Vec<_>, Vec<_>) = files.into_iter().partition(|path| {
+        let relative = path.strip_prefix(dir).unwrap_or(path);
+        relative.components().any(|c| c.as_os_str() == "tests") || relative.file_stem().is_some_and(|s| s == "tests")
+    });
+    skipped.extend(tests.into_iter().map(|path| SkippedFile {
+        path: path.to_string_lossy().into_owned(),
+        reason: "test code".to_string(),
+    }));
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+// The `.rs` files under `dir` a load reads, plus the files it leaves out and why
+fn walk_rust_files(dir: &str, options: &LoadOptions) -> (Vec<std::path::PathBuf>, Vec<SkippedFile>) {
+    let mut files = vec![];
+    let mut skipped = vec![];
+    let ignores: Vec<IgnoreRules> = [Some(IGNORE_FILE), options.gitignore.then_some(".gitignore")]
+        .into_iter()
+        .flatten()
+        .filter_map(|name| IgnoreRules::read(dir, name))
+        .collect();
 
-            let mut file_to_types: HashMap<String, Vec<Item>> = HashMap::new();
-            for (type_name, (_, item)) in &project.types {
-                if reachable_types.contains(type_name) {
-                    let (file_path, _) = project.types.get(type_name).unwrap();
-                    file_to_types.entry(file_path.clone()).or_default().push(item.clone());
+    for entry in WalkDir::new(dir).follow_links(options.follow_links) {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                if let Some(path) = e.path() {
+                    skipped.push(SkippedFile {
+                        path: path.to_string_lossy().into_owned(),
+                        reason: format!("unreadable: {}", e),
+                    });
                 }
+                continue;
             }
+        };
+
+        if !entry.file_type().is_file() || entry.path().extension().is_none_or(|e| e != "rs") {
+            continue;
+        }
+
+        let path_str = entry.path().to_string_lossy();
+        let relative = relative_path(dir, entry.path());
+        let ignored_by = || ignores.iter().find_map(|ignore| Some((ignore.source, ignore.excluding(&relative)?)));
+        let reason = if options.blacklist.iter().any(|bl| path_str.contains(bl)) {
+            Some("blacklisted".to_string())
+        } else if let Some((source, pattern)) = ignored_by() {
+            Some(format!("ignored by {} ({})", source, pattern))
+        } else if let Some(glob) = options.exclude_globs.iter().find(|glob| glob_matches(glob, &relative)) {
+            Some(format!("excluded by {}", glob))
+        } else if !options.include_globs.is_empty()
+            && !options.include_globs.iter().any(|glob| glob_matches(glob, &relative))
+        {
+            Some("not included".to_string())
+        } else {
+            let size = entry.metadata().map_or(0, |metadata| metadata.len());
+            options
+                .max_file_size
+                .filter(|&limit| size > limit)
+                .map(|limit| format!("{} bytes, over the {} byte limit", size, limit))
+        };
+        if let Some(reason) = reason {
+            skipped.push(SkippedFile { path: path_str.into_owned(), reason });
+            continue;
+        }
+
+        files.push(entry.into_path());
+    }
+
+    // Directory listing order varies by platform and filesystem; index in path order instead
+    files.sort();
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
+    (files, skipped)
+}
+
+// Standing exclusions committed with a project, in gitignore syntax, read from the loaded directory
+pub const IGNORE_FILE: &str = ".morphoignore";
+
+// The patterns of one ignore file, in order; as in git, the last pattern matching a path decides, and
+// nothing under an ignored directory can be taken back in
+struct IgnoreRules {
+    source: &'static str, // the file's name, for skip reasons
+    rules: Vec<IgnoreRule>,
+}
+
+struct IgnoreRule {
+    pattern: String, // as written
+    glob: String, // without the `!`, the leading `/` or the trailing `/`
+    negated: bool, // `!pattern` takes paths back in
+    directories_only: bool, // `pattern/`
+    anchored: bool, // has a `/` before its end, so it matches from the loaded directory rather than at any depth
+}
+
+impl IgnoreRules {
+    fn read(dir: &str, source: &'static str) -> Option<IgnoreRules> {
+        let text = std::fs::read_to_string(std::path::Path::new(dir).join(source)).ok()?;
+        let rules = text
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (negated, glob) = line.strip_prefix('!').map_or((false, line), |glob| (true, glob));
+                // `\#` and `\!` start patterns that would otherwise be a comment or a negation
+                let glob = glob.strip_prefix('\\').unwrap_or(glob);
+                let (directories_only, glob) = glob.strip_suffix('/').map_or((false, glob), |glob| (true, glob));
+                let anchored = glob.contains('/');
+                let glob = glob.trim_start_matches('/');
+                (!glob.is_empty()).then(|| IgnoreRule {
+                    pattern: line.to_string(),
+                    glob: glob.to_string(),
+                    negated,
+                    directories_only,
+                    anchored,
+                })
+            })
+            .collect();
+        Some(IgnoreRules { source, rules })
+    }
+
+    // The pattern leaving out `path` (relative, `/`-separated), checked for each directory above it first
+    fn excluding(&self, path: &str) -> Option<&str> {
+        let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+        (1..=segments.len()).find_map(|end| {
+            let is_directory = end < segments.len();
+            let rule = self.rules.iter().rev().find(|rule| rule.matches(&segments[..end], is_directory))?;
+            (!rule.negated).then_some(rule.pattern.as_str())
+        })
+    }
+}
+
+impl IgnoreRule {
+    fn matches(&self, path: &[&str], is_directory: bool) -> bool {
+        if self.directories_only && !is_directory {
+            return false;
+        }
+        if self.anchored {
+            let glob: Vec<&str> = self.glob.split('/').filter(|segment| !segment.is_empty()).collect();
+            segments_match(&glob, path)
+        } else {
+            path.last().is_some_and(|name| segment_glob_matches(&self.glob, name))
+        }
+    }
+}
+
+// `path` below `dir` with `/` separators, as globs see it; a loaded single file is its own name
+fn relative_path(dir: &str, path: &std::path::Path) -> String {
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    let relative = match relative.as_os_str().is_empty() {
+        true => std::path::Path::new(path.file_name().unwrap_or_default()),
+        false => relative,
+    };
+    let segments: Vec<_> = relative.iter().map(|segment| segment.to_string_lossy()).collect();
+    segments.join("/")
+}
+
+// Whether a relative path matches a glob, as described on `LoadOptions::exclude_globs`
+fn glob_matches(glob: &str, path: &str) -> bool {
+    let path: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+    if !glob.contains('/') {
+        return path.last().is_some_and(|name| segment_glob_matches(glob, name));
+    }
+    let glob: Vec<&str> = glob.split('/').filter(|segment| !segment.is_empty()).collect();
+    segments_match(&glob, &path)
+}
+
+fn segments_match(glob: &[&str], path: &[&str]) -> bool {
+    match glob.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skipped| segments_match(rest, &path[skipped..])),
+        Some((segment, rest)) => path
+            .split_first()
+            .is_some_and(|(name, path)| segment_glob_matches(segment, name) && segments_match(rest, path)),
+    }
+}
+
+fn segment_glob_matches(glob: &str, name: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    segment_matches(&glob, &name)
+}
+
+fn segment_matches(glob: &[char], name: &[char]) -> bool {
+    match glob.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skipped| segment_matches(rest, &name[skipped..])),
+        Some(('?', rest)) => !name.is_empty() && segment_matches(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && segment_matches(rest, &name[1..]),
+    }
+}
+
+// Fingerprint of the sources a load of `dirs` would read, without parsing them. Matches
+// `project.stats.fingerprint` of the project loaded from the same directories and blacklist.
+pub fn fingerprint_dirs(dirs: &[String], blacklist: &[String]) -> String {
+    fingerprint_dirs_with_options(
+        dirs,
+        &LoadOptions {
+            blacklist: blacklist.to_vec(),
+            ..Default::default()
+        },
+    )
+}
+
+// As `fingerprint_dirs`, for loads with the same `options`
+pub fn fingerprint_dirs_with_options(dirs: &[String], options: &LoadOptions) -> String {
+    let fingerprints: Vec<String> = dirs
+        .iter()
+        .map(|dir| fingerprint_files(&walk_rust_files(dir, options).0))
+        .collect();
+    combine_fingerprints(&fingerprints)
+}
+
+// Hashes each file's path, modification time and size, in path order so the walk order doesn't matter
+fn fingerprint_files(files: &[std::path::PathBuf]) -> String {
+    let mut entries: Vec<(String, u128, u64)> = files
+        .iter()
+        .map(|path| {
+            let metadata = std::fs::metadata(path).ok();
+            let modified = metadata
+                .as_ref()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_nanos());
+            (path.to_string_lossy().into_owned(), modified, metadata.map_or(0, |m| m.len()))
+        })
+        .collect();
+    entries.sort();
+
+    let mut hash = Fnv1a::default();
+    for (path, modified, len) in &entries {
+        hash.write(path.as_bytes());
+        hash.write(&[0]);
+        hash.write(&modified.to_le_bytes());
+        hash.write(&len.to_le_bytes());
+    }
+    format!("{:016x}", hash.0)
+}
+
+fn combine_fingerprints(fingerprints: &[String]) -> String {
+    if let [single] = fingerprints {
+        return single.clone();
+    }
+    let mut hash = Fnv1a::default();
+    for fingerprint in fingerprints {
+        hash.write(fingerprint.as_bytes());
+    }
+    format!("{:016x}", hash.0)
+}
+
+// 64-bit FNV-1a; unlike `DefaultHasher` its output is stable across Rust releases
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf29ce484222325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+// Indexes the items of a file or inline `mod` block. Items inside modules are qualified with the
+// module path, both in function names (`file::net::Client::send`) and type keys (`net::Client`).
+// `cfgs` are the predicates of the enclosing file and modules, recorded on functions with `all_cfgs`.
+fn index_items(
+    project: &mut Project,
+    items: &[Item],
+    file_path_str: &str,
+    module_path: &str,
+    cfgs: &[String],
+    options: &LoadOptions,
+) {
+    let scope = if module_path.is_empty() {
+        file_path_str.to_string()
+    } else {
+        format!("{}::{}", file_path_str, module_path)
+    };
+    let type_key = |ident: &syn::Ident| {
+        if module_path.is_empty() {
+            ident.to_string()
+        } else {
+            format!("{}::{}", module_path, ident)
+        }
+    };
+    let crate_module = join_path(&file_module_path(file_path_str), module_path);
+    project.modules.insert(crate_module.clone());
+
+    for item in items {
+        if options.exclude_tests && is_test_item(item) {
+            continue;
+        }
+        if !cfg_enabled(attrs_of(item), options) {
+            // The module's file is walked like any other, so it's dropped once every file is indexed
+            if let Item::Mod(m @ syn::ItemMod { content: None, .. }) = item {
+                let module_file = module_file_base(file_path_str, module_path, m);
+                project.stats.disabled_modules.push((file_path_str.to_string(), module_file));
+            }
+            continue;
+        }
+        match item {
+            syn::Item::Fn(f) => {
+                let cfg = item_cfgs(cfgs, &f.attrs, options);
+                index_function(project, Function::from_fn(f, &scope).in_module(module_path).with_cfg(cfg), options);
+            }
+            syn::Item::Impl(imp) => {
+                let impl_target_str = format_type(&imp.self_ty);
+                let trait_path = imp.trait_.as_ref().map(|(_, path, _)| path);
+                let impl_cfgs = item_cfgs(cfgs, &imp.attrs, options);
+
+                // Private methods are indexed too, so calls into helpers resolve; `VisibilityFilter`
+                // decides what gets shown
+                for item in &imp.items {
+                    match item {
+                        syn::ImplItem::Fn(method) => {
+                            if options.exclude_tests && is_test_code(&method.attrs) || !cfg_enabled(&method.attrs, options) {
+                                continue;
+                            }
+                            let fn_item = Function::from_impl_method(
+                                method,
+                                impl_target_str.clone(),
+                                trait_path,
+                                &imp.generics,
+                                &scope,
+                            );
+                            let cfg = item_cfgs(&impl_cfgs, &method.attrs, options);
+                            index_function(project, fn_item.in_module(module_path).with_cfg(cfg), options);
+                        }
+                        syn::ImplItem::Const(constant) => {
+                            if options.exclude_tests && is_test_code(&constant.attrs) || !cfg_enabled(&constant.attrs, options) {
+                                continue;
+                            }
+                            project.impl_consts.push(ImplConst {
+                                segment: match trait_path {
+                                    Some(path) => trait_impl_segment(&impl_target_str, &trait_ref(path)),
+                                    None => impl_target_str.clone(),
+                                },
+                                generics: format_generics(&imp.generics),
+                                file_path: file_path_str.to_string(),
+                                module_path: module_path.to_string(),
+                                line: line_range(constant).0,
+                                item: constant.clone(),
+                            });
+                        }
+                        syn::ImplItem::Type(ty) => {
+                            if options.exclude_tests && is_test_code(&ty.attrs) || !cfg_enabled(&ty.attrs, options) {
+                                continue;
+                            }
+                            project.impl_types.push(ImplType {
+                                segment: match trait_path {
+                                    Some(path) => trait_impl_segment(&impl_target_str, &trait_ref(path)),
+                                    None => impl_target_str.clone(),
+                                },
+                                generics: format_generics(&imp.generics),
+                                file_path: file_path_str.to_string(),
+                                module_path: module_path.to_string(),
+                                line: line_range(ty).0,
+                                item: ty.clone(),
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+
+                if let Some(last_seg) = trait_path.and_then(|path| path.segments.last()) {
+                    let (start_line, end_line) = line_range(imp);
+                    project.trait_impls.entry(last_seg.ident.to_string()).or_default().push(TraitImpl {
+                        self_ty: impl_target_str,
+                        trait_ref: trait_ref(trait_path.unwrap()),
+                        file_path: file_path_str.to_string(),
+                        module_path: module_path.to_string(),
+                        generics: format_generics(&imp.generics),
+                        start_line,
+                        end_line,
+                    });
+                }
+            }
+
+            syn::Item::Struct(s) => {
+                index_type(project, type_key(&s.ident), file_path_str, item);
+            }
+            syn::Item::Union(u) => {
+                index_type(project, type_key(&u.ident), file_path_str, item);
+            }
+            syn::Item::Enum(e) => {
+                index_type(project, type_key(&e.ident), file_path_str, item);
+            }
+            syn::Item::Trait(t) => {
+                let trait_cfgs = item_cfgs(cfgs, &t.attrs, options);
+                for trait_item in &t.items {
+                    if let syn::TraitItem::Fn(method) = trait_item {
+                        if !cfg_enabled(&method.attrs, options) {
+                            continue;
+                        }
+                        let fn_item = Function::from_trait_method(method, t, &scope);
+                        let cfg = item_cfgs(&trait_cfgs, &method.attrs, options);
+                        index_function(project, fn_item.in_module(module_path).with_cfg(cfg), options);
+                    }
+                }
+                index_type(project, type_key(&t.ident), file_path_str, item);
+            }
+            syn::Item::Type(t) => {
+                index_type(project, type_key(&t.ident), file_path_str, item);
+            }
+            // `const _: () = ...;` assertions have no name to list
+            syn::Item::Const(c) if c.ident != "_" => {
+                index_item(project, type_key(&c.ident), file_path_str, item);
+            }
+            syn::Item::Static(s) => {
+                index_item(project, type_key(&s.ident), file_path_str, item);
+            }
+            syn::Item::Macro(m) if m.mac.path.is_ident("macro_rules") => {
+                if let Some(ident) = &m.ident {
+                    index_item(project, type_key(ident), file_path_str, item);
+                }
+            }
+            syn::Item::Mod(m) => {
+                if is_public(&m.vis) {
+                    project.public_modules.insert(join_path(&crate_module, &m.ident.to_string()), file_path_str.to_string());
+                }
+                // `mod foo;` declarations live in their own file and are picked up by the walk
+                if let Some((_, content)) = &m.content {
+                    let nested_path = if module_path.is_empty() {
+                        m.ident.to_string()
+                    } else {
+                        format!("{}::{}", module_path, m.ident)
+                    };
+                    index_items(project, content, file_path_str, &nested_path, &item_cfgs(cfgs, &m.attrs, options), options);
+                }
+            }
+            // Foreign functions are indexed as body-less functions, so calls into them resolve; foreign
+            // statics and types are left out
+            syn::Item::ForeignMod(foreign) => {
+                let foreign_cfgs = item_cfgs(cfgs, &foreign.attrs, options);
+                for foreign_item in &foreign.items {
+                    if let syn::ForeignItem::Fn(f) = foreign_item {
+                        if !cfg_enabled(&f.attrs, options) {
+                            continue;
+                        }
+                        let cfg = item_cfgs(&foreign_cfgs, &f.attrs, options);
+                        let func = Function::from_foreign_fn(f, &foreign.abi, &scope).in_module(module_path).with_cfg(cfg);
+                        index_function(project, func, options);
+                    }
+                }
+            }
+            // `extern crate serde as json;` names a crate the way `use serde as json;` would; `extern crate
+            // self as name;` names this one
+            syn::Item::ExternCrate(e) => {
+                let path = if e.ident == "self" { "crate".to_string() } else { e.ident.to_string() };
+                project.imports.push(Import {
+                    file_path: file_path_str.to_string(),
+                    module: crate_module.clone(),
+                    name: e.rename.as_ref().map_or(path.clone(), |(_, rename)| rename.to_string()),
+                    path,
+                    is_pub: matches!(e.vis, Visibility::Public(_)),
+                });
+            }
+            syn::Item::Use(u) => {
+                let mut names = vec![];
+                flatten_use_tree(&u.tree, &mut vec![], &mut names);
+                for (name, path) in names {
+                    project.imports.push(Import {
+                        file_path: file_path_str.to_string(),
+                        module: crate_module.clone(),
+                        name,
+                        path,
+                        is_pub: matches!(u.vis, Visibility::Public(_)),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn index_type(project: &mut Project, key: String, file_path: &str, item: &Item) {
+    project.type_lines.insert(key.clone(), line_range(item));
+    project.type_bytes.insert(key.clone(), byte_range(item, attrs_of(item)));
+    project.types.insert(key, (file_path.to_string(), item.clone()));
+}
+
+fn index_item(project: &mut Project, key: String, file_path: &str, item: &Item) {
+    project.item_lines.insert(key.clone(), line_range(item));
+    project.item_bytes.insert(key.clone(), byte_range(item, attrs_of(item)));
+    project.items.insert(key, (file_path.to_string(), item.clone()));
+}
+
+// `///` and `//!` lines, and `#[doc = "..."]` attributes, joined without their leading space as rustdoc does
+pub(crate) fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value: Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(text), .. }),
+                ..
+            }) => Some(text.value()),
+            _ => None,
+        })
+        .map(|line| line.strip_prefix(' ').map_or(line.clone(), str::to_string))
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+// Every attribute but `#[doc]`, as written without `#[..]`, e.g., "instrument(skip(self))"; prettyplease
+// lays each one out on a stand-in function so spacing doesn't depend on the source
+fn attribute_texts(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| !attr.path().is_ident("doc"))
+        .map(|attr| match &attr.meta {
+            syn::Meta::Path(path) => format_path(path),
+            _ => {
+                let stand_in: syn::ItemFn = syn::parse_quote!(#attr fn f() {});
+                let printed = pretty_item(&Item::Fn(stand_in));
+                let printed = printed.strip_suffix("fn f() {}").unwrap_or(&printed);
+                let attribute = printed.split_whitespace().collect::<Vec<_>>().join(" ");
+                let attribute = attribute.strip_prefix("#[").and_then(|a| a.strip_suffix(']')).unwrap_or(&attribute);
+                attribute.to_string()
+            }
+        })
+        .collect()
+}
+
+// The attributes of a type, const, static or macro that can carry docs
+fn item_attrs(item: &Item) -> &[syn::Attribute] {
+    match item {
+        Item::Struct(s) => &s.attrs,
+        Item::Union(u) => &u.attrs,
+        Item::Enum(e) => &e.attrs,
+        Item::Trait(t) => &t.attrs,
+        Item::Type(t) => &t.attrs,
+        Item::Const(c) => &c.attrs,
+        Item::Static(s) => &s.attrs,
+        Item::Macro(m) => &m.attrs,
+        _ => &[],
+    }
+}
+
+// A copy of the item without its `#[doc]` attributes
+fn without_docs(item: &Item) -> Item {
+    let mut item = item.clone();
+    let attrs = match &mut item {
+        Item::Struct(s) => &mut s.attrs,
+        Item::Union(u) => &mut u.attrs,
+        Item::Enum(e) => &mut e.attrs,
+        Item::Trait(t) => &mut t.attrs,
+        Item::Type(t) => &mut t.attrs,
+        Item::Const(c) => &mut c.attrs,
+        Item::Static(s) => &mut s.attrs,
+        Item::Macro(m) => &mut m.attrs,
+        _ => return item,
+    };
+    attrs.retain(|attr| !attr.path().is_ident("doc"));
+    item
+}
+
+// A doc comment as `///` lines, ready to go above a signature; empty without docs
+fn doc_lines(docs: Option<&str>) -> String {
+    let Some(docs) = docs else { return String::new() };
+    docs.lines().map(|line| if line.is_empty() { "///\n".to_string() } else { format!("/// {}\n", line) }).collect()
+}
+
+// `mod tests`, or an item marked as test code
+fn is_test_item(item: &Item) -> bool {
+    match item {
+        Item::Mod(m) if m.ident == "tests" => true,
+        _ => is_test_code(attrs_of(item)),
+    }
+}
+
+// The outer attributes of any item the index looks at
+fn attrs_of(item: &Item) -> &[syn::Attribute] {
+    match item {
+        Item::Mod(m) => &m.attrs,
+        Item::Fn(f) => &f.attrs,
+        Item::Impl(i) => &i.attrs,
+        Item::Struct(s) => &s.attrs,
+        Item::Union(u) => &u.attrs,
+        Item::Enum(e) => &e.attrs,
+        Item::Trait(t) => &t.attrs,
+        Item::Type(t) => &t.attrs,
+        Item::Const(c) => &c.attrs,
+        Item::Static(s) => &s.attrs,
+        Item::Macro(m) => &m.attrs,
+        Item::Use(u) => &u.attrs,
+        Item::ForeignMod(f) => &f.attrs,
+        Item::ExternCrate(e) => &e.attrs,
+        _ => &[],
+    }
+}
+
+// `#[test]` (or `#[tokio::test]` and the like), or `#[cfg(test)]` / `#[cfg(all(test, ..))]`
+fn is_test_code(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| match &attr.meta {
+        syn::Meta::Path(path) => path.segments.last().is_some_and(|seg| seg.ident == "test"),
+        syn::Meta::List(list) if list.path.is_ident("cfg") => cfg_requires_test(list.tokens.clone()),
+        _ => false,
+    })
+}
+
+// Whether a cfg predicate only holds in test builds; `any(test, ..)` and `not(test)` don't
+fn cfg_requires_test(predicate: proc_macro2::TokenStream) -> bool {
+    let mut tokens = predicate.into_iter();
+    while let Some(token) = tokens.next() {
+        match token {
+            proc_macro2::TokenTree::Ident(ident) if ident == "test" => return true,
+            proc_macro2::TokenTree::Ident(ident) if ident == "all" => {
+                if let Some(proc_macro2::TokenTree::Group(group)) = tokens.next() {
+                    if cfg_requires_test(group.stream()) {
+                        return true;
+                    }
+                }
+            }
+            proc_macro2::TokenTree::Ident(_) => {
+                tokens.next(); // `any(..)`, `not(..)`, or a `key = "value"` pair's `=`
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+// Whether every `#[cfg(..)]` on an item holds for the load's `cfg`; always without one, or with `all_cfgs`
+fn cfg_enabled(attrs: &[syn::Attribute], options: &LoadOptions) -> bool {
+    let Some(cfg) = options.cfg.as_ref().filter(|_| !options.all_cfgs) else {
+        return true;
+    };
+    cfg_predicates(attrs).iter().all(|predicate| cfg.eval(predicate) != Some(false))
+}
+
+// The predicates in force inside an item, its enclosing items' first; only gathered with `all_cfgs`
+fn item_cfgs(enclosing: &[String], attrs: &[syn::Attribute], options: &LoadOptions) -> Vec<String> {
+    if !options.all_cfgs {
+        return vec![];
+    }
+    enclosing.iter().cloned().chain(cfg_predicates(attrs).iter().map(format_cfg)).collect()
+}
+
+// The predicates of `#[cfg(..)]` and `#![cfg(..)]` attributes; ones that don't parse are left out
+fn cfg_predicates(attrs: &[syn::Attribute]) -> Vec<syn::Meta> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .filter_map(|attr| attr.parse_args().ok())
+        .collect()
+}
+
+// The predicates inside `all(..)`, `any(..)` or `not(..)`
+fn nested_cfgs(list: &syn::MetaList) -> syn::Result<Vec<syn::Meta>> {
+    let nested = list.parse_args_with(syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated)?;
+    Ok(nested.into_iter().collect())
+}
+
+// A predicate as written, e.g., `all(unix, feature = "tls")`
+fn format_cfg(predicate: &syn::Meta) -> String {
+    match predicate {
+        syn::Meta::Path(path) => format_path(path),
+        syn::Meta::NameValue(pair) => format!("{} = {}", format_path(&pair.path), pair.value.to_token_stream()),
+        syn::Meta::List(list) => match nested_cfgs(list) {
+            Ok(nested) => {
+                let nested: Vec<String> = nested.iter().map(format_cfg).collect();
+                format!("{}({})", format_path(&list.path), nested.join(", "))
+            }
+            Err(_) => list.to_token_stream().to_string(),
+        },
+    }
+}
+
+impl CfgOptions {
+    // Whether a predicate holds in this build; None when it names a setting that isn't modeled
+    fn eval(&self, predicate: &syn::Meta) -> Option<bool> {
+        match predicate {
+            syn::Meta::Path(path) => {
+                let name = path.get_ident()?.to_string();
+                match name.as_str() {
+                    "unix" | "windows" => self.target_family().map(|family| family == name),
+                    _ => Some(self.flags.contains(&name)),
+                }
+            }
+            syn::Meta::NameValue(pair) => {
+                let Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(value), .. }) = &pair.value else {
+                    return None;
+                };
+                let value = value.value();
+                match pair.path.get_ident()?.to_string().as_str() {
+                    "feature" => Some(self.features.contains(&value)),
+                    "target_os" => Some(self.target_os() == value),
+                    "target_family" => self.target_family().map(|family| family == value),
+                    _ => None,
+                }
+            }
+            syn::Meta::List(list) => {
+                let results: Vec<Option<bool>> = nested_cfgs(list).ok()?.iter().map(|p| self.eval(p)).collect();
+                match list.path.get_ident()?.to_string().as_str() {
+                    "all" if results.contains(&Some(false)) => Some(false),
+                    "any" if results.contains(&Some(true)) => Some(true),
+                    "all" | "any" if results.contains(&None) => None,
+                    "all" => Some(true),
+                    "any" => Some(false),
+                    "not" if results.len() == 1 => results[0].map(|holds| !holds),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn target_os(&self) -> &str {
+        self.target_os.as_deref().unwrap_or(std::env::consts::OS)
+    }
+
+    fn target_family(&self) -> Option<&'static str> {
+        match self.target_os() {
+            "windows" => Some("windows"),
+            "linux" | "macos" | "ios" | "android" | "freebsd" | "netbsd" | "openbsd" | "dragonfly" | "solaris"
+            | "illumos" => Some("unix"),
+            _ => None,
+        }
+    }
+}
+
+// Where the file of a `mod name;` declared in `file_path` (inside inline `module_path`) lives, without its
+// `.rs`: beside lib.rs, main.rs and mod.rs, else in the directory named after the declaring file; or
+// where its `#[path = ".."]` points
+fn module_file_base(file_path: &str, module_path: &str, m: &syn::ItemMod) -> std::path::PathBuf {
+    let file = std::path::Path::new(file_path);
+    let mut base = file.parent().map(std::path::Path::to_path_buf).unwrap_or_default();
+    let path_attr = m.attrs.iter().find(|attr| attr.path().is_ident("path")).and_then(|attr| match &attr.meta {
+        syn::Meta::NameValue(syn::MetaNameValue {
+            value: Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(path), .. }),
+            ..
+        }) => Some(path.value()),
+        _ => None,
+    });
+    if let Some(path) = path_attr {
+        return base.join(path).with_extension("");
+    }
+    if let Some(stem) = file.file_stem().filter(|stem| !matches!(stem.to_str(), Some("lib" | "main" | "mod"))) {
+        base.push(stem);
+    }
+    base.extend(module_path.split("::").filter(|segment| !segment.is_empty()));
+    base.join(m.ident.to_string())
+}
+
+// Leaves out the files of disabled `mod` declarations, e.g., `unix.rs` and everything under `unix/` for
+// `#[cfg(unix)] mod unix;`, recording them in `skipped`
+fn drop_disabled_modules(project: &mut Project, files: &[std::path::PathBuf]) {
+    if project.stats.disabled_modules.is_empty() {
+        return;
+    }
+    let modules: Vec<std::path::PathBuf> = project.stats.disabled_modules.iter().map(|(_, m)| m.clone()).collect();
+    let disabled = |file: &str| {
+        let path = std::path::Path::new(file);
+        modules.iter().any(|module| path.starts_with(module) || path == module.with_extension("rs"))
+    };
+    let already_skipped: HashSet<String> = project.stats.skipped.iter().map(|skipped| skipped.path.clone()).collect();
+    let dropped: Vec<String> = files
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .filter(|file| disabled(file) && !already_skipped.contains(file))
+        .collect();
+    *project = project.filter_files(|file| !disabled(file));
+    project.stats.files_parsed = project.stats.files_parsed.saturating_sub(dropped.len());
+    project.stats.skipped.extend(dropped.into_iter().map(|path| SkippedFile {
+        path,
+        reason: "disabled by cfg".to_string(),
+    }));
+    project.stats.skipped.sort_by(|a, b| a.path.cmp(&b.path));
+}
+
+// Flattens `use a::{b, c as d, e::*}` into (name in scope, full path) pairs
+fn flatten_use_tree(tree: &syn::UseTree, prefix: &mut Vec<String>, out: &mut Vec<(String, String)>) {
+    let full = |prefix: &[String], last: &syn::Ident| {
+        if last == "self" {
+            prefix.join("::")
+        } else {
+            prefix.iter().cloned().chain(std::iter::once(last.to_string())).collect::<Vec<_>>().join("::")
+        }
+    };
+    match tree {
+        syn::UseTree::Path(p) => {
+            prefix.push(p.ident.to_string());
+            flatten_use_tree(&p.tree, prefix, out);
+            prefix.pop();
+        }
+        syn::UseTree::Name(n) => {
+            // `use a::b::{self}` brings `b` itself into scope
+            let name = if n.ident == "self" {
+                prefix.last().cloned().unwrap_or_default()
+            } else {
+                n.ident.to_string()
+            };
+            out.push((name, full(prefix, &n.ident)));
+        }
+        syn::UseTree::Rename(r) => out.push((r.rename.to_string(), full(prefix, &r.ident))),
+        syn::UseTree::Glob(_) => out.push(("*".to_string(), prefix.join("::"))),
+        syn::UseTree::Group(g) => {
+            for tree in &g.items {
+                flatten_use_tree(tree, prefix, out);
+            }
+        }
+    }
+}
+
+// Crate module implied by a file's location: src/lib.rs -> "", src/net/mod.rs -> "net",
+// src/net/http.rs -> "net::http". Binaries under src/bin are crate roots of their own.
+pub fn file_module_path(file_path: &str) -> String {
+    let components: Vec<String> = std::path::Path::new(file_path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let start = components
+        .iter()
+        .rposition(|c| c == "src")
+        .map_or(components.len().saturating_sub(1), |i| i + 1);
+    let mut segments = components[start..].to_vec();
+    if segments.first().is_some_and(|s| s == "bin") {
+        return String::new();
+    }
+    if let Some(last) = segments.pop() {
+        let stem = last.trim_end_matches(".rs");
+        if !matches!(stem, "lib" | "main" | "mod") {
+            segments.push(stem.to_string());
+        }
+    }
+    segments.join("::")
+}
+
+pub(crate) fn join_path(parent: &str, child: &str) -> String {
+    match (parent.is_empty(), child.is_empty()) {
+        (true, _) => child.to_string(),
+        (_, true) => parent.to_string(),
+        _ => format!("{}::{}", parent, child),
+    }
+}
+
+// Inserts a function along with any `fn` items declared inside its body, qualified by the parent
+// (`file::outer::helper`), and any closures large enough to become nodes of their own
+fn index_function(project: &mut Project, mut func: Function, options: &LoadOptions) {
+    if let Some(block) = &func.block {
+        let mut visitor = NestedFnVisitor::default();
+        visitor.visit_block(block);
+        for nested in &visitor.items {
+            if !cfg_enabled(&nested.attrs, options) {
+                continue;
+            }
+            let cfg = item_cfgs(func.cfg.as_slice(), &nested.attrs, options);
+            let nested = Function::from_fn(nested, &func.qualified_name).in_module(&func.module_path).with_cfg(cfg);
+            index_function(project, nested, options);
+        }
+
+        if options.signatures_only {
+            func.block = None;
+            func.call_sites.clear();
+            func.type_refs = func.extract_type_refs(None);
+            func.unsafe_blocks.clear();
+        } else if options.closure_nodes.is_some() {
+            let mut visitor = ClosureVisitor {
+                min_stmts: options.closure_nodes,
+                closures: vec![],
+            };
+            visitor.visit_block(block);
+            for (closure, line) in &visitor.closures {
+                let node = Function::from_closure(closure, line, &func.qualified_name)
+                    .in_module(&func.module_path)
+                    .with_cfg(func.cfg.iter().cloned().collect());
+                index_function(project, node, options);
+            }
+            func.call_sites = func.extract_call_sites(options.closure_nodes);
+            func.type_refs = func.extract_type_refs(options.closure_nodes);
+            func.unsafe_blocks = func.extract_unsafe_blocks(options.closure_nodes);
+        }
+    }
+    project.functions.insert(func.qualified_name.clone(), func);
+}
+
+// Collects the `fn` items declared directly in a body (their own bodies are handled recursively)
+#[derive(Default)]
+struct NestedFnVisitor {
+    items: Vec<syn::ItemFn>,
+}
+
+impl<'ast> Visit<'ast> for NestedFnVisitor {
+    fn visit_item_fn(&mut self, f: &'ast syn::ItemFn) {
+        self.items.push(f.clone());
+    }
+
+    fn visit_item_impl(&mut self, _: &'ast syn::ItemImpl) {}
+}
+
+// Collects the outermost closures in a body that qualify as nodes, with their line numbers
+struct ClosureVisitor {
+    min_stmts: Option<usize>,
+    closures: Vec<(syn::ExprClosure, usize)>,
+}
+
+impl<'ast> Visit<'ast> for ClosureVisitor {
+    fn visit_expr_closure(&mut self, closure: &'ast syn::ExprClosure) {
+        match closure_node_line(closure, self.min_stmts) {
+            // Closures nested inside this one are picked up when it is indexed
+            Some(line) => self.closures.push((closure.clone(), line)),
+            None => visit::visit_expr_closure(self, closure),
+        }
+    }
+
+    fn visit_item_fn(&mut self, _: &'ast syn::ItemFn) {}
+
+    fn visit_item_impl(&mut self, _: &'ast syn::ItemImpl) {}
+}
+
+// Records the types a body names. Paths are read by case: in `a::B::c` the type is `B`, in `a::B` it's
+// `B` and in `B::C` it's `B` with `C` a variant; nested items are left to their own index entries.
+struct TypeRefVisitor<'a> {
+    self_type: Option<&'a str>,
+    closure_nodes: Option<usize>,
+    refs: Vec<TypeRef>,
+}
+
+impl TypeRefVisitor<'_> {
+    fn record(&mut self, usage: TypeUsage, name: &str, text: String, span: proc_macro2::Span) {
+        let name = match name {
+            "Self" => match self.self_type {
+                Some(self_type) => self_type,
+                None => return,
+            },
+            name => name,
+        };
+        let type_ref = TypeRef {
+            name: name.to_string(),
+            usage,
+            text,
+            line: span.start().line,
+        };
+        if !self.refs.contains(&type_ref) {
+            self.refs.push(type_ref);
+        }
+    }
+
+    fn record_type(&mut self, usage: TypeUsage, ty: &Type) {
+        let text = format_type(ty);
+        for name in referenced_type_names(ty) {
+            self.record(usage, &name, text.clone(), ty.span());
+        }
+    }
+
+    fn record_bounds<'b>(&mut self, bounded: &str, bounds: impl Iterator<Item = &'b TypeParamBound>, span: proc_macro2::Span) {
+        let bounds: Vec<&TypeParamBound> = bounds.collect();
+        let text = format!(
+            "{}: {}",
+            bounded,
+            bounds.iter().map(|b| format_type_param_bound(b)).collect::<Vec<_>>().join(" + ")
+        );
+        for name in trait_bound_names(bounds.into_iter()) {
+            self.record(TypeUsage::Bounds, &name, text.clone(), span);
+        }
+    }
+
+    // `Circle(..)`, `Unit::Metric` and `Config::load` in expressions; `call` adds the `(..)`
+    fn record_path_expr(&mut self, path: &syn::Path, call: bool) {
+        let Some((name, names_type)) = path_type(path) else { return };
+        let usage = if names_type || path.segments.last().is_some_and(|seg| is_type_like(&seg.ident)) {
+            TypeUsage::Constructs
+        } else {
+            TypeUsage::Mentions
+        };
+        let text = if call && usage == TypeUsage::Constructs {
+            format!("{}(..)", format_path(path))
+        } else {
+            format_path(path)
+        };
+        self.record(usage, &name, text, path.span());
+    }
+
+    fn visit_path_generics(&mut self, path: &'_ syn::Path) {
+        for seg in &path.segments {
+            self.visit_path_arguments(&seg.arguments);
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for TypeRefVisitor<'_> {
+    fn visit_expr_struct(&mut self, e: &'ast syn::ExprStruct) {
+        if let Some((name, _)) = path_type(&e.path) {
+            self.record(TypeUsage::Constructs, &name, format!("{} {{ .. }}", format_path(&e.path)), e.path.span());
+        }
+        self.visit_path_generics(&e.path);
+        for field in &e.fields {
+            self.visit_expr(&field.expr);
+        }
+        if let Some(rest) = &e.rest {
+            self.visit_expr(rest);
+        }
+    }
+
+    fn visit_expr_call(&mut self, e: &'ast syn::ExprCall) {
+        match e.func.as_ref() {
+            Expr::Path(p) if p.qself.is_none() => {
+                self.record_path_expr(&p.path, true);
+                self.visit_path_generics(&p.path);
+            }
+            func => self.visit_expr(func),
+        }
+        for arg in &e.args {
+            self.visit_expr(arg);
+        }
+    }
+
+    fn visit_expr_path(&mut self, e: &'ast syn::ExprPath) {
+        if e.qself.is_none() {
+            self.record_path_expr(&e.path, false);
+        }
+        visit::visit_expr_path(self, e);
+    }
+
+    fn visit_pat(&mut self, pat: &'ast syn::Pat) {
+        let (path, text) = match pat {
+            syn::Pat::Struct(p) => (&p.path, format!("{} {{ .. }}", format_path(&p.path))),
+            syn::Pat::TupleStruct(p) => (&p.path, format!("{}(..)", format_path(&p.path))),
+            syn::Pat::Path(p) if p.qself.is_none() => (&p.path, format_path(&p.path)),
+            _ => return visit::visit_pat(self, pat),
+        };
+        if let Some((name, _)) = path_type(path) {
+            self.record(TypeUsage::Matches, &name, text, path.span());
+        }
+        match pat {
+            syn::Pat::Struct(p) => p.fields.iter().for_each(|field| self.visit_pat(&field.pat)),
+            syn::Pat::TupleStruct(p) => p.elems.iter().for_each(|elem| self.visit_pat(elem)),
+            _ => {}
+        }
+    }
+
+    fn visit_type(&mut self, ty: &'ast Type) {
+        self.record_type(TypeUsage::Mentions, ty);
+    }
+
+    fn visit_expr_closure(&mut self, closure: &'ast syn::ExprClosure) {
+        if closure_node_line(closure, self.closure_nodes).is_none() {
+            visit::visit_expr_closure(self, closure);
+        }
+    }
+
+    fn visit_item(&mut self, _: &'ast Item) {}
+}
+
+// The type a path names and whether it's the last segment: `a::B` -> (B, true), `B::c` -> (B, false)
+fn path_type(path: &syn::Path) -> Option<(String, bool)> {
+    let mut segments = path.segments.iter().rev();
+    let last = &segments.next()?.ident;
+    match segments.next() {
+        Some(parent) if is_type_like(&parent.ident) => Some((parent.ident.to_string(), false)),
+        _ if is_type_like(last) => Some((last.to_string(), true)),
+        _ => None,
+    }
+}
+
+// `Circle` or `Self`, but not `run`, `shapes` or `MAX_SIZE`
+fn is_type_like(ident: &syn::Ident) -> bool {
+    let name = ident.to_string();
+    name.starts_with(|c: char| c.is_ascii_uppercase()) && name.contains(|c: char| c.is_ascii_lowercase())
+}
+
+// Records the lines of `unsafe {}` blocks in a body, leaving out nested items and closures that are nodes
+struct UnsafeBlockVisitor {
+    closure_nodes: Option<usize>,
+    blocks: Vec<(usize, usize)>,
+}
+
+impl<'ast> Visit<'ast> for UnsafeBlockVisitor {
+    // A block inside an unsafe block adds nothing, so only the outermost is recorded
+    fn visit_expr_unsafe(&mut self, e: &'ast syn::ExprUnsafe) {
+        self.blocks.push(line_range(e));
+    }
+
+    fn visit_expr_closure(&mut self, closure: &'ast syn::ExprClosure) {
+        if closure_node_line(closure, self.closure_nodes).is_none() {
+            visit::visit_expr_closure(self, closure);
+        }
+    }
+
+    fn visit_item(&mut self, _: &'ast Item) {}
+}
+
+// Counts branch points in a body, leaving out nested `fn` items (they're indexed separately)
+#[derive(Default)]
+struct ComplexityVisitor {
+    branches: usize,
+}
+
+impl<'ast> Visit<'ast> for ComplexityVisitor {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match expr {
+            Expr::If(_) | Expr::While(_) | Expr::ForLoop(_) | Expr::Try(_) => self.branches += 1,
+            Expr::Match(m) => self.branches += m.arms.len().saturating_sub(1),
+            Expr::Binary(b) if matches!(b.op, syn::BinOp::And(_) | syn::BinOp::Or(_)) => self.branches += 1,
+            _ => {}
+        }
+        visit::visit_expr(self, expr);
+    }
+
+    fn visit_item_fn(&mut self, _: &'ast syn::ItemFn) {}
+
+    fn visit_item_impl(&mut self, _: &'ast syn::ItemImpl) {}
+}
+
+impl Project {
+    // Moves everything indexed in `other` into this project, keeping this project's fingerprint;
+    // `other` wins where both define the same function or type
+    fn absorb(&mut self, other: Project) {
+        self.paths = std::sync::OnceLock::new();
+        self.functions.extend(other.functions);
+        self.types.extend(other.types);
+        self.type_lines.extend(other.type_lines);
+        self.items.extend(other.items);
+        self.item_lines.extend(other.item_lines);
+        self.type_bytes.extend(other.type_bytes);
+        self.item_bytes.extend(other.item_bytes);
+        self.sources.extend(other.sources);
+        self.impl_consts.extend(other.impl_consts);
+        self.impl_types.extend(other.impl_types);
+        for (trait_name, impls) in other.trait_impls {
+            self.trait_impls.entry(trait_name).or_default().extend(impls);
+        }
+        self.imports.extend(other.imports);
+        self.modules.extend(other.modules);
+        self.public_modules.extend(other.public_modules);
+        self.packages.extend(other.packages);
+        self.loaded_from.extend(other.loaded_from);
+        self.roots.extend(other.roots);
+        self.stats.files_parsed += other.stats.files_parsed;
+        self.stats.skipped.extend(other.stats.skipped);
+        self.stats.disabled_modules.extend(other.stats.disabled_modules);
+        self.stats.indexed_at = self.stats.indexed_at.max(other.stats.indexed_at);
+        self.stats.timings.absorb(other.stats.timings);
+    }
+
+    // A file's path, or a name starting with one (a qualified name or type key), as rendered: relative to
+    // the directory loaded, or for a project merged from several directories, relative to each one's
+    // parent so their files stay apart. Anything not under a loaded directory is returned as it is.
+    pub fn relative_path(&self, path: &str) -> String {
+        relative_to(path, &path_prefixes(&self.loaded_from, &self.roots))
+    }
+
+    // A function's signature under its qualified name as rendered, see `relative_path`
+    pub fn signature(&self, func: &Function) -> String {
+        func.signature_named(&self.relative_path(&func.qualified_name))
+    }
+
+    // Shows this load's files under `name` once merged with others, e.g., "gpui/src/lib.rs::run" rather
+    // than under the directory's own name, so two checkouts of the same name stay apart
+    pub fn with_namespace(mut self, name: &str) -> Project {
+        for namespace in self.roots.values_mut() {
+            *namespace = name.to_string();
+        }
+        self
+    }
+
+    // The loaded directory (a `roots` key) a file, or the file of a qualified name, is under; the
+    // innermost when loads are nested
+    pub fn root_of(&self, path: &str) -> Option<&str> {
+        let file = path.split_once("::").map_or(path, |(file, _)| file);
+        self.roots
+            .keys()
+            .filter(|root| {
+                let root = root.trim_end_matches('/');
+                file.strip_prefix(root).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .max_by_key(|root| root.len())
+            .map(String::as_str)
+    }
+
+    // The name of the project `callee` is in when it isn't `caller`'s, i.e., the call crosses into
+    // another directory merged with the caller's, e.g., "gpui-component"
+    pub fn dependency_name(&self, caller: &str, callee: &str) -> Option<&str> {
+        let root = self.root_of(callee)?;
+        (self.root_of(caller) != Some(root)).then(|| self.roots[root].as_str())
+    }
+
+    // Finds the `types` key for a type name as seen from `module_path`: the innermost enclosing module
+    // defining it wins, then a file-level definition, then a definition in any other module
+    pub fn resolve_type_key(&self, name: &str, module_path: &str) -> Option<&String> {
+        let mut scope = module_path;
+        loop {
+            let candidate = if scope.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}::{}", scope, name)
+            };
+            if let Some((key, _)) = self.types.get_key_value(&candidate) {
+                return Some(key);
+            }
+            if scope.is_empty() {
+                break;
+            }
+            scope = scope.rsplit_once("::").map_or("", |(parent, _)| parent);
+        }
+
+        let suffix = format!("::{}", name);
+        self.types.keys().find(|key| key.ends_with(&suffix))
+    }
+
+    // Every resolved call in the project: callers in name order, each caller's calls in source order.
+    // Calls with several possible callees yield one edge per candidate.
+    pub fn call_edges(&self) -> Vec<CallEdge> {
+        self.call_edges_from(self.functions.values())
+    }
+
+    // The resolved calls made by `callers` only, in the same form as `call_edges`
+    pub fn call_edges_from<'a>(&'a self, callers: impl IntoIterator<Item = &'a Function>) -> Vec<CallEdge> {
+        let all_funcs: BTreeMap<String, &Function> = self.functions.iter().map(|(qn, f)| (qn.clone(), f)).collect();
+        let mut edges = vec![];
+        for func in callers {
+            for edge in tree_edges(func, self, &all_funcs, false) {
+                match edge {
+                    TreeEdge::Call { qualified, context, line, column, .. } => edges.push(CallEdge {
+                        caller: func.qualified_name.clone(),
+                        callee: qualified,
+                        context,
+                        line,
+                        column,
+                    }),
+                    TreeEdge::FanOut { note, context, candidates, line, column, .. } => {
+                        let context = Some(match context {
+                            Some(ctx) => format!("{}, {}", ctx, note),
+                            None => note,
+                        });
+                        edges.extend(candidates.into_iter().map(|callee| CallEdge {
+                            caller: func.qualified_name.clone(),
+                            callee,
+                            context: context.clone(),
+                            line,
+                            column,
+                        }));
+                    }
+                    TreeEdge::External { .. } => {}
+                }
+            }
+        }
+        edges
+    }
+
+    // Every dependency between project types, in (from, to, relation) order: types named in struct
+    // fields and enum variants (including generic arguments), alias targets, supertraits and trait impls
+    pub fn type_dependencies(&self) -> Vec<TypeEdge> {
+        let mut edges = std::collections::BTreeSet::new();
+        let mut add = |from: &str, names: Vec<String>, module_path: &str, relation: &'static str| {
+            for name in names {
+                if let Some(to) = self.resolve_type_key(&name, module_path) {
+                    edges.insert(TypeEdge { from: from.to_string(), to: to.clone(), relation });
+                }
+            }
+        };
+
+        for (key, (_, item)) in &self.types {
+            let module_path = key.rsplit_once("::").map_or("", |(module, _)| module);
+            match item {
+                Item::Struct(s) => {
+                    let names = s.fields.iter().flat_map(|f| referenced_type_names(&f.ty)).collect();
+                    add(key, names, module_path, "field");
+                }
+                Item::Union(u) => {
+                    let names = u.fields.named.iter().flat_map(|f| referenced_type_names(&f.ty)).collect();
+                    add(key, names, module_path, "field");
+                }
+                Item::Enum(e) => {
+                    let names = e
+                        .variants
+                        .iter()
+                        .flat_map(|v| v.fields.iter())
+                        .flat_map(|f| referenced_type_names(&f.ty))
+                        .collect();
+                    add(key, names, module_path, "variant");
+                }
+                Item::Type(t) => add(key, referenced_type_names(&t.ty), module_path, "alias"),
+                Item::Trait(t) => add(key, trait_bound_names(t.supertraits.iter()), module_path, "supertrait"),
+                _ => {}
+            }
+        }
+
+        for (trait_name, impls) in &self.trait_impls {
+            for imp in impls {
+                let self_name = imp.self_ty.split('<').next().unwrap_or_default().trim();
+                if let Some(from) = self.resolve_type_key(self_name, &imp.module_path) {
+                    add(from, vec![trait_name.clone()], &imp.module_path, "implements");
+                }
+            }
+        }
+
+        edges.into_iter().collect()
+    }
+
+    // Every call into `target` from its transitive callers up to `depth` levels, nearest callers first,
+    // and the functions those calls connect (the target included)
+    pub(crate) fn caller_edges(&self, target: &str, depth: Option<usize>) -> (BTreeSet<String>, Vec<CallEdge>) {
+        let graph = self.graph();
+        let mut included = BTreeSet::from([target.to_string()]);
+        let mut edges = vec![];
+        let mut frontier: Vec<FunctionId> = graph.id(target).into_iter().collect();
+        let mut level = 0;
+        while !frontier.is_empty() && depth.is_none_or(|depth| level < depth) {
+            let mut next = vec![];
+            for &callee in &frontier {
+                for edge in graph.calls_into(callee) {
+                    edges.push(edge.call.clone());
+                    if included.insert(edge.call.caller.clone()) {
+                        next.push(edge.from);
+                    }
+                }
+            }
+            frontier = next;
+            level += 1;
+        }
+        (included, edges)
+    }
+
+    // Direct callers of each function, from `call_edges`: callers in name order, one entry per caller
+    // with the context of its first call
+    pub fn callers_index(&self) -> BTreeMap<String, Vec<CallEdge>> {
+        let graph = self.graph();
+        let mut callers: BTreeMap<String, Vec<CallEdge>> = BTreeMap::new();
+        for (i, func) in graph.functions.iter().enumerate() {
+            let edges: Vec<CallEdge> = graph.callers(FunctionId(i)).into_iter().map(|edge| edge.call.clone()).collect();
+            if !edges.is_empty() {
+                callers.insert(func.qualified_name.clone(), edges);
+            }
+        }
+        callers
+    }
+
+    // Copy restricted to the files accepted by `keep`, e.g., one subdirectory or everything outside a
+    // blacklist. Stats and fingerprint still describe the full load.
+    pub fn filter_files(&self, keep: impl Fn(&str) -> bool) -> Project {
+        let function_file = |qn: &str| qn.split_once("::").map_or(qn, |(file, _)| file).to_string();
+        Project {
+            functions: self
+                .functions
+                .iter()
+                .filter(|(qn, _)| keep(&function_file(qn)))
+                .map(|(qn, f)| (qn.clone(), f.clone()))
+                .collect(),
+            types: self
+                .types
+                .iter()
+                .filter(|(_, (file, _))| keep(file))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            type_lines: self
+                .type_lines
+                .iter()
+                .filter(|(key, _)| self.types.get(*key).is_some_and(|(file, _)| keep(file)))
+                .map(|(key, lines)| (key.clone(), *lines))
+                .collect(),
+            items: self
+                .items
+                .iter()
+                .filter(|(_, (file, _))| keep(file))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            item_lines: self
+                .item_lines
+                .iter()
+                .filter(|(key, _)| self.items.get(*key).is_some_and(|(file, _)| keep(file)))
+                .map(|(key, lines)| (key.clone(), *lines))
+                .collect(),
+            type_bytes: self
+                .type_bytes
+                .iter()
+                .filter(|(key, _)| self.types.get(*key).is_some_and(|(file, _)| keep(file)))
+                .map(|(key, bytes)| (key.clone(), bytes.clone()))
+                .collect(),
+            item_bytes: self
+                .item_bytes
+                .iter()
+                .filter(|(key, _)| self.items.get(*key).is_some_and(|(file, _)| keep(file)))
+                .map(|(key, bytes)| (key.clone(), bytes.clone()))
+                .collect(),
+            sources: self
+                .sources
+                .iter()
+                .filter(|(file, _)| keep(file))
+                .map(|(file, text)| (file.clone(), text.clone()))
+                .collect(),
+            impl_consts: self.impl_consts.iter().filter(|constant| keep(&constant.file_path)).cloned().collect(),
+            impl_types: self.impl_types.iter().filter(|ty| keep(&ty.file_path)).cloned().collect(),
+            trait_impls: self
+                .trait_impls
+                .iter()
+                .map(|(name, impls)| {
+                    let impls = impls.iter().filter(|imp| keep(&imp.file_path)).cloned().collect();
+                    (name.clone(), impls)
+                })
+                .collect(),
+            imports: self.imports.iter().filter(|import| keep(&import.file_path)).cloned().collect(),
+            modules: self.modules.clone(),
+            public_modules: self
+                .public_modules
+                .iter()
+                .filter(|(_, file)| keep(file))
+                .map(|(module, file)| (module.clone(), file.clone()))
+                .collect(),
+            packages: self.packages.clone(),
+            loaded_from: self.loaded_from.clone(),
+            roots: self.roots.clone(),
+            stats: self.stats.clone(),
+            paths: std::sync::OnceLock::new(),
+        }
+    }
+
+    // Re-indexes only the `changed` files of a project loaded from `dir` with `options`: their entries are
+    // dropped, and the ones still part of the load (not deleted, blacklisted or test code) are parsed
+    // again. Paths are as the load walked them, e.g., "./src/lib.rs" for `dir` ".". The fingerprint and
+    // file counts are brought up to date with the whole directory.
+    pub fn reindex_files(&mut self, dir: &str, changed: &[std::path::PathBuf], options: &LoadOptions) {
+        let started = Instant::now();
+        let (files, mut walk_skipped) = walk_rust_files(dir, options);
+        let fingerprint = fingerprint_files(&files);
+        let files = indexed_files(dir, files, options, &mut walk_skipped);
+        let walk = started.elapsed();
+
+        let changed: HashSet<String> = changed.iter().map(|path| path.to_string_lossy().into_owned()).collect();
+        let is_changed = |path: &std::path::PathBuf| changed.contains(path.to_string_lossy().as_ref());
+        let mut project = self.filter_files(|file| !changed.contains(file));
+        project.stats.skipped.retain(|skipped| !changed.contains(&skipped.path));
+        project.stats.disabled_modules.retain(|(declared_in, _)| !changed.contains(declared_in));
+        project.stats.skipped.extend(walk_skipped.into_iter().filter(|skipped| changed.contains(&skipped.path)));
+
+        let mut part = Project::default();
+        let reparsed: Vec<std::path::PathBuf> = files.iter().filter(|path| is_changed(path)).cloned().collect();
+        load_files(&mut part, &reparsed, options);
+        part.stats.timings.walk = walk;
+        project.stats.timings = Timings::default();
+        project.stats.files_parsed = 0;
+        project.absorb(part);
+        drop_disabled_modules(&mut project, &files);
+        project.stats.skipped.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let indexed: HashSet<String> = files.iter().map(|path| path.to_string_lossy().into_owned()).collect();
+        let failed = project.stats.skipped.iter().filter(|skipped| indexed.contains(&skipped.path)).count();
+        project.stats.files_parsed = files.len() - failed;
+        project.stats.fingerprint = fingerprint;
+        if options.expand_trait_calls {
+            mark_trait_calls(&mut project);
+        }
+        *self = project;
+    }
+
+    // Crate path of an indexed function, e.g., "src/net.rs::Client::connect" -> "net::Client::connect"
+    pub fn function_crate_path(&self, qualified_name: &str) -> Option<String> {
+        let (file, rest) = qualified_name.split_once("::")?;
+        Some(join_path(&file_module_path(file), rest))
+    }
+
+    // Qualified name of the function defined at a crate path
+    pub fn function_at_crate_path(&self, crate_path: &str) -> Option<&String> {
+        self.paths().functions.get(crate_path)?.first()
+    }
+
+    fn paths(&self) -> &PathIndex {
+        self.paths.get_or_init(|| {
+            let mut paths = PathIndex::default();
+            for qn in self.functions.keys() {
+                if let Some(crate_path) = self.function_crate_path(qn) {
+                    paths.functions.entry(crate_path).or_default().push(qn.clone());
+                }
+                if let Some(package_path) = self.function_package_path(qn) {
+                    paths.packaged.entry(package_path).or_insert_with(|| qn.clone());
+                }
+            }
+            for import in self.imports.iter().filter(|import| import.is_pub && import.name != "*") {
+                paths
+                    .reexports
+                    .entry(join_path(&import.module, &import.name))
+                    .or_insert_with(|| self.absolute_path(&import.path, &import.module));
+            }
+            paths
+        })
+    }
+
+    // Crate path of an indexed type, from its `types` key
+    pub fn type_crate_path(&self, key: &str) -> Option<String> {
+        let (file, _) = self.types.get(key)?;
+        Some(join_path(&file_module_path(file), key))
+    }
+
+    // Crate path prefixed with the name of the crate compiling the file, e.g., "my_crate::net::connect";
+    // None when `packages` doesn't say which crate that is
+    pub fn function_package_path(&self, qualified_name: &str) -> Option<String> {
+        let (file, _) = qualified_name.split_once("::")?;
+        let target = workspace::crate_target(&self.packages, file)?;
+        Some(join_path(&target.crate_name, &self.function_crate_path(qualified_name)?))
+    }
+
+    pub fn type_package_path(&self, key: &str) -> Option<String> {
+        let (file, _) = self.types.get(key)?;
+        let target = workspace::crate_target(&self.packages, file)?;
+        Some(join_path(&target.crate_name, &self.type_crate_path(key)?))
+    }
+
+    // The function named by a path starting with its crate's name
+    pub fn function_at_package_path(&self, path: &str) -> Option<&Function> {
+        if self.packages.is_empty() {
+            return None;
+        }
+        self.functions.get(self.paths().packaged.get(path)?)
+    }
+
+    // Turns a `use`-style path written inside `from_module` into a path from the crate root
+    pub fn absolute_path(&self, path: &str, from_module: &str) -> String {
+        let mut segments: Vec<&str> = path.split("::").filter(|s| !s.is_empty()).collect();
+        let mut base: Vec<&str> = from_module.split("::").filter(|s| !s.is_empty()).collect();
+        match segments.first().copied() {
+            Some("crate") => {
+                segments.remove(0);
+                base.clear();
+            }
+            Some("self") => {
+                segments.remove(0);
+            }
+            Some("super") => {
+                while segments.first() == Some(&"super") {
+                    segments.remove(0);
+                    base.pop();
+                }
+            }
+            // Paths starting with a child module are relative; anything else is from the root
+            Some(first) if !self.modules.contains(&join_path(from_module, first)) => base.clear(),
+            _ => {}
+        }
+        base.extend(segments);
+        base.join("::")
+    }
+
+    // Follows `pub use` re-exports from a crate path (`prelude::Thing`, optionally `crate::`-prefixed)
+    // to where the item is defined (`net::client::Thing`)
+    pub fn canonical_path(&self, path: &str) -> String {
+        let mut current = self.absolute_path(path, "");
+        // Bounded so re-export cycles cannot loop forever
+        for _ in 0..16 {
+            // The longest re-exported prefix wins, e.g., "prelude::Thing" for "prelude::Thing::new"
+            let next = std::iter::successors(Some(current.as_str()), |path| path.rsplit_once("::").map(|(up, _)| up))
+                .find_map(|public| {
+                    let target = self.paths().reexports.get(public)?;
+                    Some(join_path(target, current[public.len()..].trim_start_matches("::")))
+                });
+            match next {
+                Some(next) if next != current => current = next,
+                _ => break,
+            }
+        }
+        current
+    }
+
+    // Public paths (`crate::prelude::Thing`) under which each defined item is re-exported, keyed by
+    // the item's canonical crate path
+    pub fn reexport_paths(&self) -> HashMap<String, Vec<String>> {
+        let mut paths: HashMap<String, Vec<String>> = HashMap::new();
+        for import in self.imports.iter().filter(|import| import.is_pub && import.name != "*") {
+            let public = join_path(&import.module, &import.name);
+            let canonical = self.canonical_path(&public);
+            if canonical != public {
+                paths.entry(canonical).or_default().push(format!("crate::{}", public));
+            }
+        }
+        for list in paths.values_mut() {
+            list.sort();
+            list.dedup();
+        }
+        paths
+    }
+}
+
+impl Timings {
+    // Adds another set of timings, e.g., from a later batch or another project
+    pub fn absorb(&mut self, other: Timings) {
+        self.walk += other.walk;
+        self.read += other.read;
+        self.parse += other.parse;
+        self.index += other.index;
+        self.trace += other.trace;
+        self.render += other.render;
+        self.slowest_files.extend(other.slowest_files);
+        self.slowest_files.sort_by_key(|(_, duration)| std::cmp::Reverse(*duration));
+        self.slowest_files.truncate(SLOWEST_FILES);
+    }
+
+    pub fn total(&self) -> Duration {
+        self.walk + self.read + self.parse + self.index + self.trace + self.render
+    }
+
+    pub fn report(&self) -> String {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let mut report = String::from("Profile:\n");
+        for (phase, duration) in [
+            ("walk", self.walk),
+            ("read", self.read),
+            ("parse", self.parse),
+            ("index", self.index),
+            ("trace", self.trace),
+            ("render", self.render),
+            ("total", self.total()),
+        ] {
+            report.push_str(&format!("  {:<8}{:>10.1} ms\n", phase, ms(duration)));
+        }
+        if !self.slowest_files.is_empty() {
+            report.push_str("Slowest files (read + parse + index):\n");
+            for (path, duration) in &self.slowest_files {
+                report.push_str(&format!("  {:>10.1} ms  {}\n", ms(*duration), path));
+            }
+        }
+        report
+    }
+}
+
+impl Function {
+    pub fn signature(&self) -> String {
+        self.signature_named(&self.qualified_name)
+    }
+
+    // The signature with `name` in place of the qualified name, e.g., just the method name inside an impl
+    pub fn signature_named(&self, name: &str) -> String {
+        let vis = visibility_to_string(&self.vis);
+        let asyncness = if self.sig.asyncness.is_some() {
+            "async "
+        } else {
+            ""
+        };
+        let constness = if self.sig.constness.is_some() {
+            "const "
+        } else {
+            ""
+        };
+        let unsafety = if self.sig.unsafety.is_some() {
+            "unsafe "
+        } else {
+            ""
+        };
+        let unsafety = format!("{}{}", unsafety, abi_prefix(&self.sig));
+        let generics = format_generics(&self.sig.generics);
+        let args = format_args(&self.sig.inputs.iter().collect::<Vec<_>>());
+        let ret = match &self.sig.output {
+            syn::ReturnType::Default => "()".to_string(),
+            syn::ReturnType::Type(_, ty) => format_type(ty),
+        };
+        let where_clause = format_where_clause(&self.sig.generics);
+
+        format!(
+            "{}{}{}{}fn {}{}({}) -> {}{}",
+            vis, asyncness, constness, unsafety, name, generics, args, ret, where_clause
+        )
+    }
+
+    // The function as written when the project kept its file's text, else its signature and indented body
+    pub fn full_body(&self, project: &Project) -> String {
+        if let Some(text) = project.source_text(ItemRef::Function(self)) {
+            return format!("{}\n", text);
+        }
+        let sig = project.signature(self);
+        if let Some(block) = &self.block {
+            format!("{}\n{{\n{}}}\n", sig, indent_block(block))
+        } else {
+            format!("{}\n{{ ... }}\n", sig)
+        }
+    }
+
+    // Cyclomatic complexity: 1 plus one per `if`, `while`, `for`, extra match arm, `&&`/`||` and `?`.
+    // Functions without a body count as 1.
+    pub fn complexity(&self) -> usize {
+        let mut visitor = ComplexityVisitor::default();
+        if let Some(block) = &self.block {
+            visitor.visit_block(block);
+        }
+        1 + visitor.branches
+    }
+
+    // Whether one of its attributes is `name`, given with or without `#[..]`: "instrument" matches
+    // `#[instrument(skip(self))]` and `#[tracing::instrument]`, "tokio::main" only the latter path, and a
+    // name with arguments, e.g., "cfg_attr(test, inline)", only that attribute as written
+    pub fn has_attribute(&self, name: &str) -> bool {
+        let name = name.trim();
+        let name = name.strip_prefix("#[").and_then(|name| name.strip_suffix(']')).unwrap_or(name).trim();
+        self.attributes.iter().any(|attribute| {
+            if name.contains('(') || name.contains('=') {
+                return attribute == name;
+            }
+            let path = attribute.split(['(', ' ', '=']).next().unwrap_or(attribute);
+            path == name || path.strip_suffix(name).is_some_and(|prefix| prefix.ends_with("::"))
+        })
+    }
+
+    pub fn calls(&self) -> Vec<CallSite> {
+        self.call_sites.clone()
+    }
+
+    fn extract_call_sites(&self, closure_nodes: Option<usize>) -> Vec<CallSite> {
+        let mut calls = vec![];
+        if let Some(block) = &self.block {
+            extract_calls_from_block(block, &mut calls, closure_nodes);
+        }
+
+        // A binding passed along as an argument or stored in a literal is a value, not a callback to a project
+        // function: a parameter, or a name bound by a `let`, `for`, closure, `if let` or match arm pattern
+        let mut locals = PatBindingVisitor::default();
+        locals.visit_signature(&self.sig);
+        if let Some(block) = &self.block {
+            locals.visit_block(block);
+        }
+        let locals = locals.names;
+        calls.retain(|call| !call.callback || !locals.contains(&call.name));
+
+        let bounded_args = self.trait_bounded_args();
+        let trait_objects = self.trait_object_bindings();
+        let receiver_types = self.receiver_types();
+        for call in &mut calls {
+            // The start of a chain is typed the same way
+            let mut link = Some(call);
+            while let Some(call) = link {
+                if let Some(receiver) = &call.receiver {
+                    if let Some(traits) = trait_objects.get(receiver) {
+                        call.dispatch = Dispatch::Dynamic(traits.clone());
+                    } else if let Some(traits) = bounded_args.get(receiver) {
+                        call.dispatch = Dispatch::Generic(traits.clone());
+                    } else {
+                        call.receiver_type = receiver_types.get(receiver).cloned();
+                    }
+                }
+                link = call.receiver_call.as_deref_mut();
+            }
+        }
+
+        calls
+    }
+
+    // Maps `self`, parameters and `let` bindings to the type they hold, where it can be read off the
+    // source: a type annotation, a struct literal, or a constructor-style call such as `Config::new(..)`
+    fn receiver_types(&self) -> HashMap<String, String> {
+        let generic_params: HashSet<String> = self
+            .impl_generics
+            .iter()
+            .chain(std::iter::once(&self.sig.generics))
+            .flat_map(|generics| generics.type_params().map(|t| t.ident.to_string()))
+            .collect();
+        let named = |ty: &Type| {
+            named_type(ty, self.self_type.as_deref()).filter(|name| !generic_params.contains(name))
+        };
+
+        let mut types = HashMap::new();
+        for arg in &self.sig.inputs {
+            match arg {
+                FnArg::Receiver(_) => {
+                    if let Some(self_type) = &self.self_type {
+                        types.insert("self".to_string(), self_type.clone());
+                    }
+                }
+                FnArg::Typed(pat_type) => {
+                    if let syn::Pat::Ident(pat_ident) = pat_type.pat.as_ref() {
+                        if let Some(name) = named(&pat_type.ty) {
+                            types.insert(pat_ident.ident.to_string(), name);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(block) = &self.block {
+            let mut visitor = LetTypeVisitor::default();
+            visitor.visit_block(block);
+            for (name, ty) in visitor.bindings {
+                match named(&ty) {
+                    Some(type_name) => types.insert(name, type_name),
+                    None => types.remove(&name),
+                };
+            }
+            for (name, type_name) in visitor.constructed {
+                let type_name = match type_name.as_str() {
+                    "Self" => self.self_type.clone(),
+                    _ => Some(type_name),
+                };
+                if let Some(type_name) = type_name {
+                    types.insert(name, type_name);
+                }
+            }
+        }
+        types
+    }
+
+    // Maps arguments and annotated `let` bindings to the traits of their trait-object type
+    fn trait_object_bindings(&self) -> HashMap<String, Vec<String>> {
+        let mut bindings = HashMap::new();
+        for arg in &self.sig.inputs {
+            if let FnArg::Typed(pat_type) = arg {
+                if let syn::Pat::Ident(pat_ident) = pat_type.pat.as_ref() {
+                    let traits = trait_object_traits(&pat_type.ty);
+                    if !traits.is_empty() {
+                        bindings.insert(pat_ident.ident.to_string(), traits);
+                    }
+                }
+            }
+        }
+
+        if let Some(block) = &self.block {
+            let mut visitor = LetTypeVisitor::default();
+            visitor.visit_block(block);
+            for (name, ty) in visitor.bindings {
+                let traits = trait_object_traits(&ty);
+                if !traits.is_empty() {
+                    bindings.insert(name, traits);
+                }
+            }
+        }
+        bindings
+    }
+
+    // Maps argument names to the traits bounding their generic type (`t: T` with `T: Storage`, `s: &impl Storage`)
+    fn trait_bounded_args(&self) -> HashMap<String, Vec<String>> {
+        let mut type_bounds: HashMap<String, Vec<String>> = HashMap::new();
+        for generics in self.impl_generics.iter().chain(std::iter::once(&self.sig.generics)) {
+            for param in &generics.params {
+                if let GenericParam::Type(t) = param {
+                    type_bounds
+                        .entry(t.ident.to_string())
+                        .or_default()
+                        .extend(trait_bound_names(t.bounds.iter()));
+                }
+            }
+            if let Some(where_clause) = &generics.where_clause {
+                for predicate in &where_clause.predicates {
+                    if let WherePredicate::Type(pt) = predicate {
+                        if let Type::Path(p) = &pt.bounded_ty {
+                            if let Some(ident) = p.path.get_ident() {
+                                type_bounds
+                                    .entry(ident.to_string())
+                                    .or_default()
+                                    .extend(trait_bound_names(pt.bounds.iter()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut bounded_args = HashMap::new();
+        for arg in &self.sig.inputs {
+            if let FnArg::Typed(pat_type) = arg {
+                if let syn::Pat::Ident(pat_ident) = pat_type.pat.as_ref() {
+                    let traits = bound_traits_of_type(&pat_type.ty, &type_bounds);
+                    if !traits.is_empty() {
+                        bounded_args.insert(pat_ident.ident.to_string(), traits);
+                    }
+                }
+            }
+        }
+        bounded_args
+    }
+
+    pub fn from_fn(f: &syn::ItemFn, file_path: &str) -> Self {
+        Function {
+            vis: f.vis.clone(),
+            sig: f.sig.clone(),
+            block: Some(*f.block.clone()),
+            qualified_name: format!("{}::{}", file_path, f.sig.ident),
+            impl_generics: None,
+            call_sites: vec![],
+            module_path: String::new(),
+            self_type: None,
+            impl_trait: None,
+            start_line: 0,
+            end_line: 0,
+            column: 0,
+            byte_range: 0..0,
+            type_refs: vec![],
+            unsafe_blocks: vec![],
+            docs: doc_comment(&f.attrs),
+            attributes: attribute_texts(&f.attrs),
+            cfg: None,
+        }
+        .with_position(f, &f.attrs, f.sig.ident.span())
+        .with_call_sites()
+        .with_type_refs()
+        .with_unsafe_blocks()
+    }
+
+    // Inherent methods are keyed `Type::method`, trait impl methods `<Type as Trait>::method`
+    pub fn from_impl_method(
+        method: &syn::ImplItemFn,
+        impl_target_str: String,
+        trait_path: Option<&syn::Path>,
+        impl_generics: &syn::Generics,
+        file_path: &str,
+    ) -> Self {
+        let impl_segment = match trait_path {
+            Some(path) => trait_impl_segment(&impl_target_str, &trait_ref(path)),
+            None => impl_target_str.clone(),
+        };
+        Function {
+            vis: method.vis.clone(),
+            sig: method.sig.clone(),
+            block: Some(method.block.clone()),
+            qualified_name: format!("{}::{}::{}", file_path, impl_segment, method.sig.ident),
+            impl_generics: Some(impl_generics.clone()),
+            call_sites: vec![],
+            module_path: String::new(),
+            self_type: Some(base_type_name(&impl_target_str).to_string()),
+            impl_trait: trait_path.map(format_path),
+            start_line: 0,
+            end_line: 0,
+            column: 0,
+            byte_range: 0..0,
+            type_refs: vec![],
+            unsafe_blocks: vec![],
+            docs: doc_comment(&method.attrs),
+            attributes: attribute_texts(&method.attrs),
+            cfg: None,
+        }
+        .with_position(method, &method.attrs, method.sig.ident.span())
+        .with_call_sites()
+        .with_type_refs()
+        .with_unsafe_blocks()
+    }
+
+    pub fn from_trait_method(method: &syn::TraitItemFn, trait_item: &syn::ItemTrait, file_path: &str) -> Self {
+        Function {
+            vis: trait_item.vis.clone(),
+            sig: method.sig.clone(),
+            block: method.default.clone(),
+            qualified_name: format!("{}::{}::{}", file_path, trait_item.ident, method.sig.ident),
+            impl_generics: Some(trait_item.generics.clone()),
+            call_sites: vec![],
+            module_path: String::new(),
+            self_type: None,
+            impl_trait: None,
+            start_line: 0,
+            end_line: 0,
+            column: 0,
+            byte_range: 0..0,
+            type_refs: vec![],
+            unsafe_blocks: vec![],
+            docs: doc_comment(&method.attrs),
+            attributes: attribute_texts(&method.attrs),
+            cfg: None,
+        }
+        .with_position(method, &method.attrs, method.sig.ident.span())
+        .with_call_sites()
+        .with_type_refs()
+        .with_unsafe_blocks()
+    }
+
+    // A function declared in an `extern` block, e.g., `fn strlen(s: *const c_char) -> usize;` in `extern "C"`.
+    // It has no body; its signature is as callers see it, `unsafe extern "C" fn`.
+    pub fn from_foreign_fn(f: &syn::ForeignItemFn, abi: &syn::Abi, file_path: &str) -> Self {
+        let mut sig = f.sig.clone();
+        sig.unsafety = Some(Default::default());
+        sig.abi = Some(abi.clone());
+        Function {
+            vis: f.vis.clone(),
+            sig,
+            block: None,
+            qualified_name: format!("{}::{}", file_path, f.sig.ident),
+            impl_generics: None,
+            call_sites: vec![],
+            module_path: String::new(),
+            self_type: None,
+            impl_trait: None,
+            start_line: 0,
+            end_line: 0,
+            column: 0,
+            byte_range: 0..0,
+            type_refs: vec![],
+            unsafe_blocks: vec![],
+            docs: doc_comment(&f.attrs),
+            attributes: attribute_texts(&f.attrs),
+            cfg: None,
+        }
+        .with_position(f, &f.attrs, f.sig.ident.span())
+        .with_type_refs()
+    }
+
+    // A closure materialized as a pseudo-function, e.g. `file::main::{closure@12}`
+    pub fn from_closure(closure: &syn::ExprClosure, line: &usize, parent: &str) -> Self {
+        let inputs = closure.inputs.iter().map(|pat| match pat {
+            syn::Pat::Type(pat_type) => pat_type.to_token_stream(),
+            untyped => quote::quote!(#untyped: _),
+        });
+        let mut sig: syn::Signature = syn::parse_quote!(fn closure(#(#inputs),*));
+        sig.asyncness = closure.asyncness;
+        sig.output = closure.output.clone();
+
+        let block = match closure.body.as_ref() {
+            Expr::Block(b) => b.block.clone(),
+            body => Block {
+                brace_token: Default::default(),
+                stmts: vec![syn::Stmt::Expr(body.clone(), None)],
+            },
+        };
+
+        Function {
+            vis: Visibility::Inherited,
+            sig,
+            block: Some(block),
+            qualified_name: format!("{}::{}", parent, closure_node_name(*line)),
+            impl_generics: None,
+            call_sites: vec![],
+            module_path: String::new(),
+            self_type: None,
+            impl_trait: None,
+            start_line: 0,
+            end_line: 0,
+            column: 0,
+            byte_range: 0..0,
+            type_refs: vec![],
+            unsafe_blocks: vec![],
+            docs: None,
+            attributes: attribute_texts(&closure.attrs),
+            cfg: None,
+        }
+        .with_position(closure, &closure.attrs, closure.or1_token.span)
+        .with_call_sites()
+        .with_type_refs()
+        .with_unsafe_blocks()
+    }
+
+    // The impl block a method is keyed under, e.g., "Config<T>" or "<Length as From<f64>>"; None outside impls
+    pub fn impl_segment(&self) -> Option<&str> {
+        self.self_type.as_ref()?;
+        let (_, mut rest) = self.qualified_name.split_once("::")?;
+        if !self.module_path.is_empty() {
+            rest = rest.strip_prefix(&self.module_path)?.strip_prefix("::")?;
+        }
+        rest.strip_suffix(&self.sig.ident.to_string())?.strip_suffix("::")
+    }
+
+    // Crate module the function is declared in, combining its file's location and inline modules
+    pub fn crate_module(&self) -> String {
+        let file = self.qualified_name.split_once("::").map_or("", |(file, _)| file);
+        join_path(&file_module_path(file), &self.module_path)
+    }
+
+    fn in_module(mut self, module_path: &str) -> Self {
+        self.module_path = module_path.to_string();
+        self
+    }
+
+    // Several predicates are shown as the `all(..)` of them
+    fn with_cfg(mut self, cfgs: Vec<String>) -> Self {
+        self.cfg = match cfgs.len() {
+            0 => None,
+            1 => cfgs.into_iter().next(),
+            _ => Some(format!("all({})", cfgs.join(", "))),
+        };
+        self
+    }
+
+    // Lines and bytes of the whole item, and the column of the name an editor should jump to
+    fn with_position(mut self, node: &impl Spanned, attrs: &[syn::Attribute], name: proc_macro2::Span) -> Self {
+        (self.start_line, self.end_line) = line_range(node);
+        self.byte_range = byte_range(node, attrs);
+        self.column = name.start().column + 1;
+        self
+    }
+
+    fn with_call_sites(mut self) -> Self {
+        self.call_sites = self.extract_call_sites(None);
+        self
+    }
+
+    fn with_type_refs(mut self) -> Self {
+        self.type_refs = self.extract_type_refs(None);
+        self
+    }
+
+    fn with_unsafe_blocks(mut self) -> Self {
+        self.unsafe_blocks = self.extract_unsafe_blocks(None);
+        self
+    }
+
+    // Outermost `unsafe {}` blocks, in source order; closures that become nodes keep their own
+    fn extract_unsafe_blocks(&self, closure_nodes: Option<usize>) -> Vec<(usize, usize)> {
+        let mut visitor = UnsafeBlockVisitor { closure_nodes, blocks: vec![] };
+        if let Some(block) = &self.block {
+            visitor.visit_block(block);
+        }
+        visitor.blocks
+    }
+
+    // Signature types first, then the body; closures that become nodes keep their own references
+    fn extract_type_refs(&self, closure_nodes: Option<usize>) -> Vec<TypeRef> {
+        let mut visitor = TypeRefVisitor {
+            self_type: self.self_type.as_deref(),
+            closure_nodes,
+            refs: vec![],
+        };
+        for arg in &self.sig.inputs {
+            match arg {
+                FnArg::Typed(pat_type) => visitor.record_type(TypeUsage::Receives, &pat_type.ty),
+                FnArg::Receiver(receiver) => {
+                    let text = match (&receiver.reference, &receiver.mutability) {
+                        (Some(_), Some(_)) => "&mut self",
+                        (Some(_), None) => "&self",
+                        (None, Some(_)) => "mut self",
+                        (None, None) => "self",
+                    };
+                    visitor.record(TypeUsage::Receives, "Self", text.to_string(), receiver.span());
+                }
+            }
+        }
+        if let syn::ReturnType::Type(_, ty) = &self.sig.output {
+            visitor.record_type(TypeUsage::Returns, ty);
+        }
+        for param in self.sig.generics.type_params() {
+            visitor.record_bounds(&param.ident.to_string(), param.bounds.iter(), param.span());
+        }
+        if let Some(where_clause) = &self.sig.generics.where_clause {
+            for predicate in &where_clause.predicates {
+                if let WherePredicate::Type(pt) = predicate {
+                    visitor.record_bounds(&format_type(&pt.bounded_ty), pt.bounds.iter(), pt.span());
+                }
+            }
+        }
+        if let Some(block) = &self.block {
+            visitor.visit_block(block);
+        }
+        visitor.refs
+    }
+}
+
+pub fn trace_calls(
+    root_func: &str,
+    project: &Project,
+) -> Result<(HashSet<String>, HashSet<String>), MorphoError> {
+    let mut visited = HashSet::new();
+    let mut reachable_types = HashSet::<String>::new();
+
+    if !project.functions.contains_key(root_func) {
+        return Err(MorphoError::not_found("function", root_func));
+    }
+
+    _trace_calls(root_func, project, None, None, &mut visited, &mut reachable_types);
+
+    Ok((visited, reachable_types))
+}
+
+// With `home`, a loaded directory, functions outside it are reached but not traced further, so a graph
+// stops where it calls into a dependency; with `scope`, functions outside it aren't reached at all
+fn _trace_calls(
+    func_name: &str,
+    project: &Project,
+    home: Option<&str>,
+    scope: Option<&str>,
+    visited: &mut HashSet<String>,
+    reachable_types: &mut HashSet<String>,
+) {
+    // Try exact match first, then try to find by short name
+    let func_entry = project.functions.get_key_value(func_name).or_else(|| {
+        // If not found, try to find a function whose qualified name ends with ::func_name
+        project.functions.iter()
+            .find(|(qualified_name, _)| {
+                qualified_name.ends_with(&format!("::{}", func_name))
+            })
+    });
+
+    let (qualified_name, func) = match func_entry {
+        Some((qn, f)) => (qn, f),
+        None => {
+            // Function not found - this can happen for external crate functions, macros, etc.
+            // Just skip it silently
+            return;
+        }
+    };
+
+    // The root is traced wherever it is
+    if !visited.is_empty() && scope.is_some_and(|scope| !in_scope(func, scope, project)) {
+        return;
+    }
+
+    // Use the actual qualified name for visited tracking
+    if !visited.insert(qualified_name.clone()) {
+        return;
+    }
+    if home.is_some_and(|home| project.root_of(qualified_name) != Some(home)) {
+        return;
+    }
+
+    // Types named in the signature (generic arguments included, so `Option<Config>` reaches `Config`) and
+    // the body: struct literals, variants, `Config::default()` paths, turbofish and patterns. Trait bounds
+    // are left out. Names resolve to the closest definition in scope of the function's module.
+    for type_ref in func.type_refs.iter().filter(|r| r.usage != TypeUsage::Bounds) {
+        match project.resolve_type_key(&type_ref.name, &func.module_path) {
+            Some(key) => reachable_types.insert(key.clone()),
+            None => reachable_types.insert(type_ref.name.clone()),
+        };
+    }
+
+    for callee in &func.calls() {
+        if let Some((_, targets)) = dispatch_targets(callee, &func.module_path, project) {
+            for target in &targets {
+                _trace_calls(target, project, home, scope, visited, reachable_types);
+            }
+            continue;
+        }
+
+        match receiver_method(func, callee, project) {
+            Some(ReceiverMethod::Found(method)) => {
+                _trace_calls(&method, project, home, scope, visited, reachable_types);
+                continue;
+            }
+            Some(ReceiverMethod::External) => continue,
+            None => {}
+        }
+
+        // Paths and names brought in by `use` (possibly under an alias) are followed to their definition
+        match path_callee(func, callee, project) {
+            Some(PathCallee::Found(target)) => {
+                _trace_calls(&target, project, home, scope, visited, reachable_types);
+                continue;
+            }
+            Some(PathCallee::External) => continue,
+            None => {}
+        }
+
+        // Functions nested in the caller's body or its module shadow same-named functions elsewhere,
+        // which in turn shadow names brought in by glob imports
+        if let Some(scoped) = scoped_callee_candidates(func, callee)
+            .into_iter()
+            .find(|candidate| project.functions.contains_key(candidate))
+        {
+            _trace_calls(&scoped, project, home, scope, visited, reachable_types);
+            continue;
+        }
+        let globbed = glob_callee_candidates(func, &callee.name, project);
+        if globbed.is_empty() {
+            if let Some(target) = suffix_callee(func, callee, project.functions.keys(), project) {
+                _trace_calls(&target, project, home, scope, visited, reachable_types);
+            }
+        }
+        for (_, target) in globbed {
+            _trace_calls(&target, project, home, scope, visited, reachable_types);
+        }
+    }
+}
+
+// Expands a call through a trait bound or trait object into the trait method plus every known
+// implementor's method. Returns None when the receiver isn't trait-typed or the trait isn't in the project.
+fn dispatch_targets(call: &CallSite, caller_module: &str, project: &Project) -> Option<(String, Vec<String>)> {
+    let bounds = match &call.dispatch {
+        Dispatch::Static => return None,
+        Dispatch::Generic(bounds) | Dispatch::Dynamic(bounds) | Dispatch::Trait(bounds) => bounds,
+    };
+
+    let (trait_name, trait_key, trait_file) = bounds.iter().find_map(|trait_name| {
+        let key = project.resolve_type_key(trait_name, caller_module)?;
+        match project.types.get(key) {
+            Some((file_path, Item::Trait(t))) if trait_declares_method(t, &call.name) => {
+                Some((trait_name, key, file_path))
+            }
+            _ => None,
+        }
+    })?;
+
+    let mut targets = vec![];
+    let trait_method = format!("{}::{}::{}", trait_file, trait_key, call.name);
+    if project.functions.contains_key(&trait_method) {
+        targets.push(trait_method);
+    }
+    // The trait method leads; implementors follow in name order
+    let mut impl_methods: Vec<String> = project
+        .trait_impls
+        .get(trait_name)
+        .into_iter()
+        .flatten()
+        .map(|imp| {
+            let impl_segment = trait_impl_segment(&imp.self_ty, &imp.trait_ref);
+            if imp.module_path.is_empty() {
+                format!("{}::{}::{}", imp.file_path, impl_segment, call.name)
+            } else {
+                format!("{}::{}::{}::{}", imp.file_path, imp.module_path, impl_segment, call.name)
+            }
+        })
+        .filter(|impl_method| project.functions.contains_key(impl_method))
+        .collect();
+    impl_methods.sort();
+    targets.extend(impl_methods);
+
+    Some((trait_name.clone(), targets))
+}
+
+fn trait_declares_method(t: &syn::ItemTrait, method: &str) -> bool {
+    t.items
+        .iter()
+        .any(|item| matches!(item, syn::TraitItem::Fn(f) if f.sig.ident == method))
+}
+
+pub fn generate_output(dir: &str, mode: OutputMode) -> Result<Output, MorphoError> {
+    generate_output_with_blacklist(dir, mode, &[])
+}
+
+pub fn generate_output_multi_dir(dirs: &[String], mode: OutputMode, blacklist: &[String]) -> Result<Output, MorphoError> {
+    let project = load_multiple_projects(dirs, blacklist)?;
+    generate_output_for_project(&project, mode)
+}
+
+pub fn generate_output_with_blacklist(dir: &str, mode: OutputMode, blacklist: &[String]) -> Result<Output, MorphoError> {
+    let project = load_project_with_blacklist(dir, blacklist)?;
+    generate_output_for_project(&project, mode)
+}
+
+pub fn generate_output_with_options(dir: &str, mode: OutputMode, options: &LoadOptions) -> Result<Output, MorphoError> {
+    generate_output_with_format(dir, mode, options, OutputFormat::Text)
+}
+
+pub fn generate_output_with_format(
+    dir: &str,
+    mode: OutputMode,
+    options: &LoadOptions,
+    format: OutputFormat,
+) -> Result<Output, MorphoError> {
+    let mut content = vec![];
+    let output = generate_output_to(&mut content, dir, mode, options, format)?;
+    let content = String::from_utf8(content).expect("output is always UTF-8");
+    Ok(Output { content, ..output })
+}
+
+// As `generate_output_with_format`, writing the output to `out` as it is rendered rather than returning it
+// in `content`, which is left empty. Text listings without a budget are written a file at a time (a batch of
+// files under a memory limit); other outputs are rendered whole, then written.
+pub fn generate_output_to(
+    out: &mut impl Write,
+    dir: &str,
+    mode: OutputMode,
+    options: &LoadOptions,
+    format: OutputFormat,
+) -> Result<Output, MorphoError> {
+    if let Some(limit_mb) = options.memory_limit_mb {
+        return match (mode, format) {
+            (OutputMode::ListAll { visibility, hide_items, include_docs, .. }, OutputFormat::Text) => {
+                generate_list_all_low_memory(dir, visibility, hide_items, include_docs, options, limit_mb, out)
+            }
+            _ => Err(MorphoError::Unsupported(
+                "A memory limit is only supported when listing items as text".to_string(),
+            )),
+        };
+    }
+
+    if let OutputMode::Diff { before_dir, after_dir, root } = mode {
+        let output = generate_diff_output(&before_dir, &after_dir, root.as_deref(), options, format)?;
+        out.write_all(output.content.as_bytes()).map_err(write_error)?;
+        return Ok(Output { content: String::new(), ..output });
+    }
+    if let OutputMode::Semver { before_dir, after_dir } = mode {
+        let output = generate_semver_output(&before_dir, &after_dir, options, format)?;
+        out.write_all(output.content.as_bytes()).map_err(write_error)?;
+        return Ok(Output { content: String::new(), ..output });
+    }
+
+    let project = load_project_with_options(dir, options)?;
+    generate_output_for_project_to(out, &project, mode, format)
+}
+
+fn generate_diff_output(
+    before_dir: &str,
+    after_dir: &str,
+    root: Option<&str>,
+    options: &LoadOptions,
+    format: OutputFormat,
+) -> Result<Output, MorphoError> {
+    // Diffs name functions relative to each directory themselves
+    let options = &LoadOptions { absolute_paths: false, ..options.clone() };
+    let before = load_project_with_options(before_dir, options)?;
+    let after = load_project_with_options(after_dir, options)?;
+    let mut timings = after.stats.timings.clone();
+    timings.absorb(before.stats.timings.clone());
+
+    let started = Instant::now();
+    let diff = diff::diff_projects(before_dir, &before, after_dir, &after, root)?;
+    timings.trace = started.elapsed();
+
+    let started = Instant::now();
+    let content = match format {
+        OutputFormat::Text => diff::render_text(&diff, before_dir, after_dir),
+        OutputFormat::Json => {
+            let document = json::diff(&diff, before_dir, &before, after_dir, &after, root);
+            serde_json::to_string_pretty(&document).expect("JSON values always serialize")
+        }
+        OutputFormat::Mermaid => {
+            return Err(MorphoError::Unsupported(
+                "Mermaid output is only available for call graphs, callers, call paths and cycles".to_string(),
+            ))
+        }
+        OutputFormat::RustdocJson => {
+            return Err(MorphoError::Unsupported("rustdoc JSON output is only available for listings".to_string()))
+        }
+    };
+    timings.render = started.elapsed();
+    Ok(Output {
+        content,
+        fingerprint: combine_fingerprints(&[before.stats.fingerprint, after.stats.fingerprint]),
+        timings,
+    })
+}
+
+fn generate_semver_output(
+    before_dir: &str,
+    after_dir: &str,
+    options: &LoadOptions,
+    format: OutputFormat,
+) -> Result<Output, MorphoError> {
+    let before = load_project_with_options(before_dir, options)?;
+    let after = load_project_with_options(after_dir, options)?;
+    let mut timings = after.stats.timings.clone();
+    timings.absorb(before.stats.timings.clone());
+
+    let started = Instant::now();
+    let report = semver::compare_apis(&before, &after);
+    timings.trace = started.elapsed();
+
+    let started = Instant::now();
+    let content = match format {
+        OutputFormat::Text => semver::render_text(&report),
+        OutputFormat::Json => {
+            let document = json::semver(&report, before_dir, after_dir);
+            serde_json::to_string_pretty(&document).expect("JSON values always serialize")
+        }
+        OutputFormat::Mermaid => {
+            return Err(MorphoError::Unsupported(
+                "Mermaid output is only available for call graphs, callers, call paths and cycles".to_string(),
+            ))
+        }
+        OutputFormat::RustdocJson => {
+            return Err(MorphoError::Unsupported("rustdoc JSON output is only available for listings".to_string()))
+        }
+    };
+    timings.render = started.elapsed();
+    Ok(Output {
+        content,
+        fingerprint: combine_fingerprints(&[before.stats.fingerprint, after.stats.fingerprint]),
+        timings,
+    })
+}
+
+// JSON documents and Mermaid diagrams are built straight from the index, so their whole cost is reported
+// as render time
+pub fn generate_output_for_project_with_format(
+    project: &Project,
+    mode: OutputMode,
+    format: OutputFormat,
+) -> Result<Output, MorphoError> {
+    match format {
+        OutputFormat::Text => generate_output_for_project(project, mode),
+        OutputFormat::Json | OutputFormat::RustdocJson => {
+            let mut timings = project.stats.timings.clone();
+            let started = Instant::now();
+            let document = match format {
+                OutputFormat::Json => json::render(project, mode)?,
+                _ => rustdoc::render(project, mode)?,
+            };
+            let content = serde_json::to_string_pretty(&document).expect("JSON values always serialize");
+            timings.render = started.elapsed();
+            Ok(Output {
+                content,
+                fingerprint: project.stats.fingerprint.clone(),
+                timings,
+            })
+        }
+        OutputFormat::Mermaid => {
+            let mut timings = project.stats.timings.clone();
+            let started = Instant::now();
+            let content = mermaid::render(project, mode)?;
+            timings.render = started.elapsed();
+            Ok(Output {
+                content,
+                fingerprint: project.stats.fingerprint.clone(),
+                timings,
+            })
+        }
+    }
+}
+
+// As `generate_output_for_project_with_format`, writing the output to `out`; see `generate_output_to`
+pub fn generate_output_for_project_to(
+    out: &mut impl Write,
+    project: &Project,
+    mode: OutputMode,
+    format: OutputFormat,
+) -> Result<Output, MorphoError> {
+    match (mode, format) {
+        (OutputMode::ListAll { visibility, hide_items, include_docs, budget, page }, OutputFormat::Text)
+            if budget.max_nodes.is_none() && budget.max_bytes.is_none() =>
+        {
+            let mut timings = project.stats.timings.clone();
+            let started = Instant::now();
+            let reexports = list_reexports(project, visibility);
+            let page = project.list_page(visibility, hide_items, page.offset, page.limit)?;
+            write_list_all(project, &page.items, &reexports, include_docs, out).map_err(write_error)?;
+            out.write_all(list_all_footer(&page, page.items.len()).as_bytes()).map_err(write_error)?;
+            out.flush().map_err(write_error)?;
+            timings.render = started.elapsed();
+            Ok(Output {
+                content: String::new(),
+                fingerprint: project.stats.fingerprint.clone(),
+                timings,
+            })
+        }
+        (mode, format) => {
+            let output = generate_output_for_project_with_format(project, mode, format)?;
+            out.write_all(output.content.as_bytes()).map_err(write_error)?;
+            Ok(Output { content: String::new(), ..output })
+        }
+    }
+}
+
+fn write_error(e: std::io::Error) -> MorphoError {
+    MorphoError::Write(e.to_string())
+}
+
+pub fn generate_output_for_project(project: &Project, mode: OutputMode) -> Result<Output, MorphoError> {
+    let mut timings = project.stats.timings.clone();
+    let output = match mode {
+        OutputMode::ListAll { visibility, hide_items, include_docs, budget, page } => {
+            let started = Instant::now();
+            let output = generate_list_all(project, visibility, hide_items, include_docs, budget, page);
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::CallGraph { roots, visibility, budget, descend_into_deps, show_external, scope } => {
+            let started = Instant::now();
+            let graph = project.call_graph_limited(&roots, &TraceLimits { stop_at_deps: !descend_into_deps, scope });
+            timings.trace = started.elapsed();
+
+            let started = Instant::now();
+            let output = graph
+                .and_then(|graph| generate_call_graph_output(project, &graph, visibility, budget, show_external));
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::Callers { target, depth } => {
+            let started = Instant::now();
+            let callers = project.callers_of(&target, depth);
+            timings.trace = started.elapsed();
+
+            let started = Instant::now();
+            let output = callers.and_then(|callers| generate_callers_output(project, &callers, depth));
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::Source { function, include_docs } => {
+            let started = Instant::now();
+            let output = generate_source(project, &function, include_docs);
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::TypeUsages { type_name } => {
+            let started = Instant::now();
+            let output = generate_type_usages(project, &type_name);
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::TypeApi { type_name } => {
+            let started = Instant::now();
+            let output = generate_type_api(project, &type_name);
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::Implementors { trait_name } => {
+            let started = Instant::now();
+            let output = project.implementors(&trait_name).map(|implementors| generate_implementors(project, &implementors));
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::Search { query, limit } => {
+            let started = Instant::now();
+            let output = generate_search(project, &query, limit);
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::Diff { .. } | OutputMode::Semver { .. } => Err(diff_needs_directories()),
+        OutputMode::Unreachable { roots } => {
+            let started = Instant::now();
+            let unreachable = project.unreachable(&roots);
+            timings.trace = started.elapsed();
+
+            let started = Instant::now();
+            let output = unreachable.and_then(|unreachable| generate_unreachable_output(project, &roots, &unreachable));
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::Metrics { sort, visibility, limit } => {
+            let started = Instant::now();
+            let metrics = project.metrics(sort, visibility);
+            timings.trace = started.elapsed();
+
+            let started = Instant::now();
+            let output = generate_metrics_output(project, &metrics, sort, limit);
+            timings.render = started.elapsed();
+            Ok(output)
+        }
+        OutputMode::UnsafeAudit { root } => {
+            let started = Instant::now();
+            let audit = project.unsafe_audit(root.as_deref());
+            timings.trace = started.elapsed();
+
+            let started = Instant::now();
+            let output = audit.and_then(|audit| generate_unsafe_audit_output(project, &audit));
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::CallEdges { root } => {
+            let started = Instant::now();
+            let edges = project.edge_list(root.as_deref());
+            timings.trace = started.elapsed();
+
+            let started = Instant::now();
+            let output = edges.map(|edges| generate_call_edges_output(project, &edges));
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::PathBetween { from, to } => {
+            let started = Instant::now();
+            let paths = project.paths_between(&from, &to);
+            timings.trace = started.elapsed();
+
+            let started = Instant::now();
+            let output = paths.map(|paths| generate_call_paths_output(project, &paths));
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::CallGraphSummary { roots, visibility } => {
+            let started = Instant::now();
+            let graph = project.call_graph_from(&roots);
+            timings.trace = started.elapsed();
+
+            let started = Instant::now();
+            let output = graph.and_then(|graph| generate_call_graph_summary(project, &graph, visibility));
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::CallGraphSource { roots } => {
+            let started = Instant::now();
+            let graph = project.call_graph_from(&roots);
+            timings.trace = started.elapsed();
+
+            let started = Instant::now();
+            let output = graph.and_then(|graph| generate_call_graph_source(project, &graph));
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::WithAttribute { attribute, visibility } => {
+            let started = Instant::now();
+            let output = generate_with_attribute(project, &attribute, visibility);
+            timings.render = started.elapsed();
+            output
+        }
+        OutputMode::Cycles => {
+            let started = Instant::now();
+            let cycles = project.cycles();
+            timings.trace = started.elapsed();
+
+            let started = Instant::now();
+            let output = generate_cycles(project, &cycles);
+            timings.render = started.elapsed();
+            Ok(output)
+        }
+        OutputMode::PublicApi => {
+            let started = Instant::now();
+            let api = project.public_api();
+            timings.trace = started.elapsed();
+
+            let started = Instant::now();
+            let output = generate_public_api(project, &api);
+            timings.render = started.elapsed();
+            Ok(output)
+        }
+        OutputMode::EntryPoints => {
+            let started = Instant::now();
+            let entry_points = project.entry_points();
+            timings.trace = started.elapsed();
+
+            let started = Instant::now();
+            let output = generate_entry_points(project, &entry_points);
+            timings.render = started.elapsed();
+            Ok(output)
+        }
+    };
+    output.map(|output| Output {
+        fingerprint: project.stats.fingerprint.clone(),
+        timings,
+        ..output
+    })
+}
+
+// === INTERNAL HELPERS (no I/O) ===
+
+// Renders with as many of `total` nodes as `budget` allows: at most `max_nodes`, then fewer until the output
+// fits in `max_bytes` (but never fewer than one). `render` gets the number of nodes to keep, or None for all
+// of them, and is expected to grow with it.
+pub(crate) fn fit_budget<T>(
+    budget: Budget,
+    total: usize,
+    size: impl Fn(&T) -> usize,
+    render: impl Fn(Option<usize>) -> Result<T, MorphoError>,
+) -> Result<T, MorphoError> {
+    let keep = |nodes: usize| (nodes < total).then_some(nodes);
+    let most = budget.max_nodes.map_or(total, |max| max.clamp(1, total.max(1)));
+    let output = render(keep(most))?;
+    match budget.max_bytes {
+        Some(max_bytes) if size(&output) > max_bytes && most > 1 => {}
+        _ => return Ok(output),
+    }
+
+    // Binary search for the most nodes that fit; one node is kept even when it doesn't
+    let (mut fits, mut over, mut best) = (1, most, None);
+    while over - fits > 1 {
+        let nodes = (fits + over) / 2;
+        let candidate = render(keep(nodes))?;
+        if size(&candidate) <= budget.max_bytes.unwrap_or(usize::MAX) {
+            fits = nodes;
+            best = Some(candidate);
+        } else {
+            over = nodes;
+        }
+    }
+    match best {
+        Some(output) => Ok(output),
+        None => render(keep(1)),
+    }
+}
+
+fn diff_needs_directories() -> MorphoError {
+    MorphoError::Unsupported("A diff loads its own two directories; use generate_output_with_format".to_string())
+}
+
+// With `include_docs`, the doc comment comes first as `///` lines, and a type's `#[doc]` attributes are
+// left out of its source so it isn't shown twice
+fn generate_source(project: &Project, name: &str, include_docs: bool) -> Result<Output, MorphoError> {
+    let item = project.source(name)?;
+    let docs = if include_docs { doc_lines(item.docs().as_deref()) } else { String::new() };
+    let content = match item {
+        ItemRef::Function(func) => {
+            let file_path = find_file_for_function(&func.qualified_name, project)?;
+            format!("=== {} ===\n{}{}\n", file_path, docs, item_source(project, item))
+        }
+        ItemRef::Type(ty) | ItemRef::Item(ty) => {
+            format!("=== {} ===\n{}{}\n", project.relative_path(ty.file), docs, item_source(project, item))
+        }
+    };
+    Ok(Output { content, ..Default::default() })
+}
+
+// The type's definition, then each function using it under its file's header
+fn generate_type_usages(project: &Project, type_name: &str) -> Result<Output, MorphoError> {
+    let TypeUsages { ty, usages } = project.type_usages(type_name)?;
+    let mut current_file = project.relative_path(ty.file);
+    let mut output = format!("=== {} ===\n{}\n", current_file, format_type_item(ty.item));
+
+    if usages.is_empty() {
+        output.push_str(&format!("// no functions use {}\n", project.relative_path(ty.key)));
+    }
+    for (func, refs) in usages {
+        let file = find_file_for_function(&func.qualified_name, project)?;
+        if file != current_file {
+            output.push_str(&format!("=== {} ===\n", file));
+            current_file = file;
+        }
+        output.push_str(&format!("{}{}\n", project.signature(func), lines_note(func)));
+        for type_ref in refs {
+            output.push_str(&format!("    {} {}{}\n", type_ref.usage.name(), type_ref.text, line_note(type_ref.line)));
+        }
+    }
+
+    Ok(Output { content: output, ..Default::default() })
+}
+
+// The type's definition, then each impl block with its associated types, consts and method signatures under
+// its file's header
+fn generate_type_api(project: &Project, type_name: &str) -> Result<Output, MorphoError> {
+    let TypeApi { ty, impls } = project.type_api(type_name)?;
+    let mut output = format!("=== {} ===\n{}\n", project.relative_path(ty.file), format_type_item(ty.item));
+
+    let mut current_file = ty.file.clone();
+    for block in impls {
+        if block.file != current_file {
+            output.push_str(&format!("=== {} ===\n", project.relative_path(&block.file)));
+            current_file = block.file.clone();
+        }
+        if block.methods.is_empty() && block.consts.is_empty() && block.types.is_empty() {
+            output.push_str(&format!("{} {{}}\n", block.header));
+            continue;
+        }
+        output.push_str(&format!("{} {{\n", block.header));
+        for ty in &block.types {
+            output.push_str(&format!("    {}  // line {}\n", format_impl_type(&ty.item), ty.line));
+        }
+        for constant in &block.consts {
+            output.push_str(&format!("    {}  // line {}\n", format_impl_const(&constant.item), constant.line));
+        }
+        for method in &block.methods {
+            let signature = method.signature_named(&method.sig.ident.to_string());
+            output.push_str(&format!("    {}{}\n", signature, lines_note(method)));
+        }
+        output.push_str("}\n");
+    }
+
+    Ok(Output { content: output, ..Default::default() })
+}
+
+// A summary line naming the trait, then each impl by file with the methods it defines; default methods it
+// keeps are noted after them
+fn generate_implementors(project: &Project, implementors: &Implementors) -> Output {
+    let mut output = match implementors.trait_def {
+        Some(ty) => {
+            let (start, end) = project.type_lines.get(ty.key).copied().unwrap_or_default();
+            format!(
+                "// {} implementors of trait {} ({}, lines {}-{})\n",
+                implementors.impls.len(),
+                implementors.trait_name,
+                project.relative_path(ty.file),
+                start,
+                end
+            )
+        }
+        None => format!(
+            "// {} implementors of {}, a trait from another crate\n",
+            implementors.impls.len(),
+            implementors.trait_name
+        ),
+    };
+
+    let mut current_file = "";
+    for implementor in &implementors.impls {
+        let imp = implementor.imp;
+        if imp.file_path != current_file {
+            output.push_str(&format!("=== {} ===\n", project.relative_path(&imp.file_path)));
+            current_file = &imp.file_path;
+        }
+        let header = impl_header(&imp.generics, &trait_impl_segment(&imp.self_ty, &imp.trait_ref));
+        output.push_str(&format!("{} {{  // lines {}-{}\n", header, imp.start_line, imp.end_line));
+        for method in &implementor.provides {
+            let signature = method.signature_named(&method.sig.ident.to_string());
+            output.push_str(&format!("    {}{}\n", signature, lines_note(method)));
+        }
+        for method in &implementor.overrides {
+            let signature = method.signature_named(&method.sig.ident.to_string());
+            output.push_str(&format!("    {}{} (overrides the default)\n", signature, lines_note(method)));
+        }
+        if !implementor.inherits.is_empty() {
+            let names: Vec<String> = implementor.inherits.iter().map(|method| method.sig.ident.to_string()).collect();
+            output.push_str(&format!("    // inherits {}\n", names.join(", ")));
+        }
+        output.push_str("}\n");
+    }
+
+    Output { content: output, ..Default::default() }
+}
+
+fn source_not_found(name: &str) -> MorphoError {
+    MorphoError::not_found("function or type", name)
+}
+
+// Crate paths such as `crate::prelude::Thing` resolve through re-exports to the definition
+fn source_canonical_path(project: &Project, name: &str) -> Option<String> {
+    (!name.contains(".rs")).then(|| project.canonical_path(name))
+}
+
+// A summary line, then the unreached functions grouped by file
+fn generate_unreachable_output(
+    project: &Project,
+    roots: &[String],
+    unreachable: &Unreachable,
+) -> Result<Output, MorphoError> {
+    let from = if roots.is_empty() {
+        format!("{} roots (main, pub functions and trait impl methods)", unreachable.roots.len())
+    } else {
+        roots.join(", ")
+    };
+    let mut output = format!(
+        "// {} of {} functions are never reached from {}\n",
+        unreachable.functions.len(),
+        project.functions.len(),
+        from
+    );
+
+    let mut current_file = String::new();
+    for func in &unreachable.functions {
+        let file = find_file_for_function(&func.qualified_name, project)?;
+        if file != current_file {
+            output.push_str(&format!("=== {} ===\n", file));
+            current_file = file;
+        }
+        output.push_str(&format!("{}{}\n", project.signature(func), lines_note(func)));
+    }
+
+    Ok(Output { content: output, ..Default::default() })
+}
+
+// A table of functions, highest `sort` metric first, with a header line saying how many are shown
+fn generate_metrics_output(
+    project: &Project,
+    metrics: &[FunctionMetrics],
+    sort: Metric,
+    limit: Option<usize>,
+) -> Output {
+    let shown = limit.map_or(metrics.len(), |limit| limit.min(metrics.len()));
+    let mut output = if shown < metrics.len() {
+        format!("// top {} of {} functions by {}\n", shown, metrics.len(), sort.name())
+    } else {
+        format!("// {} functions by {}\n", metrics.len(), sort.name())
+    };
+    output.push_str(&format!("{:>5} {:>10} {:>7} {:>6}  function\n", "loc", "complexity", "callees", "fan-in"));
+    for row in &metrics[..shown] {
+        output.push_str(&format!(
+            "{:>5} {:>10} {:>7} {:>6}  {}  // lines {}-{}\n",
+            row.lines,
+            row.complexity,
+            row.callees,
+            row.fan_in,
+            project.relative_path(&row.function.qualified_name),
+            row.function.start_line,
+            row.function.end_line
+        ));
+    }
+    Output { content: output, ..Default::default() }
+}
+
+// A summary line, the functions holding unsafe code by file with their `unsafe {}` blocks, then the call
+// paths from the root into them
+fn generate_unsafe_audit_output(project: &Project, audit: &UnsafeAudit) -> Result<Output, MorphoError> {
+    let unsafe_fns = audit.functions.iter().filter(|func| func.sig.unsafety.is_some()).count();
+    let blocks: usize = audit.functions.iter().map(|func| func.unsafe_blocks.len()).sum();
+    let mut output = format!(
+        "// {} of {} functions hold unsafe code (unsafe fns: {}, unsafe blocks: {})\n",
+        audit.functions.len(),
+        project.functions.len(),
+        unsafe_fns,
+        blocks
+    );
+
+    let mut current_file = String::new();
+    for func in &audit.functions {
+        let file = find_file_for_function(&func.qualified_name, project)?;
+        if file != current_file {
+            output.push_str(&format!("=== {} ===\n", file));
+            current_file = file;
+        }
+        output.push_str(&format!("{}{}\n", project.signature(func), lines_note(func)));
+        for &(start, end) in &func.unsafe_blocks {
+            let lines = if start == end { format!("line {}", start) } else { format!("lines {}-{}", start, end) };
+            output.push_str(&format!("    unsafe {{ ... }}  // {}\n", lines));
+        }
+    }
+
+    if let Some(root) = audit.root {
+        output.push_str(&format!("=== call paths from {} ===\n", project.relative_path(&root.qualified_name)));
+        if audit.paths.is_empty() {
+            output.push_str("// no unsafe code is reached\n");
+        }
+        for path in &audit.paths {
+            let names: Vec<String> = path.iter().map(|func| project.relative_path(&func.qualified_name)).collect();
+            output.push_str(&format!("{}\n", names.join(" -> ")));
+        }
+    }
+
+    Ok(Output { content: output, ..Default::default() })
+}
+
+// One `caller<TAB>callee<TAB>context` line per distinct call, with no header, for NetworkX's
+// `read_edgelist`, DuckDB's `read_csv` and the like. Calls repeated with the same context are listed once
+// since their lines aren't shown; the context is empty for a plain call.
+fn generate_call_edges_output(project: &Project, edges: &[CallEdge]) -> Output {
+    let mut seen = HashSet::new();
+    let mut output = String::new();
+    for edge in edges {
+        let context = edge.context.as_deref().unwrap_or_default().replace(['\t', '\n'], " ");
+        if seen.insert((&edge.caller, &edge.callee, context.clone())) {
+            let (caller, callee) = (project.relative_path(&edge.caller), project.relative_path(&edge.callee));
+            output.push_str(&format!("{}\t{}\t{}\n", caller, callee, context));
+        }
+    }
+    Output { content: output, ..Default::default() }
+}
+
+// A summary line, then one chain per line, each call with its context and line in the caller's file as in
+// the call graph: `a -> b (line 3) -> c [in: map closure] (line 9)`
+fn generate_call_paths_output(project: &Project, paths: &CallPaths) -> Output {
+    let from = project.relative_path(&paths.from.qualified_name);
+    let to = project.relative_path(&paths.to.qualified_name);
+    let Some(first) = paths.paths.first() else {
+        return Output { content: format!("// no call path from {} to {}\n", from, to), ..Default::default() };
+    };
+    let plural = |count: usize, noun: &str| format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" });
+    let mut output = format!(
+        "// {} from {} to {}, {}{}\n",
+        plural(paths.total, "shortest call path"),
+        from,
+        to,
+        plural(first.len(), "call"),
+        if paths.total > 1 { " each" } else { "" }
+    );
+    if paths.total > paths.paths.len() {
+        output.push_str(&format!("// showing the first {}\n", paths.paths.len()));
+    }
+    for path in &paths.paths {
+        output.push_str(&from);
+        for edge in path {
+            output.push_str(&format!(" -> {}", project.relative_path(&edge.callee)));
+            if let Some(context) = &edge.context {
+                output.push_str(&format!(" [in: {}]", context));
+            }
+            output.push_str(&format!(" (line {})", edge.line));
+        }
+        output.push('\n');
+    }
+    Output { content: output, ..Default::default() }
+}
+
+// "// 9 functions and 3 types reachable from src/lib.rs::run", then those items as a listing shows them
+fn generate_call_graph_summary(
+    project: &Project,
+    graph: &CallGraph,
+    visibility: VisibilityFilter,
+) -> Result<Output, MorphoError> {
+    let items = graph.summary(visibility);
+    let mut listing = vec![];
+    write_list_all(project, &items, &HashMap::new(), false, &mut listing).map_err(write_error)?;
+    let content = format!("{}{}", reachable_header(project, graph, &items), String::from_utf8_lossy(&listing));
+    Ok(Output { content, ..Default::default() })
+}
+
+// "// 3 functions with #[instrument]", then those functions as a listing shows them
+fn generate_with_attribute(
+    project: &Project,
+    attribute: &str,
+    visibility: VisibilityFilter,
+) -> Result<Output, MorphoError> {
+    let items: Vec<ItemRef> =
+        project.functions_with_attribute(attribute, visibility).into_iter().map(ItemRef::Function).collect();
+    let mut listing = vec![];
+    write_list_all(project, &items, &HashMap::new(), false, &mut listing).map_err(write_error)?;
+    let content = format!(
+        "// {} function{} with #[{}]\n{}",
+        items.len(),
+        if items.len() == 1 { "" } else { "s" },
+        attribute.trim().trim_start_matches("#[").trim_end_matches(']'),
+        String::from_utf8_lossy(&listing)
+    );
+    Ok(Output { content, ..Default::default() })
+}
+
+// "// 2 cycles through 3 functions", then a section per cycle with its functions' signatures and the calls
+// between them
+fn generate_cycles(project: &Project, cycles: &[query::Cycle]) -> Output {
+    let functions: usize = cycles.iter().map(|cycle| cycle.functions.len()).sum();
+    let mut output = format!(
+        "// {} cycle{} through {} function{}\n",
+        cycles.len(),
+        if cycles.len() == 1 { "" } else { "s" },
+        functions,
+        if functions == 1 { "" } else { "s" }
+    );
+    for (i, cycle) in cycles.iter().enumerate() {
+        match cycle.functions.len() {
+            1 => output.push_str(&format!("=== cycle {}: direct recursion ===\n", i + 1)),
+            count => output.push_str(&format!("=== cycle {}: {} functions ===\n", i + 1, count)),
+        }
+        for func in &cycle.functions {
+            output.push_str(&format!("{}{}\n", project.signature(func), lines_note(func)));
+        }
+        for edge in &cycle.edges {
+            let context = edge.context.as_ref().map(|ctx| format!(" [in: {}]", ctx)).unwrap_or_default();
+            let (caller, callee) = (project.relative_path(&edge.caller), project.relative_path(&edge.callee));
+            output.push_str(&format!("    {} -> {}{} (line {})\n", caller, callee, context, edge.line));
+        }
+    }
+    Output { content: output, ..Default::default() }
+}
+
+// "// 12 items exported", then one line per public path with where its definition is, e.g.,
+// "pub struct crate::Motor  // re-exports crate::engine::Engine, src/lib.rs, line 130"
+fn generate_public_api(project: &Project, api: &[query::PublicItem]) -> Output {
+    let mut output = format!("// {} item{} exported\n", api.len(), if api.len() == 1 { "" } else { "s" });
+    for public in api {
+        let (line, lines) = match public.item {
+            ItemRef::Function(func) => (func.signature_named(&public.path), (func.start_line, func.end_line)),
+            ItemRef::Type(ty) => {
+                let kind = match ty.item {
+                    Item::Struct(_) => "struct",
+                    Item::Union(_) => "union",
+                    Item::Enum(_) => "enum",
+                    Item::Trait(_) => "trait",
+                    _ => "type",
+                };
+                (format!("pub {} {}", kind, public.path), project.type_lines.get(ty.key).copied().unwrap_or_default())
+            }
+            ItemRef::Item(it) => {
+                let line = match it.item {
+                    Item::Const(c) => format!("pub const {}: {}", public.path, format_type(&c.ty)),
+                    Item::Static(s) => format!("pub static {}: {}", public.path, format_type(&s.ty)),
+                    _ => format!("macro_rules! {}", public.path),
+                };
+                (line, project.item_lines.get(it.key).copied().unwrap_or_default())
+            }
+        };
+        let root = public.path.split("::").next().unwrap_or_default();
+        let definition = join_path(root, &public.defined_at);
+        let mut notes = vec![];
+        if definition != public.path {
+            notes.push(format!("re-exports {}", definition));
+        }
+        notes.push(project.relative_path(public.item.file()));
+        match lines {
+            (0, _) => {}
+            (start, end) if start == end => notes.push(format!("line {}", start)),
+            (start, end) => notes.push(format!("lines {}-{}", start, end)),
+        }
+        output.push_str(&format!("{}  // {}\n", line, notes.join(", ")));
+    }
+    Output { content: output, ..Default::default() }
+}
+
+// "// 4 entry points", then a section per kind with each function's signature and what marks it, then
+// the bin targets
+fn generate_entry_points(project: &Project, entry_points: &EntryPoints) -> Output {
+    let count = entry_points.functions.len();
+    let mut output = format!("// {} entry point{}\n", count, if count == 1 { "" } else { "s" });
+    let mut current_kind = None;
+    for entry in &entry_points.functions {
+        if current_kind != Some(entry.kind) {
+            output.push_str(&format!("=== {} ===\n", entry.kind.name()));
+            current_kind = Some(entry.kind);
+        }
+        let note = lines_note(entry.function);
+        let note = match (&entry.detail, note.strip_prefix("  // ")) {
+            (Some(detail), Some(lines)) => format!("  // {}, {}", detail, lines),
+            (Some(detail), None) => format!("  // {}", detail),
+            (None, _) => note,
+        };
+        output.push_str(&format!("{}{}\n", project.signature(entry.function), note));
+    }
+    if !entry_points.binaries.is_empty() {
+        output.push_str("=== binaries ===\n");
+        for target in &entry_points.binaries {
+            output.push_str(&format!("{}  // {}\n", target.crate_name, project.relative_path(&target.src_path)));
+        }
+    }
+    Output { content: output, ..Default::default() }
+}
+
+// The same header, then under each file's header the definitions of its reached types and the full source
+// of its reached functions
+fn generate_call_graph_source(project: &Project, graph: &CallGraph) -> Result<Output, MorphoError> {
+    let items = graph.summary(VisibilityFilter::All);
+    let mut output = reachable_header(project, graph, &items);
+    let mut current_file = String::new();
+    for item in &items {
+        let file = match item {
+            ItemRef::Function(func) => find_file_for_function(&func.qualified_name, project)?,
+            ItemRef::Type(ty) | ItemRef::Item(ty) => project.relative_path(ty.file),
+        };
+        if file != current_file {
+            output.push_str(&format!("=== {} ===\n", file));
+            current_file = file;
+        }
+        // Written names don't say which impl or module a function is in, so the qualified one goes above
+        if let ItemRef::Function(func) = item {
+            let (_, name) = func.qualified_name.split_once("::").unwrap_or_default();
+            if func.sig.ident != name {
+                output.push_str(&format!("// {}\n", name));
+            }
+        }
+        output.push_str(&format!("{}\n", item_source(project, *item)));
+    }
+    Ok(Output { content: output, ..Default::default() })
+}
+
+// The header of the summary and source of a call graph: how many functions and types its roots reach
+fn reachable_header(project: &Project, graph: &CallGraph, items: &[ItemRef]) -> String {
+    let functions = items.iter().filter(|item| matches!(item, ItemRef::Function(_))).count();
+    let types = items.len() - functions;
+    let roots: Vec<String> = graph.roots.iter().map(|root| project.relative_path(&root.qualified_name)).collect();
+    format!(
+        "// {} function{} and {} type{} reachable from {}\n",
+        functions,
+        if functions == 1 { "" } else { "s" },
+        types,
+        if types == 1 { "" } else { "s" },
+        roots.join(", ")
+    )
+}
+
+// Ranked matches, one line each: a function's signature, or a type's kind and qualified name
+fn generate_search(project: &Project, query: &str, limit: Option<usize>) -> Result<Output, MorphoError> {
+    let hits = project.search(query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))?;
+    let mut output = String::new();
+    if hits.is_empty() {
+        output.push_str(&format!("// nothing matches '{}'\n", query));
+    }
+    for hit in hits {
+        match hit.item {
+            ItemRef::Function(func) => {
+                output.push_str(&format!("{}{}\n", project.signature(func), lines_note(func)));
+            }
+            ItemRef::Type(ty) | ItemRef::Item(ty) => {
+                let lines = match hit.item {
+                    ItemRef::Item(_) => &project.item_lines,
+                    _ => &project.type_lines,
+                };
+                let note = match lines.get(ty.key) {
+                    Some(&(start, end)) if start == end => format!("  // line {}", start),
+                    Some(&(start, end)) => format!("  // lines {}-{}", start, end),
+                    None => String::new(),
+                };
+                let name = format!("{}::{}", project.relative_path(ty.file), ty.key);
+                output.push_str(&format!("{}{}\n", type_summary(&name, ty.item), note));
+            }
+        }
+    }
+
+    Ok(Output { content: output, ..Default::default() })
+}
+
+// The trimmed query; an empty one would match everything
+pub(crate) fn search_query(query: &str) -> Result<&str, MorphoError> {
+    match query.trim() {
+        "" => Err(MorphoError::Unsupported("Search needs a non-empty query".to_string())),
+        query => Ok(query),
+    }
+}
+
+// One line naming a type, e.g., "pub struct src/lib.rs::Config<T>"
+fn type_summary(name: &str, item: &Item) -> String {
+    match item {
+        Item::Struct(s) => format!("{}struct {}{}", visibility_to_string(&s.vis), name, format_generics(&s.generics)),
+        Item::Union(u) => format!("{}union {}{}", visibility_to_string(&u.vis), name, format_generics(&u.generics)),
+        Item::Enum(e) => format!("{}enum {}{}", visibility_to_string(&e.vis), name, format_generics(&e.generics)),
+        Item::Trait(t) => format!("{}trait {}{}", visibility_to_string(&t.vis), name, format_generics(&t.generics)),
+        Item::Type(t) => format!("{}type {}{}", visibility_to_string(&t.vis), name, format_generics(&t.generics)),
+        Item::Const(c) => format!("{}const {}: {}", visibility_to_string(&c.vis), name, format_type(&c.ty)),
+        Item::Static(s) => format!("{}static {}: {}", visibility_to_string(&s.vis), name, format_type(&s.ty)),
+        Item::Macro(_) => format!("macro_rules! {}", name),
+        _ => name.to_string(),
+    }
+}
+
+// The function `name` refers to: exact qualified name, crate path, `file#function`, name suffix, trait impl
+// method, simple-name suffix, then path normalization. A name several functions match is an error.
+fn find_source_function<'a>(project: &'a Project, name: &str) -> Result<Option<&'a Function>, MorphoError> {
+    // Extract just the item name (last component after ::)
+    let simple_name = name.split("::").last().unwrap_or(name);
+    let canonical = source_canonical_path(project, name);
+    let by_crate_path = |path: Option<String>| path.is_some() && path == canonical;
+
+    let found = project.functions.get(name).or_else(|| {
+        project.functions.iter()
+            .find(|(qn, _)| by_crate_path(project.function_crate_path(qn)))
+            .map(|(_, f)| f)
+    }).or_else(|| {
+        project.function_at_package_path(name)
+    });
+    if found.is_some() {
+        return Ok(found);
+    }
+
+    let suffix = format!("::{}", name);
+    let simple_suffix = format!("::{}", simple_name);
+    let mut matches = match name.split_once('#') {
+        Some((file, function)) => functions_in_file(project, file, function),
+        None => project.functions.values().filter(|f| f.qualified_name.ends_with(&suffix)).collect(),
+    };
+    if matches.is_empty() {
+        matches = trait_impl_methods(project, name);
+    }
+    if matches.is_empty() {
+        matches = project.functions.values().filter(|f| f.qualified_name.ends_with(&simple_suffix)).collect();
+    }
+    if matches.is_empty() {
+        // Try matching by converting absolute paths to relative or vice versa
+        return Ok(project.functions.iter().find(|(qn, _)| paths_match(qn, name)).map(|(_, f)| f));
+    }
+    only_match(project, name, matches).map(Some)
+}
+
+// The type `name` refers to, with its `types` key, looked up the same way as functions
+fn find_source_type<'a>(project: &'a Project, name: &str) -> Option<(&'a String, &'a (String, Item))> {
+    let simple_name = name.split("::").last().unwrap_or(name);
+    let canonical = source_canonical_path(project, name);
+    let by_crate_path = |path: Option<String>| path.is_some() && path == canonical;
+
+    project.types.get_key_value(name).or_else(|| {
+        project.types.iter()
+            .find(|(key, _)| by_crate_path(project.type_crate_path(key)))
+    }).or_else(|| {
+        project.types.iter()
+            .find(|(key, _)| !project.packages.is_empty() && project.type_package_path(key).as_deref() == Some(name))
+    }).or_else(|| {
+        // Try suffix match with simple name
+        project.types.iter()
+            .find(|(qn, _)| {
+                qn.ends_with(&format!("::{}", simple_name)) ||
+                *qn == simple_name
+            })
+    }).or_else(|| {
+        // Try matching by path normalization
+        project.types.iter()
+            .find(|(qn, _)| paths_match(qn, name))
+    })
+}
+
+// The const, static or macro `name` refers to: key in `items`, crate path, or simple name
+fn find_source_item<'a>(project: &'a Project, name: &str) -> Option<(&'a String, &'a (String, Item))> {
+    let simple_name = name.split("::").last().unwrap_or(name);
+    let canonical = source_canonical_path(project, name);
+    project.items.get_key_value(name).or_else(|| {
+        project.items.iter().find(|(key, (file, _))| {
+            canonical.as_deref() == Some(join_path(&file_module_path(file), key).as_str())
+                || key.ends_with(&format!("::{}", simple_name))
+                || *key == simple_name
+        })
+    })
+}
+
+// Helper to check if two qualified names refer to the same item
+// Handles cases where one is absolute and one is relative
+fn paths_match(stored_qn: &str, search_qn: &str) -> bool {
+    // If they're exactly equal, match
+    if stored_qn == search_qn {
+        return true;
+    }
+
+    // Extract file path and item name from both
+    let stored_parts: Vec<&str> = stored_qn.splitn(2, "::").collect();
+    let search_parts: Vec<&str> = search_qn.splitn(2, "::").collect();
+
+    if stored_parts.len() != 2 || search_parts.len() != 2 {
+        return false;
+    }
+
+    let stored_file = stored_parts[0];
+    let stored_item = stored_parts[1];
+    let search_file = search_parts[0];
+    let search_item = search_parts[1];
+
+    // Items must match exactly
+    if stored_item != search_item {
+        return false;
+    }
+
+    // Check if the file paths refer to the same file
+    // Handle both relative (./path) and absolute (/full/path) paths
+    let stored_normalized = stored_file.trim_start_matches("./");
+    let search_normalized = search_file.trim_start_matches("./");
+
+    // Check if one ends with the other (handles absolute vs relative)
+    stored_normalized.ends_with(search_normalized) ||
+    search_normalized.ends_with(stored_normalized)
+}
+
+// `extern "C" ` for a function with an ABI; empty for Rust functions
+fn abi_prefix(sig: &syn::Signature) -> String {
+    match &sig.abi {
+        Some(syn::Abi { name: Some(name), .. }) => format!("extern \"{}\" ", name.value()),
+        Some(_) => "extern ".to_string(),
+        None => String::new(),
+    }
+}
+
+fn format_function_source(func: &Function) -> String {
+    let vis = visibility_to_string(&func.vis);
+    let asyncness = if func.sig.asyncness.is_some() { "async " } else { "" };
+    let constness = if func.sig.constness.is_some() { "const " } else { "" };
+    let unsafety = if func.sig.unsafety.is_some() { "unsafe " } else { "" };
+    let unsafety = format!("{}{}", unsafety, abi_prefix(&func.sig));
+
+    let generics = format_generics(&func.sig.generics);
+    let args = format_args(&func.sig.inputs.iter().collect::<Vec<_>>());
+    let ret = match &func.sig.output {
+        syn::ReturnType::Default => "".to_string(),
+        syn::ReturnType::Type(_, ty) => format!(" -> {}", format_type(ty)),
+    };
+    let ret = format!("{}{}", ret, format_where_clause(&func.sig.generics));
+
+    // Get just the function name without file path for display
+    let display_name = if let Some(first_separator) = func.qualified_name.find("::") {
+        &func.qualified_name[first_separator + 2..]
+    } else {
+        &func.qualified_name
+    };
+
+    if let Some(block) = &func.block {
+        format!(
+            "{}{}{}{}fn {}{}({}){} {}\n",
+            vis, asyncness, constness, unsafety, display_name, generics, args, ret, pretty_block(block)
+        )
+    } else {
+        format!(
+            "{}{}{}{}fn {}{}({}){} {{ ... }}\n",
+            vis, asyncness, constness, unsafety, display_name, generics, args, ret
+        )
+    }
+}
+
+// The item's code as written, or when the load kept no text for its file, as prettyplease lays it out;
+// doc comments are left out either way
+pub(crate) fn item_source(project: &Project, item: ItemRef) -> String {
+    project.source_text(item).unwrap_or_else(|| match item {
+        ItemRef::Function(func) => format_function_source(func).trim_end().to_string(),
+        ItemRef::Type(ty) | ItemRef::Item(ty) => pretty_item(&without_docs(ty.item)),
+    })
+}
+
+// The block laid out as rustfmt would, e.g. "{\n    run();\n}"; prettyplease only prints whole items, so the
+// block is printed as the body of a stand-in function
+fn pretty_block(block: &Block) -> String {
+    let stand_in = Item::Fn(syn::ItemFn {
+        attrs: vec![],
+        vis: Visibility::Inherited,
+        sig: syn::parse_quote!(fn f()),
+        block: Box::new(block.clone()),
+    });
+    let printed = pretty_item(&stand_in);
+    printed.strip_prefix("fn f() ").unwrap_or(&printed).to_string()
+}
+
+// The item laid out as rustfmt would, without the trailing newline
+fn pretty_item(item: &Item) -> String {
+    let file = syn::File { shebang: None, attrs: vec![], items: vec![item.clone()] };
+    prettyplease::unparse(&file).trim_end().to_string()
+}
+
+pub(crate) fn is_public(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+// Any `pub`, restricted or not, except `pub(self)`, which is the same as private
+fn is_crate_visible(vis: &Visibility) -> bool {
+    match vis {
+        Visibility::Public(_) => true,
+        Visibility::Restricted(restricted) => !restricted.path.is_ident("self"),
+        Visibility::Inherited => false,
+    }
+}
+
+fn item_visibility(item: &Item) -> Option<&Visibility> {
+    match item {
+        Item::Struct(s) => Some(&s.vis),
+        Item::Union(u) => Some(&u.vis),
+        Item::Enum(e) => Some(&e.vis),
+        Item::Trait(t) => Some(&t.vis),
+        Item::Type(t) => Some(&t.vis),
+        Item::Const(c) => Some(&c.vis),
+        Item::Static(s) => Some(&s.vis),
+        _ => None,
+    }
+}
+
+// `#[macro_export]` puts a macro_rules! macro at the crate root for every crate to use
+pub(crate) fn is_macro_export(item: &syn::ItemMacro) -> bool {
+    item.attrs.iter().any(|attr| attr.path().is_ident("macro_export"))
+}
+
+fn matches_visibility_filter(vis: &Visibility, filter: VisibilityFilter) -> bool {
+    match filter {
+        VisibilityFilter::All => true,
+        VisibilityFilter::CrateVisible => is_crate_visible(vis),
+        VisibilityFilter::PublicOnly => is_public(vis),
+    }
+}
+
+fn item_matches_visibility_filter(item: &Item, filter: VisibilityFilter) -> bool {
+    match (filter, item) {
+        (VisibilityFilter::All, _) => true,
+        (_, Item::Macro(m)) => is_macro_export(m),
+        (filter, item) => item_visibility(item).is_some_and(|vis| matches_visibility_filter(vis, filter)),
+    }
+}
+
+fn generate_list_all(
+    project: &Project,
+    visibility: VisibilityFilter,
+    hide_items: bool,
+    include_docs: bool,
+    budget: Budget,
+    page: Page,
+) -> Result<Output, MorphoError> {
+    let reexports = list_reexports(project, visibility);
+    let page = project.list_page(visibility, hide_items, page.offset, page.limit)?;
+    let total = page.items.len();
+
+    let content = fit_budget(budget, total, String::len, |limit| {
+        let shown = limit.unwrap_or(total);
+        let mut output = vec![];
+        write_list_all(project, &page.items[..shown], &reexports, include_docs, &mut output)
+            .expect("writing to memory never fails");
+        let mut output = String::from_utf8(output).expect("output is always UTF-8");
+        if let Some(next) = page.items.get(shown) {
+            output.push_str(&format!(
+                "// truncated: listed {} of {} items by file, then name; the other {} start in {}\n",
+                shown,
+                total,
+                total - shown,
+                project.relative_path(next.file())
+            ));
+        }
+        output.push_str(&list_all_footer(&page, shown));
+        Ok(output)
+    })?;
+    Ok(Output { content, ..Default::default() })
+}
+
+// The public API report shows the paths users import, not just where items are defined
+fn list_reexports(project: &Project, visibility: VisibilityFilter) -> HashMap<String, Vec<String>> {
+    match visibility {
+        VisibilityFilter::PublicOnly => project.reexport_paths(),
+        VisibilityFilter::All | VisibilityFilter::CrateVisible => HashMap::new(),
+    }
+}
+
+// Where a paged listing stopped, after `shown` items of the page; empty for a whole listing. Budgets cut
+// within the page, so the next page starts after the last item shown.
+fn list_all_footer(page: &query::ListPage, shown: usize) -> String {
+    let end = page.offset + shown;
+    if shown == 0 && page.offset > 0 {
+        return format!("// page: no items at offset {} of {}\n", page.offset, page.total);
+    }
+    if page.offset == 0 && end >= page.total {
+        return String::new();
+    }
+    let mut footer = format!("// page: items {}-{} of {}", page.offset + 1, end, page.total);
+    if end < page.total {
+        footer.push_str(&format!("; the next page starts at offset {}", end));
+    }
+    footer.push('\n');
+    footer
+}
+
+// Items grouped under a header for each file, as `Project::list_items` orders them
+fn write_list_all(
+    project: &Project,
+    items: &[ItemRef],
+    reexports: &HashMap<String, Vec<String>>,
+    include_docs: bool,
+    out: &mut impl Write,
+) -> std::io::Result<()> {
+    let reexport_note = |crate_path: Option<String>| {
+        crate_path
+            .and_then(|path| reexports.get(&path))
+            .map(|paths| format!("    (re-exported as {})\n", paths.join(", ")))
+            .unwrap_or_default()
+    };
+
+    let mut current_file = None;
+    for item in items {
+        if current_file != Some(item.file()) {
+            current_file = Some(item.file());
+            writeln!(out, "=== {} ===", project.relative_path(item.file()))?;
+        }
+        if include_docs {
+            write!(out, "{}", doc_lines(item.docs().as_deref()))?;
+        }
+        match item {
+            ItemRef::Type(ty) => {
+                writeln!(out, "{}", format_type_item(ty.item))?;
+                write!(out, "{}", reexport_note(project.type_crate_path(ty.key)))?;
+            }
+            ItemRef::Item(it) => writeln!(out, "{}", format_type_item(it.item))?,
+            ItemRef::Function(func) => {
+                writeln!(out, "{}{}", project.signature(func), lines_note(func))?;
+                write!(out, "{}", reexport_note(project.function_crate_path(&func.qualified_name)))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Reachable types matching `visibility` grouped by file, then each root's call tree
+fn generate_call_graph_output(
+    project: &Project,
+    graph: &CallGraph,
+    visibility: VisibilityFilter,
+    budget: Budget,
+    show_external: bool,
+) -> Result<Output, MorphoError> {
+    let order = graph.breadth_first();
+    let content = fit_budget(budget, order.len(), String::len, |limit| {
+        let omitted: HashSet<String> =
+            order.iter().skip(limit.unwrap_or(order.len())).map(|func| func.qualified_name.clone()).collect();
+        let mut output = render_call_graph(project, graph, visibility, &omitted, show_external)?;
+        if !omitted.is_empty() {
+            output.push_str(&format!(
+                "// truncated: expanded the {} functions nearest the root, breadth-first; calls into the other {} \
+                 of {} are marked (omitted)\n",
+                order.len() - omitted.len(),
+                omitted.len(),
+                order.len()
+            ));
+        }
+        Ok(output)
+    })?;
+    Ok(Output { content, ..Default::default() })
+}
+
+// Functions in `omitted` appear where they are called, without their own calls; with `show_external`,
+// so do the calls left unresolved
+fn render_call_graph(
+    project: &Project,
+    graph: &CallGraph,
+    visibility: VisibilityFilter,
+    omitted: &HashSet<String>,
+    show_external: bool,
+) -> Result<String, MorphoError> {
+    let mut output = String::new();
+
+    let mut types_by_file: BTreeMap<&str, Vec<&Item>> = BTreeMap::new();
+    for ty in graph.types.iter().filter(|ty| item_matches_visibility_filter(ty.item, visibility)) {
+        types_by_file.entry(ty.file).or_default().push(ty.item);
+    }
+    for (file_path, items) in types_by_file {
+        output.push_str(&format!("=== {} ===\n", project.relative_path(file_path)));
+        for item in items {
+            output.push_str(&format_type_item(item));
+            output.push('\n');
+        }
+    }
+
+    // Each root's tree is complete on its own, so functions several roots reach appear under each
+    let all_funcs: BTreeMap<String, &Function> =
+        graph.functions.iter().map(|func| (func.qualified_name.clone(), *func)).collect();
+    for root in &graph.roots {
+        output.push_str(&format!("=== {} ===\n", find_file_for_function(&root.qualified_name, project)?));
+        let mut visited_in_tree = HashSet::new();
+        render_function_tree(
+            root, project, &all_funcs, omitted, &graph.boundary, show_external, &mut visited_in_tree, 0, "",
+            &mut output,
+        );
+    }
+
+    Ok(output)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_function_tree(
+    func: &Function,
+    project: &Project,
+    all_funcs: &BTreeMap<String, &Function>,
+    omitted: &HashSet<String>,
+    boundary: &HashSet<String>,
+    show_external: bool,
+    visited_in_tree: &mut HashSet<String>,
+    depth: usize,
+    prefix: &str,
+    output: &mut String,
+) {
+    // Print function signature
+    if depth == 0 {
+        output.push_str(&format!("{}\n", project.signature(func)));
+    }
+
+    visited_in_tree.insert(func.qualified_name.clone());
+
+    let edges = tree_edges(func, project, all_funcs, show_external);
+
+    // Render each call as a tree node
+    for (i, edge) in edges.iter().enumerate() {
+        let is_last = i == edges.len() - 1;
+        let branch = if is_last { "└── " } else { "├── " };
+        let extension = if is_last { "    " } else { "│   " };
+
+        match edge {
+            TreeEdge::Call { qualified, context, display, line, .. } => render_call_edge(
+                qualified, context, &dep_label(display, func, qualified, project), *line, project, all_funcs,
+                omitted, boundary, show_external, visited_in_tree, depth, prefix, branch, extension, output,
+            ),
+            TreeEdge::External { name, context, line } => {
+                let label = format!("{} [external]", name);
+                match context {
+                    Some(ctx) => output.push_str(&format!("{}{}{} [in: {}]", prefix, branch, label, ctx)),
+                    None => output.push_str(&format!("{}{}{}", prefix, branch, label)),
+                }
+                output.push_str(&format!("{}\n", line_note(*line)));
+            }
+            TreeEdge::FanOut { name, note, context, candidates, line, .. } => {
+                let label = format!("{} [{}]", name, note);
+                match context {
+                    Some(ctx) => output.push_str(&format!("{}{}{} [in: {}]", prefix, branch, label, ctx)),
+                    None => output.push_str(&format!("{}{}{}", prefix, branch, label)),
+                }
+                output.push_str(&format!("{}\n", line_note(*line)));
+
+                let fan_prefix = format!("{}{}", prefix, extension);
+                for (j, candidate) in candidates.iter().enumerate() {
+                    let is_last = j == candidates.len() - 1;
+                    let branch = if is_last { "└── " } else { "├── " };
+                    let extension = if is_last { "    " } else { "│   " };
+                    // The call's line is on the fan-out node already
+                    let display = dep_label(&display_name_with_type(candidate), func, candidate, project);
+                    render_call_edge(
+                        candidate, &None, &display, 0, project, all_funcs, omitted, boundary, show_external,
+                        visited_in_tree, depth, &fan_prefix, branch, extension, output,
+                    );
+                }
+            }
+        }
+    }
+}
+
+// The resolved calls of `func` to functions in `all_funcs`, in source order; with `external`, the calls
+// that resolve to none of them as well, except enum variants and tuple structs (`Some(..)`, `Ok(..)`)
+fn tree_edges(
+    func: &Function,
+    project: &Project,
+    all_funcs: &BTreeMap<String, &Function>,
+    external: bool,
+) -> Vec<TreeEdge> {
+    let mut edges: Vec<TreeEdge> = vec![];
+
+    for call in &func.calls() {
+        if let Some((trait_name, targets)) = dispatch_targets(call, &func.module_path, project) {
+            let targets: Vec<String> = targets.into_iter().filter(|t| all_funcs.contains_key(t)).collect();
+            match call.dispatch {
+                // Trait objects and expanded trait calls: one node for the call, candidate implementations
+                // beneath it. A required trait method without a default body can never be the runtime callee
+                Dispatch::Dynamic(_) | Dispatch::Trait(_) => edges.push(TreeEdge::FanOut {
+                    name: call.name.clone(),
+                    note: match call.dispatch {
+                        Dispatch::Dynamic(_) => format!("dyn {}", trait_name),
+                        _ => format!("trait {}", trait_name),
+                    },
+                    context: call.context.clone(),
+                    candidates: targets.into_iter().filter(|t| all_funcs[t].block.is_some()).collect(),
+                    line: call.start_line,
+                    column: call.column,
+                }),
+                // Trait-bound generics fan out to the trait method and each implementor
+                _ => {
+                    let label = match &call.context {
+                        Some(ctx) => format!("{}, generic dispatch via {}", ctx, trait_name),
+                        None => format!("generic dispatch via {}", trait_name),
+                    };
+                    for target in targets {
+                        let display = display_name_with_type(&target);
+                        edges.push(TreeEdge::Call {
+                            qualified: target,
+                            context: Some(label.clone()),
+                            display,
+                            line: call.start_line,
+                            column: call.column,
+                        });
+                    }
+                }
+            }
+            continue;
+        }
+
+        // Try to resolve the call to a qualified name
+        match resolve_call_to_qualified(func, call, project, all_funcs) {
+            Some(Resolution::Unique(qualified_name)) => {
+                // Display name (strip file path for readability)
+                let display = qualified_name.split("::").last().unwrap_or(&qualified_name).to_string();
+                edges.push(TreeEdge::Call {
+                    qualified: qualified_name,
+                    context: call.context.clone(),
+                    display,
+                    line: call.start_line,
+                    column: call.column,
+                });
+            }
+            // Several glob imports supply the name; list each candidate under one node
+            Some(Resolution::AmbiguousGlob { globs, candidates }) => edges.push(TreeEdge::FanOut {
+                name: call.name.clone(),
+                note: format!("ambiguous: glob imports {}", globs.join(", ")),
+                context: call.context.clone(),
+                candidates,
+                line: call.start_line,
+                column: call.column,
+            }),
+            None if external && !call.name.starts_with(char::is_uppercase) => edges.push(TreeEdge::External {
+                name: external_call_name(func, call, project),
+                context: call.context.clone(),
+                line: call.start_line,
+            }),
+            None => {}
+        }
+    }
+
+    edges
+}
+
+// Whether `func` is under `scope`: a directory or file as paths are shown, e.g., "src/server/" or
+// "src/db.rs", or a module it is in or under, e.g., "server::handlers" (with or without `crate::`)
+fn in_scope(func: &Function, scope: &str, project: &Project) -> bool {
+    if scope.contains('/') || scope.ends_with(".rs") {
+        let file = func.qualified_name.split_once("::").map_or("", |(file, _)| file);
+        let shown = project.relative_path(file);
+        let (shown, scope) = (shown.trim_start_matches("./"), scope.trim_start_matches("./"));
+        return match scope.ends_with(".rs") {
+            true => shown == scope,
+            false => shown.starts_with(&format!("{}/", scope.trim_end_matches('/'))),
+        };
+    }
+    let (module, scope) = (func.crate_module(), scope.trim_start_matches("crate::"));
+    module == scope || module.starts_with(&format!("{}::", scope))
+}
+
+// An unresolved call as written, or with its receiver's type when known: "sqlx::query", "Client::get"
+fn external_call_name(caller: &Function, call: &CallSite, project: &Project) -> String {
+    if let Some(qualifier) = &call.qualifier {
+        return format!("{}::{}", qualifier, call.name);
+    }
+    match receiver_type(caller, call, project) {
+        Some(type_name) => format!("{}::{}", type_name, call.name),
+        None => call.name.clone(),
+    }
+}
+
+// Qualified names a call could refer to from inside `caller`, innermost scope first: a function nested
+// in the caller's body, then one in the caller's module, its parent modules, and the file root
+fn scoped_callee_candidates(caller: &Function, call: &CallSite) -> Vec<String> {
+    // A path names its own module; `net::send()` isn't the caller's `send`
+    if call.qualifier.is_some() {
+        return vec![];
+    }
+    let call_name = call.name.as_str();
+    let mut candidates = vec![format!("{}::{}", caller.qualified_name, call_name)];
+    let Some((file, _)) = caller.qualified_name.split_once("::") else {
+        return candidates;
+    };
 
-            generate_call_graph_output(&file_to_funcs, &file_to_types, visibility, Some(&root))
+    let mut scope = caller.module_path.as_str();
+    loop {
+        if scope.is_empty() {
+            candidates.push(format!("{}::{}", file, call_name));
+            break;
         }
-        OutputMode::Source { function } => generate_source(&project, &function),
+        candidates.push(format!("{}::{}::{}", file, scope, call_name));
+        scope = scope.rsplit_once("::").map_or("", |(parent, _)| parent);
     }
+    candidates
 }
 
-pub fn generate_output_with_blacklist(dir: &str, mode: OutputMode, blacklist: &[String]) -> Result<Output, String> {
-    let project = load_project_with_blacklist(dir, blacklist)?;
-
-    match mode {
-        OutputMode::ListAll { visibility } => generate_list_all(&project, visibility),
-        OutputMode::CallGraph { root, visibility } => {
-            let (visited_funcs, reachable_types) = trace_calls(&root, &project)?;
-
-            // Filter functions and types by reachability
-            let mut file_to_funcs: HashMap<String, Vec<Function>> = HashMap::new();
-            for (name, func) in &project.functions {
-                if visited_funcs.contains(name) {
-                    let file = find_file_for_function(&func.qualified_name, &project)?;
-                    file_to_funcs.entry(file).or_default().push(func.clone());
-                }
-            }
+// Where a call's path leads
+enum PathCallee {
+    Found(String), // the function's qualified name
+    External,      // a path into another crate, e.g., `io::copy()` after `use std::io;`
+}
 
-            let mut file_to_types: HashMap<String, Vec<Item>> = HashMap::new();
-            for (type_name, (_, item)) in &project.types {
-                if reachable_types.contains(type_name) {
-                    let file = find_file_for_type(&type_name, &project)?;
-                    file_to_types.entry(file).or_default().push(item.clone());
-                }
-            }
+// Function a call's path names, read from the caller's module: `quux()` after `use foo::bar as quux;`,
+// `Baz::new()` after `use foo::Bar as Baz;`, or `net::connect()` and `crate::net::connect()` for a module
+// path. None when the path says nothing about the callee, e.g., an unqualified name that isn't imported.
+fn path_callee(caller: &Function, call: &CallSite, project: &Project) -> Option<PathCallee> {
+    let module = caller.crate_module();
+    let path = match &call.qualifier {
+        Some(qualifier) => join_path(qualifier, &call.name),
+        None => call.name.clone(),
+    };
+    let (head, rest) = path.split_once("::").unwrap_or((&path, ""));
+    let import = project.imports.iter().find(|import| import.module == module && import.name == head);
 
-            generate_call_graph_output(&file_to_funcs, &file_to_types, visibility, Some(&root))
-        }
-        OutputMode::Source { function } => generate_source(&project, &function),
+    let (written, target) = match import {
+        Some(import) => (import.path.as_str(), join_path(&project.absolute_path(&import.path, &module), rest)),
+        None if call.qualifier.is_some() => (path.as_str(), project.absolute_path(&path, &module)),
+        None => return None,
+    };
+    if let Some(found) = function_at_crate_path_from(caller, &project.canonical_path(&target), project) {
+        return Some(PathCallee::Found(found.clone()));
+    }
+    if let Some(found) = project.function_at_package_path(&target) {
+        return Some(PathCallee::Found(found.qualified_name.clone()));
     }
+    is_external_path(written, &module, project, import.is_some()).then_some(PathCallee::External)
 }
 
-// === INTERNAL HELPERS (no I/O) ===
-
-fn generate_source(project: &Project, name: &str) -> Result<Output, String> {
-    // Extract just the item name (last component after ::)
-    let simple_name = name.split("::").last().unwrap_or(name);
+// Whether a path as written starts outside the project: at a crate that isn't loaded (`std::io`,
+// `tokio::spawn`). Unimported paths are only judged by the standard library roots, since their first
+// segment may just as well be a type or trait.
+fn is_external_path(path: &str, module: &str, project: &Project, imported: bool) -> bool {
+    let first = path.split("::").next().unwrap_or(path);
+    if matches!(first, "std" | "core" | "alloc") {
+        return true;
+    }
+    imported
+        && !matches!(first, "crate" | "self" | "super")
+        && !project.modules.contains(&join_path(module, first))
+        && !project.modules.contains(first)
+        && !project.packages.iter().flat_map(|package| &package.targets).any(|target| target.crate_name == first)
+}
 
-    // Try to find as a function first
-    let func = project.functions.get(name).or_else(|| {
-        // Try suffix match with simple name
-        project.functions.iter()
-            .find(|(qn, _)| {
-                qn.ends_with(&format!("::{}", simple_name)) ||
-                qn == &simple_name
+// Last resort: any function with the call's name, preferring one whose crate path ends with the call's
+// path, e.g., `util::copy` for `util::copy()`
+fn suffix_callee<'a>(
+    caller: &Function,
+    call: &CallSite,
+    names: impl Iterator<Item = &'a String>,
+    project: &Project,
+) -> Option<String> {
+    let suffix = format!("::{}", call.name);
+    let candidates = same_root_first(caller, names.filter(|qn| qn.ends_with(&suffix)), project);
+    let by_path = call.qualifier.as_ref().and_then(|qualifier| {
+        let path = format!("{}::{}", qualifier.trim_start_matches("crate::"), call.name);
+        candidates.iter().find(|qn| {
+            project.function_crate_path(qn).is_some_and(|crate_path| {
+                crate_path == path || crate_path.ends_with(&format!("::{}", path))
             })
-            .map(|(_, f)| f)
-    }).or_else(|| {
-        // Try matching by converting absolute paths to relative or vice versa
-        project.functions.iter()
-            .find(|(qn, _)| paths_match(qn, name))
-            .map(|(_, f)| f)
+        })
     });
+    by_path.or(candidates.first()).map(|qn| qn.to_string())
+}
 
-    if let Some(func) = func {
-        let mut output = String::new();
-        let file_path = find_file_for_function(&func.qualified_name, project)?;
-        output.push_str(&format!("=== {} ===\n", file_path));
-        output.push_str(&format_function_source(func));
-        return Ok(Output { content: output });
+// Functions a call could reach through `use path::*` imports in the caller's module, as
+// (glob as written, qualified name) pairs. More than one entry means the name is ambiguous.
+fn glob_callee_candidates(caller: &Function, call_name: &str, project: &Project) -> Vec<(String, String)> {
+    let module = caller.crate_module();
+    let mut candidates: Vec<(String, String)> = project
+        .imports
+        .iter()
+        .filter(|import| import.name == "*" && import.module == module)
+        .filter_map(|import| {
+            let globbed = project.canonical_path(&project.absolute_path(&import.path, &module));
+            let target = function_at_crate_path_from(caller, &join_path(&globbed, call_name), project)?;
+            Some((format!("{}::*", import.path), target.clone()))
+        })
+        .collect();
+    candidates.sort();
+    // The same item reached through two globs is not ambiguous
+    candidates.dedup_by(|a, b| a.1 == b.1);
+    candidates
+}
+
+// A callee's name in the tree, marked `[dep: name]` when the call leaves the caller's project
+fn dep_label(display: &str, caller: &Function, callee: &str, project: &Project) -> String {
+    match project.dependency_name(&caller.qualified_name, callee) {
+        Some(dep) => format!("{} [dep: {}]", display, dep),
+        None => display.to_string(),
     }
+}
 
-    // Not a function, try to find as a type
-    let type_result = project.types.get(name).or_else(|| {
-        // Try suffix match with simple name
-        project.types.iter()
-            .find(|(qn, _)| {
-                qn.ends_with(&format!("::{}", simple_name)) ||
-                *qn == simple_name
-            })
-            .map(|(_, pair)| pair)
-    }).or_else(|| {
-        // Try matching by path normalization
-        project.types.iter()
-            .find(|(qn, _)| paths_match(qn, name))
-            .map(|(_, pair)| pair)
-    });
+// One child line under a function in the rendered tree
+enum TreeEdge {
+    Call { qualified: String, context: Option<String>, display: String, line: usize, column: usize },
+    // A call whose callee isn't known statically (trait object, ambiguous glob); candidates are listed
+    // beneath a `name [note]` node
+    FanOut { name: String, note: String, context: Option<String>, candidates: Vec<String>, line: usize, column: usize },
+    // A call the project doesn't resolve, e.g., into another crate; only listed with `show_external`
+    External { name: String, context: Option<String>, line: usize },
+}
 
-    if let Some((file_path, item)) = type_result {
-        let mut output = String::new();
-        output.push_str(&format!("=== {} ===\n", file_path));
-        output.push_str(&format!("{}\n", item.to_token_stream()));
-        return Ok(Output { content: output });
+#[allow(clippy::too_many_arguments)]
+fn render_call_edge(
+    callee_qualified: &str,
+    context: &Option<String>,
+    display_name: &str,
+    line: usize,
+    project: &Project,
+    all_funcs: &BTreeMap<String, &Function>,
+    omitted: &HashSet<String>,
+    boundary: &HashSet<String>,
+    show_external: bool,
+    visited_in_tree: &mut HashSet<String>,
+    depth: usize,
+    prefix: &str,
+    branch: &str,
+    extension: &str,
+    output: &mut String,
+) {
+    if let Some(ctx) = context {
+        output.push_str(&format!("{}{}{} [in: {}]", prefix, branch, display_name, ctx));
+    } else {
+        output.push_str(&format!("{}{}{}", prefix, branch, display_name));
+    }
+    output.push_str(&line_note(line));
+
+    // Check if already visited in this tree (cycle detection)
+    if visited_in_tree.contains(callee_qualified) {
+        output.push_str(" (already shown)\n");
+    } else if omitted.contains(callee_qualified) {
+        output.push_str(" (omitted)\n");
+    } else if let Some(callee_func) = all_funcs.get(callee_qualified).filter(|_| !boundary.contains(callee_qualified)) {
+        output.push('\n');
+        // Recursively render the callee's tree
+        let new_prefix = format!("{}{}", prefix, extension);
+        render_function_tree(
+            callee_func, project, all_funcs, omitted, boundary, show_external, visited_in_tree, depth + 1,
+            &new_prefix, output,
+        );
+    } else {
+        output.push('\n');
     }
-
-    Err(format!("Function or type '{}' not found. Use list_rust_items to see available items.", name))
 }
 
-// Helper to check if two qualified names refer to the same item
-// Handles cases where one is absolute and one is relative
-fn paths_match(stored_qn: &str, search_qn: &str) -> bool {
-    // If they're exactly equal, match
-    if stored_qn == search_qn {
-        return true;
+fn generate_callers_output(project: &Project, callers: &Callers, depth: Option<usize>) -> Result<Output, MorphoError> {
+    // The tree shows each caller once under each callee, with the context of its first call
+    let mut direct: BTreeMap<String, Vec<CallEdge>> = BTreeMap::new();
+    for edge in &callers.edges {
+        let entry = direct.entry(edge.callee.clone()).or_default();
+        if !entry.iter().any(|known| known.caller == edge.caller) {
+            entry.push(edge.clone());
+        }
     }
 
-    // Extract file path and item name from both
-    let stored_parts: Vec<&str> = stored_qn.splitn(2, "::").collect();
-    let search_parts: Vec<&str> = search_qn.splitn(2, "::").collect();
+    let func = callers.target;
+    let mut output = format!("=== {} ===\n", find_file_for_function(&func.qualified_name, project)?);
+    output.push_str(&format!("{}\n", project.signature(func)));
+    let mut visited_in_tree = HashSet::from([func.qualified_name.clone()]);
+    render_callers_tree(&func.qualified_name, &direct, &mut visited_in_tree, depth, "", &mut output);
 
-    if stored_parts.len() != 2 || search_parts.len() != 2 {
-        return false;
+    Ok(Output { content: output, ..Default::default() })
+}
+
+// Exact qualified name first, then the one function whose name ends with `::name` (or that `file#function`
+// selects), then a trait impl method named `Type::method`
+fn function_by_name<'a>(project: &'a Project, name: &str) -> Result<&'a Function, MorphoError> {
+    if let Some(func) = project.functions.get(name) {
+        return Ok(func);
+    }
+    // Names as rendered, relative to a loaded directory or under its namespace
+    let prefixes = path_prefixes(&project.loaded_from, &project.roots);
+    if let Some(func) = prefixes.iter().find_map(|(prefix, shown)| {
+        let rest = name.strip_prefix(shown.as_str())?;
+        project.functions.get(&format!("{}{}", prefix, rest))
+    }) {
+        return Ok(func);
+    }
+    // A short name, or a relative path into a project named by absolute paths
+    let (suffix, path_suffix) = (format!("::{}", name), format!("/{}", name));
+    let mut matches: Vec<&Function> = match name.split_once('#') {
+        Some((file, function)) => functions_in_file(project, file, function),
+        None => project
+            .functions
+            .values()
+            .filter(|f| f.qualified_name.ends_with(&suffix) || f.qualified_name.ends_with(&path_suffix))
+            .collect(),
+    };
+    if matches.is_empty() {
+        matches = trait_impl_methods(project, name);
     }
+    match project.function_at_package_path(name) {
+        Some(func) if matches.is_empty() => Ok(func),
+        _ => only_match(project, name, matches),
+    }
+}
 
-    let stored_file = stored_parts[0];
-    let stored_item = stored_parts[1];
-    let search_file = search_parts[0];
-    let search_item = search_parts[1];
+// What a `file#function` selector picks: functions in files whose path is `file` or ends with `/file`,
+// named `function` or with a name ending in `::function`, e.g., "config.rs#load" or "src/a.rs#Config::new"
+fn functions_in_file<'a>(project: &'a Project, file: &str, function: &str) -> Vec<&'a Function> {
+    let (file_suffix, suffix) = (format!("/{}", file), format!("::{}", function));
+    project
+        .functions
+        .values()
+        .filter(|f| {
+            let Some((func_file, name)) = f.qualified_name.split_once("::") else { return false };
+            (func_file == file || func_file.ends_with(&file_suffix)) && (name == function || name.ends_with(&suffix))
+        })
+        .collect()
+}
 
-    // Items must match exactly
-    if stored_item != search_item {
-        return false;
+// The function when `matches` has just one; else `name` is unknown, or ambiguous with the matches listed
+fn only_match<'a>(project: &Project, name: &str, matches: Vec<&'a Function>) -> Result<&'a Function, MorphoError> {
+    match matches.as_slice() {
+        [func] => Ok(func),
+        [] => Err(MorphoError::not_found("function", name)),
+        _ => Err(MorphoError::Ambiguous {
+            name: name.to_string(),
+            candidates: matches.iter().map(|f| project.relative_path(&f.qualified_name)).collect(),
+        }),
     }
+}
 
-    // Check if the file paths refer to the same file
-    // Handle both relative (./path) and absolute (/full/path) paths
-    let stored_normalized = stored_file.trim_start_matches("./");
-    let search_normalized = search_file.trim_start_matches("./");
-
-    // Check if one ends with the other (handles absolute vs relative)
-    stored_normalized.ends_with(search_normalized) ||
-    search_normalized.ends_with(stored_normalized)
+// What `Project::relative_path` strips, longest first: "dir/" for one directory, else each one's parent
+// (prefix, what it is shown as) pairs, longest prefix first. Merged loads keep their directory's name,
+// stripping its parent, or are shown under the namespace they were given.
+fn path_prefixes(loaded_from: &[String], roots: &BTreeMap<String, String>) -> Vec<(String, String)> {
+    let mut prefixes: Vec<(String, String)> = loaded_from
+        .iter()
+        .filter_map(|dir| {
+            let namespace = roots.get(dir).filter(|name| loaded_from.len() > 1 && *name != directory_name(dir));
+            if let Some(namespace) = namespace {
+                return Some((format!("{}/", dir.trim_end_matches('/')), format!("{}/", namespace)));
+            }
+            let base = if loaded_from.len() > 1 { std::path::Path::new(dir).parent()?.to_str()? } else { dir };
+            let base = base.trim_end_matches('/');
+            (!base.is_empty()).then(|| (format!("{}/", base), String::new()))
+        })
+        .collect();
+    prefixes.sort();
+    prefixes.dedup();
+    prefixes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+    prefixes
 }
 
-fn format_function_source(func: &Function) -> String {
-    let vis = visibility_to_string(&func.vis);
-    let asyncness = if func.sig.asyncness.is_some() { "async " } else { "" };
-    let constness = if func.sig.constness.is_some() { "const " } else { "" };
-    let unsafety = if func.sig.unsafety.is_some() { "unsafe " } else { "" };
+// The name a load is shown under by default: the last component of its directory
+fn directory_name(dir: &str) -> &str {
+    std::path::Path::new(dir).file_name().and_then(|name| name.to_str()).unwrap_or(dir)
+}
 
-    let args = format_args(&func.sig.inputs.iter().collect::<Vec<_>>());
-    let ret = match &func.sig.output {
-        syn::ReturnType::Default => "".to_string(),
-        syn::ReturnType::Type(_, ty) => format!(" -> {}", format_type(ty)),
-    };
+// `path` with the longest of `prefixes` it starts with replaced by what that prefix is shown as
+fn relative_to(path: &str, prefixes: &[(String, String)]) -> String {
+    prefixes
+        .iter()
+        .find_map(|(prefix, shown)| path.strip_prefix(prefix.as_str()).map(|rest| format!("{}{}", shown, rest)))
+        .unwrap_or_else(|| path.to_string())
+}
 
-    // Get just the function name without file path for display
-    let display_name = if let Some(first_separator) = func.qualified_name.find("::") {
-        &func.qualified_name[first_separator + 2..]
-    } else {
-        &func.qualified_name
-    };
+// "Circle::area" for a method only defined in a trait impl, keyed `<Circle as Shape>::area`; one per trait
+// implementing it, e.g., `Length::from` for both `From<f64>` and `From<u32>`
+fn trait_impl_methods<'a>(project: &'a Project, name: &str) -> Vec<&'a Function> {
+    let Some((type_path, method)) = name.rsplit_once("::") else { return vec![] };
+    let type_name = type_path.rsplit("::").next().unwrap_or(type_path);
+    project
+        .functions
+        .values()
+        .filter(|f| f.impl_trait.is_some() && f.self_type.as_deref() == Some(type_name) && f.sig.ident == method)
+        .collect()
+}
 
-    if let Some(block) = &func.block {
-        // Use the raw token stream for the block to preserve formatting
-        let block_str = block.to_token_stream().to_string();
-        format!(
-            "{}{}{}{}fn {}({}){} {}\n",
-            vis, asyncness, constness, unsafety, display_name, args, ret, block_str
-        )
-    } else {
-        format!(
-            "{}{}{}{}fn {}({}){} {{ ... }}\n",
-            vis, asyncness, constness, unsafety, display_name, args, ret
-        )
+fn render_callers_tree(
+    callee: &str,
+    callers: &BTreeMap<String, Vec<CallEdge>>,
+    visited_in_tree: &mut HashSet<String>,
+    remaining_depth: Option<usize>,
+    prefix: &str,
+    output: &mut String,
+) {
+    if remaining_depth == Some(0) {
+        return;
     }
-}
+    let Some(direct) = callers.get(callee) else { return };
 
-fn is_public(vis: &Visibility) -> bool {
-    matches!(vis, Visibility::Public(_))
+    for (i, edge) in direct.iter().enumerate() {
+        let caller = &edge.caller;
+        let is_last = i == direct.len() - 1;
+        let branch = if is_last { "└── " } else { "├── " };
+        let extension = if is_last { "    " } else { "│   " };
+
+        output.push_str(&format!("{}{}{}", prefix, branch, display_name_with_type(caller)));
+        if let Some(ctx) = &edge.context {
+            output.push_str(&format!(" [in: {}]", ctx));
+        }
+        output.push_str(&line_note(edge.line));
+
+        // Cycle detection, as in the call graph
+        if !visited_in_tree.insert(caller.clone()) {
+            output.push_str(" (already shown)\n");
+            continue;
+        }
+        output.push('\n');
+        let new_prefix = format!("{}{}", prefix, extension);
+        render_callers_tree(caller, callers, visited_in_tree, remaining_depth.map(|d| d - 1), &new_prefix, output);
+    }
 }
 
-fn item_is_public(item: &Item) -> bool {
-    match item {
-        Item::Struct(s) => is_public(&s.vis),
-        Item::Enum(e) => is_public(&e.vis),
-        Item::Trait(t) => is_public(&t.vis),
-        Item::Type(t) => is_public(&t.vis),
-        _ => false,
+// " (line 12)" after a tree node, for the line of the call in the caller's file
+fn line_note(line: usize) -> String {
+    if line == 0 {
+        String::new()
+    } else {
+        format!(" (line {})", line)
     }
 }
 
-fn matches_visibility_filter(vis: &Visibility, filter: VisibilityFilter) -> bool {
-    match filter {
-        VisibilityFilter::All => true,
-        VisibilityFilter::PublicOnly => is_public(vis),
+// "  // lines 7-9" after a listed signature, then the function's cfg when the load recorded it, e.g.,
+// "  // lines 7-9, cfg(unix)"; nothing when neither is known
+fn lines_note(func: &Function) -> String {
+    let lines = match (func.start_line, func.end_line) {
+        (0, _) => None,
+        (start, end) if start == end => Some(format!("line {}", start)),
+        (start, end) => Some(format!("lines {}-{}", start, end)),
+    };
+    let notes: Vec<String> = lines.into_iter().chain(func.cfg.as_ref().map(|cfg| format!("cfg({})", cfg))).collect();
+    if notes.is_empty() {
+        String::new()
+    } else {
+        format!("  // {}", notes.join(", "))
     }
 }
 
-fn item_matches_visibility_filter(item: &Item, filter: VisibilityFilter) -> bool {
-    match filter {
-        VisibilityFilter::All => true,
-        VisibilityFilter::PublicOnly => item_is_public(item),
+// How well an item named `name` at `path` (e.g., "Client::connect") matches a lowercase query: the whole
+// name, a prefix of it, part of it, part of the path, then the query's characters in order (so "tcp"
+// finds "try_connect_pool"). None if it doesn't match at all.
+fn search_score(query: &str, name: &str, path: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    if name == query {
+        Some(100)
+    } else if name.starts_with(query) {
+        Some(80)
+    } else if name.contains(query) {
+        Some(60)
+    } else if path.to_lowercase().contains(query) {
+        Some(40)
+    } else {
+        let mut chars = name.chars();
+        query.chars().all(|q| chars.any(|c| c == q)).then_some(20)
     }
 }
 
-fn generate_list_all(project: &Project, visibility: VisibilityFilter) -> Result<Output, String> {
-    let mut output = String::new();
+// "Type::method" rather than just "method", so implementors can be told apart
+fn display_name_with_type(qualified_name: &str) -> String {
+    qualified_name
+        .split_once("::")
+        .map_or(qualified_name, |(_, rest)| rest)
+        .to_string()
+}
 
-    // Group types by file
-    let mut types_by_file: HashMap<String, Vec<Item>> = HashMap::new();
-    for (_type_name, (file_path, item)) in &project.types {
-        if item_matches_visibility_filter(item, visibility) {
-            types_by_file
-                .entry(file_path.clone())
-                .or_default()
-                .push(item.clone());
-        }
-    }
+// What the type of a call's receiver (or its `Type::` qualifier) says about the callee
+enum ReceiverMethod {
+    Found(String), // the method on that type, or the trait default it inherits
+    External,      // the type is known but the project doesn't define the method
+}
 
-    // Group functions by file
-    let mut funcs_by_file: HashMap<String, Vec<&Function>> = HashMap::new();
-    for (name, func) in &project.functions {
-        if matches_visibility_filter(&func.vis, visibility) {
-            let file_path = find_file_for_function(name, project)
-                .unwrap_or_else(|_| "<unknown>".to_string());
-            funcs_by_file.entry(file_path).or_default().push(func);
+fn receiver_method(caller: &Function, call: &CallSite, project: &Project) -> Option<ReceiverMethod> {
+    let type_name = match &call.receiver_call {
+        Some(previous) => match chained_value(caller, previous, call.receiver_tried, project) {
+            ChainValue::Typed(ty, self_type) => named_type(&ty, self_type.as_deref())?,
+            ChainValue::External => return Some(ReceiverMethod::External),
+            ChainValue::Unknown => return None,
+        },
+        None => receiver_type(caller, call, project)?,
+    };
+    // Methods reached through a type alias live on its target; leave those to name matching
+    if let Some(key) = project.resolve_type_key(&type_name, &caller.module_path) {
+        if matches!(project.types[key].1, Item::Type(_)) {
+            return None;
         }
     }
 
-    // Get all unique file paths and sort them
-    let mut all_files: Vec<String> = types_by_file.keys()
-        .chain(funcs_by_file.keys())
-        .map(|s| s.clone())
-        .collect::<std::collections::HashSet<_>>()
-        .into_iter()
+    // Inherent methods take precedence over trait impl methods, as in Rust. Several types can share a
+    // name across files; the caller's own file wins, then its own project.
+    let caller_file = caller.qualified_name.split_once("::").map_or("", |(file, _)| file);
+    let caller_root = project.root_of(&caller.qualified_name);
+    let methods: Vec<&Function> = project
+        .functions
+        .values()
+        .filter(|f| f.self_type.as_deref() == Some(type_name.as_str()) && f.sig.ident == call.name)
         .collect();
-    all_files.sort();
-
-    // Output types and functions grouped by file
-    for file_path in all_files {
-        output.push_str(&format!("=== {} ===\n", file_path));
+    let pick = |inherent: bool| {
+        let candidates: Vec<&&Function> = methods.iter().filter(|f| f.impl_trait.is_none() == inherent).collect();
+        let in_file = candidates.iter().find(|f| f.qualified_name.starts_with(&format!("{}::", caller_file)));
+        in_file
+            .or_else(|| candidates.iter().find(|f| project.root_of(&f.qualified_name) == caller_root))
+            .or(candidates.first())
+            .copied()
+    };
+    if let Some(method) = pick(true).or_else(|| pick(false)) {
+        return Some(ReceiverMethod::Found(method.qualified_name.clone()));
+    }
 
-        // Output types for this file
-        if let Some(types) = types_by_file.get(&file_path) {
-            for item in types {
-                output.push_str(&format_type_item(item));
-                output.push('\n');
-            }
+    // A default method of a trait the type implements without overriding it
+    for (trait_name, impls) in &project.trait_impls {
+        if !impls.iter().any(|imp| base_type_name(&imp.self_ty) == type_name) {
+            continue;
         }
-
-        // Output functions for this file
-        if let Some(funcs) = funcs_by_file.get_mut(&file_path) {
-            // Sort functions by qualified name
-            funcs.sort_by(|a, b| a.qualified_name.cmp(&b.qualified_name));
-            for func in funcs {
-                output.push_str(&format!("{}\n", func.signature()));
-            }
+        let suffix = format!("::{}::{}", trait_name, call.name);
+        let defaults = project.functions.iter().filter(|(qn, f)| qn.ends_with(&suffix) && f.block.is_some());
+        if let Some(method) = same_root_first(caller, defaults.map(|(qn, _)| qn), project).first() {
+            return Some(ReceiverMethod::Found(method.to_string()));
         }
     }
 
-    Ok(Output { content: output })
+    Some(ReceiverMethod::External)
 }
 
-fn generate_call_graph_output(
-    file_to_funcs: &HashMap<String, Vec<Function>>,
-    file_to_types: &HashMap<String, Vec<Item>>,
-    visibility: VisibilityFilter,
-    root_func: Option<&str>,
-) -> Result<Output, String> {
-    let mut output = String::new();
-
-    // Get all unique file paths and sort them
-    let mut all_files: Vec<String> = file_to_types.keys()
-        .chain(file_to_funcs.keys())
-        .map(|s| s.clone())
-        .collect::<std::collections::HashSet<_>>()
-        .into_iter()
-        .collect();
-    all_files.sort();
+// What a call in a chain hands the next method: the return type of the project function it resolves to,
+// nothing known when it calls into another crate (so neither does the rest of the chain), or Unknown
+// when it can't be resolved without matching names
+enum ChainValue {
+    Typed(Box<Type>, Option<String>), // the type, and the callee's `Self` type for reading it
+    External,
+    Unknown,
+}
 
-    // Build a flat map of all reachable functions for easy lookup
-    let mut all_funcs: HashMap<String, &Function> = HashMap::new();
-    for functions in file_to_funcs.values() {
-        for func in functions {
-            all_funcs.insert(func.qualified_name.clone(), func);
+impl ChainValue {
+    // The `T` of an `Option<T>` or `Result<T, E>` (including aliases such as `io::Result<T>`)
+    fn unwrapped(self) -> ChainValue {
+        let ChainValue::Typed(ty, self_type) = self else { return self };
+        let Type::Path(p) = ty.as_ref() else { return ChainValue::Unknown };
+        let Some(last) = p.path.segments.last().filter(|seg| seg.ident == "Option" || seg.ident == "Result") else {
+            return ChainValue::Unknown;
+        };
+        let PathArguments::AngleBracketed(args) = &last.arguments else { return ChainValue::Unknown };
+        match args.args.first() {
+            Some(GenericArgument::Type(inner)) => ChainValue::Typed(Box::new(inner.clone()), self_type),
+            _ => ChainValue::Unknown,
         }
     }
+}
 
-    // Output types grouped by file
-    for file_path in &all_files {
-        if let Some(items) = file_to_types.get(file_path) {
-            let filtered_items: Vec<_> = items.iter()
-                .filter(|item| item_matches_visibility_filter(item, visibility))
-                .collect();
+// Methods taking the value out of an `Option` or `Result`, as `?` does
+const UNWRAPPING_METHODS: &[&str] = &["unwrap", "expect", "unwrap_or", "unwrap_or_else", "unwrap_or_default"];
+
+// The value `call` returns, unwrapped from its `Option` or `Result` when `tried`
+fn chained_value(caller: &Function, call: &CallSite, tried: bool, project: &Project) -> ChainValue {
+    let value = match (&call.receiver_call, UNWRAPPING_METHODS.contains(&call.name.as_str())) {
+        (Some(previous), true) => chained_value(caller, previous, call.receiver_tried, project).unwrapped(),
+        _ => returned_value(caller, call, project),
+    };
+    if tried {
+        value.unwrapped()
+    } else {
+        value
+    }
+}
 
-            if !filtered_items.is_empty() {
-                output.push_str(&format!("=== {} ===\n", file_path));
-                for item in filtered_items {
-                    output.push_str(&format_type_item(item));
-                    output.push('\n');
+fn returned_value(caller: &Function, call: &CallSite, project: &Project) -> ChainValue {
+    let callee = match receiver_method(caller, call, project) {
+        Some(ReceiverMethod::Found(method)) => method,
+        Some(ReceiverMethod::External) => return ChainValue::External,
+        // A method on a value of unknown type
+        None if call.receiver.is_some() || call.receiver_call.is_some() => return ChainValue::Unknown,
+        None => match path_callee(caller, call, project) {
+            Some(PathCallee::Found(target)) => target,
+            Some(PathCallee::External) => return ChainValue::External,
+            None => {
+                let mut scoped = scoped_callee_candidates(caller, call).into_iter();
+                match scoped.find(|candidate| project.functions.contains_key(candidate)) {
+                    Some(scoped) => scoped,
+                    None => return ChainValue::Unknown,
                 }
             }
-        }
+        },
+    };
+    let Some(func) = project.functions.get(&callee) else { return ChainValue::Unknown };
+    let syn::ReturnType::Type(_, ty) = &func.sig.output else { return ChainValue::Unknown };
+    // A generic return type is whatever the caller picks
+    let name = named_type(ty, None);
+    let mut generics = func.impl_generics.iter().chain([&func.sig.generics]).flat_map(syn::Generics::type_params);
+    match generics.any(|param| name.as_ref().is_some_and(|name| param.ident == name)) {
+        true => ChainValue::Unknown,
+        false => ChainValue::Typed(ty.clone(), func.self_type.clone()),
     }
+}
 
-    // If we have a root function, only show that function's tree
-    if let Some(root_name) = root_func {
-        if let Some(root_function) = all_funcs.get(root_name) {
-            // Get the file for the root function
-            let root_file = find_file_for_function(root_name, &Project {
-                functions: all_funcs.iter().map(|(k, v)| (k.clone(), (*v).clone())).collect(),
-                types: HashMap::new(),
-            })?;
-
-            output.push_str(&format!("=== {} ===\n", root_file));
-
-            let mut visited_in_tree = HashSet::new();
-            render_function_tree(root_function, &all_funcs, &mut visited_in_tree, 0, "", &mut output);
-        }
-    } else {
-        // No root specified - show all functions as separate trees (old behavior)
-        for file_path in &all_files {
-            if let Some(functions) = file_to_funcs.get(file_path) {
-                let mut funcs_to_show: Vec<_> = functions.iter()
-                    .filter(|func| matches_visibility_filter(&func.vis, visibility))
-                    .collect();
-
-                if !funcs_to_show.is_empty() {
-                    // Only print header if we haven't already (from types section)
-                    if !file_to_types.contains_key(file_path) {
-                        output.push_str(&format!("=== {} ===\n", file_path));
-                    }
-
-                    funcs_to_show.sort_by(|a, b| a.qualified_name.cmp(&b.qualified_name));
+// The receiver's type as recorded at extraction, a `self.field`'s declared type, or the type named by a
+// `Type::`/`Self::` qualifier. Qualifiers that aren't project types (`Vec::new`, `module::func`) give None.
+fn receiver_type(caller: &Function, call: &CallSite, project: &Project) -> Option<String> {
+    if let Some(type_name) = &call.receiver_type {
+        return Some(type_name.clone());
+    }
 
-                    for func in funcs_to_show {
-                        let mut visited_in_tree = HashSet::new();
-                        render_function_tree(func, &all_funcs, &mut visited_in_tree, 0, "", &mut output);
-                        output.push('\n');
-                    }
-                }
-            }
-        }
+    if let Some(field) = call.receiver.as_deref().and_then(|receiver| receiver.strip_prefix("self.")) {
+        let self_type = caller.self_type.as_deref()?;
+        let key = project.resolve_type_key(self_type, &caller.module_path)?;
+        let Item::Struct(s) = &project.types[key].1 else { return None };
+        let field = s.fields.iter().find(|f| f.ident.as_ref().is_some_and(|ident| ident == field))?;
+        let generic_params: HashSet<String> = s.generics.type_params().map(|t| t.ident.to_string()).collect();
+        return named_type(&field.ty, Some(self_type)).filter(|name| !generic_params.contains(name));
     }
 
-    Ok(Output { content: output })
+    let qualifier = call.qualifier.as_deref()?;
+    let type_name = qualifier.rsplit("::").next().unwrap_or(qualifier);
+    if type_name == "Self" {
+        return caller.self_type.clone();
+    }
+    project.resolve_type_key(type_name, &caller.module_path).map(|_| type_name.to_string())
 }
 
-fn render_function_tree(
-    func: &Function,
-    all_funcs: &HashMap<String, &Function>,
-    visited_in_tree: &mut HashSet<String>,
-    depth: usize,
-    prefix: &str,
-    output: &mut String,
-) {
-    // Print function signature
-    if depth == 0 {
-        output.push_str(&format!("{}\n", func.signature()));
-    }
+enum Resolution {
+    Unique(String),
+    // Several glob imports in the caller's module supply the name
+    AmbiguousGlob { globs: Vec<String>, candidates: Vec<String> },
+}
 
-    visited_in_tree.insert(func.qualified_name.clone());
+fn resolve_call_to_qualified(
+    caller: &Function,
+    call: &CallSite,
+    project: &Project,
+    all_funcs: &BTreeMap<String, &Function>,
+) -> Option<Resolution> {
+    let call_name = call.name.as_str();
 
-    // Get calls and filter to only project functions
-    let calls = func.calls();
-    let mut project_calls: Vec<(String, Option<String>)> = vec![];
+    // Try exact match first
+    if all_funcs.contains_key(call_name) {
+        return Some(Resolution::Unique(call_name.to_string()));
+    }
 
-    for call in &calls {
-        // Try to resolve the call to a qualified name
-        if let Some(qualified_name) = resolve_call_to_qualified(&call.name, all_funcs) {
-            project_calls.push((qualified_name, call.context.clone()));
+    // Then the method of the receiver's type, when that type is known; methods the project doesn't
+    // define (std, derives, other crates) are left out rather than matched by name
+    match receiver_method(caller, call, project) {
+        Some(ReceiverMethod::Found(method)) => {
+            return all_funcs.contains_key(&method).then_some(Resolution::Unique(method));
         }
+        Some(ReceiverMethod::External) => return None,
+        None => {}
     }
 
-    // Render each call as a tree node
-    for (i, (callee_qualified, context)) in project_calls.iter().enumerate() {
-        let is_last = i == project_calls.len() - 1;
-        let branch = if is_last { "└── " } else { "├── " };
-        let extension = if is_last { "    " } else { "│   " };
-
-        // Display name (strip file path for readability)
-        let display_name = callee_qualified.split("::").last().unwrap_or(callee_qualified);
-
-        if let Some(ctx) = context {
-            output.push_str(&format!("{}{}{} [in: {}]", prefix, branch, display_name, ctx));
-        } else {
-            output.push_str(&format!("{}{}{}", prefix, branch, display_name));
+    // Then a path from the caller's module, or a name imported by `use` under its own name or an alias
+    match path_callee(caller, call, project) {
+        Some(PathCallee::Found(target)) => {
+            return all_funcs.contains_key(&target).then_some(Resolution::Unique(target));
         }
+        Some(PathCallee::External) => return None,
+        None => {}
+    }
 
-        // Check if already visited in this tree (cycle detection)
-        if visited_in_tree.contains(callee_qualified) {
-            output.push_str(" (already shown)\n");
-        } else if let Some(callee_func) = all_funcs.get(callee_qualified) {
-            output.push('\n');
-            // Recursively render the callee's tree
-            let new_prefix = format!("{}{}", prefix, extension);
-            render_function_tree(callee_func, all_funcs, visited_in_tree, depth + 1, &new_prefix, output);
-        } else {
-            output.push('\n');
+    // Then a function nested in the caller's body or declared in its module
+    if let Some(scoped) = scoped_callee_candidates(caller, call)
+        .into_iter()
+        .find(|candidate| all_funcs.contains_key(candidate))
+    {
+        return Some(Resolution::Unique(scoped));
+    }
+
+    // Then the modules brought into scope with `use path::*`
+    let globbed: Vec<(String, String)> = glob_callee_candidates(caller, call_name, project)
+        .into_iter()
+        .filter(|(_, target)| all_funcs.contains_key(target))
+        .collect();
+    match globbed.len() {
+        0 => {}
+        1 => return globbed.into_iter().next().map(|(_, target)| Resolution::Unique(target)),
+        _ => {
+            let (globs, candidates) = globbed.into_iter().unzip();
+            return Some(Resolution::AmbiguousGlob { globs, candidates });
         }
     }
+
+    suffix_callee(caller, call, all_funcs.keys(), project).map(Resolution::Unique)
 }
 
-fn resolve_call_to_qualified(call_name: &str, all_funcs: &HashMap<String, &Function>) -> Option<String> {
-    // Try exact match first
-    if all_funcs.contains_key(call_name) {
-        return Some(call_name.to_string());
-    }
+// `candidates` with the functions under the caller's own loaded directory first, each group in its order,
+// so a name several merged projects define resolves within the caller's project
+fn same_root_first<'a>(
+    caller: &Function,
+    candidates: impl Iterator<Item = &'a String>,
+    project: &Project,
+) -> Vec<&'a String> {
+    let root = project.root_of(&caller.qualified_name);
+    let (mut own, other): (Vec<&String>, Vec<&String>) = candidates.partition(|qn| project.root_of(qn) == root);
+    own.extend(other);
+    own
+}
 
-    // Try to find a function whose qualified name ends with ::call_name
-    all_funcs.keys()
-        .find(|qn| qn.ends_with(&format!("::{}", call_name)))
-        .map(|s| s.clone())
+// `Project::function_at_crate_path`, preferring the caller's own project where several define the path;
+// only then are the roots of the definitions looked at
+fn function_at_crate_path_from<'a>(caller: &Function, crate_path: &str, project: &'a Project) -> Option<&'a String> {
+    let defined = project.paths().functions.get(crate_path)?;
+    if defined.len() > 1 {
+        let root = project.root_of(&caller.qualified_name);
+        if let Some(own) = defined.iter().find(|qn| project.root_of(qn) == root) {
+            return Some(own);
+        }
+    }
+    defined.first()
 }
 
 // === HELPER FUNCTIONS (NO I/O) ===
-fn format_type_item(item: &Item) -> String {
-    match item {
-        Item::Struct(s) => {
-            let vis = visibility_to_string(&s.vis);
-            let fields: Vec<(String, String)> = s
-                .fields
-                .iter()
-                .map(|f| {
-                    let vis_str = visibility_to_string(&f.vis);
-                    let ty = format_type(&f.ty);
-                    if let Some(ident) = &f.ident {
-                        (format!("{}{}", vis_str, ident), ty)
-                    } else {
-                        (ty.clone(), ty)
-                    }
-                })
-                .collect();
+fn format_impl_const(item: &syn::ImplItemConst) -> String {
+    format_const("const", &item.vis, &item.ident, &item.ty, &item.expr)
+}
 
-            let field_lines: Vec<String> = fields
-                .iter()
-                .map(|(name, ty)| format!("    {}: {}", name, ty))
-                .collect();
+// "type Item = Token;"
+fn format_impl_type(item: &syn::ImplItemType) -> String {
+    let generics = format!("{}{}", format_generics(&item.generics), format_where_clause(&item.generics));
+    format!("{}type {}{} = {};", visibility_to_string(&item.vis), item.ident, generics, format_type(&item.ty))
+}
 
-            format!(
-                "{}struct {} {{\n{}\n}}",
-                vis,
-                s.ident,
-                field_lines.join(",\n")
-            )
-        }
+// Longest initializer shown with a const or static; longer ones are elided
+const MAX_CONST_VALUE: usize = 40;
+
+// "pub const MAX_RETRIES: u32 = 3;", or "... = ...;" when the initializer is long
+fn format_const(kind: &str, vis: &Visibility, ident: &syn::Ident, ty: &Type, expr: &Expr) -> String {
+    let value = expr.to_token_stream().to_string();
+    let value = if value.len() <= MAX_CONST_VALUE { value } else { "...".to_string() };
+    format!("{}{} {}: {} = {};", visibility_to_string(vis), kind, ident, format_type(ty), value)
+}
+
+fn format_type_item(item: &Item) -> String {
+    match item {
+        Item::Struct(s) => format_fields_item("struct", &s.vis, &s.ident, &s.generics, s.fields.iter()),
+        Item::Union(u) => format_fields_item("union", &u.vis, &u.ident, &u.generics, u.fields.named.iter()),
 
         Item::Enum(e) => {
             let vis = visibility_to_string(&e.vis);
@@ -783,9 +5469,10 @@ fn format_type_item(item: &Item) -> String {
                 .collect();
 
             format!(
-                "{}enum {} {{\n{}\n}}",
+                "{}enum {}{} {{\n{}\n}}",
                 vis,
                 e.ident,
+                format_generics(&e.generics),
                 variants
                     .iter()
                     .map(|v| format!("    {}", v))
@@ -817,6 +5504,7 @@ fn format_type_item(item: &Item) -> String {
                             ""
                         };
 
+                        let generics = format_generics(&method.sig.generics);
                         let args = format_args(&method.sig.inputs.iter().collect::<Vec<_>>());
 
                         let ret = match &method.sig.output {
@@ -825,8 +5513,8 @@ fn format_type_item(item: &Item) -> String {
                         };
 
                         items.push(format!(
-                            "{}{}{}{}fn {}({}){};",
-                            vis, asyncness, constness, unsafety, method.sig.ident, args, ret
+                            "{}{}{}{}fn {}{}({}){};",
+                            vis, asyncness, constness, unsafety, method.sig.ident, generics, args, ret
                         ));
                     }
                     syn::TraitItem::Type(ty) => {
@@ -843,150 +5531,726 @@ fn format_type_item(item: &Item) -> String {
                 }
             }
 
+            let generics = format_generics(&t.generics);
             if items.is_empty() {
-                format!("{}trait {} {{\n}}", vis, t.ident)
+                format!("{}trait {}{} {{\n}}", vis, t.ident, generics)
             } else {
                 let indented = items
                     .iter()
                     .map(|i| format!("    {}", i))
                     .collect::<Vec<_>>()
                     .join("\n");
-                format!("{}trait {} {{\n{}\n}}", vis, t.ident, indented)
+                format!("{}trait {}{} {{\n{}\n}}", vis, t.ident, generics, indented)
             }
         }
 
         Item::Type(t) => {
             let vis = visibility_to_string(&t.vis);
-            let ty_str = match &*t.ty {
-                syn::Type::Path(p) => p.path.to_token_stream().to_string(),
-                _ => t.ty.to_token_stream().to_string(),
-            };
-            format!("{}type {} = {};", vis, t.ident, ty_str)
+            format!(
+                "{}type {}{} = {};",
+                vis,
+                t.ident,
+                format_generics(&t.generics),
+                format_type(&t.ty)
+            )
+        }
+
+        Item::Const(c) => format_const("const", &c.vis, &c.ident, &c.ty, &c.expr),
+        Item::Static(s) => {
+            let kind = if matches!(s.mutability, syn::StaticMutability::Mut(_)) { "static mut" } else { "static" };
+            format_const(kind, &s.vis, &s.ident, &s.ty, &s.expr)
+        }
+        Item::Macro(m) => {
+            let export = if is_macro_export(m) { "#[macro_export] " } else { "" };
+            format!("{}macro_rules! {}", export, m.ident.as_ref().map(ToString::to_string).unwrap_or_default())
         }
 
         _ => unreachable!(),
     }
 }
 
-fn format_type(t: &Type) -> String {
+// A struct or union with one field per line
+fn format_fields_item<'a>(
+    kind: &str,
+    vis: &Visibility,
+    ident: &syn::Ident,
+    generics: &syn::Generics,
+    fields: impl Iterator<Item = &'a syn::Field>,
+) -> String {
+    let vis = visibility_to_string(vis);
+    let fields: Vec<(String, String)> = fields
+        .map(|f| {
+            let vis_str = visibility_to_string(&f.vis);
+            let ty = format_type(&f.ty);
+            if let Some(ident) = &f.ident {
+                (format!("{}{}", vis_str, ident), ty)
+            } else {
+                (ty.clone(), ty)
+            }
+        })
+        .collect();
+
+    let field_lines: Vec<String> = fields
+        .iter()
+        .map(|(name, ty)| format!("    {}: {}", name, ty))
+        .collect();
+
+    format!(
+        "{}{} {}{} {{\n{}\n}}",
+        vis,
+        kind,
+        ident,
+        format_generics(generics),
+        field_lines.join(",\n")
+    )
+}
+
+pub(crate) fn format_type(t: &Type) -> String {
     match t {
-        Type::Path(p) => p.path.to_token_stream().to_string(),
+        Type::Path(p) => match &p.qself {
+            None => format_path(&p.path),
+            Some(qself) => format_qualified_path(qself, &p.path),
+        },
+        Type::Array(a) => format!("[{}; {}]", format_type(&a.elem), format_const_expr(&a.len)),
+        Type::Slice(s) => format!("[{}]", format_type(&s.elem)),
+        Type::Reference(r) => {
+            let lifetime = r.lifetime.as_ref().map(|l| format!("{} ", l)).unwrap_or_default();
+            let mutability = if r.mutability.is_some() { "mut " } else { "" };
+            format!("&{}{}{}", lifetime, mutability, format_type(&r.elem))
+        }
+        Type::Ptr(p) => {
+            let mutability = if p.mutability.is_some() { "mut" } else { "const" };
+            format!("*{} {}", mutability, format_type(&p.elem))
+        }
+        // A one-element tuple keeps its trailing comma, `(T,)`, so it doesn't read as a parenthesized type
+        Type::Tuple(t) if t.elems.len() == 1 => format!("({},)", format_type(&t.elems[0])),
+        Type::Tuple(t) => format!("({})", t.elems.iter().map(format_type).collect::<Vec<_>>().join(", ")),
+        Type::ImplTrait(i) => format!("impl {}", format_bounds(&i.bounds)),
+        Type::TraitObject(o) => {
+            let dyn_keyword = if o.dyn_token.is_some() { "dyn " } else { "" };
+            format!("{}{}", dyn_keyword, format_bounds(&o.bounds))
+        }
+        Type::BareFn(f) => {
+            let unsafety = if f.unsafety.is_some() { "unsafe " } else { "" };
+            let abi = f.abi.as_ref().map(format_abi).unwrap_or_default();
+            let inputs: Vec<String> = f
+                .inputs
+                .iter()
+                .map(|arg| match &arg.name {
+                    Some((name, _)) => format!("{}: {}", name, format_type(&arg.ty)),
+                    None => format_type(&arg.ty),
+                })
+                .chain(f.variadic.as_ref().map(|_| "...".to_string()))
+                .collect();
+            let ret = match &f.output {
+                syn::ReturnType::Default => String::new(),
+                syn::ReturnType::Type(_, ty) => format!(" -> {}", format_type(ty)),
+            };
+            format!("{}{}{}fn({}){}", format_bound_lifetimes(&f.lifetimes), unsafety, abi, inputs.join(", "), ret)
+        }
+        Type::Paren(p) => format!("({})", format_type(&p.elem)),
+        Type::Group(g) => format_type(&g.elem),
+        Type::Never(_) => "!".to_string(),
+        Type::Infer(_) => "_".to_string(),
         _ => t.to_token_stream().to_string(),
     }
 }
 
+// `impl` and `dyn` bounds, e.g., "Fn(u64) -> u64 + Send + 'static"
+fn format_bounds<'a>(bounds: impl IntoIterator<Item = &'a TypeParamBound>) -> String {
+    bounds.into_iter().map(format_type_param_bound).collect::<Vec<_>>().join(" + ")
+}
+
+// `extern "C" ` before a function pointer or signature
+fn format_abi(abi: &syn::Abi) -> String {
+    match &abi.name {
+        Some(name) => format!("extern {} ", name.to_token_stream()),
+        None => "extern ".to_string(),
+    }
+}
+
+// `<T as Trait>::Assoc`, or `<T>::Assoc` without a trait
+fn format_qualified_path(qself: &syn::QSelf, path: &syn::Path) -> String {
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|seg| format_path(&syn::Path::from(seg.clone())))
+        .collect();
+    let (trait_path, rest) = segments.split_at(qself.position.min(segments.len()));
+    let self_ty = match trait_path {
+        [] => format!("<{}>", format_type(&qself.ty)),
+        _ => format!("<{} as {}>", format_type(&qself.ty), trait_path.join("::")),
+    };
+    std::iter::once(self_ty).chain(rest.iter().cloned()).collect::<Vec<_>>().join("::")
+}
+
+fn format_path(path: &syn::Path) -> String {
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|seg| match &seg.arguments {
+            PathArguments::None => seg.ident.to_string(),
+            PathArguments::AngleBracketed(args) => {
+                let args: Vec<String> = args.args.iter().map(format_generic_argument).collect();
+                format!("{}<{}>", seg.ident, args.join(", "))
+            }
+            PathArguments::Parenthesized(args) => {
+                let inputs: Vec<String> = args.inputs.iter().map(format_type).collect();
+                let ret = match &args.output {
+                    syn::ReturnType::Default => String::new(),
+                    syn::ReturnType::Type(_, ty) => format!(" -> {}", format_type(ty)),
+                };
+                format!("{}({}){}", seg.ident, inputs.join(", "), ret)
+            }
+        })
+        .collect();
+
+    let leading = if path.leading_colon.is_some() { "::" } else { "" };
+    format!("{}{}", leading, segments.join("::"))
+}
+
+fn format_generic_argument(arg: &GenericArgument) -> String {
+    match arg {
+        GenericArgument::Lifetime(l) => l.to_string(),
+        GenericArgument::Type(ty) => format_type(ty),
+        GenericArgument::Const(expr) => format_const_expr(expr),
+        GenericArgument::AssocType(assoc) => format!("{} = {}", assoc.ident, format_type(&assoc.ty)),
+        GenericArgument::AssocConst(assoc) => {
+            format!("{} = {}", assoc.ident, format_const_expr(&assoc.value))
+        }
+        GenericArgument::Constraint(c) => {
+            let bounds: Vec<String> = c.bounds.iter().map(format_type_param_bound).collect();
+            format!("{}: {}", c.ident, bounds.join(" + "))
+        }
+        _ => arg.to_token_stream().to_string(),
+    }
+}
+
+// Const generic arguments and array lengths (`N`, `3`, `{ N + 1 }`)
+fn format_const_expr(expr: &Expr) -> String {
+    expr.to_token_stream().to_string()
+}
+
+fn format_type_param_bound(bound: &TypeParamBound) -> String {
+    match bound {
+        TypeParamBound::Trait(t) => {
+            let modifier = match t.modifier {
+                syn::TraitBoundModifier::Maybe(_) => "?",
+                syn::TraitBoundModifier::None => "",
+            };
+            format!("{}{}{}", format_bound_lifetimes(&t.lifetimes), modifier, format_path(&t.path))
+        }
+        TypeParamBound::Lifetime(l) => l.to_string(),
+        _ => bound.to_token_stream().to_string(),
+    }
+}
+
+// Renders the `<...>` parameter list of an item, or an empty string when it has none
+fn format_generics(generics: &syn::Generics) -> String {
+    if generics.params.is_empty() {
+        return String::new();
+    }
+
+    let params: Vec<String> = generics
+        .params
+        .iter()
+        .map(|param| match param {
+            GenericParam::Lifetime(l) => {
+                if l.bounds.is_empty() {
+                    l.lifetime.to_string()
+                } else {
+                    let bounds: Vec<String> = l.bounds.iter().map(|b| b.to_string()).collect();
+                    format!("{}: {}", l.lifetime, bounds.join(" + "))
+                }
+            }
+            GenericParam::Type(t) => {
+                let mut s = t.ident.to_string();
+                if !t.bounds.is_empty() {
+                    let bounds: Vec<String> = t.bounds.iter().map(format_type_param_bound).collect();
+                    s.push_str(&format!(": {}", bounds.join(" + ")));
+                }
+                if let Some(default) = &t.default {
+                    s.push_str(&format!(" = {}", format_type(default)));
+                }
+                s
+            }
+            GenericParam::Const(c) => {
+                let mut s = format!("const {}: {}", c.ident, format_type(&c.ty));
+                if let Some(default) = &c.default {
+                    s.push_str(&format!(" = {}", format_const_expr(default)));
+                }
+                s
+            }
+        })
+        .collect();
+
+    format!("<{}>", params.join(", "))
+}
+
+// " where T: Into<u64>, F: for<'a> Fn(&'a T) -> u64" to follow a signature, or an empty string when the
+// item has no where clause
+fn format_where_clause(generics: &syn::Generics) -> String {
+    let Some(where_clause) = generics.where_clause.as_ref().filter(|w| !w.predicates.is_empty()) else {
+        return String::new();
+    };
+
+    let predicates: Vec<String> = where_clause
+        .predicates
+        .iter()
+        .map(|predicate| match predicate {
+            syn::WherePredicate::Type(p) => {
+                let bounds: Vec<String> = p.bounds.iter().map(format_type_param_bound).collect();
+                format!("{}{}: {}", format_bound_lifetimes(&p.lifetimes), format_type(&p.bounded_ty), bounds.join(" + "))
+            }
+            syn::WherePredicate::Lifetime(p) => {
+                let bounds: Vec<String> = p.bounds.iter().map(|b| b.to_string()).collect();
+                format!("{}: {}", p.lifetime, bounds.join(" + "))
+            }
+            other => other.to_token_stream().to_string(),
+        })
+        .collect();
+    format!(" where {}", predicates.join(", "))
+}
+
+// "for<'a> " before a higher-ranked bound, or an empty string
+fn format_bound_lifetimes(lifetimes: &Option<syn::BoundLifetimes>) -> String {
+    match lifetimes {
+        Some(lifetimes) => {
+            let params: Vec<String> = lifetimes
+                .lifetimes
+                .iter()
+                .map(|param| match param {
+                    GenericParam::Lifetime(l) => l.lifetime.to_string(),
+                    other => other.to_token_stream().to_string(),
+                })
+                .collect();
+            format!("for<{}> ", params.join(", "))
+        }
+        None => String::new(),
+    }
+}
+
+// The visibility as written, with a trailing space: "pub ", "pub(crate) ", "pub(in crate::net) ", or
+// nothing for private items
 fn visibility_to_string(vis: &Visibility) -> String {
     match vis {
-        Visibility::Public(_) => "pub ",
-        _ => "",
+        Visibility::Inherited => String::new(),
+        vis => format!("{} ", visibility_text(vis)),
+    }
+}
+
+// "pub", "pub(crate)", "pub(super)" or "pub(in crate::net)"; empty for private items
+pub(crate) fn visibility_text(vis: &Visibility) -> String {
+    match vis {
+        Visibility::Public(_) => "pub".to_string(),
+        Visibility::Restricted(restricted) => {
+            let path = restricted.path.to_token_stream().to_string().replace(' ', "");
+            match restricted.in_token {
+                Some(_) => format!("pub(in {})", path),
+                None => format!("pub({})", path),
+            }
+        }
+        Visibility::Inherited => String::new(),
     }
-    .to_string()
 }
 
+// "host: String, port: u16", with the receiver as written
 fn format_args(args: &[&FnArg]) -> String {
     args.iter()
         .map(|arg| match arg {
-            FnArg::Receiver(_) => "self".to_string(),
-            FnArg::Typed(pat_type) => pat_type.ty.to_token_stream().to_string(),
+            FnArg::Receiver(receiver) => format_receiver(receiver),
+            FnArg::Typed(pat_type) => format!("{}: {}", format_pat(&pat_type.pat), format_type(&pat_type.ty)),
         })
         .collect::<Vec<_>>()
         .join(", ")
 }
 
-fn find_file_for_function(qualified_name: &str, _project: &Project) -> Result<String, String> {
+// An argument pattern as a signature shows it: the binding name without `mut`, `_`, or a destructuring
+// pattern such as `(x, y)` or `Point { x, y }`
+fn format_pat(pat: &syn::Pat) -> String {
+    match pat {
+        syn::Pat::Ident(p) => p.ident.to_string(),
+        syn::Pat::Wild(_) => "_".to_string(),
+        syn::Pat::Reference(p) => format!("&{}{}", if p.mutability.is_some() { "mut " } else { "" }, format_pat(&p.pat)),
+        syn::Pat::Tuple(p) => {
+            let elems: Vec<String> = p.elems.iter().map(format_pat).collect();
+            format!("({})", elems.join(", "))
+        }
+        syn::Pat::TupleStruct(p) => {
+            let elems: Vec<String> = p.elems.iter().map(format_pat).collect();
+            format!("{}({})", format_path(&p.path), elems.join(", "))
+        }
+        syn::Pat::Struct(p) => {
+            let mut fields: Vec<String> = p
+                .fields
+                .iter()
+                .map(|field| match (&field.member, field.colon_token) {
+                    (syn::Member::Named(name), None) => name.to_string(),
+                    (member, _) => format!("{}: {}", member.to_token_stream(), format_pat(&field.pat)),
+                })
+                .collect();
+            if p.rest.is_some() {
+                fields.push("..".to_string());
+            }
+            format!("{} {{ {} }}", format_path(&p.path), fields.join(", "))
+        }
+        syn::Pat::Rest(_) => "..".to_string(),
+        other => other.to_token_stream().to_string(),
+    }
+}
+
+// `self`, `mut self`, `&'a self`, `&mut self` or an explicitly typed `self: Box<Self>`
+fn format_receiver(receiver: &syn::Receiver) -> String {
+    if receiver.colon_token.is_some() {
+        return format!("self: {}", format_type(&receiver.ty));
+    }
+
+    let mutability = if receiver.mutability.is_some() { "mut " } else { "" };
+    match &receiver.reference {
+        Some((_, Some(lifetime))) => format!("&{} {}self", lifetime, mutability),
+        Some((_, None)) => format!("&{}self", mutability),
+        None => format!("{}self", mutability),
+    }
+}
+
+fn find_file_for_function(qualified_name: &str, project: &Project) -> Result<String, MorphoError> {
     // Extract file path from qualified_name (format: "file_path::function_name" or "file_path::Type::method")
     if let Some(first_separator) = qualified_name.find("::") {
-        Ok(qualified_name[..first_separator].to_string())
+        Ok(project.relative_path(&qualified_name[..first_separator]))
     } else {
-        Err(format!("Invalid qualified name format: {}", qualified_name))
+        Err(MorphoError::not_found("function", qualified_name))
+    }
+}
+
+// Last path segment of every type named in `ty`, looking inside generic arguments, e.g.,
+// `HashMap<String, Vec<Circle>>` -> HashMap, String, Vec, Circle
+fn referenced_type_names(ty: &Type) -> Vec<String> {
+    match ty {
+        Type::Path(p) => p
+            .path
+            .segments
+            .last()
+            .map(|seg| {
+                let mut names = vec![seg.ident.to_string()];
+                if let PathArguments::AngleBracketed(args) = &seg.arguments {
+                    for arg in &args.args {
+                        if let GenericArgument::Type(inner) = arg {
+                            names.extend(referenced_type_names(inner));
+                        }
+                    }
+                }
+                names
+            })
+            .unwrap_or_default(),
+        Type::Reference(r) => referenced_type_names(&r.elem),
+        Type::Array(a) => referenced_type_names(&a.elem),
+        Type::Slice(s) => referenced_type_names(&s.elem),
+        Type::Paren(p) => referenced_type_names(&p.elem),
+        Type::Ptr(p) => referenced_type_names(&p.elem),
+        Type::Tuple(t) => t.elems.iter().flat_map(referenced_type_names).collect(),
+        Type::TraitObject(obj) => trait_bound_names(obj.bounds.iter()),
+        Type::ImplTrait(it) => trait_bound_names(it.bounds.iter()),
+        _ => vec![],
+    }
+}
+
+fn trait_bound_names<'a>(bounds: impl Iterator<Item = &'a TypeParamBound>) -> Vec<String> {
+    bounds
+        .filter_map(|bound| match bound {
+            TypeParamBound::Trait(t) => t.path.segments.last().map(|seg| seg.ident.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+// Traits reachable from an argument type through a generic parameter or `impl Trait`
+fn bound_traits_of_type(ty: &Type, type_bounds: &HashMap<String, Vec<String>>) -> Vec<String> {
+    match ty {
+        Type::Reference(r) => bound_traits_of_type(&r.elem, type_bounds),
+        Type::Paren(p) => bound_traits_of_type(&p.elem, type_bounds),
+        Type::Path(p) if p.qself.is_none() => p
+            .path
+            .get_ident()
+            .and_then(|ident| type_bounds.get(&ident.to_string()))
+            .cloned()
+            .unwrap_or_default(),
+        Type::ImplTrait(it) => trait_bound_names(it.bounds.iter()),
+        _ => vec![],
     }
 }
 
-fn find_file_for_type(name: &str, project: &Project) -> Result<String, String> {
-    for (type_name, (file_path, _)) in project.types.iter() {
-        if type_name == name {
-            return Ok(file_path.clone());
+// Traits behind `dyn Trait`, looking through references and smart pointers (`Box`, `Arc`, `Rc`, ...)
+fn trait_object_traits(ty: &Type) -> Vec<String> {
+    match ty {
+        Type::TraitObject(obj) => trait_bound_names(obj.bounds.iter()),
+        Type::Reference(r) => trait_object_traits(&r.elem),
+        Type::Paren(p) => trait_object_traits(&p.elem),
+        Type::Path(p) if p.qself.is_none() => {
+            let Some(last_seg) = p.path.segments.last() else { return vec![] };
+            if !matches!(last_seg.ident.to_string().as_str(), "Box" | "Arc" | "Rc" | "RefCell" | "Mutex" | "RwLock") {
+                return vec![];
+            }
+            match &last_seg.arguments {
+                PathArguments::AngleBracketed(args) => args
+                    .args
+                    .iter()
+                    .find_map(|arg| match arg {
+                        GenericArgument::Type(inner) => Some(trait_object_traits(inner)),
+                        _ => None,
+                    })
+                    .unwrap_or_default(),
+                _ => vec![],
+            }
         }
+        _ => vec![],
+    }
+}
+
+// The trait of an impl block as it appears in method keys: its last segment with any generic arguments,
+// so `impl From<A> for T` and `impl From<B> for T` get distinct keys
+fn trait_ref(path: &syn::Path) -> String {
+    let last = syn::Path {
+        leading_colon: None,
+        segments: path.segments.iter().last().cloned().into_iter().collect(),
+    };
+    format_path(&last)
+}
+
+// "<Circle as Shape>", the key segment of methods in `impl Shape for Circle`
+fn trait_impl_segment(self_ty: &str, trait_ref: &str) -> String {
+    format!("<{} as {}>", self_ty, trait_ref)
+}
+
+// "<Length as From<f64>>" -> ("Length", "From<f64>"); None for inherent impl segments
+fn split_trait_impl_segment(segment: &str) -> Option<(&str, &str)> {
+    segment.strip_prefix('<')?.strip_suffix('>')?.split_once(" as ")
+}
+
+// "impl<T> Config<T>" or "impl From<f64> for Length", from an impl segment
+fn impl_header(generics: &str, segment: &str) -> String {
+    match split_trait_impl_segment(segment) {
+        Some((self_ty, trait_ref)) => format!("impl{} {} for {}", generics, trait_ref, self_ty),
+        None => format!("impl{} {}", generics, segment),
     }
-    Err(format!("Type {} not found in project", name))
 }
 
-fn collect_types_in_signature(sig: &syn::Signature, out: &mut HashSet<String>) {
-    for arg in sig.inputs.iter() {
-        if let FnArg::Typed(t) = arg {
-            collect_types_in_type(&t.ty, out);
+// "Config" for `Config`, `Config<T>`, `&mut config::Config` or `crate::Config<'a>`
+fn base_type_name(type_str: &str) -> &str {
+    let without_generics = type_str.split('<').next().unwrap_or(type_str);
+    let name = without_generics.rsplit("::").next().unwrap_or(without_generics);
+    let name = name.trim().trim_start_matches('&').trim_start();
+    name.strip_prefix("mut ").unwrap_or(name).trim()
+}
+
+// The type whose methods a value of type `ty` calls: through references and `Box`/`Rc`/`Arc`, with
+// `Self` standing for `self_type`
+fn named_type(ty: &Type, self_type: Option<&str>) -> Option<String> {
+    match ty {
+        Type::Reference(r) => named_type(&r.elem, self_type),
+        Type::Paren(p) => named_type(&p.elem, self_type),
+        Type::Path(p) if p.qself.is_none() => {
+            let last_seg = p.path.segments.last()?;
+            let name = last_seg.ident.to_string();
+            match name.as_str() {
+                "Self" => self_type.map(str::to_string),
+                "Box" | "Rc" | "Arc" => match &last_seg.arguments {
+                    PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+                        GenericArgument::Type(inner) => named_type(inner, self_type),
+                        _ => None,
+                    }),
+                    _ => None,
+                },
+                _ => Some(name),
+            }
         }
+        _ => None,
     }
+}
 
-    match &sig.output {
-        syn::ReturnType::Type(_, t) => collect_types_in_type(t, out),
-        _ => {}
+// The type an initializer evidently builds: `Type { .. }`, or a constructor-style associated function
+// (`new*`, `from*`, `with_*`, `default`) on a capitalized type, optionally followed by `?`
+fn constructed_type(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Struct(s) => s.path.segments.last().map(|seg| seg.ident.to_string()),
+        Expr::Try(t) => constructed_type(&t.expr),
+        Expr::Paren(p) => constructed_type(&p.expr),
+        Expr::Call(call) => {
+            let Expr::Path(p) = call.func.as_ref() else { return None };
+            let segments: Vec<String> = p.path.segments.iter().map(|seg| seg.ident.to_string()).collect();
+            let [.., type_name, function] = segments.as_slice() else { return None };
+            let is_constructor = function == "default"
+                || function.starts_with("new")
+                || function.starts_with("from")
+                || function.starts_with("with_");
+            (is_constructor && type_name.starts_with(char::is_uppercase)).then(|| type_name.clone())
+        }
+        _ => None,
     }
 }
 
-fn collect_types_in_type(typ: &Type, out: &mut HashSet<String>) {
-    match typ {
-        Type::Path(p) => {
-            if let Some(last_seg) = p.path.segments.last() {
-                out.insert(last_seg.ident.to_string());
+// Collects `let name: Type = ...` bindings anywhere in a function body
+#[derive(Default)]
+struct LetTypeVisitor {
+    bindings: Vec<(String, Type)>,
+    constructed: Vec<(String, String)>, // unannotated `let`s whose initializer names the type built
+}
+
+impl<'ast> Visit<'ast> for LetTypeVisitor {
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        match &local.pat {
+            syn::Pat::Type(pat_type) => {
+                if let syn::Pat::Ident(pat_ident) = pat_type.pat.as_ref() {
+                    self.bindings.push((pat_ident.ident.to_string(), (*pat_type.ty).clone()));
+                }
+            }
+            syn::Pat::Ident(pat_ident) => {
+                if let Some(type_name) = local.init.as_ref().and_then(|init| constructed_type(&init.expr)) {
+                    self.constructed.push((pat_ident.ident.to_string(), type_name));
+                }
             }
+            _ => {}
         }
+        visit::visit_local(self, local);
+    }
+
+    // Nested functions have their own scope
+    fn visit_item_fn(&mut self, _: &'ast syn::ItemFn) {}
+}
 
-        Type::Reference(r) => collect_types_in_type(&r.elem, out),
-        Type::Array(a) => collect_types_in_type(&a.elem, out),
-        Type::Slice(s) => collect_types_in_type(&s.elem, out),
+// Collects every name a pattern binds in a signature or function body
+#[derive(Default)]
+struct PatBindingVisitor {
+    names: HashSet<String>,
+}
 
-        _ => {}
+impl<'ast> Visit<'ast> for PatBindingVisitor {
+    fn visit_pat_ident(&mut self, pat: &'ast syn::PatIdent) {
+        self.names.insert(pat.ident.to_string());
+        visit::visit_pat_ident(self, pat);
     }
+
+    // Nested functions have their own scope
+    fn visit_item_fn(&mut self, _: &'ast syn::ItemFn) {}
 }
 
 fn indent_block(block: &Block) -> String {
     let mut s = String::new();
     for stmt in &block.stmts {
-        match stmt {
-            syn::Stmt::Expr(expr, _) => {
-                s.push_str(&format!("  {}\n", expr.to_token_stream().to_string()))
-            }
-            _ => {}
+        if let syn::Stmt::Expr(expr, _) = stmt {
+            s.push_str(&format!("  {}\n", expr.to_token_stream()))
         }
     }
     s
 }
 
-fn extract_calls_from_block(block: &Block, out: &mut Vec<CallSite>) {
+fn extract_calls_from_block(block: &Block, out: &mut Vec<CallSite>, closure_nodes: Option<usize>) {
     for stmt in &block.stmts {
         match stmt {
-            syn::Stmt::Expr(expr, _) => extract_calls_from_expr(&expr, out),
-            _ => {}
+            syn::Stmt::Expr(expr, _) => extract_calls_from_expr(expr, out, closure_nodes),
+            syn::Stmt::Local(local) => {
+                if let Some(init) = &local.init {
+                    extract_calls_from_expr(&init.expr, out, closure_nodes);
+                    if let Some((_, diverge)) = &init.diverge {
+                        let mut else_calls = vec![];
+                        extract_calls_from_expr(diverge, &mut else_calls, closure_nodes);
+                        for mut call in else_calls {
+                            call.context = Some("let else".to_string());
+                            out.push(call);
+                        }
+                    }
+                }
+            }
+            // Nested `fn` items are indexed as `outer::helper` nodes with their own calls
+            syn::Stmt::Item(_) | syn::Stmt::Macro(_) => {}
         }
     }
 }
 
-fn extract_calls_from_expr(expr: &Expr, out: &mut Vec<CallSite>) {
+// Methods whose closure arguments get labelled in the tree, e.g. `[in: map closure]`
+const ITERATOR_COMBINATORS: &[&str] = &[
+    "map", "filter", "filter_map", "flat_map", "for_each", "try_for_each", "fold", "try_fold",
+    "and_then", "or_else", "map_err", "map_or", "map_or_else", "unwrap_or_else", "then",
+    "inspect", "any", "all", "find", "find_map", "position", "take_while", "skip_while",
+    "scan", "reduce", "retain", "sort_by", "sort_by_key", "max_by_key", "min_by_key",
+];
+
+fn extract_calls_from_expr(expr: &Expr, out: &mut Vec<CallSite>, closure_nodes: Option<usize>) {
     match expr {
-        Expr::Call(call) => extract_path_ident(&call.func, out),
+        Expr::Call(call) => {
+            match call.func.as_ref() {
+                Expr::Path(p) => extract_path_ident(p, call, out),
+                // `(self.handler)(req)`, `make_parser()(input)`
+                func => extract_calls_from_expr(func, out, closure_nodes),
+            }
+            extract_callback_args(call.args.iter(), out);
+            let spawns = matches!(call.func.as_ref(), Expr::Path(p) if p.path.segments.last().is_some_and(|seg| {
+                SPAWN_FUNCTIONS.contains(&seg.ident.to_string().as_str())
+            }));
+            if spawns {
+                extract_spawned_calls(call.args.iter(), out, closure_nodes);
+            } else {
+                extract_arg_calls(call.args.iter(), out, closure_nodes);
+            }
+        }
         Expr::MethodCall(method_call) => {
+            let receiver_calls = out.len();
+            extract_calls_from_expr(&method_call.receiver, out, closure_nodes);
+            let (receiver_call, receiver_tried) = match chained_call(&method_call.receiver, &out[receiver_calls..]) {
+                Some((call, tried)) => (Some(Box::new(call)), tried),
+                None => (None, false),
+            };
+
             let name = method_call.method.to_string();
+            let is_combinator = ITERATOR_COMBINATORS.contains(&name.as_str());
             out.push(CallSite {
-                name,
+                name: name.clone(),
                 context: None,
-            });
+                receiver: simple_receiver_name(&method_call.receiver),
+                receiver_call,
+                receiver_tried,
+                ..Default::default()
+            }
+            .at(method_call.method.span(), method_call));
+            if ROUTE_METHODS.contains(&name.as_str()) {
+                extract_route_handlers(method_call.args.iter(), out, closure_nodes);
+                return;
+            }
+            extract_callback_args(method_call.args.iter(), out);
+
+            if is_combinator {
+                for arg in &method_call.args {
+                    if let Expr::Closure(closure) = arg {
+                        let mut closure_calls = vec![];
+                        extract_calls_from_closure(closure, &mut closure_calls, closure_nodes);
+                        for mut call in closure_calls {
+                            call.context = Some(format!("{} closure", name));
+                            out.push(call);
+                        }
+                    } else {
+                        extract_calls_from_expr(arg, out, closure_nodes);
+                    }
+                }
+            } else if SPAWN_FUNCTIONS.contains(&name.as_str()) {
+                extract_spawned_calls(method_call.args.iter(), out, closure_nodes);
+            } else {
+                extract_arg_calls(method_call.args.iter(), out, closure_nodes);
+            }
         }
-        Expr::Unary(unary) => extract_calls_from_expr(&unary.expr, out),
+        Expr::Unary(unary) => extract_calls_from_expr(&unary.expr, out, closure_nodes),
         Expr::Binary(binary) => {
-            extract_calls_from_expr(&binary.left, out);
-            extract_calls_from_expr(&binary.right, out);
+            extract_calls_from_expr(&binary.left, out, closure_nodes);
+            extract_calls_from_expr(&binary.right, out, closure_nodes);
         }
-        Expr::Group(group) => extract_calls_from_expr(&group.expr, out),
+        Expr::Group(group) => extract_calls_from_expr(&group.expr, out, closure_nodes),
         Expr::Block(block_expr) => {
-            extract_calls_from_block(&block_expr.block, out);
+            extract_calls_from_block(&block_expr.block, out, closure_nodes);
         }
         Expr::If(i) => {
             let cond_str = i.cond.to_token_stream().to_string();
-            extract_calls_from_expr(&i.cond, out);
+            extract_calls_from_expr(&i.cond, out, closure_nodes);
 
             let mut then_calls = vec![];
-            extract_calls_from_block(&i.then_branch, &mut then_calls);
+            extract_calls_from_block(&i.then_branch, &mut then_calls, closure_nodes);
             for mut call in then_calls {
                 call.context = Some(format!("if ({})", cond_str));
                 out.push(call);
@@ -996,7 +6260,7 @@ fn extract_calls_from_expr(expr: &Expr, out: &mut Vec<CallSite>) {
                 match else_expr.as_ref() {
                     Expr::Block(block) => {
                         let mut else_calls = vec![];
-                        extract_calls_from_block(&block.block, &mut else_calls);
+                        extract_calls_from_block(&block.block, &mut else_calls, closure_nodes);
                         for mut call in else_calls {
                             call.context = Some("else".to_string());
                             out.push(call);
@@ -1004,7 +6268,7 @@ fn extract_calls_from_expr(expr: &Expr, out: &mut Vec<CallSite>) {
                     }
                     other_expr => {
                         let mut else_calls = vec![];
-                        extract_calls_from_expr(other_expr, &mut else_calls);
+                        extract_calls_from_expr(other_expr, &mut else_calls, closure_nodes);
                         for mut call in else_calls {
                             call.context = Some("else".to_string());
                             out.push(call);
@@ -1015,14 +6279,14 @@ fn extract_calls_from_expr(expr: &Expr, out: &mut Vec<CallSite>) {
         }
 
         Expr::Match(m) => {
-            extract_calls_from_expr(&m.expr, out);
+            extract_calls_from_expr(&m.expr, out, closure_nodes);
 
             for arm in &m.arms {
                 let pattern_str = arm.pat.to_token_stream().to_string();
                 match arm.body.as_ref() {
                     Expr::Block(block) => {
                         let mut body_calls = vec![];
-                        extract_calls_from_block(&block.block, &mut body_calls);
+                        extract_calls_from_block(&block.block, &mut body_calls, closure_nodes);
                         for mut call in body_calls {
                             call.context = Some(format!("match {}", pattern_str));
                             out.push(call);
@@ -1030,7 +6294,7 @@ fn extract_calls_from_expr(expr: &Expr, out: &mut Vec<CallSite>) {
                     }
                     other_expr => {
                         let mut body_calls = vec![];
-                        extract_calls_from_expr(other_expr, &mut body_calls);
+                        extract_calls_from_expr(other_expr, &mut body_calls, closure_nodes);
                         for mut call in body_calls {
                             call.context = Some(format!("match {}", pattern_str));
                             out.push(call);
@@ -1041,14 +6305,14 @@ fn extract_calls_from_expr(expr: &Expr, out: &mut Vec<CallSite>) {
         }
 
         Expr::Loop(l) => {
-            extract_calls_from_block(&l.body, out);
+            extract_calls_from_block(&l.body, out, closure_nodes);
         }
 
         Expr::While(w) => {
             let cond_str = w.cond.to_token_stream().to_string();
-            extract_calls_from_expr(&w.cond, out);
+            extract_calls_from_expr(&w.cond, out, closure_nodes);
             let mut body_calls = vec![];
-            extract_calls_from_block(&w.body, &mut body_calls);
+            extract_calls_from_block(&w.body, &mut body_calls, closure_nodes);
             for mut call in body_calls {
                 call.context = Some(format!("while ({})", cond_str));
                 out.push(call);
@@ -1057,9 +6321,9 @@ fn extract_calls_from_expr(expr: &Expr, out: &mut Vec<CallSite>) {
 
         Expr::ForLoop(f) => {
             let expr_str = f.expr.to_token_stream().to_string();
-            extract_calls_from_expr(&f.expr, out);
+            extract_calls_from_expr(&f.expr, out, closure_nodes);
             let mut body_calls = vec![];
-            extract_calls_from_block(&f.body, &mut body_calls);
+            extract_calls_from_block(&f.body, &mut body_calls, closure_nodes);
             for mut call in body_calls {
                 call.context = Some(format!("for {}", expr_str));
                 out.push(call);
@@ -1067,50 +6331,267 @@ fn extract_calls_from_expr(expr: &Expr, out: &mut Vec<CallSite>) {
         }
 
         Expr::Async(a) => {
-            extract_calls_from_block(&a.block, out);
+            extract_calls_from_block(&a.block, out, closure_nodes);
+        }
+
+        Expr::Unsafe(u) => {
+            extract_calls_from_block(&u.block, out, closure_nodes);
+        }
+
+        Expr::TryBlock(t) => {
+            extract_calls_from_block(&t.block, out, closure_nodes);
         }
 
         Expr::Try(t) => {
-            extract_calls_from_expr(&t.expr, out);
+            extract_calls_from_expr(&t.expr, out, closure_nodes);
         }
 
+        Expr::Closure(closure) => extract_calls_from_closure(closure, out, closure_nodes),
+
         Expr::Macro(m) => {
-            extract_path_from_syn_path(&m.mac.path, out);
+            extract_path_from_syn_path(&m.mac.path, m, out);
+        }
+
+        // Literals: the calls in their elements, and functions stored in them as values (`Hooks { on_save: save }`,
+        // `[parse_json, parse_toml]`), which the caller hands on to be run later
+        Expr::Struct(s) => {
+            // `Config { timeout }` names a local, not a function
+            let values = s.fields.iter().filter(|field| field.colon_token.is_some()).map(|field| &field.expr);
+            extract_callback_args(values, out);
+            let values = s.fields.iter().map(|field| &field.expr);
+            extract_arg_calls(values.chain(s.rest.as_deref()), out, closure_nodes);
+        }
+        Expr::Array(a) => {
+            extract_callback_args(a.elems.iter(), out);
+            extract_arg_calls(a.elems.iter(), out, closure_nodes);
+        }
+        Expr::Tuple(t) => {
+            extract_callback_args(t.elems.iter(), out);
+            extract_arg_calls(t.elems.iter(), out, closure_nodes);
+        }
+        Expr::Repeat(r) => {
+            extract_callback_args(std::iter::once(r.expr.as_ref()), out);
+            extract_arg_calls([r.expr.as_ref(), r.len.as_ref()].into_iter(), out, closure_nodes);
         }
+        Expr::Range(r) => extract_arg_calls(r.start.iter().chain(&r.end).map(Box::as_ref), out, closure_nodes),
 
         Expr::Lit(_) | Expr::Const(_) => {}
 
-        _ => {}
+        // Everything else without a context of its own (`.await`, `&`, fields, indexing, `return`, casts,
+        // `let` conditions, ...): the calls in its subexpressions
+        _ => {
+            let mut subexprs = SubExprs::default();
+            visit::visit_expr(&mut subexprs, expr);
+            for subexpr in subexprs.0 {
+                extract_calls_from_expr(subexpr, out, closure_nodes);
+            }
+        }
     }
 }
 
-fn extract_path_from_syn_path(path: &syn::Path, out: &mut Vec<CallSite>) {
-    if let Some(last_seg) = path.segments.last() {
-        out.push(CallSite {
-            name: last_seg.ident.to_string(),
-            context: None,
-        });
+// The immediate subexpressions of an expression; nested items are indexed on their own
+#[derive(Default)]
+struct SubExprs<'ast>(Vec<&'ast Expr>);
+
+impl<'ast> Visit<'ast> for SubExprs<'ast> {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        self.0.push(expr);
     }
+
+    fn visit_item(&mut self, _: &'ast Item) {}
 }
 
-fn extract_path_ident(expr: &Expr, out: &mut Vec<CallSite>) {
-    match expr {
-        Expr::Path(p) => {
-            if let Some(last_seg) = p.path.segments.last() {
+// Calls in arguments; closures handed to a call (`spawn(|| ...)`, `router.on(|req| ...)`) run on the
+// caller's behalf
+fn extract_arg_calls<'a>(args: impl Iterator<Item = &'a Expr>, out: &mut Vec<CallSite>, closure_nodes: Option<usize>) {
+    for arg in args {
+        extract_calls_from_expr(arg, out, closure_nodes);
+    }
+}
+
+// Functions and methods running their argument on another thread or task: `thread::spawn(|| ..)`,
+// `tokio::spawn(async move { .. })`, `scope.spawn(..)`, `tokio::task::spawn_blocking(..)` and the like
+const SPAWN_FUNCTIONS: &[&str] = &["spawn", "spawn_blocking", "spawn_local"];
+
+// Calls in a spawn's arguments, labelled `[in: spawned]` in the tree since they run concurrently with the
+// caller; function paths handed over keep their callback label
+fn extract_spawned_calls<'a>(args: impl Iterator<Item = &'a Expr>, out: &mut Vec<CallSite>, closure_nodes: Option<usize>) {
+    let mut spawned = vec![];
+    extract_arg_calls(args, &mut spawned, closure_nodes);
+    for mut call in spawned {
+        call.context = Some("spawned".to_string());
+        out.push(call);
+    }
+}
+
+// A closure materialized as a node becomes a single edge to it; otherwise its calls belong to the caller
+fn extract_calls_from_closure(closure: &syn::ExprClosure, out: &mut Vec<CallSite>, closure_nodes: Option<usize>) {
+    match closure_node_line(closure, closure_nodes) {
+        Some(line) => out.push(
+            CallSite {
+                name: closure_node_name(line),
+                context: None,
+                ..Default::default()
+            }
+            .at(closure.or1_token.span, closure),
+        ),
+        None => extract_calls_from_expr(&closure.body, out, closure_nodes),
+    }
+}
+
+// The closure's line if it has more than `min_stmts` statements and should become its own node
+fn closure_node_line(closure: &syn::ExprClosure, min_stmts: Option<usize>) -> Option<usize> {
+    let min_stmts = min_stmts?;
+    let stmt_count = match closure.body.as_ref() {
+        Expr::Block(b) => b.block.stmts.len(),
+        Expr::Async(a) => a.block.stmts.len(),
+        _ => 1,
+    };
+    if stmt_count > min_stmts {
+        Some(closure.or1_token.span.start().line)
+    } else {
+        None
+    }
+}
+
+fn closure_node_name(line: usize) -> String {
+    format!("{{closure@{}}}", line)
+}
+
+// First and last line of a syntax node; only meaningful on the thread that parsed it, during the load
+fn line_range(node: &impl Spanned) -> (usize, usize) {
+    let span = node.span();
+    (span.start().line, span.end().line)
+}
+
+// Bytes of the whole item in the text parsed, from after its leading doc comments, which renderers show
+// apart from the code
+fn byte_range(node: &impl Spanned, attrs: &[syn::Attribute]) -> std::ops::Range<usize> {
+    let range = node.span().byte_range();
+    let docs = attrs.iter().take_while(|attr| attr.path().is_ident("doc")).last();
+    match docs.map(|attr| attr.span().byte_range().end) {
+        Some(docs_end) if range.contains(&docs_end) => docs_end..range.end,
+        _ => range,
+    }
+}
+
+// `store` in `store.save()`, `self` in `self.run()`; None for chained or complex receivers
+fn simple_receiver_name(receiver: &Expr) -> Option<String> {
+    match receiver {
+        Expr::Path(p) if p.qself.is_none() => p.path.get_ident().map(|i| i.to_string()),
+        Expr::Paren(p) => simple_receiver_name(&p.expr),
+        Expr::Unary(u) if matches!(u.op, syn::UnOp::Deref(_)) => simple_receiver_name(&u.expr),
+        // `self.store`, so the field's declared type can pick the method
+        Expr::Field(f) => match (f.base.as_ref(), &f.member) {
+            (Expr::Path(p), syn::Member::Named(field)) if p.path.is_ident("self") => Some(format!("self.{}", field)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// The call among `calls` (those extracted from a method's receiver) whose result the receiver is, looked
+// up by where its name is, through `.await`, parentheses and `?`; true when a `?` was passed
+fn chained_call(receiver: &Expr, calls: &[CallSite]) -> Option<(CallSite, bool)> {
+    let name = match receiver {
+        Expr::Await(a) => return chained_call(&a.base, calls),
+        Expr::Paren(p) => return chained_call(&p.expr, calls),
+        Expr::Try(t) => return chained_call(&t.expr, calls).map(|(call, _)| (call, true)),
+        Expr::MethodCall(method_call) => &method_call.method,
+        Expr::Call(call) => match call.func.as_ref() {
+            Expr::Path(p) => &p.path.segments.last()?.ident,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let start = name.span().start();
+    let call = calls.iter().find(|call| {
+        call.start_line == start.line && call.column == start.column + 1 && *name == call.name.as_str()
+    })?;
+    Some((call.clone(), false))
+}
+
+const CALLBACK_CONTEXT: &str = "passed as callback";
+
+// Router methods registering request handlers: axum's `.route("/", get(index))` and `.fallback(..)`,
+// actix-web's `web::get().to(index)` and `.service(index)`
+const ROUTE_METHODS: &[&str] = &["route", "fallback", "to", "service"];
+
+pub(crate) const ROUTE_HANDLER_CONTEXT: &str = "route handler";
+
+// Calls in a route registration's arguments; function paths handed over, directly or through `get(..)`
+// and the like, are labelled `[in: route handler]` so entry points can find them
+fn extract_route_handlers<'a>(
+    args: impl Iterator<Item = &'a Expr> + Clone,
+    out: &mut Vec<CallSite>,
+    closure_nodes: Option<usize>,
+) {
+    let mut registered = vec![];
+    extract_callback_args(args.clone(), &mut registered);
+    extract_arg_calls(args, &mut registered, closure_nodes);
+    for mut call in registered {
+        if call.context.as_deref() == Some(CALLBACK_CONTEXT) {
+            call.context = Some(ROUTE_HANDLER_CONTEXT.to_string());
+        }
+        out.push(call);
+    }
+}
+
+// Function paths handed over as values (`register(handler)`, `.map(parse_line)`, `spawn(Self::run)`)
+fn extract_callback_args<'a>(args: impl Iterator<Item = &'a Expr>, out: &mut Vec<CallSite>) {
+    for arg in args {
+        let path = match arg {
+            Expr::Path(p) => p,
+            Expr::Reference(r) => match r.expr.as_ref() {
+                Expr::Path(p) => p,
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        if path.qself.is_some() {
+            continue;
+        }
+
+        // Only snake_case names can be functions; skip `None`, `Ordering::Less`, constants, etc.
+        if let Some(last_seg) = path.path.segments.last() {
+            let name = last_seg.ident.to_string();
+            if name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_') && name != "self" {
                 out.push(CallSite {
-                    name: last_seg.ident.to_string(),
-                    context: None,
-                });
+                    name,
+                    context: Some(CALLBACK_CONTEXT.to_string()),
+                    callback: true,
+                    ..Default::default()
+                }
+                .at(last_seg.ident.span(), path));
             }
         }
+    }
+}
 
-        Expr::MethodCall(m) => {
-            out.push(CallSite {
-                name: m.method.to_string(),
-                context: None,
-            });
+fn extract_path_from_syn_path(path: &syn::Path, call: &impl Spanned, out: &mut Vec<CallSite>) {
+    if let Some(last_seg) = path.segments.last() {
+        out.push(CallSite {
+            name: last_seg.ident.to_string(),
+            context: None,
+            ..Default::default()
         }
+        .at(last_seg.ident.span(), call));
+    }
+}
 
-        _ => {}
+fn extract_path_ident(p: &syn::ExprPath, call: &impl Spanned, out: &mut Vec<CallSite>) {
+    if let Some(last_seg) = p.path.segments.last() {
+        let qualifier: Vec<String> = p.path.segments.iter()
+            .take(p.path.segments.len() - 1)
+            .map(|seg| seg.ident.to_string())
+            .collect();
+        out.push(CallSite {
+            name: last_seg.ident.to_string(),
+            context: None,
+            qualifier: (!qualifier.is_empty()).then(|| qualifier.join("::")),
+            ..Default::default()
+        }
+        .at(last_seg.ident.span(), call));
     }
 }
@@ -0,0 +1,128 @@
+// Mermaid flowcharts of the call graph, the callers tree, call paths and cycles, fenced so they render when
+// pasted into GitHub issues and Markdown docs. Nodes are numbered in qualified-name order and labelled `Type::method`
+// as in the text tree; edges carry their context, and repeated calls between two functions are drawn once.
+
+use crate::{
+    display_name_with_type, fit_budget, matches_visibility_filter, Budget, CallEdge, MorphoError, OutputMode, Project,
+    TraceLimits, VisibilityFilter,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub(crate) fn render(project: &Project, mode: OutputMode) -> Result<String, MorphoError> {
+    match mode {
+        OutputMode::CallGraph { roots, visibility, budget, descend_into_deps, scope, .. } => {
+            let limits = TraceLimits { stop_at_deps: !descend_into_deps, scope };
+            call_graph(project, &roots, visibility, budget, &limits)
+        }
+        OutputMode::Callers { target, depth } => callers(project, &target, depth),
+        OutputMode::PathBetween { from, to } => path_between(project, &from, &to),
+        OutputMode::Cycles => Ok(cycles(project)),
+        OutputMode::ListAll { .. }
+        | OutputMode::Source { .. }
+        | OutputMode::TypeUsages { .. }
+        | OutputMode::TypeApi { .. }
+        | OutputMode::Search { .. }
+        | OutputMode::Unreachable { .. }
+        | OutputMode::Metrics { .. }
+        | OutputMode::UnsafeAudit { .. }
+        | OutputMode::Implementors { .. }
+        | OutputMode::CallEdges { .. }
+        | OutputMode::CallGraphSummary { .. }
+        | OutputMode::WithAttribute { .. }
+        | OutputMode::EntryPoints
+        | OutputMode::PublicApi
+        | OutputMode::CallGraphSource { .. }
+        | OutputMode::Diff { .. }
+        | OutputMode::Semver { .. } => {
+            Err(MorphoError::Unsupported(
+                "Mermaid output is only available for call graphs, callers, call paths and cycles".to_string(),
+            ))
+        }
+    }
+}
+
+// Reachable functions matching `visibility` (the roots always) and the calls between them
+// A budget keeps the functions nearest the roots, breadth-first
+fn call_graph(
+    project: &Project,
+    roots: &[String],
+    visibility: VisibilityFilter,
+    budget: Budget,
+    limits: &TraceLimits,
+) -> Result<String, MorphoError> {
+    let graph = project.call_graph_limited(roots, limits)?;
+    let roots: Vec<&str> = graph.roots.iter().map(|root| root.qualified_name.as_str()).collect();
+    let order = graph.breadth_first();
+    fit_budget(budget, order.len(), String::len, |limit| {
+        let nodes: BTreeSet<String> = order
+            .iter()
+            .take(limit.unwrap_or(order.len()))
+            .filter(|f| roots.contains(&f.qualified_name.as_str()) || matches_visibility_filter(&f.vis, visibility))
+            .map(|f| f.qualified_name.clone())
+            .collect();
+        // Functions the graph stopped at are drawn without their calls
+        let callers = nodes.iter().filter(|name| !graph.boundary.contains(*name));
+        let edges = project.call_edges_from(callers.map(|name| &project.functions[name]));
+        Ok(flowchart(&nodes, &edges, &roots))
+    })
+}
+
+fn callers(project: &Project, target: &str, depth: Option<usize>) -> Result<String, MorphoError> {
+    let callers = project.callers_of(target, depth)?;
+    let nodes: BTreeSet<String> = callers.functions.iter().map(|f| f.qualified_name.clone()).collect();
+    Ok(flowchart(&nodes, &callers.edges, &[&callers.target.qualified_name]))
+}
+
+// The functions on the shortest chains and the calls between them, which draw every chain at once
+fn path_between(project: &Project, from: &str, to: &str) -> Result<String, MorphoError> {
+    let paths = project.paths_between(from, to)?;
+    let edges: Vec<CallEdge> = paths.paths.into_iter().flatten().collect();
+    let mut nodes: BTreeSet<String> = edges.iter().flat_map(|edge| [edge.caller.clone(), edge.callee.clone()]).collect();
+    nodes.insert(paths.from.qualified_name.clone());
+    Ok(flowchart(&nodes, &edges, &[&paths.from.qualified_name]))
+}
+
+// Every cycle in one chart; they share no functions, so each is drawn apart from the others
+fn cycles(project: &Project) -> String {
+    let cycles = project.cycles();
+    let nodes: BTreeSet<String> =
+        cycles.iter().flat_map(|cycle| &cycle.functions).map(|f| f.qualified_name.clone()).collect();
+    let edges: Vec<CallEdge> = cycles.into_iter().flat_map(|cycle| cycle.edges).collect();
+    flowchart(&nodes, &edges, &[])
+}
+
+// The starting functions are drawn with rounded ends so they stand out
+fn flowchart(nodes: &BTreeSet<String>, edges: &[CallEdge], starts: &[&str]) -> String {
+    let ids: BTreeMap<&str, String> = nodes.iter().enumerate().map(|(i, name)| (name.as_str(), format!("f{}", i))).collect();
+
+    let mut out = String::from("```mermaid\nflowchart TD\n");
+    for (name, id) in &ids {
+        let label = escape(&display_name_with_type(name));
+        if starts.contains(name) {
+            out.push_str(&format!("    {}([\"{}\"])\n", id, label));
+        } else {
+            out.push_str(&format!("    {}[\"{}\"]\n", id, label));
+        }
+    }
+
+    let mut drawn = BTreeSet::new();
+    for edge in edges {
+        let (Some(caller), Some(callee)) = (ids.get(edge.caller.as_str()), ids.get(edge.callee.as_str())) else {
+            continue;
+        };
+        if !drawn.insert((caller, callee, &edge.context)) {
+            continue;
+        }
+        match &edge.context {
+            Some(ctx) => out.push_str(&format!("    {} -->|\"{}\"| {}\n", caller, escape(ctx), callee)),
+            None => out.push_str(&format!("    {} --> {}\n", caller, callee)),
+        }
+    }
+    out.push_str("```");
+    out
+}
+
+// Mermaid reads quotes and angle brackets inside labels as markup; entity codes keep them literal
+fn escape(text: &str) -> String {
+    text.replace('"', "#quot;").replace('<', "#lt;").replace('>', "#gt;")
+}
@@ -0,0 +1,158 @@
+// Text and HTML renderings for interactive use in evcxr / Jupyter (enabled by the `notebook` feature).
+//
+// Every helper returns a `Rendered` value: it prints as plain text through `Display`, and evcxr shows
+// its HTML form when it is the last expression of a cell, via the `evcxr_display` protocol.
+//
+//     let project = morpho_rs::load_project("path/to/crate")?;
+//     morpho_rs::notebook::project_summary(&project)
+
+use crate::{
+    function_by_name, generate_output_for_project, CallEdge, MorphoError, OutputMode, Project, VisibilityFilter,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Files listed in the project summary, by function count
+const SUMMARY_TOP_FILES: usize = 10;
+
+#[derive(Debug, Clone, Default)]
+pub struct Rendered {
+    pub text: String,
+    pub html: String,
+}
+
+impl Rendered {
+    // Called by evcxr for the value of a cell's last expression
+    pub fn evcxr_display(&self) {
+        println!("EVCXR_BEGIN_CONTENT text/html\n{}\nEVCXR_END_CONTENT", self.html);
+    }
+}
+
+impl fmt::Display for Rendered {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+// Index counts plus the files defining the most functions
+pub fn project_summary(project: &Project) -> Rendered {
+    let trait_impls: usize = project.trait_impls.values().map(Vec::len).sum();
+    let counts = [
+        ("files parsed", project.stats.files_parsed),
+        ("files skipped", project.stats.skipped.len()),
+        ("modules", project.modules.len()),
+        ("functions", project.functions.len()),
+        ("types", project.types.len()),
+        ("trait impls", trait_impls),
+    ];
+
+    let mut per_file: BTreeMap<&str, usize> = BTreeMap::new();
+    for name in project.functions.keys() {
+        *per_file.entry(name.split_once("::").map_or("", |(file, _)| file)).or_default() += 1;
+    }
+    let mut top_files: Vec<(&str, usize)> = per_file.into_iter().collect();
+    top_files.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    top_files.truncate(SUMMARY_TOP_FILES);
+
+    let mut text = format!("Project {}\n", project.stats.fingerprint);
+    for (label, count) in counts {
+        text.push_str(&format!("  {:<14}{}\n", label, count));
+    }
+    text.push_str("Largest files (functions):\n");
+    for (file, count) in &top_files {
+        text.push_str(&format!("  {:>5}  {}\n", count, file));
+    }
+
+    let mut html = format!("<h4>Project <code>{}</code></h4>\n<table>\n", escape(&project.stats.fingerprint));
+    for (label, count) in counts {
+        html.push_str(&format!("<tr><th align=\"left\">{}</th><td>{}</td></tr>\n", label, count));
+    }
+    html.push_str("</table>\n<table>\n<tr><th align=\"left\">file</th><th>functions</th></tr>\n");
+    for (file, count) in &top_files {
+        html.push_str(&format!("<tr><td><code>{}</code></td><td>{}</td></tr>\n", escape(file), count));
+    }
+    html.push_str("</table>");
+
+    Rendered { text, html }
+}
+
+// The call graph rooted at `root` (a qualified or short function name), as the CLI renders it
+pub fn call_graph(project: &Project, root: &str, visibility: VisibilityFilter) -> Result<Rendered, MorphoError> {
+    let output = generate_output_for_project(
+        project,
+        OutputMode::CallGraph {
+            roots: vec![root.to_string()],
+            visibility,
+            budget: Default::default(),
+            descend_into_deps: true,
+            show_external: false,
+            scope: None,
+        },
+    )?;
+    let html = format!("<pre>{}</pre>", escape(&output.content));
+    Ok(Rendered {
+        text: output.content,
+        html,
+    })
+}
+
+// Signature, location, complexity, and the resolved callees and callers of one function
+pub fn function_summary(project: &Project, name: &str) -> Result<Rendered, MorphoError> {
+    let func = function_by_name(project, name)?;
+    let name = project.relative_path(&func.qualified_name);
+    let file = name.split_once("::").map_or("", |(file, _)| file);
+    let signature = project.signature(func);
+    let edges = project.call_edges();
+    let shown =
+        |names: Vec<&str>| -> Vec<String> { names.into_iter().map(|name| project.relative_path(name)).collect() };
+    let callees = shown(neighbours(&edges, |edge| (edge.caller == func.qualified_name).then_some(&edge.callee)));
+    let callers = shown(neighbours(&edges, |edge| (edge.callee == func.qualified_name).then_some(&edge.caller)));
+
+    let mut text = format!(
+        "{}\n  {}:{}-{}\n  complexity {}\n",
+        signature,
+        file,
+        func.start_line,
+        func.end_line,
+        func.complexity()
+    );
+    for (label, names) in [("calls", &callees), ("called by", &callers)] {
+        text.push_str(&format!("  {} ({}):\n", label, names.len()));
+        for name in names {
+            text.push_str(&format!("    {}\n", name));
+        }
+    }
+
+    let mut html = format!(
+        "<pre>{}</pre>\n<p><code>{}</code> lines {}&ndash;{}, complexity {}</p>\n",
+        escape(&signature),
+        escape(file),
+        func.start_line,
+        func.end_line,
+        func.complexity()
+    );
+    for (label, names) in [("calls", &callees), ("called by", &callers)] {
+        html.push_str(&format!("<details><summary>{} ({})</summary>\n<ul>\n", label, names.len()));
+        for name in names {
+            html.push_str(&format!("<li><code>{}</code></li>\n", escape(name)));
+        }
+        html.push_str("</ul></details>\n");
+    }
+
+    Ok(Rendered { text, html })
+}
+
+// Distinct names picked out of the edges, in edge order
+fn neighbours<'a>(edges: &'a [CallEdge], pick: impl Fn(&'a CallEdge) -> Option<&'a String>) -> Vec<&'a str> {
+    let mut names: Vec<&str> = vec![];
+    for name in edges.iter().filter_map(pick) {
+        if !names.contains(&name.as_str()) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,1189 @@
+// Typed queries over a loaded project, for library users who want structures rather than rendered text.
+// Load once, then ask as many questions as needed; every output mode's text, JSON and Mermaid renderers
+// are built on these.
+//
+//     let project = morpho_rs::load_project("path/to/crate")?;
+//     let graph = project.call_graph("main")?;
+//     let callers = project.callers_of("save", None)?;
+//     let api = project.type_api("Config")?;
+//
+// Names are looked up as the output modes look them up: a qualified name, a unique `::name` suffix, a
+// crate path, or `Type::method` for trait impl methods.
+
+use crate::{
+    base_type_name, display_name_with_type, doc_comment, item_attrs, find_source_function, find_source_item, find_source_type, format_generics,
+    function_by_name, impl_header, is_macro_export, join_path, is_public, item_matches_visibility_filter, matches_visibility_filter, search_query,
+    search_score, source_not_found, split_trait_impl_segment, trait_impl_segment, _trace_calls, CallEdge,
+    TraceLimits,
+    Function, ImplConst, ImplType, Metric, MorphoError, Project, TraitImpl, TypeRef, VisibilityFilter, MAX_CALL_PATHS,
+    ROUTE_HANDLER_CONTEXT,
+};
+use crate::workspace::CrateTarget;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use syn::Item;
+
+// Everything reachable from one or more root functions
+pub struct CallGraph<'a> {
+    pub roots: Vec<&'a Function>, // in the order asked for, each once
+    pub functions: Vec<&'a Function>, // reached functions in name order, the roots included
+    pub types: Vec<TypeDef<'a>>, // types named in their signatures and bodies, in key order
+    pub edges: Vec<CallEdge>, // every call between reached functions
+    // Functions in a dependency reached but not followed, when the graph stops at dependencies
+    pub boundary: HashSet<String>,
+}
+
+impl<'a> CallGraph<'a> {
+    // Reached functions nearest a root first: the roots, then their callees in call order, then theirs.
+    // Functions reached only through calls `edges` doesn't record come last, in name order.
+    pub fn breadth_first(&self) -> Vec<&'a Function> {
+        let mut callees: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for edge in &self.edges {
+            callees.entry(edge.caller.as_str()).or_default().push(edge.callee.as_str());
+        }
+        let by_name: BTreeMap<&str, &'a Function> =
+            self.functions.iter().map(|func| (func.qualified_name.as_str(), *func)).collect();
+
+        let mut seen: HashSet<&str> = self.roots.iter().map(|root| root.qualified_name.as_str()).collect();
+        let mut order = self.roots.clone();
+        let mut next = 0;
+        while next < order.len() {
+            let func = order[next];
+            for callee in callees.get(func.qualified_name.as_str()).into_iter().flatten() {
+                if let Some(&callee) = by_name.get(callee).filter(|_| seen.insert(callee)) {
+                    order.push(callee);
+                }
+            }
+            next += 1;
+        }
+        order.extend(self.functions.iter().filter(|func| !seen.contains(func.qualified_name.as_str())));
+        order
+    }
+
+    // The reached types and functions matching `visibility` (the roots always), grouped by file as a
+    // listing groups them: each file's types in key order, then its functions in name order
+    pub fn summary(&self, visibility: VisibilityFilter) -> Vec<ItemRef<'a>> {
+        let is_root = |func: &Function| self.roots.iter().any(|root| root.qualified_name == func.qualified_name);
+        let types = self
+            .types
+            .iter()
+            .filter(|ty| item_matches_visibility_filter(ty.item, visibility))
+            .map(|ty| ItemRef::Type(*ty));
+        let functions = self
+            .functions
+            .iter()
+            .filter(|func| is_root(func) || matches_visibility_filter(&func.vis, visibility))
+            .map(|func| ItemRef::Function(func));
+        let mut items: Vec<ItemRef<'a>> = types.chain(functions).collect();
+        items.sort_by_key(|item| (item.file(), matches!(item, ItemRef::Function(_))));
+        items
+    }
+}
+
+// A function and everything that calls it, directly or transitively
+pub struct Callers<'a> {
+    pub target: &'a Function,
+    pub functions: Vec<&'a Function>, // the target and its callers, in name order
+    pub edges: Vec<CallEdge>, // calls into the target and into each caller, nearest callers first
+}
+
+// A struct, enum, trait or type alias as indexed in `Project::types`, or a const, static or macro_rules!
+// macro as indexed in `Project::items`
+#[derive(Clone, Copy)]
+pub struct TypeDef<'a> {
+    pub key: &'a String, // e.g., "net::Client"
+    pub file: &'a String,
+    pub item: &'a Item,
+}
+
+pub struct TypeApi<'a> {
+    pub ty: TypeDef<'a>,
+    pub impls: Vec<ImplBlock<'a>>, // the type's own file first
+}
+
+// An impl block as the type API lists it, with the methods indexed from it in name order
+pub struct ImplBlock<'a> {
+    pub file: String,
+    pub header: String, // e.g., "impl<T> Config<T>" or "impl From<f64> for Length"
+    pub methods: Vec<&'a Function>,
+    pub consts: Vec<&'a ImplConst>, // in the order they are declared
+    pub types: Vec<&'a ImplType>, // the same
+}
+
+pub struct TypeUsages<'a> {
+    pub ty: TypeDef<'a>,
+    // Functions naming the type, in name order, with their references by usage then line
+    pub usages: Vec<(&'a Function, Vec<&'a TypeRef>)>,
+}
+
+// A function or type matched by a search, with how well its name matched (higher is better)
+pub struct SearchHit<'a> {
+    pub score: u32,
+    pub item: ItemRef<'a>,
+}
+
+// A function, type or other item, e.g., whatever a name given to `Project::source` refers to
+#[derive(Clone, Copy)]
+pub enum ItemRef<'a> {
+    Function(&'a Function),
+    Type(TypeDef<'a>),
+    Item(TypeDef<'a>), // a const, static or macro_rules! macro
+}
+
+impl<'a> ItemRef<'a> {
+    // e.g., "./src/net.rs"
+    pub fn file(&self) -> &'a str {
+        match self {
+            ItemRef::Function(func) => func.qualified_name.split_once("::").map_or("", |(file, _)| file),
+            ItemRef::Type(ty) | ItemRef::Item(ty) => ty.file,
+        }
+    }
+
+    // The item's doc comment, one line per `///` line; None when undocumented
+    pub fn docs(&self) -> Option<String> {
+        match self {
+            ItemRef::Function(func) => func.docs.clone(),
+            ItemRef::Type(ty) | ItemRef::Item(ty) => doc_comment(item_attrs(ty.item)),
+        }
+    }
+}
+
+pub struct Unreachable<'a> {
+    pub roots: Vec<String>, // qualified names of the roots traced
+    pub functions: Vec<&'a Function>, // never reached from them, in name order
+}
+
+// Every impl of a trait, see `Project::implementors`
+pub struct Implementors<'a> {
+    pub trait_name: String, // e.g., "Shape" or "From"
+    pub trait_def: Option<TypeDef<'a>>, // None for traits from other crates, e.g., `Display`
+    pub impls: Vec<Implementor<'a>>, // by file, then line
+}
+
+// One impl of a trait and the methods it defines, each in name order
+pub struct Implementor<'a> {
+    pub imp: &'a TraitImpl,
+    pub provides: Vec<&'a Function>, // methods without a default in the trait, or all of them when it isn't indexed
+    pub overrides: Vec<&'a Function>, // methods replacing the trait's default
+    pub inherits: Vec<&'a Function>, // the trait's default methods the impl doesn't replace
+}
+
+// Functions containing unsafe code, and how a root reaches them; see `Project::unsafe_audit`
+pub struct UnsafeAudit<'a> {
+    pub root: Option<&'a Function>,
+    pub functions: Vec<&'a Function>, // `unsafe fn`s and functions with `unsafe {}` blocks, in name order
+    // From the root to each of `functions` it reaches, by the fewest calls, in the order of `functions`
+    pub paths: Vec<Vec<&'a Function>>,
+}
+
+// The shortest chains of calls from one function to another; see `Project::paths_between`
+pub struct CallPaths<'a> {
+    pub from: &'a Function,
+    pub to: &'a Function,
+    // The calls along each chain, in order; at most MAX_CALL_PATHS of them, none when `to` isn't reached
+    pub paths: Vec<Vec<CallEdge>>,
+    pub total: usize, // shortest chains there are, including those past MAX_CALL_PATHS
+}
+
+// Size and coupling of one function, see `Project::metrics`
+pub struct FunctionMetrics<'a> {
+    pub function: &'a Function,
+    pub lines: usize, // of the whole item, from its first to its last line
+    pub complexity: usize, // cyclomatic, see `Function::complexity`
+    pub callees: usize, // distinct functions it calls
+    pub fan_in: usize, // distinct functions calling it
+}
+
+impl FunctionMetrics<'_> {
+    pub fn get(&self, metric: Metric) -> usize {
+        match metric {
+            Metric::Lines => self.lines,
+            Metric::Complexity => self.complexity,
+            Metric::Callees => self.callees,
+            Metric::FanIn => self.fan_in,
+        }
+    }
+}
+
+// One name a library exports, see `Project::public_api`
+pub struct PublicItem<'a> {
+    // As downstream crates write it, e.g., "crate::prelude::Thing", or with the crate's name for its
+    // root when the project was loaded with its packages, e.g., "my_crate::prelude::Thing"
+    pub path: String,
+    pub item: ItemRef<'a>,
+    pub defined_at: String, // crate path of the definition, e.g., "net::client::Thing"
+}
+
+// Where execution can start, see `Project::entry_points`
+pub struct EntryPoints<'a> {
+    pub binaries: Vec<&'a CrateTarget>, // bin targets of the loaded Cargo packages; none without them
+    pub functions: Vec<EntryPoint<'a>>, // by kind, in `EntryKind` order, then name
+}
+
+pub struct EntryPoint<'a> {
+    pub function: &'a Function,
+    pub kind: EntryKind,
+    // What gives it away: the attribute, e.g., "#[tokio::main]" or "#[no_mangle]", `extern "C"`, or for a
+    // handler, the function registering it
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntryKind {
+    Main, // `fn main` outside any impl
+    RuntimeMain, // an async `main` under a runtime attribute such as `#[tokio::main]`
+    Exported, // `#[no_mangle]`, `#[export_name]` or a `pub extern "C" fn`, callable from other languages
+    Handler, // registered with a router (`.route("/", get(index))`, `.to(index)`) or under `#[get("/")]`
+    Test, // `#[test]`, `#[tokio::test]` and the like, when test code is loaded
+}
+
+impl EntryKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EntryKind::Main => "main",
+            EntryKind::RuntimeMain => "runtime main",
+            EntryKind::Exported => "exported",
+            EntryKind::Handler => "handler",
+            EntryKind::Test => "test",
+        }
+    }
+}
+
+// actix-web's routing attributes, e.g., `#[get("/")]`
+const ROUTE_ATTRIBUTES: &[&str] = &["get", "post", "put", "delete", "patch", "head", "route", "routes"];
+
+// Part of the listing of every item: by file, each file's types, other items and functions, in name order
+pub struct ListPage<'a> {
+    pub items: Vec<ItemRef<'a>>,
+    pub offset: usize, // of the first item in the whole listing
+    pub total: usize, // items in the whole listing
+}
+
+impl ListPage<'_> {
+    // Where the page after this one starts, if there are more items
+    pub fn next_offset(&self) -> Option<usize> {
+        let end = self.offset + self.items.len();
+        (end < self.total).then_some(end)
+    }
+}
+
+// Functions that call each other in a loop, so a call into any of them can recurse; see `Project::cycles`
+pub struct Cycle<'a> {
+    pub functions: Vec<&'a Function>, // in name order; one for a function calling itself
+    pub edges: Vec<CallEdge>, // the calls between them, the first from each caller to each callee
+}
+
+// Every resolved call in the project as a directed graph, for running graph algorithms (components,
+// dominators, centrality) on the structure rather than on rendered trees. Nodes are numbered by
+// `FunctionId` in name order; with the `petgraph` feature, `to_petgraph` hands the same numbering over.
+pub struct Graph<'a> {
+    pub functions: Vec<&'a Function>, // `FunctionId(i)` is `functions[i]`
+    pub edges: Vec<GraphEdge>, // every call site, by caller in name order, then in source order
+    ids: HashMap<&'a str, FunctionId>,
+    out_of: Vec<Vec<usize>>, // indices into `edges` by caller
+    into: Vec<Vec<usize>>, // indices into `edges` by callee
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FunctionId(pub usize);
+
+#[derive(Debug, Clone)]
+pub struct GraphEdge {
+    pub from: FunctionId,
+    pub to: FunctionId,
+    pub call: CallEdge,
+}
+
+impl<'a> Graph<'a> {
+    pub fn id(&self, qualified_name: &str) -> Option<FunctionId> {
+        self.ids.get(qualified_name).copied()
+    }
+
+    pub fn function(&self, id: FunctionId) -> &'a Function {
+        self.functions[id.0]
+    }
+
+    // Every call `id` makes, in source order
+    pub fn calls(&self, id: FunctionId) -> impl Iterator<Item = &GraphEdge> {
+        self.out_of[id.0].iter().map(|&edge| &self.edges[edge])
+    }
+
+    // Every call into `id`, by caller in name order
+    pub fn calls_into(&self, id: FunctionId) -> impl Iterator<Item = &GraphEdge> {
+        self.into[id.0].iter().map(|&edge| &self.edges[edge])
+    }
+
+    // The first call to each function `id` calls, in source order
+    pub fn callees(&self, id: FunctionId) -> Vec<&GraphEdge> {
+        let mut seen = HashSet::new();
+        self.calls(id).filter(|edge| seen.insert(edge.to)).collect()
+    }
+
+    // The first call from each function calling `id`, by caller in name order
+    pub fn callers(&self, id: FunctionId) -> Vec<&GraphEdge> {
+        let mut seen = HashSet::new();
+        self.calls_into(id).filter(|edge| seen.insert(edge.from)).collect()
+    }
+
+    // Groups of functions that can each reach the others (Tarjan's algorithm, without recursion so deep call
+    // chains can't overflow the stack), each in id order. Every function is in exactly one group, most of
+    // them alone.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<FunctionId>> {
+        const UNVISITED: usize = usize::MAX;
+        let count = self.functions.len();
+        let (mut index, mut low, mut on_stack) = (vec![UNVISITED; count], vec![0; count], vec![false; count]);
+        let (mut stack, mut components, mut next_index) = (vec![], vec![], 0);
+        for start in 0..count {
+            if index[start] != UNVISITED {
+                continue;
+            }
+            // (function, how many of its calls have been followed)
+            let mut work = vec![(start, 0)];
+            while let Some((node, followed)) = work.pop() {
+                if followed == 0 {
+                    (index[node], low[node]) = (next_index, next_index);
+                    next_index += 1;
+                    stack.push(node);
+                    on_stack[node] = true;
+                }
+                if let Some(&edge) = self.out_of[node].get(followed) {
+                    work.push((node, followed + 1));
+                    let to = self.edges[edge].to.0;
+                    if index[to] == UNVISITED {
+                        work.push((to, 0));
+                    } else if on_stack[to] {
+                        low[node] = low[node].min(index[to]);
+                    }
+                    continue;
+                }
+                if let Some(&(caller, _)) = work.last() {
+                    low[caller] = low[caller].min(low[node]);
+                }
+                if low[node] == index[node] {
+                    let mut component = vec![];
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(FunctionId(member));
+                        if member == node {
+                            break;
+                        }
+                    }
+                    component.sort();
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+    // The same graph in petgraph, node `i` weighted `FunctionId(i)`, for its algorithms, e.g.,
+    // `petgraph::algo::tarjan_scc(&graph.to_petgraph())` for mutually recursive groups
+    #[cfg(feature = "petgraph")]
+    pub fn to_petgraph(&self) -> petgraph::Graph<FunctionId, CallEdge> {
+        let mut graph = petgraph::Graph::with_capacity(self.functions.len(), self.edges.len());
+        for i in 0..self.functions.len() {
+            graph.add_node(FunctionId(i));
+        }
+        for edge in &self.edges {
+            let (from, to) = (petgraph::graph::NodeIndex::new(edge.from.0), petgraph::graph::NodeIndex::new(edge.to.0));
+            graph.add_edge(from, to, edge.call.clone());
+        }
+        graph
+    }
+}
+
+impl Project {
+    // The call graph of the whole project; see `Graph`. `call_graph` gives the part a root reaches.
+    pub fn graph(&self) -> Graph<'_> {
+        let functions: Vec<&Function> = self.functions.values().collect();
+        let ids: HashMap<&str, FunctionId> =
+            functions.iter().enumerate().map(|(i, func)| (func.qualified_name.as_str(), FunctionId(i))).collect();
+        let mut graph = Graph {
+            out_of: vec![vec![]; functions.len()],
+            into: vec![vec![]; functions.len()],
+            functions,
+            edges: vec![],
+            ids,
+        };
+        for call in self.call_edges() {
+            let (Some(from), Some(to)) = (graph.id(&call.caller), graph.id(&call.callee)) else {
+                continue;
+            };
+            graph.out_of[from.0].push(graph.edges.len());
+            graph.into[to.0].push(graph.edges.len());
+            graph.edges.push(GraphEdge { from, to, call });
+        }
+        graph
+    }
+
+    pub fn function(&self, name: &str) -> Result<&Function, MorphoError> {
+        function_by_name(self, name)
+    }
+
+    // The type `name` refers to: key in `types`, crate path, or simple name
+    pub fn type_def(&self, name: &str) -> Result<TypeDef<'_>, MorphoError> {
+        find_source_type(self, name)
+            .map(|(key, (file, item))| TypeDef { key, file, item })
+            .ok_or_else(|| MorphoError::not_found("type", name))
+    }
+
+    // The function `name` refers to, else the type, else the const, static or macro
+    pub fn source(&self, name: &str) -> Result<ItemRef<'_>, MorphoError> {
+        if let Some(func) = find_source_function(self, name)? {
+            return Ok(ItemRef::Function(func));
+        }
+        if let Some((key, (file, item))) = find_source_type(self, name) {
+            return Ok(ItemRef::Type(TypeDef { key, file, item }));
+        }
+        find_source_item(self, name)
+            .map(|(key, (file, item))| ItemRef::Item(TypeDef { key, file, item }))
+            .ok_or_else(|| source_not_found(name))
+    }
+
+    // The item's code as written, leading doc comments left out and its first line's indentation taken
+    // off every line; None when the load kept no text for its file
+    pub fn source_text(&self, item: ItemRef) -> Option<String> {
+        let bytes = match item {
+            ItemRef::Function(func) => Some(&func.byte_range),
+            ItemRef::Type(ty) => self.type_bytes.get(ty.key),
+            ItemRef::Item(ty) => self.item_bytes.get(ty.key),
+        }?;
+        let text = self.sources.get(item.file())?;
+        let code = text.get(bytes.clone())?.trim();
+        if code.is_empty() {
+            return None;
+        }
+        let start = bytes.end - text[bytes.clone()].trim_start().len();
+        let indent = &text[text[..start].rfind('\n').map_or(0, |i| i + 1)..start];
+        let indent = if indent.trim().is_empty() { indent } else { "" };
+        let lines: Vec<&str> = code
+            .lines()
+            .enumerate()
+            .map(|(i, line)| if i == 0 { line } else { line.strip_prefix(indent).unwrap_or(line) })
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    pub fn call_graph(&self, root: &str) -> Result<CallGraph<'_>, MorphoError> {
+        self.call_graph_from(&[root])
+    }
+
+    // One graph for several roots: everything any of them reaches, each function and type once
+    pub fn call_graph_from(&self, roots: &[impl AsRef<str>]) -> Result<CallGraph<'_>, MorphoError> {
+        self.call_graph_limited(roots, &TraceLimits::default())
+    }
+
+    // The call graph as far as each root's own project: calls into the other directories merged with it,
+    // e.g., the agent's dependency checkouts, are shown but their callees aren't followed
+    pub fn call_graph_stopping_at_deps(&self, roots: &[impl AsRef<str>]) -> Result<CallGraph<'_>, MorphoError> {
+        self.call_graph_limited(roots, &TraceLimits { stop_at_deps: true, ..Default::default() })
+    }
+
+    pub fn call_graph_limited(
+        &self,
+        roots: &[impl AsRef<str>],
+        limits: &TraceLimits,
+    ) -> Result<CallGraph<'_>, MorphoError> {
+        if roots.is_empty() {
+            return Err(MorphoError::Unsupported("A call graph needs at least one root function".to_string()));
+        }
+        let mut root_functions: Vec<&Function> = vec![];
+        let (mut visited, mut reachable_types, mut expanded) = (HashSet::new(), HashSet::new(), HashSet::new());
+        for root in roots {
+            // "#[instrument]" stands for every function carrying the attribute
+            let named = match root.as_ref().trim().strip_prefix("#[") {
+                Some(_) => {
+                    let functions = self.functions_with_attribute(root.as_ref(), VisibilityFilter::All);
+                    if functions.is_empty() {
+                        let name = root.as_ref().to_string();
+                        return Err(MorphoError::NotFound { kind: "function with attribute", name });
+                    }
+                    functions
+                }
+                None => vec![function_by_name(self, root.as_ref())?],
+            };
+            for root in named {
+                if root_functions.iter().any(|known| known.qualified_name == root.qualified_name) {
+                    continue;
+                }
+                let home = if limits.stop_at_deps { self.root_of(&root.qualified_name) } else { None };
+                let (scope, mut reached) = (limits.scope.as_deref(), HashSet::new());
+                _trace_calls(&root.qualified_name, self, home, scope, &mut reached, &mut reachable_types);
+                // Another root may follow a function this one stopped at
+                let followed = |name: &&String| home.is_none_or(|home| self.root_of(name) == Some(home));
+                expanded.extend(reached.iter().filter(followed).cloned());
+                visited.extend(reached);
+                root_functions.push(root);
+            }
+        }
+        let functions: Vec<&Function> =
+            self.functions.values().filter(|func| visited.contains(&func.qualified_name)).collect();
+        let types = self
+            .types
+            .iter()
+            .filter(|(key, _)| reachable_types.contains(*key))
+            .map(|(key, (file, item))| TypeDef { key, file, item })
+            .collect();
+        let boundary: HashSet<String> = visited.difference(&expanded).cloned().collect();
+        let followed = functions.iter().copied().filter(|func| !boundary.contains(&func.qualified_name));
+        // Calls out of scope stay unresolved
+        let mut edges = self.call_edges_from(followed);
+        edges.retain(|edge| visited.contains(&edge.callee));
+        Ok(CallGraph { roots: root_functions, functions, types, edges, boundary })
+    }
+
+    // Recursion: each group of mutually recursive functions and each function calling itself, ordered by
+    // their first function's name. Only resolved calls count, so recursion through trait objects or calls
+    // into other crates isn't found.
+    pub fn cycles(&self) -> Vec<Cycle<'_>> {
+        let graph = self.graph();
+        let mut cycles = vec![];
+        for component in graph.strongly_connected_components() {
+            let edges: Vec<CallEdge> = component
+                .iter()
+                .flat_map(|&id| graph.callees(id))
+                .filter(|edge| component.binary_search(&edge.to).is_ok())
+                .map(|edge| edge.call.clone())
+                .collect();
+            if edges.is_empty() {
+                continue;
+            }
+            let functions = component.iter().map(|&id| graph.function(id)).collect();
+            cycles.push((component[0], Cycle { functions, edges }));
+        }
+        cycles.sort_by_key(|(first, _)| *first);
+        cycles.into_iter().map(|(_, cycle)| cycle).collect()
+    }
+
+    // Functions carrying `attribute` and matching `visibility`, in name order, which is by file
+    pub fn functions_with_attribute(&self, attribute: &str, visibility: VisibilityFilter) -> Vec<&Function> {
+        self.functions
+            .values()
+            .filter(|func| func.has_attribute(attribute) && matches_visibility_filter(&func.vis, visibility))
+            .collect()
+    }
+
+    // The calls of the call graph from `root`, or every resolved call in the project
+    pub fn edge_list(&self, root: Option<&str>) -> Result<Vec<CallEdge>, MorphoError> {
+        match root {
+            Some(root) => self.call_graph(root).map(|graph| graph.edges),
+            None => Ok(self.call_edges()),
+        }
+    }
+
+    // Callers up to `depth` levels away (all of them when None)
+    pub fn callers_of(&self, target: &str, depth: Option<usize>) -> Result<Callers<'_>, MorphoError> {
+        let target = function_by_name(self, target)?;
+        let (included, edges) = self.caller_edges(&target.qualified_name, depth);
+        let functions = included.iter().map(|name| &self.functions[name]).collect();
+        Ok(Callers { target, functions, edges })
+    }
+
+    pub fn type_api(&self, name: &str) -> Result<TypeApi<'_>, MorphoError> {
+        let ty = self.type_def(name)?;
+        Ok(TypeApi { ty, impls: self.impl_blocks(ty.key) })
+    }
+
+    // Types are matched by name, so same-named types in different modules share references
+    pub fn type_usages(&self, name: &str) -> Result<TypeUsages<'_>, MorphoError> {
+        let ty = self.type_def(name)?;
+        let name = ty.key.rsplit("::").next().unwrap_or(ty.key);
+        let usages = self
+            .functions
+            .values()
+            .filter_map(|func| {
+                let mut refs: Vec<&TypeRef> = func.type_refs.iter().filter(|r| r.name == name).collect();
+                refs.sort_by_key(|r| (r.usage, r.line));
+                (!refs.is_empty()).then_some((func, refs))
+            })
+            .collect();
+        Ok(TypeUsages { ty, usages })
+    }
+
+    // Functions and types whose names match `query`, ignoring case: best score first, then shorter names,
+    // then name order, at most `limit` of them. Closure nodes are left out; they have no name of their own.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit<'_>>, MorphoError> {
+        let query = search_query(query)?.to_lowercase();
+        let functions = self
+            .functions
+            .values()
+            .filter(|func| !func.qualified_name.ends_with('}'))
+            .filter_map(|func| {
+                let path = display_name_with_type(&func.qualified_name);
+                let score = search_score(&query, &func.sig.ident.to_string(), &path)?;
+                Some((func.sig.ident.to_string(), path, SearchHit { score, item: ItemRef::Function(func) }))
+            });
+        let types = self.types.iter().filter_map(|(key, (file, item))| {
+            let name = key.rsplit("::").next().unwrap_or(key).to_string();
+            let score = search_score(&query, &name, key)?;
+            Some((name, key.clone(), SearchHit { score, item: ItemRef::Type(TypeDef { key, file, item }) }))
+        });
+        let items = self.items.iter().filter_map(|(key, (file, item))| {
+            let name = key.rsplit("::").next().unwrap_or(key).to_string();
+            let score = search_score(&query, &name, key)?;
+            Some((name, key.clone(), SearchHit { score, item: ItemRef::Item(TypeDef { key, file, item }) }))
+        });
+
+        let mut hits: Vec<(String, String, SearchHit)> = functions.chain(types).chain(items).collect();
+        hits.sort_by(|(a_name, a_path, a), (b_name, b_path, b)| {
+            b.score.cmp(&a.score).then(a_name.len().cmp(&b_name.len())).then(a_path.cmp(b_path))
+        });
+        Ok(hits.into_iter().take(limit).map(|(_, _, hit)| hit).collect())
+    }
+
+    // Up to `limit` items matching `visibility` (all of them when None), starting `offset` items in; with
+    // `hide_items`, functions and types only. Pages of one index never overlap or skip items, and a page
+    // of no items is refused, since the next one would start where it did.
+    pub fn list_page(
+        &self,
+        visibility: VisibilityFilter,
+        hide_items: bool,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<ListPage<'_>, MorphoError> {
+        if limit == Some(0) {
+            return Err(MorphoError::Unsupported("A listing page needs a limit of at least one item".to_string()));
+        }
+        let items = self.list_items(visibility, hide_items);
+        let total = items.len();
+        let items = items.into_iter().skip(offset).take(limit.unwrap_or(usize::MAX)).collect();
+        Ok(ListPage { items, offset, total })
+    }
+
+    pub(crate) fn list_items(&self, visibility: VisibilityFilter, hide_items: bool) -> Vec<ItemRef<'_>> {
+        let types = self
+            .types
+            .iter()
+            .filter(|(_, (_, item))| item_matches_visibility_filter(item, visibility))
+            .map(|(key, (file, item))| ItemRef::Type(TypeDef { key, file, item }));
+        let items = self
+            .items
+            .iter()
+            .filter(|_| !hide_items)
+            .filter(|(_, (_, item))| item_matches_visibility_filter(item, visibility))
+            .map(|(key, (file, item))| ItemRef::Item(TypeDef { key, file, item }));
+        let functions = self
+            .functions
+            .values()
+            .filter(|func| matches_visibility_filter(&func.vis, visibility))
+            .map(ItemRef::Function);
+        let mut listed: Vec<ItemRef> = types.chain(items).chain(functions).collect();
+        // Stable, so within a file each kind stays in key or name order
+        listed.sort_by_key(|item| {
+            let kind = match item {
+                ItemRef::Type(_) => 0,
+                ItemRef::Item(_) => 1,
+                ItemRef::Function(_) => 2,
+            };
+            (item.file(), kind)
+        });
+        listed
+    }
+
+    // Functions not reached by tracing calls from any of `roots` (see `OutputMode::Unreachable` for the
+    // default roots)
+    pub fn unreachable(&self, roots: &[String]) -> Result<Unreachable<'_>, MorphoError> {
+        let roots: Vec<String> = if roots.is_empty() {
+            self.functions
+                .values()
+                .filter(|func| {
+                    is_public(&func.vis)
+                        || func.impl_trait.is_some()
+                        || (func.sig.ident == "main" && func.self_type.is_none())
+                })
+                .map(|func| func.qualified_name.clone())
+                .collect()
+        } else {
+            roots
+                .iter()
+                .map(|root| function_by_name(self, root).map(|func| func.qualified_name.clone()))
+                .collect::<Result<_, _>>()?
+        };
+
+        let mut visited = HashSet::new();
+        let mut reachable_types = HashSet::new();
+        for root in &roots {
+            _trace_calls(root, self, None, None, &mut visited, &mut reachable_types);
+        }
+        let functions = self.functions.values().filter(|func| !visited.contains(&func.qualified_name)).collect();
+        Ok(Unreachable { roots, functions })
+    }
+
+    // Every impl of the trait named `name`, whether the trait is indexed or comes from another crate; impls
+    // are matched by the trait's last path segment, so same-named traits share implementors
+    pub fn implementors(&self, name: &str) -> Result<Implementors<'_>, MorphoError> {
+        let trait_def = match self.type_def(name) {
+            Ok(ty) if matches!(ty.item, Item::Trait(_)) => Some(ty),
+            Ok(_) => return Err(MorphoError::NotFound { kind: "trait", name: name.to_string() }),
+            Err(_) => None,
+        };
+        let trait_name = match trait_def {
+            Some(ty) => ty.key.rsplit("::").next().unwrap_or(ty.key),
+            None => name.rsplit("::").next().unwrap_or(name),
+        };
+        let Some(trait_impls) = self.trait_impls.get(trait_name) else {
+            return match trait_def {
+                Some(_) => Ok(Implementors { trait_name: trait_name.to_string(), trait_def, impls: vec![] }),
+                None => Err(MorphoError::NotFound { kind: "trait", name: name.to_string() }),
+            };
+        };
+
+        // The trait's own methods, keyed as its impls' methods are named; those with a body are defaults
+        let trait_methods: BTreeMap<String, &Function> = match trait_def {
+            Some(ty) => {
+                let prefix = format!("{}::{}::", ty.file, ty.key);
+                self.functions
+                    .range(prefix.clone()..)
+                    .take_while(|(key, _)| key.starts_with(&prefix))
+                    .filter(|(key, _)| !key[prefix.len()..].contains("::"))
+                    .map(|(_, func)| (func.sig.ident.to_string(), func))
+                    .collect()
+            }
+            None => BTreeMap::new(),
+        };
+
+        let mut impls: Vec<Implementor> = trait_impls
+            .iter()
+            .map(|imp| {
+                let segment = trait_impl_segment(&imp.self_ty, &imp.trait_ref);
+                let defined: Vec<&Function> = self
+                    .functions
+                    .values()
+                    .filter(|func| {
+                        func.impl_segment() == Some(segment.as_str())
+                            && func.module_path == imp.module_path
+                            && func.qualified_name.split_once("::").is_some_and(|(file, _)| file == imp.file_path)
+                    })
+                    .collect();
+                let default_of = |func: &Function| {
+                    trait_methods.get(&func.sig.ident.to_string()).is_some_and(|method| method.block.is_some())
+                };
+                let (overrides, provides) = defined.iter().partition(|func| default_of(func));
+                let inherits = trait_methods
+                    .values()
+                    .filter(|method| method.block.is_some())
+                    .filter(|method| !defined.iter().any(|func| func.sig.ident == method.sig.ident))
+                    .copied()
+                    .collect();
+                Implementor { imp, provides, overrides, inherits }
+            })
+            .collect();
+        impls.sort_by(|a, b| (&a.imp.file_path, a.imp.start_line).cmp(&(&b.imp.file_path, b.imp.start_line)));
+        Ok(Implementors { trait_name: trait_name.to_string(), trait_def, impls })
+    }
+
+    // Every chain of calls from `from` to `to` with the fewest calls, the first MAX_CALL_PATHS of them. Each
+    // pair of functions counts once, by its first call in source order. With `from` and `to` the same, the
+    // shortest recursion back into it.
+    pub fn paths_between(&self, from: &str, to: &str) -> Result<CallPaths<'_>, MorphoError> {
+        let from = function_by_name(self, from)?;
+        let to = function_by_name(self, to)?;
+        let graph = self.graph();
+        let callees = |caller: &str| -> Vec<&CallEdge> {
+            let calls = graph.id(caller).map(|id| graph.callees(id)).unwrap_or_default();
+            calls.into_iter().map(|edge| &edge.call).collect()
+        };
+
+        // Breadth first, keeping every call from the previous level into a function, so that walking the
+        // calls back from `to` yields exactly the shortest chains. `to` itself is never expanded.
+        let start = from.qualified_name.as_str();
+        let target = to.qualified_name.as_str();
+        let mut levels: HashMap<&str, usize> = HashMap::from([(start, 0)]);
+        let mut into: HashMap<&str, Vec<&CallEdge>> = HashMap::new();
+        let mut into_target: Vec<&CallEdge> = vec![];
+        let mut order = vec![start];
+        let mut queue = VecDeque::from([start]);
+        while let Some(caller) = queue.pop_front() {
+            let level = levels[caller] + 1;
+            if into_target.first().is_some_and(|first| levels[first.caller.as_str()] + 1 < level) {
+                break;
+            }
+            for edge in callees(caller) {
+                let callee = edge.callee.as_str();
+                if callee == target {
+                    into_target.push(edge);
+                    continue;
+                }
+                match levels.get(callee) {
+                    None => {
+                        levels.insert(callee, level);
+                        into.insert(callee, vec![edge]);
+                        order.push(callee);
+                        queue.push_back(callee);
+                    }
+                    Some(&seen) if seen == level => into.entry(callee).or_default().push(edge),
+                    Some(_) => {}
+                }
+            }
+        }
+
+        // Chains into each function, counted in breadth-first order so callers are counted first
+        let mut counts: HashMap<&str, usize> = HashMap::from([(start, 1)]);
+        for &name in &order[1..] {
+            let count = into[name].iter().fold(0usize, |sum, edge| sum.saturating_add(counts[edge.caller.as_str()]));
+            counts.insert(name, count);
+        }
+        let total = into_target.iter().fold(0usize, |sum, edge| sum.saturating_add(counts[edge.caller.as_str()]));
+
+        let mut paths = vec![];
+        let mut suffix = vec![];
+        for &edge in &into_target {
+            suffix.push(edge);
+            collect_paths(start, &into, &mut suffix, &mut paths);
+            suffix.pop();
+        }
+        Ok(CallPaths { from, to, paths, total })
+    }
+
+    // Every name a library crate exports: `pub` items whose modules are all `pub` from the crate root,
+    // and those a `pub use` in such a module re-exports (globs included), each under every path it has;
+    // `pub` methods of exported types and the methods of exported traits follow their type. Binary
+    // crates export nothing. Sorted by path.
+    pub fn public_api(&self) -> Vec<PublicItem<'_>> {
+        let module_is_public = |module: &str| {
+            let segments: Vec<&str> = module.split("::").filter(|s| !s.is_empty()).collect();
+            (1..=segments.len()).all(|depth| self.public_modules.contains_key(&segments[..depth].join("::")))
+        };
+        // Re-exports visible from outside: (public path, what it names), with globs as `module::*`
+        let reexports: Vec<(String, String)> = self
+            .imports
+            .iter()
+            .filter(|import| import.is_pub && self.is_library_file(&import.file_path) && module_is_public(&import.module))
+            .map(|import| {
+                let target = self.canonical_path(&self.absolute_path(&import.path, &import.module));
+                (join_path(&import.module, &import.name), target)
+            })
+            .collect();
+        // Paths of a `pub` definition at `defined_at`, from the crate root
+        let paths_of = |defined_at: &str| -> Vec<String> {
+            let module = defined_at.rsplit_once("::").map_or("", |(module, _)| module);
+            let mut paths = vec![];
+            if module_is_public(module) {
+                paths.push(defined_at.to_string());
+            }
+            for (public, target) in &reexports {
+                if target == defined_at && !public.ends_with('*') {
+                    paths.push(public.clone());
+                    continue;
+                }
+                let Some(rest) = defined_at.strip_prefix(&format!("{}::", target)) else { continue };
+                // Through a glob or a re-exported module, every module below it must be `pub` too
+                let below: Vec<&str> = rest.split("::").collect();
+                let hidden = (1..below.len())
+                    .any(|depth| !self.public_modules.contains_key(&join_path(target, &below[..depth].join("::"))));
+                if !hidden {
+                    let public = public.strip_suffix('*').map_or(public.clone(), |glob| glob.trim_end_matches("::").to_string());
+                    paths.push(join_path(&public, rest));
+                }
+            }
+            paths.sort();
+            paths.dedup();
+            paths
+        };
+        let root = |file: &str| {
+            crate::workspace::crate_target(&self.packages, file).map_or("crate".to_string(), |target| target.crate_name.clone())
+        };
+
+        let mut api: Vec<PublicItem> = vec![];
+        let mut type_paths: HashMap<String, Vec<String>> = HashMap::new();
+        for (key, (file, item)) in &self.types {
+            let public = crate::export::type_kind(item).is_some_and(|(_, vis)| is_public(vis));
+            let Some(defined_at) = self.type_crate_path(key).filter(|_| public && self.is_library_file(file)) else {
+                continue;
+            };
+            let paths = paths_of(&defined_at);
+            for path in &paths {
+                let item = ItemRef::Type(TypeDef { key, file, item });
+                api.push(PublicItem { path: join_path(&root(file), path), item, defined_at: defined_at.clone() });
+            }
+            type_paths.insert(defined_at, paths);
+        }
+        for (key, (file, item)) in &self.items {
+            let public = match item {
+                Item::Const(c) => is_public(&c.vis),
+                Item::Static(s) => is_public(&s.vis),
+                Item::Macro(m) => is_macro_export(m),
+                _ => false,
+            };
+            if !public || !self.is_library_file(file) {
+                continue;
+            }
+            let defined_at = join_path(&crate::file_module_path(file), key);
+            // `#[macro_export]` macros live at the crate root whatever module defines them
+            let paths = match item {
+                Item::Macro(_) => vec![key.rsplit("::").next().unwrap_or(key).to_string()],
+                _ => paths_of(&defined_at),
+            };
+            for path in paths {
+                let item = ItemRef::Item(TypeDef { key, file, item });
+                api.push(PublicItem { path: join_path(&root(file), &path), item, defined_at: defined_at.clone() });
+            }
+        }
+        for func in self.functions.values() {
+            let file = func.qualified_name.split_once("::").map_or("", |(file, _)| file);
+            let Some(defined_at) = self.function_crate_path(&func.qualified_name) else { continue };
+            if !is_public(&func.vis) || func.impl_trait.is_some() || !self.is_library_file(file) {
+                continue;
+            }
+            let (owner, name) = defined_at.rsplit_once("::").unwrap_or(("", &defined_at));
+            let paths = match type_paths.get(owner) {
+                Some(owner_paths) => owner_paths.iter().map(|path| join_path(path, name)).collect(),
+                None if func.self_type.is_some() => continue, // a method of a type that isn't exported
+                None => paths_of(&defined_at),
+            };
+            for path in paths {
+                let item = ItemRef::Function(func);
+                api.push(PublicItem { path: join_path(&root(file), &path), item, defined_at: defined_at.clone() });
+            }
+        }
+        api.sort_by(|a, b| a.path.cmp(&b.path));
+        api
+    }
+
+    // Whether `file` belongs to a library crate rather than a binary, test, example or build script: as
+    // `packages` says, or else by where it lies, `src/main.rs` and `src/bin` being binaries
+    pub fn is_library_file(&self, file: &str) -> bool {
+        if let Some(target) = crate::workspace::crate_target(&self.packages, file) {
+            return matches!(target.kind.as_str(), "lib" | "proc-macro");
+        }
+        let components: Vec<String> = std::path::Path::new(file)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        match components.iter().rposition(|c| c == "src") {
+            Some(src) => !matches!(components.get(src + 1).map(String::as_str), Some("bin" | "main.rs")),
+            None => !matches!(components.last().map(String::as_str), Some("main.rs" | "build.rs")),
+        }
+    }
+
+    // Likely places to start reading: each function classified by the first `EntryKind` it fits, and the
+    // bin targets of the loaded packages. Handlers are found by how routers are called, so a router
+    // built by a macro or another crate's helper hides them.
+    pub fn entry_points(&self) -> EntryPoints<'_> {
+        let mut registered: BTreeMap<String, String> = BTreeMap::new();
+        for edge in self.call_edges() {
+            if edge.context.as_deref() == Some(ROUTE_HANDLER_CONTEXT) {
+                let detail = format!("registered in {} (line {})", self.relative_path(&edge.caller), edge.line);
+                registered.entry(edge.callee).or_insert(detail);
+            }
+        }
+        // The first attribute whose path matches, `unsafe(..)` looked through, e.g., "unsafe(no_mangle)"
+        let attribute = |func: &Function, matches: &dyn Fn(&str) -> bool| {
+            func.attributes
+                .iter()
+                .find(|attribute| {
+                    let inner = attribute.strip_prefix("unsafe(").unwrap_or(attribute);
+                    matches(inner.split(['(', ')', ' ', '=']).next().unwrap_or(inner))
+                })
+                .map(|attribute| format!("#[{}]", attribute))
+        };
+
+        let mut functions: Vec<EntryPoint> = vec![];
+        for func in self.functions.values() {
+            let test = attribute(func, &|path| path == "test" || path.ends_with("::test"));
+            let runtime = attribute(func, &|path| path.ends_with("::main"));
+            let exported = attribute(func, &|path| matches!(path, "no_mangle" | "export_name"))
+                .or_else(|| {
+                    let abi = func.sig.abi.as_ref()?;
+                    let name = abi.name.as_ref().map_or("C".to_string(), |name| name.value());
+                    (is_public(&func.vis) && name != "Rust").then(|| format!("extern \"{}\"", name))
+                });
+            let routed = attribute(func, &|path| ROUTE_ATTRIBUTES.contains(&path));
+            let (kind, detail) = if func.sig.ident == "main" && func.self_type.is_none() && runtime.is_none() {
+                (EntryKind::Main, None)
+            } else if runtime.is_some() && test.is_none() {
+                (EntryKind::RuntimeMain, runtime)
+            } else if exported.is_some() {
+                (EntryKind::Exported, exported)
+            } else if let Some(detail) = routed.or_else(|| registered.get(&func.qualified_name).cloned()) {
+                (EntryKind::Handler, Some(detail))
+            } else if test.is_some() {
+                (EntryKind::Test, test.filter(|test| test != "#[test]"))
+            } else {
+                continue;
+            };
+            functions.push(EntryPoint { function: func, kind, detail });
+        }
+        functions.sort_by_key(|entry| entry.kind);
+
+        let binaries = self.packages.iter().flat_map(|package| &package.targets).filter(|target| target.kind == "bin");
+        EntryPoints { binaries: binaries.collect(), functions }
+    }
+
+    // Every `unsafe fn` and every function with an `unsafe {}` block; with a root, the shortest call path
+    // from it to each of them it reaches (just the root when it is unsafe itself)
+    pub fn unsafe_audit(&self, root: Option<&str>) -> Result<UnsafeAudit<'_>, MorphoError> {
+        let root = root.map(|root| function_by_name(self, root)).transpose()?;
+        let functions: Vec<&Function> = self
+            .functions
+            .values()
+            .filter(|func| func.sig.unsafety.is_some() || !func.unsafe_blocks.is_empty())
+            .collect();
+        let Some(root) = root else {
+            return Ok(UnsafeAudit { root, functions, paths: vec![] });
+        };
+
+        // Breadth first, so the first call found into a function is on a shortest path to it
+        let graph = self.graph();
+        let mut parents: BTreeMap<&str, Option<&str>> = BTreeMap::from([(root.qualified_name.as_str(), None)]);
+        let mut queue = std::collections::VecDeque::from([root.qualified_name.as_str()]);
+        while let Some(caller) = queue.pop_front() {
+            let calls = graph.id(caller).into_iter().flat_map(|id| graph.calls(id));
+            for callee in calls.map(|edge| edge.call.callee.as_str()) {
+                if !parents.contains_key(callee) {
+                    parents.insert(callee, Some(caller));
+                    queue.push_back(callee);
+                }
+            }
+        }
+
+        let mut paths = vec![];
+        for func in &functions {
+            if !parents.contains_key(func.qualified_name.as_str()) {
+                continue;
+            }
+            let mut path = vec![];
+            let mut next = Some(func.qualified_name.as_str());
+            while let Some(name) = next {
+                if let Some(func) = self.functions.get(name) {
+                    path.push(func);
+                }
+                next = parents[name];
+            }
+            path.reverse();
+            paths.push(path);
+        }
+        Ok(UnsafeAudit { root: Some(root), functions, paths })
+    }
+
+    // Every function matching `visibility` with its metrics, highest `sort` first, then by name. Calls and
+    // callers count resolved calls only, so calls into other crates are left out.
+    pub fn metrics(&self, sort: Metric, visibility: VisibilityFilter) -> Vec<FunctionMetrics<'_>> {
+        let graph = self.graph();
+        let mut metrics: Vec<FunctionMetrics> = graph
+            .functions
+            .iter()
+            .enumerate()
+            .filter(|(_, func)| matches_visibility_filter(&func.vis, visibility))
+            .map(|(i, func)| FunctionMetrics {
+                function: func,
+                lines: (func.end_line + 1).saturating_sub(func.start_line),
+                complexity: func.complexity(),
+                callees: graph.callees(FunctionId(i)).len(),
+                fan_in: graph.callers(FunctionId(i)).len(),
+            })
+            .collect();
+        // Stable, so ties stay in name order
+        metrics.sort_by_key(|row| std::cmp::Reverse(row.get(sort)));
+        metrics
+    }
+
+    // Inherent and trait impl blocks of the type keyed `key` in `types` (for a trait, the impls of it), the
+    // type's own file first. Inherent impls in one module are merged, since their methods share one key.
+    fn impl_blocks(&self, key: &str) -> Vec<ImplBlock<'_>> {
+        let name = key.rsplit("::").next().unwrap_or(key);
+        let (type_file, item) = match self.types.get(key) {
+            Some((file, item)) => (file.as_str(), item),
+            None => return vec![],
+        };
+        let of_trait = matches!(item, Item::Trait(_));
+        let block_key = |file: &str, module: &str, segment: &str| {
+            (file != type_file, file.to_string(), module.to_string(), segment.to_string())
+        };
+        let mut blocks = BTreeMap::new();
+
+        for func in self.functions.values() {
+            let Some(segment) = func.impl_segment() else { continue };
+            let implements = match split_trait_impl_segment(segment) {
+                Some((_, trait_ref)) if of_trait => trait_ref.split('<').next() == Some(name),
+                _ => !of_trait && func.self_type.as_deref() == Some(name),
+            };
+            if !implements {
+                continue;
+            }
+            let file = func.qualified_name.split_once("::").map_or("", |(file, _)| file);
+            let generics = func.impl_generics.as_ref().map(format_generics).unwrap_or_default();
+            blocks
+                .entry(block_key(file, &func.module_path, segment))
+                .or_insert_with(|| ImplBlock {
+                    file: file.to_string(),
+                    header: impl_header(&generics, segment),
+                    methods: vec![],
+                    consts: vec![],
+                    types: vec![],
+                })
+                .methods
+                .push(func);
+        }
+
+        // Trait impls without methods, e.g., `impl Eq for Config {}`
+        for (trait_name, impls) in &self.trait_impls {
+            for imp in impls {
+                let implements = if of_trait { trait_name == name } else { base_type_name(&imp.self_ty) == name };
+                if !implements {
+                    continue;
+                }
+                let segment = trait_impl_segment(&imp.self_ty, &imp.trait_ref);
+                blocks.entry(block_key(&imp.file_path, &imp.module_path, &segment)).or_insert_with(|| ImplBlock {
+                    file: imp.file_path.clone(),
+                    header: impl_header("", &segment),
+                    methods: vec![],
+                    consts: vec![],
+                    types: vec![],
+                });
+            }
+        }
+
+        // Associated consts and types, which blocks without methods are listed for too
+        let implements = |segment: &str| match split_trait_impl_segment(segment) {
+            Some((_, trait_ref)) if of_trait => trait_ref.split('<').next() == Some(name),
+            Some((self_ty, _)) => !of_trait && base_type_name(self_ty) == name,
+            None => !of_trait && base_type_name(segment) == name,
+        };
+        let empty_block = |file: &str, segment: &str, generics: &str| ImplBlock {
+            file: file.to_string(),
+            header: impl_header(generics, segment),
+            methods: vec![],
+            consts: vec![],
+            types: vec![],
+        };
+        for constant in self.impl_consts.iter().filter(|constant| implements(&constant.segment)) {
+            let (file, segment) = (&constant.file_path, &constant.segment);
+            blocks
+                .entry(block_key(file, &constant.module_path, segment))
+                .or_insert_with(|| empty_block(file, segment, &constant.generics))
+                .consts
+                .push(constant);
+        }
+        for ty in self.impl_types.iter().filter(|ty| implements(&ty.segment)) {
+            let (file, segment) = (&ty.file_path, &ty.segment);
+            blocks
+                .entry(block_key(file, &ty.module_path, segment))
+                .or_insert_with(|| empty_block(file, segment, &ty.generics))
+                .types
+                .push(ty);
+        }
+        blocks.into_values().collect()
+    }
+}
+
+// Walks the calls in `suffix` back to `start`, adding each complete chain until there are MAX_CALL_PATHS
+fn collect_paths<'e>(
+    start: &str,
+    into: &HashMap<&str, Vec<&'e CallEdge>>,
+    suffix: &mut Vec<&'e CallEdge>,
+    paths: &mut Vec<Vec<CallEdge>>,
+) {
+    if paths.len() >= MAX_CALL_PATHS {
+        return;
+    }
+    let caller = suffix.last().map_or(start, |edge| edge.caller.as_str());
+    if caller == start {
+        paths.push(suffix.iter().rev().map(|&edge| edge.clone()).collect());
+        return;
+    }
+    for &edge in &into[caller] {
+        suffix.push(edge);
+        collect_paths(start, into, suffix, paths);
+        suffix.pop();
+    }
+}
@@ -0,0 +1,188 @@
+// A self-contained HTML report of a whole project, for onboarding sessions without the HTTP agent: a
+// filterable list of functions by file, each function's signature, source, callers and calls (linked to
+// the callees), and call trees that expand on click. Styles, script and call data are inlined, so the
+// file opens offline and can be passed around as is.
+
+use crate::{display_name_with_type, format_function_source, format_type_item, CallEdge, Project};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+pub fn write_html_report(project: &Project, out: &mut impl Write) -> std::io::Result<()> {
+    // Sections are numbered in qualified-name order, as Mermaid nodes are
+    let ids: HashMap<&str, String> =
+        project.functions.keys().enumerate().map(|(i, name)| (name.as_str(), format!("f{}", i))).collect();
+    let edges = project.call_edges();
+    let mut calls: BTreeMap<&str, Vec<&CallEdge>> = BTreeMap::new();
+    let mut callers: BTreeMap<&str, Vec<&CallEdge>> = BTreeMap::new();
+    for edge in &edges {
+        calls.entry(edge.caller.as_str()).or_default().push(edge);
+        callers.entry(edge.callee.as_str()).or_default().push(edge);
+    }
+    let link = |name: &str| match ids.get(name) {
+        Some(id) => format!("<a href=\"#{}\">{}</a>", id, escape(&display_name_with_type(name))),
+        None => escape(&display_name_with_type(name)),
+    };
+
+    writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>morpho-rs report</title>\n<style>{}</style>\n</head>\n<body>", STYLE)?;
+
+    // Navigation: functions and types grouped by file
+    let mut by_file: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (name, id) in project.functions.keys().map(|name| (name, &ids[name.as_str()])) {
+        let file = name.split_once("::").map_or("", |(file, _)| file);
+        let label = escape(&display_name_with_type(name));
+        let entry = format!("<li><a href=\"#{}\">{}</a></li>", id, label);
+        by_file.entry(project.relative_path(file)).or_default().push(entry);
+    }
+    for (i, (key, (file, _))) in project.types.iter().enumerate() {
+        let link = format!("<li><a href=\"#t{}\">{}</a></li>", i, escape(key));
+        by_file.entry(project.relative_path(file)).or_default().push(link);
+    }
+    writeln!(out, "<nav>\n<input id=\"filter\" type=\"search\" placeholder=\"Filter by name\">")?;
+    for (file, entries) in &by_file {
+        writeln!(out, "<details open><summary>{}</summary><ul>{}</ul></details>", escape(file), entries.concat())?;
+    }
+    writeln!(out, "</nav>\n<main>")?;
+
+    writeln!(
+        out,
+        "<h1>morpho-rs report</h1>\n<p>{} functions, {} types, {} calls &middot; index <code>{}</code></p>",
+        project.functions.len(),
+        project.types.len(),
+        edges.len(),
+        escape(&project.stats.fingerprint)
+    )?;
+
+    for (name, func) in &project.functions {
+        let shown = project.relative_path(name);
+        let file = shown.split_once("::").map_or("", |(file, _)| file);
+        writeln!(out, "<section id=\"{}\" data-name=\"{}\">", ids[name.as_str()], escape(&shown))?;
+        writeln!(out, "<h2><code>{}</code></h2>", escape(&project.signature(func)))?;
+        writeln!(out, "<p class=\"where\">{} lines {}&ndash;{}</p>", escape(file), func.start_line, func.end_line)?;
+
+        if let Some(edges) = callers.get(name.as_str()) {
+            let items: Vec<String> = edges
+                .iter()
+                .map(|edge| format!("<li>{}{}</li>", link(&edge.caller), call_note(edge)))
+                .collect();
+            writeln!(out, "<h3>Called by</h3><ul>{}</ul>", items.concat())?;
+        }
+        if let Some(edges) = calls.get(name.as_str()) {
+            let items: Vec<String> = edges
+                .iter()
+                .map(|edge| format!("<li>{}{}</li>", link(&edge.callee), call_note(edge)))
+                .collect();
+            writeln!(out, "<h3>Calls</h3><ul>{}</ul>", items.concat())?;
+            let root = &ids[name.as_str()];
+            writeln!(out, "<details class=\"tree\" data-root=\"{}\"><summary>Call tree</summary></details>", root)?;
+        }
+        let source = escape(&format_function_source(func));
+        writeln!(out, "<details><summary>Source</summary><pre>{}</pre></details>", source)?;
+        writeln!(out, "</section>")?;
+    }
+
+    for (i, (key, (file, item))) in project.types.iter().enumerate() {
+        writeln!(out, "<section id=\"t{}\" data-name=\"{}\">", i, escape(key))?;
+        let file = project.relative_path(file);
+        writeln!(out, "<h2><code>{}</code></h2>\n<p class=\"where\">{}</p>", escape(key), escape(&file))?;
+        writeln!(out, "<pre>{}</pre>\n</section>", escape(&format_type_item(item)))?;
+    }
+
+    // Call trees are built in the browser from each function's distinct callees, so the report grows with
+    // the number of calls rather than the size of every expanded tree
+    let mut tree: BTreeMap<&str, Vec<serde_json::Value>> = BTreeMap::new();
+    for (caller, edges) in &calls {
+        let mut seen = vec![];
+        for edge in edges.iter().filter(|edge| ids.contains_key(edge.callee.as_str())) {
+            if !seen.contains(&edge.callee.as_str()) {
+                seen.push(edge.callee.as_str());
+                let callee = &ids[edge.callee.as_str()];
+                tree.entry(ids[caller].as_str()).or_default().push(json!([callee, edge.line, edge.context]));
+            }
+        }
+    }
+    let names: BTreeMap<&str, String> =
+        project.functions.keys().map(|name| (ids[name.as_str()].as_str(), display_name_with_type(name))).collect();
+    let data = json!({ "calls": tree, "names": names }).to_string().replace("</", "<\\/");
+    writeln!(out, "</main>\n<script>\nconst DATA = {};\n{}</script>\n</body>\n</html>", data, SCRIPT)
+}
+
+// " [context] (line 12)" after a call in the Calls and Called by lists
+fn call_note(edge: &CallEdge) -> String {
+    let context = edge.context.as_ref().map(|ctx| format!(" <span class=\"ctx\">[{}]</span>", escape(ctx)));
+    format!("{} <span class=\"line\">line {}</span>", context.unwrap_or_default(), edge.line)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const STYLE: &str = "
+body { margin: 0; display: flex; font: 14px/1.5 system-ui, sans-serif; color: #222; }
+nav { position: sticky; top: 0; height: 100vh; overflow: auto; width: 22rem; flex: none; padding: 1rem;
+      box-sizing: border-box; background: #f6f6f6; border-right: 1px solid #ddd; }
+nav input { width: 100%; margin-bottom: .5rem; }
+nav ul { margin: 0; padding-left: 1rem; list-style: none; }
+main { padding: 1rem 2rem; min-width: 0; }
+section { border-top: 1px solid #ddd; padding: .5rem 0; }
+section:target { background: #fffbe6; }
+h2 { font-size: 1rem; margin: .25rem 0; overflow-wrap: anywhere; }
+h3 { font-size: .9rem; margin: .5rem 0 0; }
+pre { white-space: pre-wrap; background: #f6f6f6; padding: .5rem; }
+.where, .line, .ctx { color: #777; }
+.tree ul { margin: 0; padding-left: 1.25rem; list-style: none; }
+";
+
+const SCRIPT: &str = r##"
+// Expands one level of a call tree; a function already on the path is shown but not expanded again
+function expand(parent, id, path) {
+  const list = document.createElement("ul");
+  for (const [callee, line, context] of DATA.calls[id] || []) {
+    const item = document.createElement("li");
+    const label = document.createElement("span");
+    const link = document.createElement("a");
+    link.href = "#" + callee;
+    link.textContent = DATA.names[callee];
+    label.append(link, (context ? " [" + context + "]" : "") + " (line " + line + ")");
+    if (path.includes(callee) || !DATA.calls[callee]) {
+      if (path.includes(callee)) label.append(" (already shown)");
+      item.append(label);
+    } else {
+      const node = document.createElement("details");
+      const summary = document.createElement("summary");
+      summary.append(label);
+      node.append(summary);
+      node.addEventListener("toggle", () => {
+        if (node.open && !node.dataset.expanded) {
+          node.dataset.expanded = "1";
+          expand(node, callee, path.concat(callee));
+        }
+      });
+      item.append(node);
+    }
+    list.append(item);
+  }
+  parent.append(list);
+}
+
+for (const tree of document.querySelectorAll("details.tree")) {
+  tree.addEventListener("toggle", () => {
+    if (tree.open && !tree.dataset.expanded) {
+      tree.dataset.expanded = "1";
+      expand(tree, tree.dataset.root, [tree.dataset.root]);
+    }
+  });
+}
+
+// Hides sections and list entries whose names don't contain the filter text
+document.getElementById("filter").addEventListener("input", (event) => {
+  const query = event.target.value.toLowerCase();
+  for (const section of document.querySelectorAll("section")) {
+    section.hidden = !section.dataset.name.toLowerCase().includes(query);
+  }
+  for (const entry of document.querySelectorAll("nav li")) {
+    entry.hidden = !entry.textContent.toLowerCase().includes(query);
+  }
+});
+"##;
@@ -0,0 +1,292 @@
+// The index in the shape of rustdoc's JSON output (`root`, `index`, `paths`), so doc tooling and API-diff
+// tools that read `cargo rustdoc -- --output-format json` can consume it without a nightly build. Items
+// follow rustdoc's layout: modules list their items, types their methods, and `paths` gives each item's
+// full path. Signatures are source text rather than rustdoc's structured types, and spans have lines but
+// no columns.
+
+use crate::query::{ItemRef, TypeDef};
+use crate::{
+    doc_comment, file_module_path, format_type_item, is_macro_export, join_path, workspace, Function, MorphoError,
+    OutputMode, Project, VisibilityFilter,
+};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use syn::{Item, Visibility};
+
+// Version of this schema, which is not rustdoc's own `format_version`
+const FORMAT_VERSION: u32 = 1;
+
+pub(crate) fn render(project: &Project, mode: OutputMode) -> Result<Value, MorphoError> {
+    let OutputMode::ListAll { visibility, hide_items, budget, page, .. } = mode else {
+        return Err(MorphoError::Unsupported("rustdoc JSON output is only available for listings".to_string()));
+    };
+    if budget.max_nodes.is_some() || budget.max_bytes.is_some() || page.offset > 0 || page.limit.is_some() {
+        return Err(MorphoError::Unsupported(
+            "rustdoc JSON output always covers the whole index, without budgets or pages".to_string(),
+        ));
+    }
+
+    let mut builder = Builder::default();
+    let listed = project.list_items(visibility, hide_items);
+    // Types first, so methods can be attached to them whichever file their impl is in
+    let mut type_ids: BTreeMap<Vec<String>, String> = BTreeMap::new();
+    for item in &listed {
+        if let ItemRef::Type(ty) = item {
+            let path = item_path(project, *ty);
+            let id = builder.add(&path[..path.len() - 1], &path, type_entry(project, *ty));
+            type_ids.insert(path, id);
+        }
+    }
+    for item in &listed {
+        match item {
+            ItemRef::Type(_) => {}
+            ItemRef::Item(it) => {
+                let mut path = item_path(project, *it);
+                // `#[macro_export]` puts a macro at the crate root, whatever module declares it
+                if matches!(it.item, Item::Macro(m) if is_macro_export(m)) {
+                    path.drain(1..path.len() - 1);
+                }
+                builder.add(&path[..path.len() - 1], &path, item_entry(project, *it));
+            }
+            ItemRef::Function(func) => {
+                let Some(path) = function_path(project, func) else { continue };
+                let entry = function_entry(project, func);
+                match method_owner(func, &path, &type_ids) {
+                    Some(owner) => builder.add_method(&path, entry, &owner),
+                    // A method of a type that isn't indexed is listed in the module declaring the impl
+                    None => {
+                        let module = &path[..path.len() - if func.self_type.is_some() { 2 } else { 1 }];
+                        builder.add(module, &path, entry);
+                    }
+                }
+            }
+        }
+    }
+
+    let (root, index, paths) = builder.finish();
+    Ok(json!({
+        "root": root,
+        "crate_version": null,
+        "includes_private": !matches!(visibility, VisibilityFilter::PublicOnly),
+        "index": index,
+        "paths": paths,
+        "external_crates": {},
+        "format_version": FORMAT_VERSION,
+    }))
+}
+
+#[derive(Default)]
+struct Builder {
+    index: Map<String, Value>,
+    paths: Map<String, Value>,
+    // Direct children of each module, by module path
+    modules: BTreeMap<Vec<String>, Vec<String>>,
+}
+
+impl Builder {
+    fn next_id(&self) -> String {
+        format!("0:{}", self.index.len())
+    }
+
+    // Adds an item to `module` and every module above it; returns its id
+    fn add(&mut self, module: &[String], path: &[String], entry: Entry) -> String {
+        let id = self.insert(path, entry);
+        for depth in 1..module.len() {
+            self.modules.entry(module[..depth].to_vec()).or_default();
+        }
+        self.modules.entry(module.to_vec()).or_default().push(id.clone());
+        id
+    }
+
+    // Adds a method to the type or trait with id `owner`
+    fn add_method(&mut self, path: &[String], entry: Entry, owner: &str) {
+        let id = self.insert(path, entry);
+        if let Some(inner) = self.index[owner]["inner"].as_object_mut().and_then(|inner| inner.values_mut().next()) {
+            inner["methods"].as_array_mut().expect("types always list methods").push(json!(id));
+        }
+    }
+
+    fn insert(&mut self, path: &[String], entry: Entry) -> String {
+        let id = self.next_id();
+        let kind = entry.kind;
+        self.index.insert(
+            id.clone(),
+            json!({
+                "id": id,
+                "crate_id": 0,
+                "name": path.last(),
+                "span": {
+                    "filename": entry.file,
+                    "begin": [entry.lines.0, 0],
+                    "end": [entry.lines.1, 0],
+                },
+                "visibility": entry.visibility,
+                "docs": entry.docs,
+                "links": {},
+                "attrs": [],
+                "deprecation": null,
+                "inner": { kind: entry.inner },
+            }),
+        );
+        self.paths.insert(id.clone(), json!({ "crate_id": 0, "path": path, "kind": kind }));
+        id
+    }
+
+    // Module items for every module path seen; the root is the first crate in name order
+    fn finish(mut self) -> (Value, Map<String, Value>, Map<String, Value>) {
+        let modules = std::mem::take(&mut self.modules);
+        let mut ids: BTreeMap<Vec<String>, String> = BTreeMap::new();
+        for path in modules.keys() {
+            ids.insert(path.clone(), format!("0:{}", self.index.len() + ids.len()));
+        }
+        let mut root = Value::Null;
+        for (path, mut items) in modules.clone() {
+            let id = &ids[&path];
+            let children = modules.keys().filter(|child| child.len() == path.len() + 1 && child.starts_with(&path));
+            items.extend(children.map(|child| ids[child].clone()));
+            if path.len() == 1 && root.is_null() {
+                root = json!(id);
+            }
+            self.index.insert(
+                id.clone(),
+                json!({
+                    "id": id,
+                    "crate_id": 0,
+                    "name": path.last(),
+                    "span": null,
+                    "visibility": "public",
+                    "docs": null,
+                    "links": {},
+                    "attrs": [],
+                    "deprecation": null,
+                    "inner": { "module": { "is_crate": path.len() == 1, "items": items, "is_stripped": false } },
+                }),
+            );
+            self.paths.insert(id.clone(), json!({ "crate_id": 0, "path": path, "kind": "module" }));
+        }
+        (root, self.index, self.paths)
+    }
+}
+
+struct Entry {
+    kind: &'static str, // rustdoc's item kind, e.g., "struct" or "constant"
+    inner: Value,
+    file: String,
+    lines: (usize, usize),
+    visibility: Value,
+    docs: Option<String>,
+}
+
+// Crate name of a file's crate when Cargo packages were read, else "crate"
+fn crate_name(project: &Project, file: &str) -> String {
+    workspace::crate_target(&project.packages, file).map_or("crate".to_string(), |target| target.crate_name.clone())
+}
+
+fn split_path(crate_name: String, path: &str) -> Vec<String> {
+    std::iter::once(crate_name).chain(path.split("::").filter(|s| !s.is_empty()).map(str::to_string)).collect()
+}
+
+// Full path of a type, const, static or macro, e.g., ["my_crate", "net", "Client"]
+fn item_path(project: &Project, ty: TypeDef) -> Vec<String> {
+    split_path(crate_name(project, ty.file), &join_path(&file_module_path(ty.file), ty.key))
+}
+
+// Full path of a free function or method; None for closures and functions nested in bodies, which have
+// no path of their own
+fn function_path(project: &Project, func: &Function) -> Option<Vec<String>> {
+    let (file, rest) = func.qualified_name.split_once("::")?;
+    let rest = rest.strip_prefix(&format!("{}::", func.module_path)).unwrap_or(rest);
+    let module = join_path(&file_module_path(file), &func.module_path);
+    let name = func.sig.ident.to_string();
+    let path = match (&func.self_type, rest.rsplit_once("::")) {
+        (None, None) if rest == name => name,
+        (Some(self_type), Some((_, last))) if last == name => join_path(self_type, &name),
+        _ => return None,
+    };
+    Some(split_path(crate_name(project, file), &join_path(&module, &path)))
+}
+
+// The indexed type a method belongs to: the one at the method's own module path, else the only type
+// with that name
+fn method_owner(func: &Function, path: &[String], types: &BTreeMap<Vec<String>, String>) -> Option<String> {
+    func.self_type.as_ref()?;
+    let owner = &path[..path.len() - 1];
+    if let Some(id) = types.get(owner) {
+        return Some(id.clone());
+    }
+    let name = owner.last()?;
+    let mut named = types.iter().filter(|(path, _)| path.last() == Some(name));
+    match (named.next(), named.next()) {
+        (Some((_, id)), None) => Some(id.clone()),
+        _ => None,
+    }
+}
+
+fn function_entry(project: &Project, func: &Function) -> Entry {
+    let file = func.qualified_name.split_once("::").map_or("", |(file, _)| file);
+    Entry {
+        kind: "function",
+        inner: json!({
+            "declaration": func.signature_named(&func.sig.ident.to_string()),
+            "is_method": func.self_type.is_some(),
+            "has_body": func.block.is_some(),
+        }),
+        file: project.relative_path(file),
+        lines: (func.start_line, func.end_line),
+        visibility: visibility(&func.vis),
+        docs: func.docs.clone(),
+    }
+}
+
+fn type_entry(project: &Project, ty: TypeDef) -> Entry {
+    let (kind, vis, attrs) = match ty.item {
+        Item::Struct(s) => ("struct", &s.vis, &s.attrs),
+        Item::Union(u) => ("union", &u.vis, &u.attrs),
+        Item::Enum(e) => ("enum", &e.vis, &e.attrs),
+        Item::Trait(t) => ("trait", &t.vis, &t.attrs),
+        Item::Type(t) => ("type_alias", &t.vis, &t.attrs),
+        _ => unreachable!("only structs, unions, enums, traits and type aliases are indexed as types"),
+    };
+    Entry {
+        kind,
+        inner: json!({ "declaration": format_type_item(ty.item), "methods": [] }),
+        file: project.relative_path(ty.file),
+        lines: project.type_lines.get(ty.key).copied().unwrap_or_default(),
+        visibility: visibility(vis),
+        docs: doc_comment(attrs),
+    }
+}
+
+fn item_entry(project: &Project, it: TypeDef) -> Entry {
+    let (kind, vis, attrs) = match it.item {
+        Item::Const(c) => ("constant", visibility(&c.vis), &c.attrs),
+        Item::Static(s) => ("static", visibility(&s.vis), &s.attrs),
+        Item::Macro(m) if is_macro_export(m) => ("macro", json!("public"), &m.attrs),
+        Item::Macro(m) => ("macro", json!("default"), &m.attrs),
+        _ => unreachable!("only consts, statics and macros are indexed as items"),
+    };
+    Entry {
+        kind,
+        inner: json!({ "declaration": format_type_item(it.item) }),
+        file: project.relative_path(it.file),
+        lines: project.item_lines.get(it.key).copied().unwrap_or_default(),
+        visibility: vis,
+        docs: doc_comment(attrs),
+    }
+}
+
+// As rustdoc writes it: "public", "crate", "default" for private items, or a restricted path
+fn visibility(vis: &Visibility) -> Value {
+    match vis {
+        Visibility::Public(_) => json!("public"),
+        Visibility::Inherited => json!("default"),
+        Visibility::Restricted(restricted) => {
+            let path = quote::ToTokens::to_token_stream(&restricted.path).to_string().replace(' ', "");
+            match path.as_str() {
+                "crate" => json!("crate"),
+                "self" => json!("default"),
+                _ => json!({ "restricted": { "path": path } }),
+            }
+        }
+    }
+}
@@ -0,0 +1,253 @@
+// How a library's public API changed between two versions, and the semver bump that calls for: items
+// compared by the paths downstream crates write (see `Project::public_api`), each change classified as
+// major (breaks some downstream code), minor (adds to the API) or patch (visible only in details, e.g.,
+// a private field added to a struct that can't be built with a literal anyway).
+
+use crate::query::{ItemRef, PublicItem};
+use crate::{format_type, Function, Project};
+use std::collections::BTreeMap;
+use syn::{Fields, Item};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Level {
+    #[default]
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Level {
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Patch => "patch",
+            Level::Minor => "minor",
+            Level::Major => "major",
+        }
+    }
+}
+
+// One difference at one exported path
+pub struct ApiChange {
+    pub path: String, // e.g., "crate::Config::load"
+    pub level: Level,
+    pub change: String, // what happened, e.g., "removed", "signature changed" or "field `name: String` added"
+    pub before: Option<String>, // the item as declared before and after, when it exists on that side
+    pub after: Option<String>,
+}
+
+pub struct SemverReport {
+    pub level: Level, // the bump the changes call for: the most severe one, patch when there are none
+    pub changes: Vec<ApiChange>, // most severe first, then by path
+}
+
+// Compares the public APIs of two loads of a library, e.g., two checkouts of it
+pub fn compare_apis(before: &Project, after: &Project) -> SemverReport {
+    let (old, new) = (by_path(before.public_api()), by_path(after.public_api()));
+
+    let mut changes = vec![];
+    for (path, item) in &old {
+        match new.get(path) {
+            Some(new_item) => compare_items(path, *item, *new_item, &mut changes),
+            None => changes.push(ApiChange {
+                path: path.clone(),
+                level: Level::Major,
+                change: "removed".to_string(),
+                before: Some(declaration(path, *item)),
+                after: None,
+            }),
+        }
+    }
+    for (path, item) in new.iter().filter(|(path, _)| !old.contains_key(*path)) {
+        // Every implementor has to add a trait method without a default
+        let required = matches!(item, ItemRef::Function(func) if is_required_trait_method(func));
+        changes.push(ApiChange {
+            path: path.clone(),
+            level: if required { Level::Major } else { Level::Minor },
+            change: if required { "required trait method added" } else { "added" }.to_string(),
+            before: None,
+            after: Some(declaration(path, *item)),
+        });
+    }
+
+    changes.sort_by(|a, b| b.level.cmp(&a.level).then_with(|| a.path.cmp(&b.path)));
+    let level = changes.iter().map(|change| change.level).max().unwrap_or_default();
+    SemverReport { level, changes }
+}
+
+fn by_path(api: Vec<PublicItem<'_>>) -> BTreeMap<String, ItemRef<'_>> {
+    api.into_iter().map(|public| (public.path, public.item)).collect()
+}
+
+// The differences between two items at the same path
+fn compare_items(path: &str, old: ItemRef, new: ItemRef, changes: &mut Vec<ApiChange>) {
+    let (old_declaration, new_declaration) = (declaration(path, old), declaration(path, new));
+    let mut change = |level: Level, change: String| {
+        changes.push(ApiChange {
+            path: path.to_string(),
+            level,
+            change,
+            before: Some(old_declaration.clone()),
+            after: Some(new_declaration.clone()),
+        })
+    };
+    match (old, new) {
+        (ItemRef::Function(old), ItemRef::Function(new)) => {
+            if old_declaration != new_declaration {
+                change(Level::Major, "signature changed".to_string());
+            } else if old.block.is_some() && is_required_trait_method(new) {
+                change(Level::Major, "default body removed from trait method".to_string());
+            }
+        }
+        (ItemRef::Type(old), ItemRef::Type(new)) => match (old.item, new.item) {
+            (Item::Struct(old), Item::Struct(new)) => compare_structs(old, new, &mut change),
+            (Item::Enum(old), Item::Enum(new)) => compare_enums(old, new, &mut change),
+            (Item::Trait(_), Item::Trait(_)) => {} // their methods are compared at their own paths
+            _ if old_declaration != new_declaration => change(Level::Major, "definition changed".to_string()),
+            _ => {}
+        },
+        (ItemRef::Item(old), ItemRef::Item(new)) => {
+            if old_declaration != new_declaration {
+                change(Level::Major, "type changed".to_string());
+            } else if !same_tokens(old.item, new.item) {
+                change(Level::Patch, "value changed".to_string());
+            }
+        }
+        _ => change(Level::Major, "kind changed".to_string()),
+    }
+}
+
+// Removed or retyped public fields break every use of them; added ones break struct literals, which only
+// a struct without private fields and without `#[non_exhaustive]` allows
+fn compare_structs(old: &syn::ItemStruct, new: &syn::ItemStruct, change: &mut impl FnMut(Level, String)) {
+    let (old_fields, new_fields) = (fields(&old.fields), fields(&new.fields));
+    let constructible = old_fields.iter().all(|(_, public, _)| *public) && !non_exhaustive(&old.attrs);
+    if !non_exhaustive(&old.attrs) && non_exhaustive(&new.attrs) {
+        change(Level::Major, "made #[non_exhaustive]".to_string());
+    }
+    for (name, public, ty) in &old_fields {
+        match new_fields.iter().find(|(new_name, _, _)| new_name == name) {
+            None if *public => change(Level::Major, format!("field `{}: {}` removed", name, ty)),
+            Some((_, new_public, new_ty)) if *public && (!new_public || new_ty != ty) => {
+                change(Level::Major, format!("field `{}: {}` changed", name, ty))
+            }
+            _ => {}
+        }
+    }
+    for (name, public, ty) in &new_fields {
+        if old_fields.iter().any(|(old_name, _, _)| old_name == name) {
+            continue;
+        }
+        let level = match (constructible, *public) {
+            (true, _) => Level::Major,
+            (false, true) => Level::Minor,
+            (false, false) => Level::Patch,
+        };
+        let field = if *public { "field" } else { "private field" };
+        change(level, format!("{} `{}: {}` added", field, name, ty));
+    }
+}
+
+// Every match on an exhaustive enum breaks when a variant is added, unless it is `#[non_exhaustive]`
+fn compare_enums(old: &syn::ItemEnum, new: &syn::ItemEnum, change: &mut impl FnMut(Level, String)) {
+    let variant = |v: &syn::Variant| {
+        let fields: Vec<String> = fields(&v.fields).into_iter().map(|(_, _, ty)| ty).collect();
+        match &v.fields {
+            Fields::Unit => v.ident.to_string(),
+            _ => format!("{}({})", v.ident, fields.join(", ")),
+        }
+    };
+    let old_variants: Vec<String> = old.variants.iter().map(variant).collect();
+    let new_variants: Vec<String> = new.variants.iter().map(variant).collect();
+    if !non_exhaustive(&old.attrs) && non_exhaustive(&new.attrs) {
+        change(Level::Major, "made #[non_exhaustive]".to_string());
+    }
+    for v in old_variants.iter().filter(|v| !new_variants.contains(v)) {
+        change(Level::Major, format!("variant `{}` removed or changed", v));
+    }
+    for v in new_variants.iter().filter(|v| !old_variants.contains(v)) {
+        let level = if non_exhaustive(&old.attrs) { Level::Minor } else { Level::Major };
+        change(level, format!("variant `{}` added", v));
+    }
+}
+
+// (name, is `pub`, type) of each field; tuple fields are named by position
+fn fields(fields: &Fields) -> Vec<(String, bool, String)> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let name = field.ident.as_ref().map_or(i.to_string(), |ident| ident.to_string());
+            (name, crate::is_public(&field.vis), format_type(&field.ty))
+        })
+        .collect()
+}
+
+// Trait methods carry their trait's generics and no `self` type; foreign functions have no body either
+fn is_required_trait_method(func: &Function) -> bool {
+    func.block.is_none() && func.self_type.is_none() && func.impl_generics.is_some()
+}
+
+fn non_exhaustive(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident("non_exhaustive"))
+}
+
+fn same_tokens(old: &Item, new: &Item) -> bool {
+    use quote::ToTokens;
+    old.to_token_stream().to_string() == new.to_token_stream().to_string()
+}
+
+// How the item is declared at `path`: a function's signature, a type's kind, or a const's or static's type
+pub fn declaration(path: &str, item: ItemRef) -> String {
+    match item {
+        ItemRef::Function(func) => func.signature_named(path),
+        ItemRef::Type(ty) => {
+            let kind = match ty.item {
+                Item::Struct(_) => "struct",
+                Item::Union(_) => "union",
+                Item::Enum(_) => "enum",
+                Item::Trait(_) => "trait",
+                Item::Type(alias) => return format!("pub type {} = {}", path, format_type(&alias.ty)),
+                _ => "type",
+            };
+            format!("pub {} {}", kind, path)
+        }
+        ItemRef::Item(it) => match it.item {
+            Item::Const(c) => format!("pub const {}: {}", path, format_type(&c.ty)),
+            Item::Static(s) => format!("pub static {}: {}", path, format_type(&s.ty)),
+            _ => format!("macro_rules! {}", path),
+        },
+    }
+}
+
+// "// semver: major (2 major, 1 minor, 0 patch changes)", then a section per level with each change and
+// the declarations before (-) and after (+)
+pub(crate) fn render_text(report: &SemverReport) -> String {
+    let count = |level: Level| report.changes.iter().filter(|change| change.level == level).count();
+    let mut output = format!(
+        "// semver: {} ({} major, {} minor, {} patch changes)\n",
+        report.level.name(),
+        count(Level::Major),
+        count(Level::Minor),
+        count(Level::Patch)
+    );
+    let mut current_level = None;
+    for change in &report.changes {
+        if current_level != Some(change.level) {
+            output.push_str(&format!("=== {} ===\n", change.level.name()));
+            current_level = Some(change.level);
+        }
+        output.push_str(&format!("{}  // {}\n", change.path, change.change));
+        match (&change.before, &change.after) {
+            (Some(before), Some(after)) if before == after => {}
+            (before, after) => {
+                if let Some(before) = before {
+                    output.push_str(&format!("    - {}\n", before));
+                }
+                if let Some(after) = after {
+                    output.push_str(&format!("    + {}\n", after));
+                }
+            }
+        }
+    }
+    output
+}
@@ -0,0 +1,131 @@
+// Helpers for regression tests against morpho's output format (enabled by the `testing` feature).
+//
+// A `Fixture` loads a project once and renders any mode; rendered paths are relative to the fixture
+// root, so snapshots don't depend on where the repository is checked out. `assert_snapshot` compares
+// against a file on disk; set MORPHO_UPDATE_SNAPSHOTS=1 to (re)write snapshot files instead.
+
+use crate::{
+    generate_output_for_project_with_format, load_project_with_options, LoadOptions, OutputFormat, OutputMode, Project,
+    VisibilityFilter,
+};
+use std::path::{Path, PathBuf};
+
+pub const UPDATE_SNAPSHOTS_ENV: &str = "MORPHO_UPDATE_SNAPSHOTS";
+
+pub struct Fixture {
+    pub root: String,
+    pub project: Project,
+}
+
+impl Fixture {
+    // Panics if the fixture can't be loaded, as tests should
+    pub fn load(root: impl AsRef<Path>) -> Fixture {
+        Fixture::load_with_options(root, &LoadOptions::default())
+    }
+
+    pub fn load_with_options(root: impl AsRef<Path>, options: &LoadOptions) -> Fixture {
+        let root = root.as_ref().to_string_lossy().trim_end_matches('/').to_string();
+        let project = load_project_with_options(&root, options)
+            .unwrap_or_else(|e| panic!("failed to load fixture {}: {}", root, e));
+        Fixture { root, project }
+    }
+
+    // Qualified name of an item given relative to the root, e.g., "src/lib.rs::run"
+    pub fn qualified(&self, relative: &str) -> String {
+        format!("{}/{}", self.root, relative)
+    }
+
+    // Rendered output, with every path relative to the fixture root
+    pub fn output(&self, mode: OutputMode) -> String {
+        self.output_with_format(mode, OutputFormat::Text)
+    }
+
+    pub fn output_with_format(&self, mode: OutputMode, format: OutputFormat) -> String {
+        generate_output_for_project_with_format(&self.project, mode, format)
+            .unwrap_or_else(|e| panic!("failed to render fixture {}: {}", self.root, e))
+            .content
+    }
+
+    pub fn list_all(&self, visibility: VisibilityFilter) -> String {
+        self.output(OutputMode::ListAll {
+            visibility,
+            hide_items: false,
+            include_docs: false,
+            budget: Default::default(),
+            page: Default::default(),
+        })
+    }
+
+    // `root` is relative to the fixture, e.g., "src/lib.rs::run"
+    pub fn call_graph(&self, root: &str, visibility: VisibilityFilter) -> String {
+        self.output(OutputMode::CallGraph {
+            roots: vec![self.qualified(root)],
+            visibility,
+            budget: Default::default(),
+            descend_into_deps: true,
+            show_external: false,
+            scope: None,
+        })
+    }
+
+    pub fn source(&self, function: &str) -> String {
+        self.output(OutputMode::Source {
+            function: function.to_string(),
+            include_docs: false,
+        })
+    }
+}
+
+// Compares `actual` with the snapshot file, panicking with the first differing line on mismatch.
+// A missing snapshot is written and reported as a failure so it gets reviewed before it's trusted.
+pub fn assert_snapshot(path: impl AsRef<Path>, actual: &str) {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+        write_snapshot(path, actual);
+        return;
+    }
+
+    let expected = match std::fs::read_to_string(path) {
+        Ok(expected) => expected,
+        Err(_) => {
+            write_snapshot(path, actual);
+            panic!("new snapshot written to {}; review it and rerun", path.display());
+        }
+    };
+
+    if expected != actual {
+        let lines = expected.lines().count().max(actual.lines().count());
+        let (line, want, got) = expected
+            .lines()
+            .chain(std::iter::repeat("<end of snapshot>"))
+            .zip(actual.lines().chain(std::iter::repeat("<end of output>")))
+            .take(lines)
+            .enumerate()
+            .find(|(_, (want, got))| want != got)
+            .map(|(i, (want, got))| (i + 1, want, got))
+            // Every line matches, so only line endings differ
+            .unwrap_or((lines, "<line ending differs>", "<line ending differs>"));
+        panic!(
+            "output differs from snapshot {} at line {}\n  expected: {}\n  actual:   {}\n\
+             rerun with {}=1 to accept the new output\n\n{}",
+            path.display(),
+            line,
+            want,
+            got,
+            UPDATE_SNAPSHOTS_ENV,
+            actual
+        );
+    }
+}
+
+fn write_snapshot(path: &Path, content: &str) {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).unwrap_or_else(|e| panic!("failed to create {}: {}", parent.display(), e));
+    }
+    std::fs::write(path, content).unwrap_or_else(|e| panic!("failed to write {}: {}", path.display(), e));
+}
+
+// Snapshot path under the crate's tests/snapshots directory
+pub fn snapshot_path(manifest_dir: &str, name: &str) -> PathBuf {
+    Path::new(manifest_dir).join("tests").join("snapshots").join(format!("{}.snap", name))
+}
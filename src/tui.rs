@@ -0,0 +1,329 @@
+// Terminal browser over a loaded project (enabled by the `tui` feature): the file and module tree on the
+// left, the selected function's call tree (or callers tree) in the middle and its source on the right.
+//
+//     ↑/↓, j/k     move in the focused pane           Tab        switch between the tree and the call pane
+//     Enter        open the function under the cursor Backspace  back to the previous function
+//     c            toggle calls / callers             PgUp/PgDn  scroll the source
+//     q, Esc       quit
+//
+// `Browser` holds the state and takes `Action`s, so it can be driven without a terminal; `run` wires it
+// to one.
+
+use crate::query::FunctionId;
+use crate::{generate_output_for_project, OutputMode, Project};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::widgets::{Block, List, ListState, Paragraph};
+use ratatui::Frame;
+use std::collections::{BTreeMap, HashSet};
+
+// Levels of calls (or callers) shown below the open function
+const CALL_TREE_DEPTH: usize = 3;
+
+// Lines the source pane scrolls per page
+const SOURCE_PAGE: u16 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    Open,
+    Back,
+    SwitchPane,
+    ToggleDirection,
+    PageUp,
+    PageDown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Tree,
+    Calls,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Calls,
+    Callers,
+}
+
+// A file, inline module or function in the left pane; `function` is the qualified name for functions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeRow {
+    pub depth: usize,
+    pub label: String,
+    pub function: Option<String>,
+}
+
+// A function in the middle pane, `depth` levels below the open one; `line` is where the call is made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallRow {
+    pub depth: usize,
+    pub function: String,
+    pub line: usize,
+    pub recursive: bool, // already on the path from the open function, so not expanded again
+}
+
+pub struct Browser<'a> {
+    project: &'a Project,
+    callees: BTreeMap<String, Vec<(String, usize)>>, // caller -> (callee, line), in source order
+    callers: BTreeMap<String, Vec<(String, usize)>>, // callee -> (caller, line)
+    pub tree: Vec<TreeRow>,
+    pub tree_cursor: usize,
+    pub open: Option<String>,
+    pub history: Vec<String>,
+    pub direction: Direction,
+    pub calls: Vec<CallRow>,
+    pub calls_cursor: usize,
+    pub source: String,
+    pub source_scroll: u16,
+    pub focus: Pane,
+}
+
+impl<'a> Browser<'a> {
+    pub fn new(project: &'a Project) -> Browser<'a> {
+        let graph = project.graph();
+        let mut callees: BTreeMap<String, Vec<(String, usize)>> = BTreeMap::new();
+        let mut callers: BTreeMap<String, Vec<(String, usize)>> = BTreeMap::new();
+        for (i, func) in graph.functions.iter().enumerate() {
+            let calls = graph.callees(FunctionId(i));
+            if !calls.is_empty() {
+                let calls = calls.iter().map(|edge| (edge.call.callee.clone(), edge.call.line)).collect();
+                callees.insert(func.qualified_name.clone(), calls);
+            }
+            let called_by = graph.callers(FunctionId(i));
+            if !called_by.is_empty() {
+                let called_by = called_by.iter().map(|edge| (edge.call.caller.clone(), edge.call.line)).collect();
+                callers.insert(func.qualified_name.clone(), called_by);
+            }
+        }
+
+        let mut browser = Browser {
+            project,
+            callees,
+            callers,
+            tree: file_tree(project),
+            tree_cursor: 0,
+            open: None,
+            history: vec![],
+            direction: Direction::Calls,
+            calls: vec![],
+            calls_cursor: 0,
+            source: String::new(),
+            source_scroll: 0,
+            focus: Pane::Tree,
+        };
+        if let Some(first) = browser.tree.iter().position(|row| row.function.is_some()) {
+            browser.tree_cursor = first;
+            browser.apply(Action::Open);
+        }
+        browser
+    }
+
+    pub fn apply(&mut self, action: Action) {
+        match (action, self.focus) {
+            (Action::Up, Pane::Tree) => self.tree_cursor = self.tree_cursor.saturating_sub(1),
+            (Action::Down, Pane::Tree) => {
+                self.tree_cursor = (self.tree_cursor + 1).min(self.tree.len().saturating_sub(1))
+            }
+            (Action::Up, Pane::Calls) => self.calls_cursor = self.calls_cursor.saturating_sub(1),
+            (Action::Down, Pane::Calls) => {
+                self.calls_cursor = (self.calls_cursor + 1).min(self.calls.len().saturating_sub(1))
+            }
+            (Action::Open, Pane::Tree) => {
+                if let Some(function) = self.tree.get(self.tree_cursor).and_then(|row| row.function.clone()) {
+                    self.open_function(function);
+                }
+            }
+            (Action::Open, Pane::Calls) => {
+                if let Some(row) = self.calls.get(self.calls_cursor) {
+                    self.open_function(row.function.clone());
+                }
+            }
+            (Action::Back, _) => {
+                if let Some(previous) = self.history.pop() {
+                    self.show(previous);
+                }
+            }
+            (Action::SwitchPane, Pane::Tree) => self.focus = Pane::Calls,
+            (Action::SwitchPane, Pane::Calls) => self.focus = Pane::Tree,
+            (Action::ToggleDirection, _) => {
+                self.direction = match self.direction {
+                    Direction::Calls => Direction::Callers,
+                    Direction::Callers => Direction::Calls,
+                };
+                self.calls = self.open.as_deref().map(|open| self.call_tree(open)).unwrap_or_default();
+                self.calls_cursor = 0;
+            }
+            (Action::PageUp, _) => self.source_scroll = self.source_scroll.saturating_sub(SOURCE_PAGE),
+            (Action::PageDown, _) => self.source_scroll = self.source_scroll.saturating_add(SOURCE_PAGE),
+        }
+    }
+
+    // Opens `function`, remembering the one it replaces for Back
+    fn open_function(&mut self, function: String) {
+        if self.open.as_ref() == Some(&function) {
+            return;
+        }
+        if let Some(previous) = self.open.take() {
+            self.history.push(previous);
+        }
+        self.show(function);
+    }
+
+    // Points every pane at `function`: the tree cursor at its row, its call tree and its source
+    fn show(&mut self, function: String) {
+        if let Some(row) = self.tree.iter().position(|row| row.function.as_ref() == Some(&function)) {
+            self.tree_cursor = row;
+        }
+        self.calls = self.call_tree(&function);
+        self.calls_cursor = 0;
+        self.source = generate_output_for_project(
+            self.project,
+            OutputMode::Source {
+                function: function.clone(),
+                include_docs: true,
+            },
+        )
+        .map_or_else(|e| e.to_string(), |output| output.content);
+        self.source_scroll = 0;
+        self.open = Some(function);
+    }
+
+    // The open function's calls (or callers), depth first to CALL_TREE_DEPTH levels
+    fn call_tree(&self, root: &str) -> Vec<CallRow> {
+        let edges = match self.direction {
+            Direction::Calls => &self.callees,
+            Direction::Callers => &self.callers,
+        };
+        let mut rows = vec![];
+        let mut path = HashSet::from([root.to_string()]);
+        add_call_rows(edges, root, 1, &mut path, &mut rows);
+        rows
+    }
+
+    fn render(&self, frame: &mut Frame) {
+        let [tree_area, calls_area, source_area] = Layout::horizontal([
+            Constraint::Percentage(25),
+            Constraint::Percentage(35),
+            Constraint::Percentage(40),
+        ])
+        .areas(frame.area());
+        let border = |pane: Pane| if self.focus == pane { Style::new().yellow() } else { Style::new() };
+
+        let tree = List::new(self.tree.iter().map(|row| format!("{}{}", "  ".repeat(row.depth), row.label)))
+            .block(Block::bordered().title(" Files ").border_style(border(Pane::Tree)))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(tree, tree_area, &mut ListState::default().with_selected(Some(self.tree_cursor)));
+
+        let title = match (self.direction, &self.open) {
+            (Direction::Calls, Some(open)) => format!(" Calls from {} ", short_name(open)),
+            (Direction::Callers, Some(open)) => format!(" Callers of {} ", short_name(open)),
+            (_, None) => " Calls ".to_string(),
+        };
+        let calls = List::new(self.calls.iter().map(|row| {
+            let recursive = if row.recursive { " (recursive)" } else { "" };
+            format!("{}{} (line {}){}", "  ".repeat(row.depth - 1), short_name(&row.function), row.line, recursive)
+        }))
+        .block(Block::bordered().title(title).border_style(border(Pane::Calls)))
+        .highlight_style(Style::new().reversed());
+        let selected = (!self.calls.is_empty()).then_some(self.calls_cursor);
+        frame.render_stateful_widget(calls, calls_area, &mut ListState::default().with_selected(selected));
+
+        let source = Paragraph::new(self.source.as_str())
+            .block(Block::bordered().title(" Source "))
+            .scroll((self.source_scroll, 0));
+        frame.render_widget(source, source_area);
+    }
+}
+
+// Browses `project` in the terminal until the user quits; the terminal is restored either way
+pub fn run(project: &Project) -> std::io::Result<()> {
+    let mut browser = Browser::new(project);
+    let mut terminal = ratatui::init();
+    let result = loop {
+        if let Err(e) = terminal.draw(|frame| browser.render(frame)) {
+            break Err(e);
+        }
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e),
+        };
+        let action = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => Action::Up,
+            KeyCode::Down | KeyCode::Char('j') => Action::Down,
+            KeyCode::Enter => Action::Open,
+            KeyCode::Backspace => Action::Back,
+            KeyCode::Tab => Action::SwitchPane,
+            KeyCode::Char('c') => Action::ToggleDirection,
+            KeyCode::PageUp => Action::PageUp,
+            KeyCode::PageDown => Action::PageDown,
+            _ => continue,
+        };
+        browser.apply(action);
+    };
+    ratatui::restore();
+    result
+}
+
+// One row per file, then its inline modules and functions, each module's functions under it
+fn file_tree(project: &Project) -> Vec<TreeRow> {
+    let mut by_file: BTreeMap<String, Vec<&crate::Function>> = BTreeMap::new();
+    for (name, func) in &project.functions {
+        let file = name.split_once("::").map_or("", |(file, _)| file);
+        by_file.entry(project.relative_path(file)).or_default().push(func);
+    }
+
+    let mut rows = vec![];
+    for (file, mut functions) in by_file {
+        functions.sort_by(|a, b| (&a.module_path, &a.qualified_name).cmp(&(&b.module_path, &b.qualified_name)));
+        rows.push(TreeRow { depth: 0, label: file, function: None });
+        let mut open_modules: Vec<&str> = vec![];
+        for func in functions {
+            let modules: Vec<&str> = func.module_path.split("::").filter(|m| !m.is_empty()).collect();
+            let common = open_modules.iter().zip(&modules).take_while(|(a, b)| a == b).count();
+            for (depth, module) in modules.iter().enumerate().skip(common) {
+                rows.push(TreeRow { depth: depth + 1, label: format!("mod {}", module), function: None });
+            }
+            open_modules = modules;
+
+            let name = short_name(&func.qualified_name);
+            let label = name.strip_prefix(&format!("{}::", func.module_path)).unwrap_or(name);
+            rows.push(TreeRow {
+                depth: open_modules.len() + 1,
+                label: label.to_string(),
+                function: Some(func.qualified_name.clone()),
+            });
+        }
+    }
+    rows
+}
+
+fn add_call_rows(
+    edges: &BTreeMap<String, Vec<(String, usize)>>,
+    function: &str,
+    depth: usize,
+    path: &mut HashSet<String>,
+    rows: &mut Vec<CallRow>,
+) {
+    if depth > CALL_TREE_DEPTH {
+        return;
+    }
+    for (next, line) in edges.get(function).into_iter().flatten() {
+        let recursive = path.contains(next);
+        rows.push(CallRow { depth, function: next.clone(), line: *line, recursive });
+        if !recursive {
+            path.insert(next.clone());
+            add_call_rows(edges, next, depth + 1, path, rows);
+            path.remove(next);
+        }
+    }
+}
+
+// "net::connect" for "src/lib.rs::net::connect"
+fn short_name(qualified_name: &str) -> &str {
+    qualified_name.split_once("::").map_or(qualified_name, |(_, rest)| rest)
+}
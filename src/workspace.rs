@@ -0,0 +1,87 @@
+// Cargo packages and their crate targets, read from `cargo metadata`, so indexed files can be placed in
+// the crate that compiles them and named by real crate paths (`my_crate::net::connect`).
+
+use crate::MorphoError;
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+
+// A workspace member with the crates it builds; paths are in the same form as indexed file paths, e.g.,
+// "./src/lib.rs" for the directory ".", when they lie inside the loaded directory
+#[derive(Debug, Clone)]
+pub struct Package {
+    pub name: String, // as written in Cargo.toml, e.g., "my-crate"
+    pub root: String, // directory holding the package's Cargo.toml
+    pub targets: Vec<CrateTarget>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CrateTarget {
+    pub crate_name: String, // as code names it, e.g., "my_crate"
+    pub kind: String, // "lib", "bin", "proc-macro", "test", "example", "bench" or "custom-build"
+    pub src_path: String, // the crate root file, e.g., "./src/lib.rs"
+}
+
+impl CrateTarget {
+    // Directory whose files belong to this crate unless a more specific target claims them
+    fn root_dir(&self) -> &Path {
+        Path::new(&self.src_path).parent().unwrap_or(Path::new(""))
+    }
+}
+
+// Workspace members around `dir`, without their dependencies. Runs offline, so it never touches the
+// network; fails when `dir` isn't inside a Cargo package or `cargo` isn't installed.
+pub fn read_packages(dir: &str) -> Result<Vec<Package>, MorphoError> {
+    let io_error = |message: String| MorphoError::Io { path: dir.to_string(), message };
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--no-deps", "--offline"])
+        .current_dir(dir)
+        .output()
+        .map_err(|e| io_error(format!("cannot run cargo metadata: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.lines().find(|line| !line.trim().is_empty()).unwrap_or("cargo metadata failed");
+        return Err(io_error(message.trim().to_string()));
+    }
+    let metadata: Value =
+        serde_json::from_slice(&output.stdout).map_err(|e| io_error(format!("unreadable cargo metadata: {}", e)))?;
+
+    // Cargo reports absolute paths; rewrite those under `dir` the way the walk names files
+    let base = Path::new(dir).canonicalize().map_err(|e| io_error(e.to_string()))?;
+    let local = |path: &str| match Path::new(path).strip_prefix(&base) {
+        Ok(relative) => Path::new(dir).join(relative).to_string_lossy().into_owned(),
+        Err(_) => path.to_string(),
+    };
+
+    let packages = metadata["packages"].as_array().into_iter().flatten();
+    Ok(packages
+        .map(|package| {
+            let manifest = package["manifest_path"].as_str().unwrap_or_default();
+            let root = Path::new(manifest).parent().map_or(String::new(), |root| local(&root.to_string_lossy()));
+            let targets = package["targets"].as_array().into_iter().flatten();
+            Package {
+                name: package["name"].as_str().unwrap_or_default().to_string(),
+                root,
+                targets: targets
+                    .map(|target| CrateTarget {
+                        crate_name: target["name"].as_str().unwrap_or_default().replace('-', "_"),
+                        kind: target["kind"][0].as_str().unwrap_or_default().to_string(),
+                        src_path: local(target["src_path"].as_str().unwrap_or_default()),
+                    })
+                    .collect(),
+            }
+        })
+        .collect())
+}
+
+// The crate compiling `file`: the target whose root file it is, else the target with the deepest root
+// directory above it, libraries winning ties (a package's `src/main.rs` sits beside its `src/lib.rs`)
+pub(crate) fn crate_target<'a>(packages: &'a [Package], file: &str) -> Option<&'a CrateTarget> {
+    let targets = packages.iter().flat_map(|package| &package.targets);
+    if let Some(target) = targets.clone().find(|target| target.src_path == file) {
+        return Some(target);
+    }
+    targets
+        .filter(|target| Path::new(file).starts_with(target.root_dir()))
+        .max_by_key(|target| (target.root_dir().components().count(), matches!(target.kind.as_str(), "lib" | "proc-macro")))
+}
@@ -0,0 +1,20 @@
+pub struct Config {
+    pub name: String,
+    pub verbose: bool,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Config {
+        Config::parse(path).trimmed()
+    }
+
+    pub fn parse(text: &str) -> Config {
+        Config { name: text.to_string(), verbose: false }
+    }
+
+    fn trimmed(mut self) -> Config {
+        // names are compared after trimming, so store them that way
+        self.name = self.name.trim().to_string();
+        self
+    }
+}
@@ -0,0 +1,18 @@
+pub mod circle;
+pub mod config;
+pub mod render;
+pub mod shape;
+pub mod square;
+pub mod units;
+
+pub use circle::Circle;
+
+pub fn run(path: &str) {
+    let config = config::Config::load(path);
+    render::report(&Circle { radius: 1.0 });
+    let square = square::Square { side: 2.0 };
+    square.area();
+    log_start(&config.name);
+}
+
+fn log_start(_name: &str) {}
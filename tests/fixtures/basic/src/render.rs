@@ -0,0 +1,9 @@
+use crate::shape::Shape;
+
+pub fn report<S: Shape>(shape: &S) {
+    shape.describe();
+}
+
+pub fn paint(shape: &dyn Shape) -> f64 {
+    shape.area()
+}
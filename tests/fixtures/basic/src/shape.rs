@@ -0,0 +1,8 @@
+pub trait Shape {
+    fn area(&self) -> f64;
+
+    fn describe(&self) -> String {
+        let area = self.area();
+        format!("area {}", area)
+    }
+}
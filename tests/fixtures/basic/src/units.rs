@@ -0,0 +1,27 @@
+pub type Meters = f64;
+
+pub enum Unit {
+    Metric,
+    Imperial,
+}
+
+pub struct Length {
+    pub value: Meters,
+    pub unit: Unit,
+}
+
+pub fn to_meters(length: &Length) -> Meters {
+    length.value
+}
+
+impl From<f64> for Length {
+    fn from(value: f64) -> Length {
+        Length { value, unit: Unit::Metric }
+    }
+}
+
+impl From<Unit> for Length {
+    fn from(unit: Unit) -> Length {
+        Length { value: 0.0, unit }
+    }
+}
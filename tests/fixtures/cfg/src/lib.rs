@@ -0,0 +1,30 @@
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+mod wasm;
+
+pub fn open() -> Socket {
+    Socket
+}
+
+#[cfg(feature = "tls")]
+pub fn connect_tls() {}
+
+#[cfg(not(feature = "tls"))]
+pub fn connect_plain() {}
+
+#[cfg(all(unix, feature = "tls"))]
+pub mod secure {
+    pub fn handshake() {}
+}
+
+pub struct Socket;
+
+impl Socket {
+    #[cfg(target_arch = "x86_64")]
+    pub fn fast_path(&self) {}
+
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    pub fn set_nosigpipe(&self) {}
+}
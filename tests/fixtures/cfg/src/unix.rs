@@ -0,0 +1,5 @@
+mod poll;
+
+pub fn open_fd() -> i32 {
+    poll::register()
+}
@@ -0,0 +1,21 @@
+pub fn run() {
+    let config = load();
+    validate(&config);
+    report(config);
+}
+
+fn load() -> u32 {
+    read_env().unwrap_or(1)
+}
+
+fn read_env() -> Option<u32> {
+    None
+}
+
+fn validate(_config: &u32) {}
+
+fn report(_config: u32) {}
+
+pub fn unchanged() {
+    validate(&0);
+}
@@ -0,0 +1,17 @@
+pub fn run() {
+    let config = load();
+    validate(&config);
+    legacy_log();
+}
+
+fn load() -> u32 {
+    1
+}
+
+fn validate(_config: &u32) {}
+
+fn legacy_log() {}
+
+pub fn unchanged() {
+    validate(&0);
+}
@@ -0,0 +1,237 @@
+pub struct Reading {
+    pub value: u64,
+}
+
+pub async fn run() -> Result<u64, ()> {
+    spawn(|| tick());
+    record(parse(load()?));
+    let value = measure().value;
+    fetch().await;
+    if let Some(limit) = lookup() {
+        record(limit);
+    }
+    let readings = [measure(), Reading { value: offset() }];
+    let total = readings[first()].value as u64;
+    return Ok(scale(total));
+}
+
+fn spawn(f: impl FnOnce()) {
+    f();
+}
+
+fn tick() {}
+
+fn load() -> Result<u64, ()> {
+    Ok(1)
+}
+
+fn parse(raw: u64) -> u64 {
+    raw
+}
+
+fn record(_value: u64) {}
+
+fn measure() -> Reading {
+    Reading { value: 0 }
+}
+
+async fn fetch() {}
+
+fn lookup() -> Option<u64> {
+    None
+}
+
+fn offset() -> u64 {
+    0
+}
+
+fn first() -> usize {
+    0
+}
+
+fn scale(total: u64) -> u64 {
+    total * 2
+}
+
+pub fn convert<T, F>(items: &[T], f: F) -> Vec<u64>
+where
+    T: Clone + Into<u64>,
+    F: for<'a> Fn(&'a T) -> u64,
+{
+    items.iter().map(f).collect()
+}
+
+pub struct Pair(pub u64, pub u64);
+
+fn midpoint(Pair(x, y): Pair, Reading { value }: Reading, mut steps: u64, _: bool) -> u64 {
+    steps += 1;
+    (x + y + value) / steps
+}
+
+pub fn labels(
+    raw: *const u8,
+    pair: (u64, bool),
+    single: (u64,),
+    f: Box<dyn Fn(u64) -> u64 + Send>,
+    callback: fn(u64) -> bool,
+) -> impl Iterator<Item = String> {
+    std::iter::empty()
+}
+
+pub fn read_first<I: Iterator>(items: I) -> Option<<I as Iterator>::Item> {
+    items.into_iter().next()
+}
+
+pub fn checksum(data: &[u8]) -> u64 {
+    fn fold(data: &[u8]) -> u64 {
+        fn widen(byte: u8) -> u64 {
+            byte as u64
+        }
+        data.iter().map(|byte| widen(*byte)).sum()
+    }
+    let seed = {
+        fn seed() -> u64 {
+            7
+        }
+        seed()
+    };
+    fold(data) + seed
+}
+
+pub fn first_reading() -> Option<Pair> {
+    let mut readings = std::iter::empty::<Reading>();
+    readings.next().map(|reading| Pair(reading.value, 0))
+}
+
+pub fn serve(workers: &std::thread::Scope<'_, '_>) {
+    std::thread::spawn(move || accept());
+    tokio::spawn(async move {
+        if ready() {
+            accept();
+        }
+    });
+    workers.spawn(|| flush());
+    tokio::task::spawn_blocking(flush);
+}
+
+fn accept() {}
+
+fn ready() -> bool {
+    true
+}
+
+fn flush() {}
+
+#[tracing::instrument(skip(reading))]
+pub fn log_reading(reading: Reading) {
+    flush();
+}
+
+#[inline]
+#[must_use]
+fn double(value: u64) -> u64 {
+    value * 2
+}
+
+#[instrument]
+pub fn heartbeat() -> u64 {
+    double(1)
+}
+
+pub struct Hooks {
+    pub on_tick: fn() -> u64,
+    pub window: std::ops::Range<usize>,
+}
+
+pub fn hooks(base: Hooks) -> (Hooks, [u64; 2]) {
+    let window = first()..window_end();
+    let hooks = Hooks { on_tick: heartbeat, window };
+    let copied = Hooks { window: 0..window_end(), ..base };
+    let _pair = (scale(1), copied);
+    (hooks, [offset(); 2])
+}
+
+fn window_end() -> usize {
+    4
+}
+
+pub fn is_even(n: u64) -> bool {
+    n == 0 || is_odd(n - 1)
+}
+
+fn is_odd(n: u64) -> bool {
+    n != 0 && is_even(n - 1)
+}
+
+pub fn countdown(n: u64) {
+    if n > 0 {
+        countdown(n - 1);
+    }
+}
+
+pub struct Settings {
+    pub name: String,
+}
+
+impl Settings {
+    pub fn load() -> Result<Settings, String> {
+        Ok(Settings { name: String::new() })
+    }
+
+    pub fn named(self, name: &str) -> Self {
+        Settings { name: name.to_string() }
+    }
+
+    pub fn validate(&self) -> bool {
+        !self.name.is_empty()
+    }
+}
+
+pub struct Reply;
+
+impl Reply {
+    pub fn json(&self) -> String {
+        String::new()
+    }
+}
+
+// Named like the HTTP client's methods, which a chain on the client must not reach
+pub fn get(url: &str) -> Reply {
+    Reply
+}
+
+pub fn send() -> Reply {
+    Reply
+}
+
+pub async fn fetch(client: &reqwest::Client, url: &str) -> Result<String, reqwest::Error> {
+    client.get(url).send().await?.json().await
+}
+
+pub fn configure() -> Result<bool, String> {
+    let settings = Settings::load()?.named("app");
+    Ok(settings.validate() && Settings::load().unwrap().named("other").validate())
+}
+
+pub fn tally(ticks: &[u64], pairs: &[(u64, u64)]) -> u64 {
+    let mut total = 0;
+    for heartbeat in ticks.iter().copied() {
+        total += double(heartbeat);
+    }
+    let offsets: Vec<u64> = pairs.iter().map(|&(offset, _)| double(offset)).collect();
+    if let [(first, _), ..] = pairs {
+        total += double(*first);
+    }
+    match offsets.last() {
+        Some(&scale) => total + double(scale),
+        None => total,
+    }
+}
+
+pub struct Window<const N: usize> {
+    pub slots: [u64; N],
+}
+
+pub fn window<T: Copy, const N: usize>(items: [T; N]) -> [T; N] {
+    items
+}
@@ -0,0 +1,25 @@
+extern crate self as ffi;
+
+use std::os::raw::{c_char, c_int};
+
+#[repr(C)]
+pub union Value {
+    pub int: c_int,
+    pub float: f64,
+    pub text: *const c_char,
+}
+
+extern "C" {
+    /// Length of a NUL-terminated string
+    pub fn strlen(s: *const c_char) -> usize;
+    fn abs(n: c_int) -> c_int;
+    static errno: c_int;
+}
+
+pub fn length(text: &[u8]) -> usize {
+    unsafe { strlen(text.as_ptr() as *const c_char) }
+}
+
+pub fn magnitude(value: Value) -> c_int {
+    unsafe { ffi::abs(value.int) }
+}
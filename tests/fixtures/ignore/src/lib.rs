@@ -0,0 +1,7 @@
+mod keep;
+mod messages;
+mod vendor;
+
+pub fn run() {
+    vendor::patched();
+}
@@ -0,0 +1,22 @@
+pub struct Store;
+
+impl Store {
+    pub fn save(&self) {}
+}
+
+pub fn flush(store: &Store) {
+    store.save();
+}
+
+mod log {
+    pub fn record() {}
+}
+
+pub fn audit() {
+    log::record();
+}
+
+pub fn release() {
+    audit();
+    beta::publish();
+}
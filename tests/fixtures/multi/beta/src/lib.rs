@@ -0,0 +1,12 @@
+mod log;
+mod store;
+
+use store::Store;
+
+pub fn flush(store: &Store) {
+    store.save();
+}
+
+pub fn publish() {
+    flush(&Store);
+}
@@ -0,0 +1,7 @@
+pub struct Store;
+
+impl Store {
+    pub fn save(&self) {
+        crate::log::record();
+    }
+}
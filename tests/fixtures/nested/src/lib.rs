@@ -0,0 +1,146 @@
+pub fn top() {
+    let Some(_conn) = net::connect() else {
+        net::reset();
+        return;
+    };
+}
+
+pub mod net {
+    pub fn connect() -> Option<()> {
+        http::request();
+        None
+    }
+
+    pub(crate) fn reset() {}
+
+    pub mod http {
+        pub(super) fn request() {}
+
+        pub(in crate::net) fn pool() {}
+    }
+
+    pub(crate) const TIMEOUT_MS: u64 = 5_000;
+    static mut OPEN: usize = 0;
+
+    pub struct Pool {
+        size: usize,
+    }
+
+    impl Pool {
+        pub const MAX: usize = 8;
+        const GREETING: &'static str = "a greeting far too long to show in a listing";
+
+        pub fn size(&self) -> usize {
+            self.size.min(Self::MAX)
+        }
+    }
+}
+
+#[cfg(not(test))]
+pub fn runtime_only() {}
+
+#[cfg(all(test, feature = "slow"))]
+fn slow_fixture() {}
+
+#[test]
+fn connect_fails_offline() {
+    assert!(net::connect().is_none());
+}
+
+#[cfg(test)]
+mod tests;
+
+#[macro_export]
+macro_rules! retry {
+    ($e:expr) => {
+        $e
+    };
+}
+
+macro_rules! local_only {
+    () => {};
+}
+
+pub mod store {
+    pub fn copy() {}
+}
+
+pub mod util {
+    pub fn copy() {}
+
+    pub fn swap() {}
+}
+
+use std::io;
+use std::mem::swap;
+
+pub fn copy_all() {
+    io::copy();
+    util::copy();
+    crate::util::copy();
+    swap();
+}
+
+pub fn close() {
+    release();
+}
+
+fn release() {
+    let released = unsafe { forget() };
+    assert!(released);
+}
+
+unsafe fn forget() -> bool {
+    true
+}
+
+pub trait Transport {
+    fn send(&self);
+
+    fn flush(&self) {}
+
+    fn shutdown(&self) {}
+}
+
+impl Transport for net::Pool {
+    fn send(&self) {}
+
+    fn flush(&self) {
+        self.send();
+    }
+}
+
+/// Connects through the pool.
+///
+/// Returns `None` when offline.
+pub fn dial() -> Option<()> {
+    net::connect()
+}
+
+/// How often to retry a failed connection
+pub struct Backoff {
+    pub attempts: u32,
+}
+
+mod engine {
+    pub fn start() {}
+
+    pub fn stop() {}
+
+    pub struct Engine;
+
+    impl Engine {
+        pub fn rev(&self) {}
+    }
+}
+
+pub use engine::start;
+pub use engine::Engine as Motor;
+
+impl Iterator for net::Pool {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.size.checked_sub(1).inspect(|size| self.size = *size)
+    }
+}
@@ -0,0 +1,6 @@
+use crate::top;
+
+#[test]
+fn top_returns() {
+    top();
+}
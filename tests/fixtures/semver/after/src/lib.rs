@@ -0,0 +1,47 @@
+pub mod net {
+    pub fn connect(host: &str, port: u16) -> bool {
+        !host.is_empty() && port != 0
+    }
+
+    pub fn ping() {}
+}
+
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+pub struct Settings {
+    pub verbose: bool,
+    pub timeout: u64,
+    retries: u32,
+    backoff: u32,
+}
+
+pub enum Shape {
+    Circle(f64),
+    Square(f64),
+    Triangle(f64, f64, f64),
+}
+
+#[non_exhaustive]
+pub enum Event {
+    Start,
+    Stop,
+    Pause,
+}
+
+pub trait Store {
+    fn get(&self, key: &str) -> Option<String>;
+
+    fn put(&mut self, key: &str, value: String);
+
+    fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+}
+
+pub const LIMIT: usize = 20;
+
+pub use net::connect;
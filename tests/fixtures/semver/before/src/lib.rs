@@ -0,0 +1,36 @@
+pub mod net {
+    pub fn connect(host: &str) -> bool {
+        !host.is_empty()
+    }
+
+    pub fn disconnect() {}
+}
+
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+pub struct Settings {
+    pub verbose: bool,
+    retries: u32,
+}
+
+pub enum Shape {
+    Circle(f64),
+    Square(f64),
+}
+
+#[non_exhaustive]
+pub enum Event {
+    Start,
+    Stop,
+}
+
+pub trait Store {
+    fn get(&self, key: &str) -> Option<String>;
+}
+
+pub const LIMIT: usize = 10;
+
+pub use net::connect;
@@ -0,0 +1,15 @@
+fn main() {
+    util_lib::net::connect();
+}
+
+fn router() -> Router {
+    Router::new().route("/", get(index)).route("/health", get(health).post(ping))
+}
+
+async fn index() -> &'static str {
+    "ok"
+}
+
+async fn health() {}
+
+async fn ping() {}
@@ -0,0 +1,6 @@
+pub mod net;
+
+#[no_mangle]
+pub extern "C" fn util_version() -> u32 {
+    1
+}
@@ -0,0 +1,5 @@
+pub struct Session;
+
+pub fn connect() -> Session {
+    Session
+}
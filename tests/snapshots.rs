@@ -0,0 +1,1220 @@
+use morpho_rs::testing::{assert_snapshot, snapshot_path, Fixture};
+use morpho_rs::VisibilityFilter;
+
+fn fixture() -> Fixture {
+    Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/basic"))
+}
+
+fn check(name: &str, actual: &str) {
+    assert_snapshot(snapshot_path(env!("CARGO_MANIFEST_DIR"), name), actual);
+}
+
+#[test]
+fn list_all() {
+    check("list_all", &fixture().list_all(VisibilityFilter::All));
+}
+
+#[test]
+fn list_public_api_with_reexports() {
+    check("list_public", &fixture().list_all(VisibilityFilter::PublicOnly));
+}
+
+#[test]
+fn call_graph_with_generic_dispatch() {
+    check("call_graph_run", &fixture().call_graph("src/lib.rs::run", VisibilityFilter::All));
+}
+
+#[test]
+fn call_graph_from_several_roots() {
+    let fixture = fixture();
+    check(
+        "call_graph_run_and_paint",
+        &fixture.output(morpho_rs::OutputMode::CallGraph {
+            roots: vec![fixture.qualified("src/lib.rs::run"), fixture.qualified("src/render.rs::paint")],
+            visibility: VisibilityFilter::All,
+            budget: Default::default(),
+            descend_into_deps: true,
+            show_external: false,
+            scope: None,
+        }),
+    );
+
+    // A root named twice is traced once; the types both reach are listed once
+    let graph = fixture.project.call_graph_from(&["run", "paint", "src/lib.rs::run"]).expect("both are defined once");
+    assert_eq!(graph.roots.len(), 2);
+    assert_eq!(graph.types.iter().map(|ty| ty.key.as_str()).collect::<Vec<_>>(), ["Circle", "Config", "Shape", "Square"]);
+    assert!(fixture.project.call_graph_from(&[] as &[&str]).is_err());
+}
+
+#[test]
+fn call_graph_within_a_scope() {
+    let fixture = fixture();
+    let call_graph = |scope: &str| morpho_rs::OutputMode::CallGraph {
+        roots: vec![fixture.qualified("src/lib.rs::run")],
+        visibility: VisibilityFilter::All,
+        budget: Default::default(),
+        descend_into_deps: true,
+        show_external: true,
+        scope: Some(scope.to_string()),
+    };
+    check("call_graph_run_scope_config", &fixture.output(call_graph("src/config.rs")));
+    assert_eq!(fixture.output(call_graph("crate::config")), fixture.output(call_graph("src/config.rs")));
+
+    // The root is kept wherever it is; calls out of the scope leave no edges
+    let limits = morpho_rs::TraceLimits { scope: Some("render".to_string()), ..Default::default() };
+    let graph = fixture.project.call_graph_limited(&["run"], &limits).expect("run is defined once");
+    let names: Vec<&str> = graph.functions.iter().map(|func| func.qualified_name.as_str()).collect();
+    assert_eq!(names.len(), 2, "{:?}", names);
+    assert!(graph.edges.iter().all(|edge| edge.callee.ends_with("report")), "{:?}", graph.edges);
+}
+
+#[test]
+fn call_graph_summary_without_the_tree() {
+    let fixture = fixture();
+    check(
+        "call_graph_summary_run",
+        &fixture.output(morpho_rs::OutputMode::CallGraphSummary {
+            roots: vec![fixture.qualified("src/lib.rs::run")],
+            visibility: VisibilityFilter::PublicOnly,
+        }),
+    );
+}
+
+#[test]
+fn call_graph_source_bundle() {
+    let fixture = fixture();
+    check(
+        "call_graph_source_run",
+        &fixture.output(morpho_rs::OutputMode::CallGraphSource { roots: vec![fixture.qualified("src/lib.rs::run")] }),
+    );
+}
+
+#[test]
+fn source_is_shown_as_written() {
+    let source = fixture().source("Config::trimmed");
+    assert!(source.contains("\n    // names are compared after trimming, so store them that way\n"), "{}", source);
+}
+
+#[test]
+fn source_through_reexport() {
+    check("source_circle", &fixture().source("crate::Circle"));
+}
+
+#[test]
+fn trait_object_fan_out() {
+    check("call_graph_paint", &fixture().call_graph("src/render.rs::paint", VisibilityFilter::All));
+}
+
+#[test]
+fn output_is_identical_across_loads() {
+    let first = fixture();
+    for _ in 0..5 {
+        let again = fixture();
+        assert_eq!(first.list_all(VisibilityFilter::All), again.list_all(VisibilityFilter::All));
+        assert_eq!(
+            first.call_graph("src/render.rs::paint", VisibilityFilter::All),
+            again.call_graph("src/render.rs::paint", VisibilityFilter::All)
+        );
+    }
+}
+
+#[test]
+fn parallel_load_matches_sequential() {
+    let load = |threads| {
+        let options = morpho_rs::LoadOptions { threads: Some(threads), ..Default::default() };
+        Fixture::load_with_options(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/basic"), &options)
+    };
+    let sequential = load(1);
+    let parallel = load(4);
+    assert_eq!(sequential.list_all(VisibilityFilter::All), parallel.list_all(VisibilityFilter::All));
+    assert_eq!(sequential.project.call_edges(), parallel.project.call_edges());
+    assert_eq!(sequential.project.stats.files_parsed, parallel.project.stats.files_parsed);
+}
+
+#[test]
+fn expanded_trait_calls() {
+    let options = morpho_rs::LoadOptions { expand_trait_calls: true, ..Default::default() };
+    let fixture = Fixture::load_with_options(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/basic"), &options);
+    check("call_graph_describe_expanded", &fixture.call_graph("src/shape.rs::Shape::describe", VisibilityFilter::All));
+}
+
+#[test]
+fn jsonl_export() {
+    let fixture = fixture();
+    let mut out = vec![];
+    morpho_rs::export::write_jsonl(&fixture.project, &mut out).unwrap();
+    let jsonl = String::from_utf8(out).unwrap().replace(&format!("{}/", fixture.root), "");
+    check("export_jsonl", &jsonl);
+}
+
+#[test]
+fn csv_edge_export() {
+    let fixture = fixture();
+    let mut out = vec![];
+    morpho_rs::export::write_csv_edges(&fixture.project, &mut out).unwrap();
+    let csv = String::from_utf8(out).unwrap().replace(&format!("{}/", fixture.root), "");
+    check("export_edges_csv", &csv);
+}
+
+#[test]
+fn shortest_call_paths() {
+    let fixture = fixture();
+    let path = |from: &str, to: &str| {
+        fixture.output(morpho_rs::OutputMode::PathBetween { from: from.to_string(), to: to.to_string() })
+    };
+    check("path_run_to_circle_area", &path("run", "<Circle as Shape>::area"));
+    assert!(path("paint", "run").starts_with("// no call path from src/render.rs::paint to src/lib.rs::run"));
+
+    let paths = fixture.project.paths_between("run", "Config::parse").unwrap();
+    assert_eq!(paths.total, 1);
+    let chain: Vec<&str> = paths.paths[0].iter().map(|edge| edge.callee.rsplit("::").next().unwrap()).collect();
+    assert_eq!(chain, ["load", "parse"]);
+}
+
+#[test]
+fn call_edge_list() {
+    let fixture = fixture();
+    check("call_edges_run", &fixture.output(morpho_rs::OutputMode::CallEdges { root: Some("run".to_string()) }));
+
+    let all = fixture.output(morpho_rs::OutputMode::CallEdges { root: None });
+    assert!(all.contains("src/render.rs::paint\tsrc/circle.rs::<Circle as Shape>::area\tdyn Shape\n"));
+    let document: serde_json::Value = serde_json::from_str(&fixture.output_with_format(
+        morpho_rs::OutputMode::CallEdges { root: None },
+        morpho_rs::OutputFormat::Json,
+    ))
+    .unwrap();
+    assert_eq!(document["edges"].as_array().unwrap().len(), fixture.project.call_edges().len());
+}
+
+#[test]
+fn graphml_type_dependencies() {
+    let fixture = fixture();
+    let mut out = vec![];
+    morpho_rs::export::write_graphml_types(&fixture.project, &mut out).unwrap();
+    let graphml = String::from_utf8(out).unwrap().replace(&format!("{}/", fixture.root), "");
+    check("export_types_graphml", &graphml);
+}
+
+#[test]
+fn lsif_index_links_calls_to_definitions() {
+    let fixture = fixture();
+    let mut out = vec![];
+    morpho_rs::emit::write_lsif(&fixture.project, &fixture.root, &mut out).unwrap();
+    let elements: Vec<serde_json::Value> =
+        String::from_utf8(out).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    let by_id = |id: &serde_json::Value| elements.iter().find(|element| &element["id"] == id).unwrap();
+    let out_of = |label: &str, in_v: &serde_json::Value| {
+        let edge = elements.iter().find(|element| element["label"] == label && &element["inV"] == in_v);
+        edge.map(|edge| edge["outV"].clone())
+    };
+
+    // Every range of `Config::parse`, found through its moniker: the definition and the call in `load`
+    let moniker = elements.iter().find(|element| element["identifier"] == "config::Config::parse").unwrap();
+    let result_set = out_of("moniker", &moniker["id"]).unwrap();
+    let mut ranges: Vec<(u64, u64, String)> = elements
+        .iter()
+        .filter(|element| element["label"] == "next" && element["inV"] == result_set)
+        .map(|edge| {
+            let range = by_id(&edge["outV"]);
+            let contains = elements.iter().find(|element| {
+                element["label"] == "contains" && element["inVs"].as_array().unwrap().contains(&range["id"])
+            });
+            let uri = by_id(&contains.unwrap()["outV"])["uri"].as_str().unwrap();
+            let file = uri.rsplit('/').next().unwrap().to_string();
+            (range["start"]["line"].as_u64().unwrap(), range["start"]["character"].as_u64().unwrap(), file)
+        })
+        .collect();
+    ranges.sort();
+    assert_eq!(ranges, [(7, 16, "config.rs".to_string()), (10, 11, "config.rs".to_string())]);
+    let root = std::fs::canonicalize(&fixture.root).unwrap();
+    assert_eq!(elements[0]["projectRoot"].as_str().unwrap(), format!("file://{}", root.display()));
+}
+
+#[test]
+fn notebook_function_summary() {
+    let fixture = fixture();
+    let summary = morpho_rs::notebook::function_summary(&fixture.project, "Config::load").unwrap();
+    check("notebook_config_load", &summary.text);
+    assert!(summary.html.contains("<summary>called by (1)</summary>"));
+}
+
+#[test]
+fn inline_modules_are_indexed() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nested"));
+    for name in ["src/lib.rs::top", "src/lib.rs::net::connect", "src/lib.rs::net::http::request"] {
+        assert!(fixture.project.functions.contains_key(&fixture.qualified(name)), "{} not indexed", name);
+    }
+    check("call_graph_nested", &fixture.call_graph("src/lib.rs::top", VisibilityFilter::All));
+}
+
+#[test]
+fn restricted_visibilities() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nested"));
+    check("list_crate_visible", &fixture.list_all(VisibilityFilter::CrateVisible));
+    assert!(!fixture.list_all(VisibilityFilter::PublicOnly).contains("pub(crate)"));
+}
+
+#[test]
+fn unreachable_functions() {
+    let options = morpho_rs::LoadOptions { exclude_tests: true, ..Default::default() };
+    let fixture = Fixture::load_with_options(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nested"), &options);
+    check("unreachable_nested", &fixture.output(morpho_rs::OutputMode::Unreachable { roots: vec![] }));
+
+    let from_top = fixture.output(morpho_rs::OutputMode::Unreachable { roots: vec!["top".to_string()] });
+    assert!(from_top.contains("src/lib.rs::runtime_only()"));
+    assert!(!from_top.contains("src/lib.rs::net::connect()"));
+}
+
+#[test]
+fn callers_of_trait_method() {
+    let fixture = fixture();
+    let callers = fixture.output(morpho_rs::OutputMode::Callers {
+        target: "Shape::describe".to_string(),
+        depth: None,
+    });
+    check("callers_describe", &callers);
+}
+
+#[test]
+fn html_report_links_calls_to_sources() {
+    let fixture = fixture();
+    let mut out = vec![];
+    morpho_rs::export::export(&fixture.project, morpho_rs::export::ExportFormat::Html, &mut out).unwrap();
+    let html = String::from_utf8(out).unwrap();
+
+    // Functions are numbered in name order: f1 is Config::load, f5 is run
+    assert!(html.contains("<section id=\"f5\" data-name=\"src/lib.rs::run\">"));
+    assert!(html.contains("<h3>Calls</h3><ul><li><a href=\"#f1\">Config::load</a> <span class=\"line\">line 11</span></li>"));
+    assert!(html.contains("<details class=\"tree\" data-root=\"f5\">"));
+    assert!(html.contains(r#""f5":[["f1",11,null],"#));
+    assert!(html.trim_end().ends_with("</html>"));
+}
+
+#[test]
+fn call_graph_as_json() {
+    let fixture = fixture();
+    let mode = morpho_rs::OutputMode::CallGraph {
+        roots: vec![fixture.qualified("src/lib.rs::run")],
+        visibility: VisibilityFilter::All,
+        budget: Default::default(),
+        descend_into_deps: true,
+        show_external: false,
+        scope: None,
+    };
+    let json = fixture.output_with_format(mode, morpho_rs::OutputFormat::Json);
+    let fingerprint = &fixture.project.stats.fingerprint;
+    check("call_graph_run_json", &json.replace(fingerprint.as_str(), "<fingerprint>"));
+}
+
+#[test]
+fn call_graph_as_mermaid() {
+    let fixture = fixture();
+    let mode = morpho_rs::OutputMode::CallGraph {
+        roots: vec![fixture.qualified("src/lib.rs::run")],
+        visibility: VisibilityFilter::All,
+        budget: Default::default(),
+        descend_into_deps: true,
+        show_external: false,
+        scope: None,
+    };
+    check("call_graph_run_mermaid", &fixture.output_with_format(mode, morpho_rs::OutputFormat::Mermaid));
+}
+
+#[test]
+fn errors_are_structured() {
+    let missing = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/missing");
+    let loaded = morpho_rs::load_project(missing);
+    assert!(matches!(loaded, Err(morpho_rs::MorphoError::Io { .. })));
+
+    let fixture = fixture();
+    let callers = |target: &str| {
+        let mode = morpho_rs::OutputMode::Callers { target: target.to_string(), depth: None };
+        morpho_rs::generate_output_for_project(&fixture.project, mode).unwrap_err()
+    };
+    assert!(matches!(callers("no_such_fn"), morpho_rs::MorphoError::NotFound { kind: "function", .. }));
+    match callers("area") {
+        morpho_rs::MorphoError::Ambiguous { candidates, .. } => assert!(candidates.len() > 1, "{:?}", candidates),
+        other => panic!("expected an ambiguous name, got {:?}", other),
+    }
+
+    // Source lookups no longer pick one of the matches; a type or file narrows them down
+    let source = |function: &str| {
+        let mode = morpho_rs::OutputMode::Source { function: function.to_string(), include_docs: false };
+        morpho_rs::generate_output_for_project(&fixture.project, mode).map(|output| output.content)
+    };
+    assert!(matches!(source("area"), Err(morpho_rs::MorphoError::Ambiguous { .. })));
+    assert!(source("Square::area").unwrap().contains("self.side * self.side"));
+    assert!(source("circle.rs#area").unwrap().contains("self.radius"));
+    let graph = fixture.call_graph("src/square.rs#area", VisibilityFilter::All);
+    assert!(graph.contains("Square"), "{}", graph);
+}
+
+#[test]
+fn type_usages_of_enum() {
+    let fixture = fixture();
+    let usages = fixture.output(morpho_rs::OutputMode::TypeUsages {
+        type_name: "Unit".to_string(),
+    });
+    check("type_usages_unit", &usages);
+}
+
+#[test]
+fn type_api_with_trait_impls() {
+    let fixture = fixture();
+    let api = fixture.output(morpho_rs::OutputMode::TypeApi {
+        type_name: "Length".to_string(),
+    });
+    check("type_api_length", &api);
+}
+
+#[test]
+fn search_ranks_names() {
+    let fixture = fixture();
+    let matches = fixture.output(morpho_rs::OutputMode::Search {
+        query: "len".to_string(),
+        limit: Some(8),
+    });
+    check("search_len", &matches);
+}
+
+#[test]
+fn calls_inside_arguments_and_subexpressions() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    check("call_graph_exprs", &fixture.call_graph("src/lib.rs::run", VisibilityFilter::All));
+}
+
+#[test]
+fn spawned_calls_are_marked() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    check("call_graph_serve", &fixture.call_graph("src/lib.rs::serve", VisibilityFilter::All));
+}
+
+#[test]
+fn chained_method_calls() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    // The chain starts on a client from another crate, so none of its methods is the project's `get` or `send`
+    assert!(fixture.project.call_graph("fetch").unwrap().edges.is_empty());
+    check("call_graph_configure", &fixture.call_graph("src/lib.rs::configure", VisibilityFilter::All));
+}
+
+#[test]
+fn external_calls_are_listed_on_request() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    let call_graph = morpho_rs::OutputMode::CallGraph {
+        roots: vec![fixture.qualified("src/lib.rs::fetch"), fixture.qualified("src/lib.rs::configure")],
+        visibility: VisibilityFilter::All,
+        budget: Default::default(),
+        descend_into_deps: true,
+        show_external: true,
+        scope: None,
+    };
+    check("call_graph_fetch_external", &fixture.output(call_graph));
+}
+
+#[test]
+fn calls_inside_literals() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    check("call_graph_hooks", &fixture.call_graph("src/lib.rs::hooks", VisibilityFilter::All));
+}
+
+#[test]
+fn bound_names_are_not_callbacks() {
+    // `for`, closure, slice and match bindings named like project functions are values passed along
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    let edges = fixture.output(morpho_rs::OutputMode::CallEdges { root: Some("tally".to_string()) });
+    let callees: Vec<&str> = edges.lines().filter_map(|line| line.split('\t').nth(1)).collect();
+    assert_eq!(callees, ["src/lib.rs::double"; 4], "{}", edges);
+}
+
+#[test]
+fn recursion_cycles() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    check("cycles_exprs", &fixture.output(morpho_rs::OutputMode::Cycles));
+    let mermaid = fixture.output_with_format(morpho_rs::OutputMode::Cycles, morpho_rs::OutputFormat::Mermaid);
+    assert!(mermaid.contains("f1 --> f2\n    f2 --> f1"), "{}", mermaid);
+}
+
+#[test]
+fn functions_by_attribute() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    let mode = |attribute: &str| morpho_rs::OutputMode::WithAttribute {
+        attribute: attribute.to_string(),
+        visibility: VisibilityFilter::All,
+    };
+    check("with_attribute_instrument", &fixture.output(mode("instrument")));
+    assert!(fixture.output(mode("#[inline]")).contains("fn src/lib.rs::double(value: u64) -> u64"));
+    assert!(fixture.output(mode("tracing::instrument(skip(reading))")).starts_with("// 1 function with"));
+
+    let json = fixture.output_with_format(mode("must_use"), morpho_rs::OutputFormat::Json);
+    let json: serde_json::Value = serde_json::from_str(&json).expect("valid JSON");
+    assert_eq!(json["functions"][0]["attributes"], serde_json::json!(["inline", "must_use"]));
+
+    let graph = fixture.project.call_graph_from(&["#[instrument]"]).expect("two functions are instrumented");
+    let roots: Vec<&str> = graph.roots.iter().map(|root| root.qualified_name.as_str()).collect();
+    assert_eq!(roots, [fixture.qualified("src/lib.rs::heartbeat"), fixture.qualified("src/lib.rs::log_reading")]);
+    assert!(graph.functions.iter().any(|func| func.qualified_name == fixture.qualified("src/lib.rs::double")));
+    assert!(fixture.project.call_graph_from(&["#[no_mangle]"]).is_err());
+}
+
+#[test]
+fn types_reached_through_return_types_and_turbofish() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    let graph = fixture.project.call_graph("first_reading").expect("first_reading is defined once");
+    assert_eq!(graph.types.iter().map(|ty| ty.key.as_str()).collect::<Vec<_>>(), ["Pair", "Reading"]);
+}
+
+#[test]
+fn functions_declared_in_bodies() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    for name in ["src/lib.rs::checksum::fold", "src/lib.rs::checksum::fold::widen", "src/lib.rs::checksum::seed"] {
+        assert!(fixture.project.functions.contains_key(&fixture.qualified(name)), "{} not indexed", name);
+    }
+    check("call_graph_checksum", &fixture.call_graph("src/lib.rs::checksum", VisibilityFilter::All));
+}
+
+#[test]
+fn generics_and_where_clauses_in_signatures() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    check("source_convert", &fixture.source("convert"));
+    assert!(fixture
+        .list_all(VisibilityFilter::All)
+        .contains("fn src/lib.rs::convert<T, F>(items: &[T], f: F) -> Vec<u64> where T: Clone + Into<u64>, F: for<'a> Fn(&'a T) -> u64"));
+}
+
+#[test]
+fn argument_names_in_signatures() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    let signature = fixture.project.functions[&fixture.qualified("src/lib.rs::midpoint")].signature();
+    assert!(
+        signature.ends_with("midpoint(Pair(x, y): Pair, Reading { value }: Reading, steps: u64, _: bool) -> u64"),
+        "{}",
+        signature
+    );
+}
+
+#[test]
+fn impl_trait_objects_and_tuples_in_signatures() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    let signature = |name: &str| fixture.project.functions[&fixture.qualified(name)].signature();
+    assert!(signature("src/lib.rs::labels").ends_with(
+        "labels(raw: *const u8, pair: (u64, bool), single: (u64,), f: Box<dyn Fn(u64) -> u64 + Send>, \
+         callback: fn(u64) -> bool) -> impl Iterator<Item = String>"
+    ));
+    assert!(signature("src/lib.rs::read_first").ends_with("read_first<I: Iterator>(items: I) -> Option<<I as Iterator>::Item>"));
+}
+
+#[test]
+fn const_generics_in_types_and_signatures() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    let signature = fixture.project.functions[&fixture.qualified("src/lib.rs::window")].signature();
+    assert!(signature.ends_with("window<T: Copy, const N: usize>(items: [T; N]) -> [T; N]"), "{}", signature);
+    let listing = fixture.list_all(VisibilityFilter::All);
+    assert!(listing.contains("pub struct Window<const N: usize> {\n    pub slots: [u64; N]\n}"), "{}", listing);
+}
+
+#[test]
+fn test_code_can_be_excluded() {
+    let root = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nested");
+    let test_fns = ["src/lib.rs::slow_fixture", "src/lib.rs::connect_fails_offline", "src/tests.rs::top_returns"];
+
+    let fixture = Fixture::load(root);
+    for name in test_fns.iter().chain(&["src/lib.rs::runtime_only"]) {
+        assert!(fixture.project.functions.contains_key(&fixture.qualified(name)), "{} not indexed", name);
+    }
+
+    let options = morpho_rs::LoadOptions { exclude_tests: true, ..Default::default() };
+    let fixture = Fixture::load_with_options(root, &options);
+    for name in test_fns {
+        assert!(!fixture.project.functions.contains_key(&fixture.qualified(name)), "{} indexed", name);
+    }
+    assert!(fixture.project.functions.contains_key(&fixture.qualified("src/lib.rs::runtime_only")));
+    let skipped: Vec<&str> = fixture.project.stats.skipped.iter().map(|s| s.reason.as_str()).collect();
+    assert_eq!(skipped, ["test code"]);
+}
+
+#[test]
+fn cfg_attributes_select_items() {
+    let root = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/cfg");
+    let load = |target_os: &str, features: &[&str]| {
+        let cfg = morpho_rs::CfgOptions {
+            features: features.iter().map(|f| f.to_string()).collect(),
+            target_os: Some(target_os.to_string()),
+            ..Default::default()
+        };
+        Fixture::load_with_options(root, &morpho_rs::LoadOptions { cfg: Some(cfg), ..Default::default() })
+    };
+    let functions = |fixture: &Fixture| -> Vec<String> {
+        fixture.project.functions.keys().map(|name| name.replace(&format!("{}/", fixture.root), "")).collect()
+    };
+    let skipped = |fixture: &Fixture| -> Vec<(String, String)> {
+        let stats = &fixture.project.stats;
+        stats.skipped.iter().map(|s| (s.path.replace(&format!("{}/", fixture.root), ""), s.reason.clone())).collect()
+    };
+
+    // `target_arch` isn't modeled, so `fast_path` stays in whatever the target
+    let linux = load("linux", &[]);
+    assert_eq!(
+        functions(&linux),
+        [
+            "src/lib.rs::Socket::fast_path",
+            "src/lib.rs::connect_plain",
+            "src/lib.rs::open",
+            "src/unix.rs::open_fd",
+            "src/unix/poll.rs::register",
+        ]
+    );
+    let disabled = |files: &[&str]| -> Vec<(String, String)> {
+        files.iter().map(|file| (file.to_string(), "disabled by cfg".to_string())).collect()
+    };
+    assert_eq!(skipped(&linux), disabled(&["src/wasm.rs", "src/windows.rs"]));
+    assert_eq!(linux.project.stats.files_parsed, 3);
+
+    let macos_tls = load("macos", &["tls"]);
+    assert_eq!(
+        functions(&macos_tls),
+        [
+            "src/lib.rs::Socket::fast_path",
+            "src/lib.rs::Socket::set_nosigpipe",
+            "src/lib.rs::connect_tls",
+            "src/lib.rs::open",
+            "src/lib.rs::secure::handshake",
+            "src/unix.rs::open_fd",
+            "src/unix/poll.rs::register",
+        ]
+    );
+
+    let windows = load("windows", &["tls"]);
+    assert_eq!(
+        functions(&windows),
+        ["src/lib.rs::Socket::fast_path", "src/lib.rs::connect_tls", "src/lib.rs::open", "src/windows.rs::open_handle"]
+    );
+    assert_eq!(skipped(&windows), disabled(&["src/unix.rs", "src/unix/poll.rs", "src/wasm.rs"]));
+
+    let options = morpho_rs::LoadOptions { all_cfgs: true, ..Default::default() };
+    let all = Fixture::load_with_options(root, &options);
+    assert_eq!(all.project.functions.len(), 10);
+    check("list_all_cfgs", &all.list_all(VisibilityFilter::All));
+}
+
+#[test]
+fn reindexing_files_matches_a_full_load() {
+    let full = fixture();
+    let mut reindexed = fixture();
+    let changed: Vec<std::path::PathBuf> =
+        ["src/shape.rs", "src/render.rs", "src/deleted.rs"].iter().map(|file| full.qualified(file).into()).collect();
+    reindexed.project.reindex_files(&full.root, &changed, &Default::default());
+
+    assert_eq!(full.list_all(VisibilityFilter::All), reindexed.list_all(VisibilityFilter::All));
+    assert_eq!(full.project.call_edges(), reindexed.project.call_edges());
+    assert_eq!(full.project.stats.files_parsed, reindexed.project.stats.files_parsed);
+    assert_eq!(full.project.stats.fingerprint, reindexed.project.stats.fingerprint);
+}
+
+#[test]
+fn crate_paths_from_cargo_metadata() {
+    let options = morpho_rs::LoadOptions { workspace: true, ..Default::default() };
+    let fixture = Fixture::load_with_options(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace"), &options);
+    let packages: Vec<&str> = fixture.project.packages.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(packages, ["app", "util-lib"]);
+
+    let connect = fixture.qualified("util-lib/src/net.rs::connect");
+    let main = fixture.qualified("app/src/main.rs::main");
+    assert_eq!(fixture.project.function_package_path(&connect).as_deref(), Some("util_lib::net::connect"));
+    assert_eq!(fixture.project.function_package_path(&main).as_deref(), Some("app::main"));
+    assert!(fixture.source("util_lib::net::Session").contains("pub struct Session"));
+    assert!(fixture.source("util_lib::net::connect").contains("-> Session"));
+}
+
+#[test]
+fn entry_points_of_a_workspace() {
+    let options = morpho_rs::LoadOptions { workspace: true, ..Default::default() };
+    let fixture = Fixture::load_with_options(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/workspace"), &options);
+    check("entry_points_workspace", &fixture.output(morpho_rs::OutputMode::EntryPoints));
+}
+
+#[test]
+fn public_api_follows_reexports() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nested"));
+    check("public_api_nested", &fixture.output(morpho_rs::OutputMode::PublicApi));
+}
+
+#[test]
+fn call_graph_diff_between_checkouts() {
+    let dir = |side: &str| format!("{}/tests/fixtures/diff/{}", env!("CARGO_MANIFEST_DIR"), side);
+    let mode = morpho_rs::OutputMode::Diff {
+        before_dir: dir("before"),
+        after_dir: dir("after"),
+        root: Some("run".to_string()),
+    };
+    let output = morpho_rs::generate_output_with_format(&dir("after"), mode, &Default::default(), Default::default())
+        .expect("both checkouts load");
+    check("diff_run", &output.content);
+}
+
+#[test]
+fn semver_levels_between_versions() {
+    let dir = |side: &str| format!("{}/tests/fixtures/semver/{}", env!("CARGO_MANIFEST_DIR"), side);
+    let mode = morpho_rs::OutputMode::Semver {
+        before_dir: dir("before"),
+        after_dir: dir("after"),
+    };
+    let output = morpho_rs::generate_output_with_format(&dir("after"), mode, &Default::default(), Default::default())
+        .expect("both versions load");
+    check("semver_before_after", &output.content);
+}
+
+#[test]
+fn typed_queries_on_a_loaded_project() {
+    let fixture = fixture();
+    let project = &fixture.project;
+    let names = |functions: &[&morpho_rs::Function]| -> Vec<String> {
+        functions.iter().map(|func| func.qualified_name.replace(&format!("{}/", fixture.root), "")).collect()
+    };
+
+    let graph = project.call_graph("run").expect("run is defined once");
+    assert_eq!(graph.roots[0].qualified_name, fixture.qualified("src/lib.rs::run"));
+    assert!(names(&graph.functions).contains(&"src/config.rs::Config::trimmed".to_string()));
+    // Config from the signatures of the functions it calls, Circle and Square from struct literals in its body
+    assert_eq!(graph.types.iter().map(|ty| ty.key.as_str()).collect::<Vec<_>>(), ["Circle", "Config", "Square"]);
+
+    let callers = project.callers_of("Config::parse", None).expect("parse is defined once");
+    assert_eq!(
+        names(&callers.functions),
+        ["src/config.rs::Config::load", "src/config.rs::Config::parse", "src/lib.rs::run"]
+    );
+
+    let api = project.type_api("Config").expect("Config is defined once");
+    assert_eq!(api.impls.len(), 1);
+    assert_eq!(api.impls[0].methods.len(), 3);
+}
+
+#[test]
+fn whole_project_graph() {
+    let fixture = fixture();
+    let graph = fixture.project.graph();
+    let name = |id: morpho_rs::query::FunctionId| graph.function(id).qualified_name.replace(&format!("{}/", fixture.root), "");
+    let run = graph.id(&fixture.qualified("src/lib.rs::run")).expect("run is a node");
+    assert_eq!(graph.function(run).qualified_name, fixture.qualified("src/lib.rs::run"));
+    assert_eq!(graph.edges.len(), fixture.project.call_edges().len());
+    let parse = graph.id(&fixture.qualified("src/config.rs::Config::parse")).expect("parse is a node");
+    let callers: Vec<String> = graph.callers(parse).iter().map(|edge| name(edge.from)).collect();
+    assert_eq!(callers, ["src/config.rs::Config::load"]);
+
+    // Node indices match function ids, so petgraph's results map straight back
+    let petgraph = graph.to_petgraph();
+    assert_eq!((petgraph.node_count(), petgraph.edge_count()), (graph.functions.len(), graph.edges.len()));
+    // Nothing recurses in the fixture, so callers sort before their callees
+    let order: Vec<_> = morpho_rs::petgraph::algo::toposort(&petgraph, None)
+        .expect("no recursion")
+        .into_iter()
+        .map(|node| petgraph[node])
+        .collect();
+    let position = |id| order.iter().position(|&known| known == id);
+    assert!(position(run) < position(parse));
+}
+
+#[test]
+fn ignore_files() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/ignore"));
+    let skipped: Vec<(String, String)> = fixture
+        .project
+        .stats
+        .skipped
+        .iter()
+        .map(|s| (s.path.replace(&format!("{}/", fixture.root), ""), s.reason.clone()))
+        .collect();
+    let ignored = |path: &str, pattern: &str| (path.to_string(), format!("ignored by .morphoignore ({})", pattern));
+    assert_eq!(
+        skipped,
+        [
+            ignored("src/generated/bindings.rs", "generated/"),
+            ignored("src/messages.pb.rs", "*.pb.rs"),
+            ignored("vendor/dep/lib.rs", "/vendor/"),
+        ]
+    );
+    assert_eq!(fixture.project.stats.files_parsed, 3);
+
+    // Written out here, since git itself would leave a fixture's ignored file uncommitted
+    let dir = std::env::temp_dir().join(format!("morpho-gitignore-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("src")).expect("temp dir is writable");
+    std::fs::write(dir.join(".gitignore"), "/src/out.rs\n").expect("temp dir is writable");
+    std::fs::write(dir.join("src/lib.rs"), "pub fn run() {}\n").expect("temp dir is writable");
+    std::fs::write(dir.join("src/out.rs"), "pub fn generated() {}\n").expect("temp dir is writable");
+    let parsed = |gitignore: bool| {
+        let options = morpho_rs::LoadOptions { gitignore, ..Default::default() };
+        let dirs = [dir.to_string_lossy().into_owned()];
+        let project = morpho_rs::load_project_with_options(&dirs[0], &options).expect("loads");
+        // A refresh check with the load's own options finds nothing changed
+        assert_eq!(morpho_rs::fingerprint_dirs_with_options(&dirs, &options), project.stats.fingerprint);
+        project.stats.files_parsed
+    };
+    let (without, with) = (parsed(false), parsed(true));
+    std::fs::remove_dir_all(&dir).expect("temp dir is removable");
+    assert_eq!((without, with), (2, 1));
+}
+
+#[test]
+fn glob_and_size_filters() {
+    let load = |options: morpho_rs::LoadOptions| {
+        Fixture::load_with_options(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/basic"), &options)
+    };
+    let skipped = |fixture: &Fixture| -> Vec<(String, String)> {
+        let stats = &fixture.project.stats;
+        stats.skipped.iter().map(|s| (s.path.replace(&format!("{}/", fixture.root), ""), s.reason.clone())).collect()
+    };
+
+    let excluded = load(morpho_rs::LoadOptions { exclude_globs: vec!["s*e.rs".to_string()], ..Default::default() });
+    assert_eq!(
+        skipped(&excluded),
+        [
+            ("src/shape.rs".to_string(), "excluded by s*e.rs".to_string()),
+            ("src/square.rs".to_string(), "excluded by s*e.rs".to_string()),
+        ]
+    );
+
+    let included = load(morpho_rs::LoadOptions { include_globs: vec!["src/**/c*.rs".to_string()], ..Default::default() });
+    assert_eq!(included.project.stats.files_parsed, 2);
+    assert!(skipped(&included).iter().all(|(_, reason)| reason == "not included"));
+
+    let small = load(morpho_rs::LoadOptions { max_file_size: Some(200), ..Default::default() });
+    assert!(small.project.stats.files_parsed > 0);
+    assert!(skipped(&small).iter().all(|(_, reason)| reason.ends_with("over the 200 byte limit")));
+    assert!(!skipped(&small).is_empty());
+}
+
+#[test]
+fn budgets_truncate_call_graphs_and_listings() {
+    let fixture = fixture();
+    let budget = |max_nodes, max_bytes| morpho_rs::Budget { max_nodes, max_bytes };
+    let call_graph = |budget| morpho_rs::OutputMode::CallGraph {
+    descend_into_deps: true,
+    show_external: false,
+    scope: None,
+        roots: vec![fixture.qualified("src/lib.rs::run")],
+        visibility: VisibilityFilter::All,
+        budget,
+    };
+    check("call_graph_run_max_nodes_2", &fixture.output(call_graph(budget(Some(2), None))));
+
+    // Byte budgets count the output as shown, with names relative to the fixture
+    let full = fixture.output(call_graph(Default::default()));
+    assert_eq!(fixture.output(call_graph(budget(None, Some(full.len())))), full);
+    let small = fixture.output(call_graph(budget(None, Some(full.len() - 1))));
+    assert!(small.len() < full.len());
+    assert!(small.contains(" (omitted)\n"));
+
+    let listing = fixture.output(morpho_rs::OutputMode::ListAll {
+        visibility: VisibilityFilter::All,
+        hide_items: false,
+        include_docs: false,
+        budget: budget(Some(3), None),
+        page: Default::default(),
+    });
+    assert!(listing.ends_with(
+        "// truncated: listed 3 of 21 items by file, then name; the other 18 start in src/config.rs\n\
+         // page: items 1-3 of 21; the next page starts at offset 3\n"
+    ));
+
+    let json = fixture.output_with_format(call_graph(budget(Some(2), None)), morpho_rs::OutputFormat::Json);
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(json["truncated"], serde_json::json!({ "shown": 2, "total": 9 }));
+    assert_eq!(json["functions"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn listing_pages_cover_every_item_once() {
+    let fixture = fixture();
+    let project = &fixture.project;
+    let all = project.list_page(VisibilityFilter::All, false, 0, None).expect("lists");
+    assert_eq!((all.total, all.next_offset()), (21, None));
+
+    let mut paged = vec![];
+    let mut offset = Some(0);
+    while let Some(start) = offset {
+        let page = project.list_page(VisibilityFilter::All, false, start, Some(5)).expect("lists");
+        paged.extend(page.items.iter().map(|item| (item.file().to_string(), item_name(item))));
+        offset = page.next_offset();
+    }
+    let expected: Vec<_> = all.items.iter().map(|item| (item.file().to_string(), item_name(item))).collect();
+    assert_eq!(paged, expected);
+
+    let page = |offset, limit| morpho_rs::OutputMode::ListAll {
+        visibility: VisibilityFilter::All,
+        hide_items: false,
+        include_docs: false,
+        budget: Default::default(),
+        page: morpho_rs::Page { offset, limit },
+    };
+    assert!(fixture.output(page(3, Some(4))).ends_with("// page: items 4-7 of 21; the next page starts at offset 7\n"));
+    assert!(fixture.output(page(19, None)).ends_with("// page: items 20-21 of 21\n"));
+
+    // Every page moves the listing on: an empty page is refused, and a budget too small for any item
+    // still shows one
+    let empty = morpho_rs::generate_output_for_project(project, page(3, Some(0)));
+    assert!(matches!(empty, Err(morpho_rs::MorphoError::Unsupported(_))));
+    let mut tight = page(3, Some(4));
+    if let morpho_rs::OutputMode::ListAll { budget, .. } = &mut tight {
+        budget.max_bytes = Some(1);
+    }
+    assert!(fixture.output(tight).ends_with("// page: items 4-4 of 21; the next page starts at offset 4\n"));
+
+    let json = fixture.output_with_format(page(18, Some(2)), morpho_rs::OutputFormat::Json);
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(json["page"], serde_json::json!({ "offset": 18, "total": 21, "next_offset": 20 }));
+}
+
+fn item_name(item: &morpho_rs::query::ItemRef) -> String {
+    match item {
+        morpho_rs::query::ItemRef::Function(func) => func.qualified_name.clone(),
+        morpho_rs::query::ItemRef::Type(ty) | morpho_rs::query::ItemRef::Item(ty) => ty.key.clone(),
+    }
+}
+
+#[test]
+fn consts_statics_and_macros_are_listed() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nested"));
+    let listing = fixture.list_all(VisibilityFilter::All);
+    for line in ["static mut OPEN: usize = 0;", "macro_rules! local_only", "#[macro_export] macro_rules! retry"] {
+        assert!(listing.contains(line), "{} not listed", line);
+    }
+    let hidden = fixture.output(morpho_rs::OutputMode::ListAll {
+        visibility: VisibilityFilter::All,
+        hide_items: true,
+        include_docs: false,
+        budget: Default::default(),
+        page: Default::default(),
+    });
+    assert!(!hidden.contains("TIMEOUT_MS") && !hidden.contains("macro_rules!"));
+
+    check("type_api_pool", &fixture.output(morpho_rs::OutputMode::TypeApi { type_name: "Pool".to_string() }));
+    let api = fixture.output_with_format(
+        morpho_rs::OutputMode::TypeApi { type_name: "Pool".to_string() },
+        morpho_rs::OutputFormat::Json,
+    );
+    let api: serde_json::Value = serde_json::from_str(&api).unwrap();
+    assert_eq!(api["impls"][0]["types"][0]["signature"], "type Item = usize;");
+    let source = fixture.output(morpho_rs::OutputMode::Source { function: "net::TIMEOUT_MS".to_string(), include_docs: false });
+    assert!(source.contains("pub(crate) const TIMEOUT_MS: u64 = 5_000;"));
+
+    let mode = morpho_rs::OutputMode::ListAll {
+        visibility: VisibilityFilter::PublicOnly,
+        hide_items: false,
+        include_docs: false,
+        budget: Default::default(),
+        page: Default::default(),
+    };
+    let json: serde_json::Value =
+        serde_json::from_str(&fixture.output_with_format(mode, morpho_rs::OutputFormat::Json)).unwrap();
+    let items: Vec<(&str, &str)> = json["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| (item["name"].as_str().unwrap(), item["kind"].as_str().unwrap()))
+        .collect();
+    assert_eq!(items, [("retry", "macro")]);
+}
+
+#[test]
+fn call_paths_resolve_through_modules_and_imports() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nested"));
+    let copy_all = fixture.qualified("src/lib.rs::copy_all");
+    let callees: Vec<String> = fixture
+        .project
+        .call_edges()
+        .into_iter()
+        .filter(|edge| edge.caller == copy_all)
+        .map(|edge| edge.callee.replace(&format!("{}/", fixture.root), ""))
+        .collect();
+    // `io::copy` and `swap` come from std, so neither falls back to a project function of that name
+    assert_eq!(callees, ["src/lib.rs::util::copy", "src/lib.rs::util::copy"]);
+}
+
+#[test]
+fn listing_as_rustdoc_json() {
+    use morpho_rs::OutputFormat;
+    let fixture = fixture();
+    let listing = |visibility| morpho_rs::OutputMode::ListAll {
+        visibility,
+        hide_items: false,
+        include_docs: false,
+        budget: Default::default(),
+        page: Default::default(),
+    };
+    let document = fixture.output_with_format(listing(VisibilityFilter::PublicOnly), OutputFormat::RustdocJson);
+    let document: serde_json::Value = serde_json::from_str(&document).unwrap();
+    assert_eq!(document["includes_private"], false);
+    let id_of = |path: &str| {
+        let paths = document["paths"].as_object().unwrap();
+        let segments: Vec<&str> = path.split("::").collect();
+        let found = paths.iter().find(|(_, summary)| summary["path"] == serde_json::json!(segments));
+        found.map(|(id, _)| id.clone()).unwrap_or_else(|| panic!("no path {}", path))
+    };
+
+    // Methods hang off their type, and modules list their items
+    let config = &document["index"][id_of("crate::config::Config")];
+    assert_eq!(config["visibility"], "public");
+    let methods = [id_of("crate::config::Config::load"), id_of("crate::config::Config::parse")];
+    assert_eq!(config["inner"]["struct"]["methods"], serde_json::json!(methods));
+    let root = &document["index"][document["root"].as_str().unwrap()];
+    let root_items = root["inner"]["module"]["items"].as_array().unwrap();
+    assert!(root_items.contains(&serde_json::json!(id_of("crate::run"))));
+    assert!(root_items.contains(&serde_json::json!(id_of("crate::config"))));
+    let load = &document["index"][id_of("crate::config::Config::load")];
+    assert_eq!(load["inner"]["function"]["declaration"], "pub fn load(path: &str) -> Config");
+    assert_eq!(load["span"]["filename"], "src/config.rs");
+
+    let mode = morpho_rs::OutputMode::Callers { target: "run".to_string(), depth: None };
+    let unsupported = morpho_rs::generate_output_for_project_with_format(&fixture.project, mode, OutputFormat::RustdocJson);
+    assert!(matches!(unsupported, Err(morpho_rs::MorphoError::Unsupported(_))));
+}
+
+#[test]
+fn streamed_output_matches_rendered_output() {
+    use morpho_rs::{generate_output_to, generate_output_with_format, LoadOptions, OutputFormat, OutputMode};
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/basic");
+    let listing = |limit| OutputMode::ListAll {
+        visibility: VisibilityFilter::PublicOnly,
+        hide_items: false,
+        include_docs: false,
+        budget: Default::default(),
+        page: morpho_rs::Page { offset: 1, limit },
+    };
+    let streamed = |mode: OutputMode, options: &LoadOptions| {
+        let mut out = vec![];
+        let output = generate_output_to(&mut out, dir, mode, options, OutputFormat::Text).unwrap();
+        assert!(output.content.is_empty());
+        String::from_utf8(out).unwrap()
+    };
+
+    let options = LoadOptions::default();
+    for limit in [Some(3), None] {
+        let rendered = generate_output_with_format(dir, listing(limit), &options, OutputFormat::Text).unwrap();
+        assert_eq!(streamed(listing(limit), &options), rendered.content);
+    }
+    let call_graph = || OutputMode::CallGraph {
+        roots: vec!["run".to_string()],
+        visibility: VisibilityFilter::All,
+        budget: Default::default(),
+        descend_into_deps: true,
+        show_external: false,
+        scope: None,
+    };
+    let rendered = generate_output_with_format(dir, call_graph(), &options, OutputFormat::Text).unwrap();
+    assert_eq!(streamed(call_graph(), &options), rendered.content);
+
+    // Under a memory limit each batch of files is written as soon as it is rendered
+    let low_memory = LoadOptions { memory_limit_mb: Some(1), ..Default::default() };
+    let whole = || OutputMode::ListAll {
+        visibility: VisibilityFilter::All,
+        hide_items: false,
+        include_docs: false,
+        budget: Default::default(),
+        page: Default::default(),
+    };
+    let rendered = generate_output_with_format(dir, whole(), &options, OutputFormat::Text).unwrap();
+    let parsed: syn::ItemFn = syn::parse_str("fn kept() {}").unwrap();
+    assert_eq!(streamed(whole(), &low_memory), rendered.content);
+    // Batches are parsed on a thread of their own, so spans parsed on this one are still readable
+    assert_eq!(parsed.sig.ident.span().start().line, 1);
+}
+
+#[test]
+fn metrics_rank_functions_by_each_measure() {
+    use morpho_rs::Metric;
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/exprs"));
+    let mode = morpho_rs::OutputMode::Metrics { sort: Metric::Complexity, visibility: VisibilityFilter::All, limit: Some(4) };
+    check("metrics_exprs", &fixture.output(mode));
+
+    let project = &fixture.project;
+    for sort in [Metric::Lines, Metric::Complexity, Metric::Callees, Metric::FanIn] {
+        let metrics = project.metrics(sort, VisibilityFilter::All);
+        assert_eq!(metrics.len(), project.functions.len());
+        assert!(metrics.windows(2).all(|pair| pair[0].get(sort) >= pair[1].get(sort)));
+    }
+    let by_callees = project.metrics(Metric::Callees, VisibilityFilter::All);
+    assert_eq!(by_callees[0].function.qualified_name, fixture.qualified("src/lib.rs::run"));
+    assert_eq!((by_callees[0].lines, by_callees[0].complexity), (12, 3));
+}
+
+#[test]
+fn unsafe_code_audit() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nested"));
+    check("unsafe_close", &fixture.output(morpho_rs::OutputMode::UnsafeAudit { root: Some("close".to_string()) }));
+
+    let audit = fixture.project.unsafe_audit(Some("top")).unwrap();
+    let release = fixture.project.function("release").unwrap();
+    assert_eq!(release.unsafe_blocks, [(89, 89)]);
+    assert_eq!(audit.functions.len(), 2);
+    assert!(audit.paths.is_empty());
+}
+
+#[test]
+fn trait_implementors() {
+    let nested = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nested"));
+    let mode = morpho_rs::OutputMode::Implementors { trait_name: "Transport".to_string() };
+    check("implementors_transport", &nested.output(mode));
+
+    // Traits from other crates are matched by name
+    let fixture = fixture();
+    let from = fixture.project.implementors("From").unwrap();
+    assert_eq!(from.impls.len(), 2);
+    assert!(from.trait_def.is_none() && from.impls.iter().all(|imp| imp.inherits.is_empty()));
+}
+
+#[test]
+fn doc_comments_in_listings_and_source() {
+    use morpho_rs::{OutputFormat, OutputMode};
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nested"));
+    let listing = |include_docs| OutputMode::ListAll {
+        visibility: VisibilityFilter::PublicOnly,
+        hide_items: true,
+        include_docs,
+        budget: Default::default(),
+        page: Default::default(),
+    };
+    let documented = fixture.output(listing(true));
+    assert!(documented.contains(
+        "/// Connects through the pool.\n///\n/// Returns `None` when offline.\npub fn src/lib.rs::dial() -> Option<()>"
+    ));
+    assert!(documented.contains("/// How often to retry a failed connection\npub struct Backoff {"));
+    assert!(!fixture.output(listing(false)).contains("///"));
+
+    let source = fixture.output(OutputMode::Source { function: "Backoff".to_string(), include_docs: true });
+    assert!(source.starts_with("=== src/lib.rs ===\n/// How often to retry a failed connection\npub struct Backoff"));
+
+    let json = fixture.output_with_format(listing(true), OutputFormat::Json);
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let dial = json["functions"].as_array().unwrap().iter().find(|func| func["name"] == "src/lib.rs::dial").unwrap();
+    assert_eq!(dial["docs"], "Connects through the pool.\n\nReturns `None` when offline.");
+}
+
+#[test]
+fn paths_relative_to_the_loaded_directory() {
+    use morpho_rs::{LoadOptions, OutputMode};
+    let fixture = fixture();
+    let source = |fixture: &Fixture| {
+        fixture.output(OutputMode::Source { function: "src/lib.rs::run".to_string(), include_docs: false })
+    };
+    assert!(source(&fixture).starts_with("=== src/lib.rs ===\n"));
+
+    // Merged projects keep each directory's name
+    let nested = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nested"));
+    let merged = morpho_rs::merge_projects(vec![fixture.project.clone(), nested.project]);
+    let search = OutputMode::Search { query: "connect".to_string(), limit: None };
+    let found = morpho_rs::generate_output_for_project(&merged, search).unwrap().content;
+    assert!(found.contains("nested/src/lib.rs::net::connect") && !found.contains(&fixture.root));
+
+    let options = LoadOptions { absolute_paths: true, ..Default::default() };
+    let absolute = Fixture::load_with_options(&fixture.root, &options);
+    let canonical = std::fs::canonicalize(&fixture.root).unwrap();
+    assert!(source(&absolute).starts_with(&format!("=== {}/src/lib.rs ===\n", canonical.display())));
+    assert!(absolute.project.function("src/lib.rs::run").is_ok());
+}
+
+#[test]
+fn paths_in_source_text_are_left_as_written() {
+    use morpho_rs::{OutputFormat, OutputMode};
+    // Loaded by the relative path its own string literal and comment name
+    let fixture = Fixture::load("./tests/fixtures/paths");
+    let literal = "\"./tests/fixtures/paths/config.toml\"";
+    let comment = "// reads ./tests/fixtures/paths/config.toml, next to ./config.toml";
+
+    let source = fixture.output(OutputMode::Source { function: "settings_path".to_string(), include_docs: false });
+    assert!(source.starts_with("=== src/lib.rs ===\n") && source.contains(literal) && source.contains(comment));
+
+    let bundle = fixture.output(OutputMode::CallGraphSource { roots: vec!["src/lib.rs::load".to_string()] });
+    assert!(bundle.contains("reachable from src/lib.rs::load") && bundle.contains(literal));
+
+    let json = fixture.output_with_format(
+        OutputMode::Source { function: "settings_path".to_string(), include_docs: false },
+        OutputFormat::Json,
+    );
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(json["item"]["name"], "src/lib.rs::settings_path");
+    assert!(json["item"]["source"].as_str().unwrap().contains(literal));
+}
+
+#[test]
+fn tui_browser_navigates_calls_and_callers() {
+    use morpho_rs::tui::{Action, Browser, Direction};
+    let nested = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nested"));
+    let mut browser = Browser::new(&nested.project);
+    let short = |name: &Option<String>| {
+        name.as_deref().and_then(|name| name.split_once(".rs::")).map(|(_, rest)| rest.to_string())
+    };
+    let calls = |browser: &Browser| -> Vec<String> {
+        let name = |row: &morpho_rs::tui::CallRow| short(&Some(row.function.clone())).unwrap();
+        browser.calls.iter().map(|row| format!("{}{}", row.depth, name(row))).collect()
+    };
+
+    // Modules nest under their file, functions under their module
+    let labels: Vec<String> =
+        browser.tree.iter().map(|row| format!("{}{}", "  ".repeat(row.depth), row.label)).collect();
+    assert!(labels.join("\n").contains("  mod net\n    Pool::size\n    connect\n    reset\n    mod http\n      pool"));
+
+    browser.tree_cursor = labels.iter().position(|label| label == "  top").unwrap();
+    browser.apply(Action::Open);
+    assert_eq!(calls(&browser), ["1net::connect", "2net::http::request", "1net::reset"]);
+    assert!(browser.source.starts_with("=== src/lib.rs ===\npub fn top()"));
+
+    browser.apply(Action::SwitchPane);
+    browser.apply(Action::Down);
+    browser.apply(Action::Down);
+    browser.apply(Action::Open);
+    assert_eq!(short(&browser.open).as_deref(), Some("net::reset"));
+    browser.apply(Action::Back);
+    assert_eq!(short(&browser.open).as_deref(), Some("top"));
+
+    browser.apply(Action::ToggleDirection);
+    assert_eq!(browser.direction, Direction::Callers);
+    assert_eq!(calls(&browser), ["1top_returns"]);
+}
+
+#[test]
+fn merged_projects_resolve_within_each_project() {
+    use morpho_rs::{Budget, OutputMode, VisibilityFilter};
+    let load = |name: &str| {
+        Fixture::load(format!("{}/tests/fixtures/multi/{}", env!("CARGO_MANIFEST_DIR"), name)).project
+    };
+    let merged = morpho_rs::merge_projects(vec![load("alpha"), load("beta").with_namespace("b")]);
+    let call_graph = |root: &str, descend_into_deps| {
+        let mode = OutputMode::CallGraph {
+            roots: vec![root.to_string()],
+            visibility: VisibilityFilter::All,
+            budget: Budget::default(),
+            descend_into_deps,
+            show_external: false,
+            scope: None,
+        };
+        morpho_rs::generate_output_for_project(&merged, mode).unwrap().content
+    };
+    check("call_graph_multi_beta", &call_graph("b/src/lib.rs::flush", true));
+    // Calls into the other project are marked, and followed only when asked to
+    check("call_graph_multi_release", &call_graph("alpha/src/lib.rs::release", true));
+    check("call_graph_multi_release_stop_at_deps", &call_graph("alpha/src/lib.rs::release", false));
+    assert!(merged.function("b/src/store.rs::Store::save").is_ok());
+
+    // Both projects define `Store::save` and `log::record`; only the call made across on purpose leaves
+    // its caller's project
+    let edges = merged.call_edges();
+    assert_eq!(edges.len(), 7);
+    let crossing: Vec<String> = edges
+        .iter()
+        .filter(|edge| merged.dependency_name(&edge.caller, &edge.callee).is_some())
+        .map(|edge| merged.relative_path(&edge.callee))
+        .collect();
+    assert_eq!(crossing, ["b/src/lib.rs::publish"]);
+}
+
+#[test]
+fn unions_and_foreign_functions() {
+    let fixture = Fixture::load(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/ffi"));
+    check("list_all_ffi", &fixture.list_all(VisibilityFilter::All));
+
+    // Foreign functions are call graph leaves, called by name or through `extern crate self as ffi`
+    let graph = fixture.project.call_graph_from(&["length", "magnitude"]).unwrap();
+    let callees: Vec<&str> = graph.edges.iter().map(|edge| edge.callee.as_str()).collect();
+    assert_eq!(callees, [fixture.qualified("src/lib.rs::strlen"), fixture.qualified("src/lib.rs::abs")]);
+    assert_eq!(graph.types.iter().map(|ty| ty.key.as_str()).collect::<Vec<_>>(), ["Value"]);
+    assert_eq!(fixture.project.unsafe_audit(None).unwrap().functions.len(), 4);
+}